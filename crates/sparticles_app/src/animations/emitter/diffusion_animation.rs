@@ -1,7 +1,7 @@
 use std::any::Any;
 
 use crate::{
-    model::{Clock, EmitterUniform, LifeCycle},
+    model::{Clock, EmitterUniform, LifeCycle, LifeCycleMode},
     traits::{EmitterAnimation, HandleAction, HandleAngles, RegisterEmitterAnimation},
     util::persistence::DynamicExport,
     util::ListAction,
@@ -47,6 +47,7 @@ impl RegisterEmitterAnimation for RegisterDiffusionAnimation {
                 from_sec: 0.,
                 until_sec: 5.,
                 lifetime_sec: 5.,
+                mode: LifeCycleMode::Loop,
             },
             [0., 45.].into(),
             [0., 15.].into(),
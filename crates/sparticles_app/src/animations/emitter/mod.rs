@@ -1,5 +1,9 @@
 pub mod diffusion_animation;
+pub mod path_animation;
+pub mod pulse_spawn_animation;
 pub mod sway_animation;
 
 pub use diffusion_animation::DiffusionAnimation;
+pub use path_animation::PathAnimation;
+pub use pulse_spawn_animation::PulseSpawnAnimation;
 pub use sway_animation::SwayAnimation;
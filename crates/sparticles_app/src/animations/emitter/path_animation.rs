@@ -0,0 +1,143 @@
+use std::any::Any;
+
+use crate::{
+    model::{Clock, EmitterUniform, LifeCycle, LifeCycleMode},
+    traits::{EmitterAnimation, HandleAction, RegisterEmitterAnimation},
+    util::math::catmull_rom,
+    util::persistence::DynamicExport,
+    util::ListAction,
+};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Moves the emitter box along a Catmull-Rom spline through the control points
+#[derive(Serialize, Deserialize)]
+pub struct PathAnimation {
+    pub life_cycle: LifeCycle,
+    pub control_points: Vec<Vec3>,
+    /// Closes the path, the last point curves back into the first
+    pub looped: bool,
+
+    #[serde(skip_serializing, skip_deserializing)]
+    pub selected_action: ListAction,
+
+    pub enabled: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct RegisterPathAnimation;
+
+impl RegisterEmitterAnimation for RegisterPathAnimation {
+    fn tag(&self) -> &'static str {
+        "path"
+    }
+
+    fn import(&self, value: serde_json::Value) -> Box<dyn EmitterAnimation> {
+        let anim: PathAnimation = serde_json::from_value(value).unwrap();
+        Box::new(anim)
+    }
+
+    fn create_default(&self) -> Box<dyn EmitterAnimation> {
+        let path_anim = PathAnimation::new(
+            LifeCycle {
+                from_sec: 0.,
+                until_sec: 4.,
+                lifetime_sec: 5.,
+                mode: LifeCycleMode::Loop,
+            },
+            vec![
+                [-20., -10., 0.].into(),
+                [-10., 10., 0.].into(),
+                [10., 10., 0.].into(),
+                [20., -10., 0.].into(),
+            ],
+            false,
+        );
+
+        Box::new(path_anim)
+    }
+}
+
+impl PathAnimation {
+    pub fn new(life_cycle: LifeCycle, control_points: Vec<Vec3>, looped: bool) -> Self {
+        Self {
+            life_cycle,
+            control_points,
+            looped,
+            selected_action: ListAction::None,
+            enabled: true,
+        }
+    }
+
+    /// Position at a fraction (0..1) of the path, None without control points
+    pub fn position(&self, fraction: f32) -> Option<Vec3> {
+        let points = &self.control_points;
+        let len = points.len();
+
+        match len {
+            0 => return None,
+            1 => return Some(points[0]),
+            _ => {}
+        }
+
+        // The endpoints are repeated on an open path, so two or three points still curve
+        let point = |i: isize| {
+            if self.looped {
+                points[i.rem_euclid(len as isize) as usize]
+            } else {
+                points[i.clamp(0, len as isize - 1) as usize]
+            }
+        };
+
+        let segment_count = if self.looped { len } else { len - 1 };
+        let scaled = fraction.clamp(0., 1.) * segment_count as f32;
+        let segment = (scaled as usize).min(segment_count - 1);
+        let t = scaled - segment as f32;
+        let i = segment as isize;
+
+        Some(catmull_rom(
+            point(i - 1),
+            point(i),
+            point(i + 1),
+            point(i + 2),
+            t,
+        ))
+    }
+}
+
+impl HandleAction for PathAnimation {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        DynamicExport {
+            tag: RegisterPathAnimation.tag().to_string(),
+            data: serde_json::to_value(self).unwrap(),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl EmitterAnimation for PathAnimation {
+    fn animate(&mut self, emitter: &mut EmitterUniform, clock: &Clock) {
+        let current_sec = self.life_cycle.get_current_sec(clock);
+
+        if !self.life_cycle.shoud_animate(current_sec) {
+            return;
+        }
+
+        let fraction = self.life_cycle.get_fraction(current_sec);
+
+        if let Some(position) = self.position(fraction) {
+            emitter.box_position = position;
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -1,7 +1,7 @@
 use std::any::Any;
 
 use crate::{
-    model::{Clock, EmitterUniform, LifeCycle},
+    model::{Clock, EmitterUniform, LifeCycle, LifeCycleMode},
     traits::{EmitterAnimation, HandleAction, HandleAngles, RegisterEmitterAnimation},
     util::persistence::DynamicExport,
     util::ListAction,
@@ -50,6 +50,7 @@ impl RegisterEmitterAnimation for RegisterSwayAnimation {
                 from_sec: 0.,
                 until_sec: 4.,
                 lifetime_sec: 4.,
+                mode: LifeCycleMode::Loop,
             },
             glam::Vec2::ZERO,
             Vec2::new(30., 120.),
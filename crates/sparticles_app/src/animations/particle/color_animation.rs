@@ -99,15 +99,15 @@ impl ParticleAnimation for ColorAnimation {
     fn compute<'a>(
         &'a self,
         emitter: &'a EmitterState,
-        clock: &Clock,
+        _clock: &Clock,
         compute_pass: &mut wgpu::ComputePass<'a>,
     ) {
-        let nr = clock.get_bindgroup_nr();
+        let nr = emitter.bindgroup_nr();
 
         compute_pass.set_pipeline(&self.pipeline);
         compute_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
         compute_pass.set_bind_group(1, &self.bind_group, &[]);
-        compute_pass.dispatch_workgroups(emitter.dispatch_x_count, 1, 1);
+        emitter.dispatch(compute_pass);
     }
 
     fn recreate(&self, gfx_state: &GfxState, emitter: &EmitterState) -> Box<dyn ParticleAnimation> {
@@ -0,0 +1,235 @@
+use std::any::Any;
+
+use crate::{
+    model::{Clock, EmitterState, GfxState, LifeCycle, LifeCycleMode},
+    shaders::ShaderOptions,
+    traits::{HandleAction, ParticleAnimation, RegisterParticleAnimation},
+    util::persistence::DynamicExport,
+    util::ListAction,
+};
+use egui_wgpu::wgpu::{self, util::DeviceExt};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CurlNoiseUniform {
+    pub life_cycle: LifeCycle,
+    /// Noise cells per world unit
+    pub frequency: f32,
+    /// Velocity added per second at full curl
+    pub amplitude: f32,
+    /// Moves the noise field over time in noise space
+    pub scroll_speed: Vec3,
+}
+
+impl Default for CurlNoiseUniform {
+    fn default() -> Self {
+        Self {
+            life_cycle: LifeCycle {
+                from_sec: 0.,
+                until_sec: 10.,
+                lifetime_sec: 10.,
+                mode: LifeCycleMode::Loop,
+            },
+            frequency: 0.2,
+            amplitude: 5.,
+            scroll_speed: [0., 0.3, 0.].into(),
+        }
+    }
+}
+
+impl CurlNoiseUniform {
+    fn create_buffer_content(&self) -> [f32; 8] {
+        [
+            self.scroll_speed.x,
+            self.scroll_speed.y,
+            self.scroll_speed.z,
+            self.frequency,
+            self.amplitude,
+            0., // padding
+            0., // padding
+            0., // padding
+        ]
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RegisterCurlNoiseAnimation;
+
+impl RegisterCurlNoiseAnimation {
+    /// Will append animation to emitter
+    pub fn append(uniform: CurlNoiseUniform, emitter: &mut EmitterState, gfx_state: &GfxState) {
+        let anim = Box::new(CurlNoiseAnimation::new(uniform, emitter, gfx_state));
+
+        emitter.push_particle_animation(anim);
+    }
+}
+
+impl RegisterParticleAnimation for RegisterCurlNoiseAnimation {
+    fn tag(&self) -> &'static str {
+        "curl_noise"
+    }
+
+    fn create_default(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+    ) -> Box<dyn ParticleAnimation> {
+        Box::new(CurlNoiseAnimation::new(
+            CurlNoiseUniform::default(),
+            emitter,
+            gfx_state,
+        ))
+    }
+
+    fn import(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+        value: serde_json::Value,
+    ) -> Box<dyn ParticleAnimation> {
+        let uniform = serde_json::from_value(value).unwrap();
+        Box::new(CurlNoiseAnimation::new(uniform, emitter, gfx_state))
+    }
+}
+
+pub struct CurlNoiseAnimation {
+    pub pipeline: wgpu::ComputePipeline,
+    pub uniform: CurlNoiseUniform,
+    pub bind_group: wgpu::BindGroup,
+    pub buffer: wgpu::Buffer,
+    pub update_uniform: bool,
+    pub should_animate: bool,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+}
+
+impl HandleAction for CurlNoiseAnimation {
+    fn export(&self) -> DynamicExport {
+        let animation = serde_json::to_value(self.uniform).unwrap();
+
+        DynamicExport {
+            tag: RegisterCurlNoiseAnimation.tag().to_owned(),
+            data: animation,
+        }
+    }
+
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl ParticleAnimation for CurlNoiseAnimation {
+    fn update(&mut self, clock: &Clock, gfx_state: &GfxState) {
+        let queue = &gfx_state.queue;
+        let uniform = &self.uniform;
+        let current_sec = uniform.life_cycle.get_current_sec(clock);
+        self.should_animate = uniform.life_cycle.shoud_animate(current_sec);
+
+        if self.update_uniform {
+            let buf_content_raw = self.uniform.create_buffer_content();
+            let buf_content = bytemuck::cast_slice(&buf_content_raw);
+            queue.write_buffer(&self.buffer, 0, buf_content);
+            self.update_uniform = false;
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn compute<'a>(
+        &'a self,
+        emitter: &'a EmitterState,
+        _clock: &Clock,
+        compute_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        if !self.should_animate {
+            return;
+        }
+
+        let nr = emitter.bindgroup_nr();
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
+        compute_pass.set_bind_group(1, &self.bind_group, &[]);
+        emitter.dispatch(compute_pass);
+    }
+
+    fn recreate(&self, gfx_state: &GfxState, emitter: &EmitterState) -> Box<dyn ParticleAnimation> {
+        Box::new(Self::new(self.uniform, emitter, gfx_state))
+    }
+}
+
+impl CurlNoiseAnimation {
+    fn new(uniform: CurlNoiseUniform, emitter: &EmitterState, gfx_state: &GfxState) -> Self {
+        let device = &gfx_state.device;
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["noise.wgsl", "curl_noise_anim.wgsl"],
+            label: "Curl noise animation",
+        });
+
+        let buffer_content = uniform.create_buffer_content();
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Curl noise buffer"),
+            contents: bytemuck::cast_slice(&buffer_content),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let animation_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                // Uniform data
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(buffer_content.len() as u64 * 4),
+                    },
+                    count: None,
+                },
+            ],
+            label: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &animation_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("Curl noise animation bind group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Curl noise animation layout"),
+            bind_group_layouts: &[&emitter.bg_layout, &animation_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Curl noise animation pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        Self {
+            pipeline,
+            uniform,
+            bind_group,
+            buffer,
+            update_uniform: false,
+            should_animate: false,
+            selected_action: ListAction::None,
+            enabled: true,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::any::Any;
+
+use crate::{
+    model::{Clock, EmitterState, EmitterUniform, GfxState, LifeCycle, LifeCycleMode},
+    traits::{HandleAction, ParticleAnimation, RegisterParticleAnimation},
+    util::persistence::DynamicExport,
+    util::ListAction,
+};
+use egui_wgpu::wgpu;
+use glam::Vec4;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FlipbookUniform {
+    pub life_cycle: LifeCycle,
+    pub columns: u32,
+    pub rows: u32,
+    /// Cells per second of particle age, 0 plays every cell once over the particle life
+    pub fps: f32,
+}
+
+impl Default for FlipbookUniform {
+    fn default() -> Self {
+        Self {
+            life_cycle: LifeCycle {
+                from_sec: 0.,
+                until_sec: 10.,
+                lifetime_sec: 10.,
+                mode: LifeCycleMode::Loop,
+            },
+            columns: 4,
+            rows: 4,
+            fps: 0.,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RegisterFlipbookAnimation;
+
+impl RegisterFlipbookAnimation {
+    /// Will append animation to emitter
+    pub fn append(uniform: FlipbookUniform, emitter: &mut EmitterState) {
+        let anim = Box::new(FlipbookAnimation::new(uniform));
+
+        emitter.push_particle_animation(anim);
+    }
+}
+
+impl RegisterParticleAnimation for RegisterFlipbookAnimation {
+    fn tag(&self) -> &'static str {
+        "flipbook"
+    }
+
+    fn create_default(&self, _: &GfxState, _: &EmitterState) -> Box<dyn ParticleAnimation> {
+        Box::new(FlipbookAnimation::new(FlipbookUniform::default()))
+    }
+
+    fn import(
+        &self,
+        _: &GfxState,
+        _: &EmitterState,
+        value: serde_json::Value,
+    ) -> Box<dyn ParticleAnimation> {
+        let uniform = serde_json::from_value(value).unwrap();
+        Box::new(FlipbookAnimation::new(uniform))
+    }
+}
+
+/// Plays the material textures as a sprite sheet, the cell is picked per particle from its age.
+/// Model meshes sample every material texture from the cell. Circles keep their disc shape
+/// and are colored by the particle color, so the flipbook only shows on model meshes.
+pub struct FlipbookAnimation {
+    pub uniform: FlipbookUniform,
+    pub should_animate: bool,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+}
+
+impl HandleAction for FlipbookAnimation {
+    fn export(&self) -> DynamicExport {
+        let animation = serde_json::to_value(self.uniform).unwrap();
+
+        DynamicExport {
+            tag: RegisterFlipbookAnimation.tag().to_owned(),
+            data: animation,
+        }
+    }
+
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl ParticleAnimation for FlipbookAnimation {
+    fn update(&mut self, clock: &Clock, _: &GfxState) {
+        let life_cycle = &self.uniform.life_cycle;
+        let current_sec = life_cycle.get_current_sec(clock);
+        self.should_animate = life_cycle.shoud_animate(current_sec);
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    /// The cell is picked while rendering, so there is nothing to compute
+    fn compute<'a>(
+        &'a self,
+        _emitter: &'a EmitterState,
+        _clock: &Clock,
+        _compute_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+    }
+
+    fn recreate(&self, _: &GfxState, _: &EmitterState) -> Box<dyn ParticleAnimation> {
+        Box::new(Self::new(self.uniform))
+    }
+
+    fn animate_emitter(&self, uniform: &mut EmitterUniform) {
+        if !self.should_animate {
+            return;
+        }
+
+        let fb = &self.uniform;
+
+        uniform.flipbook = Vec4::new(
+            fb.columns.max(1) as f32,
+            fb.rows.max(1) as f32,
+            fb.fps.max(0.),
+            0.,
+        );
+    }
+}
+
+impl FlipbookAnimation {
+    fn new(uniform: FlipbookUniform) -> Self {
+        Self {
+            uniform,
+            should_animate: false,
+            selected_action: ListAction::None,
+            enabled: true,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    model::{Clock, EmitterState, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, ParticleAnimation, RegisterParticleAnimation},
+    util::ListAction,
+    util::{persistence::DynamicExport, UniformContext},
+};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Bounces particles off a horizontal plane. Keep it last in the animation list, so velocity
+/// added by gravity and other forces this frame is reflected as well
+#[derive(ShaderType, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FloorUniform {
+    /// Y of the plane
+    pub height: f32,
+    /// Fraction of the vertical speed kept after a bounce
+    pub restitution: f32,
+    /// Fraction of the horizontal speed lost on each bounce
+    pub friction: f32,
+}
+
+impl Default for FloorUniform {
+    fn default() -> Self {
+        Self {
+            height: 0.,
+            restitution: 0.6,
+            friction: 0.1,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RegisterFloorCollisionAnimation;
+
+impl RegisterFloorCollisionAnimation {
+    /// Will append animation to emitter
+    pub fn append(uniform: FloorUniform, emitter: &mut EmitterState, gfx_state: &GfxState) {
+        let anim = Box::new(FloorCollisionAnimation::new(uniform, emitter, gfx_state));
+
+        emitter.push_particle_animation(anim);
+    }
+}
+
+impl RegisterParticleAnimation for RegisterFloorCollisionAnimation {
+    fn create_default(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+    ) -> Box<dyn ParticleAnimation> {
+        Box::new(FloorCollisionAnimation::new(
+            FloorUniform::default(),
+            emitter,
+            gfx_state,
+        ))
+    }
+
+    fn tag(&self) -> &'static str {
+        "floor_collision"
+    }
+
+    fn import(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+        value: serde_json::Value,
+    ) -> Box<dyn ParticleAnimation> {
+        let uniform = serde_json::from_value(value).unwrap();
+        Box::new(FloorCollisionAnimation::new(uniform, emitter, gfx_state))
+    }
+}
+
+pub struct FloorCollisionAnimation {
+    pub pipeline: wgpu::ComputePipeline,
+    pub uniform: FloorUniform,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub selected_action: ListAction,
+    pub update_uniform: bool,
+    pub enabled: bool,
+}
+
+impl HandleAction for FloorCollisionAnimation {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let animation = serde_json::to_value(self.uniform).unwrap();
+        let animation_type = RegisterFloorCollisionAnimation.tag().to_owned();
+
+        DynamicExport {
+            tag: animation_type,
+            data: animation,
+        }
+    }
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl ParticleAnimation for FloorCollisionAnimation {
+    fn update(&mut self, _: &Clock, gfx_state: &GfxState) {
+        let queue = &gfx_state.queue;
+
+        if self.update_uniform {
+            let buf_content = self.uniform.buffer_content();
+            queue.write_buffer(&self.buffer, 0, &buf_content);
+            self.update_uniform = false;
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn compute<'a>(
+        &'a self,
+        emitter: &'a EmitterState,
+        _clock: &Clock,
+        compute_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let nr = emitter.bindgroup_nr();
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
+        compute_pass.set_bind_group(1, &self.bind_group, &[]);
+        emitter.dispatch(compute_pass);
+    }
+
+    fn recreate(&self, gfx_state: &GfxState, emitter: &EmitterState) -> Box<dyn ParticleAnimation> {
+        Box::new(Self::new(self.uniform, emitter, gfx_state))
+    }
+}
+
+impl FloorCollisionAnimation {
+    fn new(uniform: FloorUniform, emitter: &EmitterState, gfx_state: &GfxState) -> Self {
+        let device = &gfx_state.device;
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["floor_collision_anim.wgsl"],
+            label: "Floor collision animation",
+        });
+
+        let floor_ctx = UniformContext::from_uniform(&uniform, device, "Floor uniform");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Floor collision layout"),
+            bind_group_layouts: &[&emitter.bg_layout, &floor_ctx.bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Floor collision animation pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        Self {
+            pipeline,
+            bind_group: floor_ctx.bg,
+            uniform,
+            buffer: floor_ctx.buf,
+            update_uniform: false,
+            selected_action: ListAction::None,
+            enabled: true,
+        }
+    }
+}
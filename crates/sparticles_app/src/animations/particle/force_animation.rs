@@ -1,7 +1,7 @@
 use std::any::Any;
 
 use crate::{
-    model::{Clock, EmitterState, GfxState, LifeCycle},
+    model::{Clock, EmitterState, GfxState, LifeCycle, LifeCycleMode},
     shaders::ShaderOptions,
     traits::{HandleAction, ParticleAnimation, RegisterParticleAnimation},
     util::persistence::DynamicExport,
@@ -26,6 +26,7 @@ impl Default for ForceUniform {
                 from_sec: 0.,
                 until_sec: 5.,
                 lifetime_sec: 10.,
+                mode: LifeCycleMode::Loop,
             },
             velocity: [-15., -15., 0.].into(),
             mass_per_unit: 0.5,
@@ -136,19 +137,19 @@ impl ParticleAnimation for ForceAnimation {
     fn compute<'a>(
         &'a self,
         emitter: &'a EmitterState,
-        clock: &Clock,
+        _clock: &Clock,
         compute_pass: &mut wgpu::ComputePass<'a>,
     ) {
         if !self.should_animate {
             return;
         }
 
-        let nr = clock.get_bindgroup_nr();
+        let nr = emitter.bindgroup_nr();
 
         compute_pass.set_pipeline(&self.pipeline);
         compute_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
         compute_pass.set_bind_group(1, &self.bind_group, &[]);
-        compute_pass.dispatch_workgroups(emitter.dispatch_x_count, 1, 1);
+        emitter.dispatch(compute_pass);
     }
 
     fn recreate(&self, gfx_state: &GfxState, emitter: &EmitterState) -> Box<dyn ParticleAnimation> {
@@ -1,5 +1,5 @@
 use crate::model::clock::Clock;
-use crate::model::{EmitterState, GfxState, LifeCycle};
+use crate::model::{EmitterState, GfxState, LifeCycle, LifeCycleMode};
 use crate::shaders::ShaderOptions;
 use crate::traits::*;
 use crate::util::persistence::DynamicExport;
@@ -10,9 +10,33 @@ use serde::{Deserialize, Serialize};
 use std::any::Any;
 use wgpu::util::DeviceExt;
 
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum GravityMode {
+    /// Attracts particles to the current position using the mass and dead zone
+    #[default]
+    PointMass,
+    /// Same acceleration for every particle, e.g. falling particles
+    Constant { acceleration: Vec3 },
+}
+
+impl GravityMode {
+    pub const EARTH: Vec3 = Vec3::new(0., -9.81, 0.);
+}
+
+impl From<GravityMode> for f32 {
+    fn from(value: GravityMode) -> Self {
+        match value {
+            GravityMode::PointMass => 0.,
+            GravityMode::Constant { .. } => 1.,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct GravityUniform {
     pub life_cycle: LifeCycle,
+    #[serde(default)]
+    pub mode: GravityMode,
     pub gravitational_force: f32,
     pub dead_zone: f32,
     pub mass: f32,
@@ -29,6 +53,7 @@ impl Default for GravityUniform {
                 from_sec: 0.,
                 until_sec: 6.,
                 lifetime_sec: 12.,
+                mode: LifeCycleMode::Loop,
             },
             gravitational_force: 0.01,
             dead_zone: 4.,
@@ -37,6 +62,7 @@ impl Default for GravityUniform {
             current_pos: [-25., 8., 0.].into(),
             end_pos: [25., 8., 0.].into(),
             should_animate: false,
+            mode: GravityMode::PointMass,
         }
     }
 }
@@ -63,10 +89,25 @@ impl GravityUniform {
             end_pos: props.end_pos,
             current_pos: props.start_pos,
             should_animate: false,
+            mode: GravityMode::PointMass,
+        }
+    }
+
+    /// Constant gravity in a single direction
+    pub fn constant(life_cycle: LifeCycle, acceleration: Vec3) -> Self {
+        Self {
+            life_cycle,
+            mode: GravityMode::Constant { acceleration },
+            ..Default::default()
         }
     }
 
-    fn create_buffer_content(&self) -> [f32; 6] {
+    fn create_buffer_content(&self) -> [f32; 12] {
+        let acceleration = match self.mode {
+            GravityMode::Constant { acceleration } => acceleration,
+            GravityMode::PointMass => Vec3::ZERO,
+        };
+
         [
             self.gravitational_force,
             self.dead_zone,
@@ -74,6 +115,12 @@ impl GravityUniform {
             self.current_pos.x,
             self.current_pos.y,
             self.current_pos.z,
+            self.mode.into(),
+            acceleration.x,
+            acceleration.y,
+            acceleration.z,
+            0., // padding
+            0., // padding
         ]
     }
 }
@@ -150,19 +197,19 @@ impl ParticleAnimation for GravityAnimation {
     fn compute<'a>(
         &'a self,
         emitter: &'a EmitterState,
-        clock: &Clock,
+        _clock: &Clock,
         compute_pass: &mut wgpu::ComputePass<'a>,
     ) {
         if !self.uniform.should_animate {
             return;
         }
 
-        let nr = clock.get_bindgroup_nr();
+        let nr = emitter.bindgroup_nr();
 
         compute_pass.set_pipeline(&self.pipeline);
         compute_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
         compute_pass.set_bind_group(1, &self.bind_group, &[]);
-        compute_pass.dispatch_workgroups(emitter.dispatch_x_count, 1, 1);
+        emitter.dispatch(compute_pass);
     }
 
     fn as_any(&mut self) -> &mut dyn Any {
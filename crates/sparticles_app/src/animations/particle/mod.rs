@@ -1,11 +1,35 @@
 pub mod color_animation;
+pub mod curl_noise_animation;
+pub mod flipbook_animation;
+pub mod floor_collision_animation;
 pub mod force_animation;
 pub mod gravity_animation;
+pub mod size_over_life_animation;
+pub mod spring_animation;
 pub mod stray_animation;
+pub mod turbulence_animation;
+pub mod vortex_animation;
+pub mod wind_animation;
 
 pub use color_animation::{ColorAnimation, ColorUniform, RegisterColorAnimation};
+pub use curl_noise_animation::{CurlNoiseAnimation, CurlNoiseUniform, RegisterCurlNoiseAnimation};
+pub use flipbook_animation::{FlipbookAnimation, FlipbookUniform, RegisterFlipbookAnimation};
+pub use floor_collision_animation::{
+    FloorCollisionAnimation, FloorUniform, RegisterFloorCollisionAnimation,
+};
 pub use force_animation::{ForceAnimation, ForceUniform, RegisterForceAnimation};
 pub use gravity_animation::{
-    GravityAnimation, GravityUniform, GravityUniformOptions, RegisterGravityAnimation,
+    GravityAnimation, GravityMode, GravityUniform, GravityUniformOptions, RegisterGravityAnimation,
+};
+pub use size_over_life_animation::{
+    RegisterSizeOverLifeAnimation, SizeOverLifeAnimation, SizeOverLifeUniform,
+};
+pub use spring_animation::{
+    RegisterSpringAnimation, SpringAnimation, SpringExport, SpringTarget, SpringUniform,
 };
 pub use stray_animation::{RegisterStrayAnimation, StrayAnimation, StrayUniform};
+pub use turbulence_animation::{
+    RegisterTurbulenceAnimation, TurbulenceAnimation, TurbulenceUniform,
+};
+pub use vortex_animation::{RegisterVortexAnimation, VortexAnimation, VortexUniform};
+pub use wind_animation::{RegisterWindAnimation, WindAnimation, WindUniform};
@@ -0,0 +1,508 @@
+use crate::{
+    model::{Clock, EmitterState, GfxState, LifeCycle, LifeCycleMode},
+    shaders::ShaderOptions,
+    traits::{HandleAction, ParticleAnimation, RegisterParticleAnimation},
+    util::persistence::DynamicExport,
+    util::ListAction,
+};
+use egui_wgpu::wgpu::{self, util::DeviceExt};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::{any::Any, f32::consts::PI};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SpringTarget {
+    /// Every particle is pulled to its own target
+    #[default]
+    Shape,
+    /// Every particle is pulled to one point moving from the target start to end
+    Moving,
+}
+
+impl From<SpringTarget> for f32 {
+    fn from(value: SpringTarget) -> Self {
+        match value {
+            SpringTarget::Shape => 0.,
+            SpringTarget::Moving => 1.,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpringUniform {
+    pub stiffness: f32,
+    pub damping: f32,
+    /// Particle lifetime from which the spring is applied
+    pub from_sec: f32,
+    #[serde(default)]
+    pub target: SpringTarget,
+    /// Moves the target, only used by the moving target
+    #[serde(default = "default_life_cycle")]
+    pub life_cycle: LifeCycle,
+    #[serde(default)]
+    pub target_start: Vec3,
+    #[serde(default)]
+    pub target_end: Vec3,
+    #[serde(skip)]
+    pub current_target: Vec3,
+}
+
+fn default_life_cycle() -> LifeCycle {
+    LifeCycle {
+        from_sec: 0.,
+        until_sec: 6.,
+        lifetime_sec: 12.,
+        mode: LifeCycleMode::Loop,
+    }
+}
+
+impl Default for SpringUniform {
+    fn default() -> Self {
+        Self {
+            stiffness: 8.,
+            damping: 2.,
+            from_sec: 0.5,
+            target: SpringTarget::Shape,
+            life_cycle: default_life_cycle(),
+            target_start: [-15., 0., 0.].into(),
+            target_end: [15., 0., 0.].into(),
+            current_target: [-15., 0., 0.].into(),
+        }
+    }
+}
+
+impl SpringUniform {
+    /// Pulls every particle to a point moving from start to end, e.g. elastic trails
+    pub fn moving(life_cycle: LifeCycle, target_start: Vec3, target_end: Vec3) -> Self {
+        Self {
+            target: SpringTarget::Moving,
+            life_cycle,
+            target_start,
+            target_end,
+            current_target: target_start,
+            ..Default::default()
+        }
+    }
+
+    fn create_buffer_content(&self) -> [f32; 8] {
+        [
+            self.stiffness,
+            self.damping,
+            self.from_sec,
+            self.target.into(),
+            self.current_target.x,
+            self.current_target.y,
+            self.current_target.z,
+            0., // padding
+        ]
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpringExport {
+    pub uniform: SpringUniform,
+    pub targets: Vec<Vec3>,
+}
+
+#[derive(Clone, Copy)]
+pub struct RegisterSpringAnimation;
+
+impl RegisterSpringAnimation {
+    /// Will append animation to emitter, targets are reused if there are more particles
+    pub fn append(
+        uniform: SpringUniform,
+        targets: Vec<Vec3>,
+        emitter: &mut EmitterState,
+        gfx_state: &GfxState,
+    ) {
+        let anim = Box::new(SpringAnimation::new(uniform, targets, emitter, gfx_state));
+
+        emitter.push_particle_animation(anim);
+    }
+}
+
+impl RegisterParticleAnimation for RegisterSpringAnimation {
+    fn create_default(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+    ) -> Box<dyn ParticleAnimation> {
+        let targets = SpringAnimation::targets_sphere(emitter.particle_count() as usize, 5.);
+
+        Box::new(SpringAnimation::new(
+            SpringUniform::default(),
+            targets,
+            emitter,
+            gfx_state,
+        ))
+    }
+
+    fn tag(&self) -> &'static str {
+        "spring"
+    }
+
+    fn import(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+        value: serde_json::Value,
+    ) -> Box<dyn ParticleAnimation> {
+        let export: SpringExport = serde_json::from_value(value).unwrap();
+        Box::new(SpringAnimation::new(
+            export.uniform,
+            export.targets,
+            emitter,
+            gfx_state,
+        ))
+    }
+}
+
+pub struct SpringAnimation {
+    pub pipeline: wgpu::ComputePipeline,
+    pub uniform: SpringUniform,
+    pub buffer: wgpu::Buffer,
+    pub targets_buffer: wgpu::Buffer,
+    pub animation_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    pub targets: Vec<Vec3>,
+    pub particle_count: usize,
+    pub selected_action: ListAction,
+    pub update_uniform: bool,
+    pub update_targets: bool,
+    pub should_animate: bool,
+    pub enabled: bool,
+}
+
+impl HandleAction for SpringAnimation {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let animation = serde_json::to_value(SpringExport {
+            uniform: self.uniform,
+            targets: self.targets.clone(),
+        })
+        .unwrap();
+
+        DynamicExport {
+            tag: RegisterSpringAnimation.tag().to_owned(),
+            data: animation,
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl ParticleAnimation for SpringAnimation {
+    fn update(&mut self, clock: &Clock, gfx_state: &GfxState) {
+        let queue = &gfx_state.queue;
+        let uniform = &mut self.uniform;
+
+        if uniform.target == SpringTarget::Moving {
+            let life_cycle = &uniform.life_cycle;
+            let current_sec = life_cycle.get_current_sec(clock);
+
+            self.should_animate = life_cycle.shoud_animate(current_sec);
+
+            if self.should_animate {
+                let fraction = life_cycle.get_fraction(current_sec);
+                uniform.current_target = uniform.target_start.lerp(uniform.target_end, fraction);
+                self.update_uniform = true;
+            }
+        } else {
+            self.should_animate = true;
+        }
+
+        if self.update_uniform {
+            let buf_content = self.uniform.create_buffer_content();
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&buf_content));
+            self.update_uniform = false;
+        }
+
+        if self.update_targets {
+            let targets_content = Self::targets_content(&self.targets, self.particle_count);
+            queue.write_buffer(
+                &self.targets_buffer,
+                0,
+                bytemuck::cast_slice(&targets_content),
+            );
+            self.update_targets = false;
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn compute<'a>(
+        &'a self,
+        emitter: &'a EmitterState,
+        _clock: &Clock,
+        compute_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        if !self.should_animate {
+            return;
+        }
+
+        let nr = emitter.bindgroup_nr();
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
+        compute_pass.set_bind_group(1, &self.bind_group, &[]);
+        emitter.dispatch(compute_pass);
+    }
+
+    fn recreate(&self, gfx_state: &GfxState, emitter: &EmitterState) -> Box<dyn ParticleAnimation> {
+        Box::new(Self::new(
+            self.uniform,
+            self.targets.clone(),
+            emitter,
+            gfx_state,
+        ))
+    }
+
+    /// Every particle needs a target, otherwise the new particles don't get a spring force
+    fn resize(&mut self, gfx_state: &GfxState, emitter: &EmitterState) {
+        let particle_count = emitter.particle_count().max(1) as usize;
+
+        if particle_count == self.particle_count {
+            return;
+        }
+
+        let device = &gfx_state.device;
+
+        self.targets_buffer = Self::create_targets_buffer(device, &self.targets, particle_count);
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.animation_layout,
+            &self.buffer,
+            &self.targets_buffer,
+        );
+        self.particle_count = particle_count;
+        self.update_targets = false;
+    }
+}
+
+impl SpringAnimation {
+    /// Replaces the targets, will be uploaded on the next update
+    pub fn set_targets(&mut self, targets: Vec<Vec3>) {
+        self.targets = targets;
+        self.update_targets = true;
+    }
+
+    /// Targets on the XY plane from the dark pixels of an image, centered around the origin
+    pub fn targets_from_image(path: &str, max_count: usize, width: f32) -> Vec<Vec3> {
+        let img = match image::open(path) {
+            Ok(img) => img.to_luma8(),
+            Err(err) => {
+                println!("Can't open image {}: {}", path, err);
+                return vec![];
+            }
+        };
+
+        let (img_width, img_height) = img.dimensions();
+        let scale = width / img_width as f32;
+
+        let dark_pixels: Vec<Vec3> = img
+            .enumerate_pixels()
+            .filter(|(_, _, luma)| luma.0[0] < 128)
+            .map(|(x, y, _)| {
+                let pos_x = (x as f32 - img_width as f32 / 2.) * scale;
+                let pos_y = (img_height as f32 / 2. - y as f32) * scale;
+
+                Vec3::new(pos_x, pos_y, 0.)
+            })
+            .collect();
+
+        if dark_pixels.len() <= max_count {
+            return dark_pixels;
+        }
+
+        let step = dark_pixels.len() as f32 / max_count as f32;
+
+        (0..max_count)
+            .map(|i| dark_pixels[(i as f32 * step) as usize])
+            .collect()
+    }
+
+    /// Evenly distributed targets on a sphere surface
+    pub fn targets_sphere(count: usize, radius: f32) -> Vec<Vec3> {
+        let golden_angle = PI * (3. - 5f32.sqrt());
+
+        (0..count)
+            .map(|i| {
+                let y = 1. - (i as f32 + 0.5) / count as f32 * 2.;
+                let ring_radius = (1. - y * y).sqrt();
+                let theta = golden_angle * i as f32;
+
+                Vec3::new(theta.cos() * ring_radius, y, theta.sin() * ring_radius) * radius
+            })
+            .collect()
+    }
+
+    /// Repeats the targets if there are more particles than targets
+    fn targets_content(targets: &[Vec3], particle_count: usize) -> Vec<f32> {
+        // A storage binding can't be empty, emitters without particles still get one target
+        let particle_count = particle_count.max(1);
+
+        if targets.is_empty() {
+            return vec![0.; particle_count * 4];
+        }
+
+        targets
+            .iter()
+            .cycle()
+            .take(particle_count)
+            .flat_map(|target| [target.x, target.y, target.z, 1.])
+            .collect()
+    }
+
+    fn create_targets_buffer(
+        device: &wgpu::Device,
+        targets: &[Vec3],
+        particle_count: usize,
+    ) -> wgpu::Buffer {
+        let targets_content = Self::targets_content(targets, particle_count);
+
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Spring targets buffer"),
+            contents: bytemuck::cast_slice(&targets_content),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        animation_layout: &wgpu::BindGroupLayout,
+        buffer: &wgpu::Buffer,
+        targets_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: animation_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: targets_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Spring animation bind group"),
+        })
+    }
+
+    fn new(
+        uniform: SpringUniform,
+        targets: Vec<Vec3>,
+        emitter: &EmitterState,
+        gfx_state: &GfxState,
+    ) -> Self {
+        let device = &gfx_state.device;
+        let particle_count = emitter.particle_count().max(1) as usize;
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["spring.wgsl"],
+            label: "Spring animation",
+        });
+
+        let buffer_content = uniform.create_buffer_content();
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Spring buffer"),
+            contents: bytemuck::cast_slice(&buffer_content),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let targets_buffer = Self::create_targets_buffer(device, &targets, particle_count);
+
+        let animation_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                // Uniform data
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(buffer_content.len() as u64 * 4),
+                    },
+                    count: None,
+                },
+                // Targets, sized by the particle count so they can be replaced on a resize
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: None,
+        });
+
+        let bind_group =
+            Self::create_bind_group(device, &animation_layout, &buffer, &targets_buffer);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Spring animation layout"),
+            bind_group_layouts: &[&emitter.bg_layout, &animation_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Spring animation pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        Self {
+            pipeline,
+            uniform,
+            buffer,
+            targets_buffer,
+            animation_layout,
+            bind_group,
+            targets,
+            particle_count,
+            selected_action: ListAction::None,
+            update_uniform: false,
+            update_targets: false,
+            should_animate: false,
+            enabled: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_content_never_empty() {
+        assert_eq!(SpringAnimation::targets_content(&[], 0), vec![0.; 4]);
+        assert_eq!(
+            SpringAnimation::targets_content(&[Vec3::new(1., 2., 3.)], 0),
+            vec![1., 2., 3., 1.]
+        );
+    }
+
+    #[test]
+    fn targets_repeat_for_more_particles() {
+        let targets = [Vec3::X, Vec3::Y];
+
+        assert_eq!(
+            SpringAnimation::targets_content(&targets, 3),
+            vec![1., 0., 0., 1., 0., 1., 0., 1., 1., 0., 0., 1.]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{
+    model::{Clock, EmitterState, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, ParticleAnimation, RegisterParticleAnimation},
+    util::ListAction,
+    util::{persistence::DynamicExport, UniformContext},
+};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Texels per axis of the curl noise texture
+const NOISE_SIZE: u32 = 32;
+
+#[derive(ShaderType, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TurbulenceUniform {
+    /// Noise texture repeats per world unit
+    pub scale: f32,
+    pub strength: f32,
+    /// Moves the noise over time
+    pub scroll_speed: f32,
+}
+
+impl Default for TurbulenceUniform {
+    fn default() -> Self {
+        Self {
+            scale: 0.05,
+            strength: 10.,
+            scroll_speed: 0.02,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RegisterTurbulenceAnimation;
+
+impl RegisterTurbulenceAnimation {
+    /// Will append animation to emitter
+    pub fn append(uniform: TurbulenceUniform, emitter: &mut EmitterState, gfx_state: &GfxState) {
+        let anim = Box::new(TurbulenceAnimation::new(uniform, emitter, gfx_state));
+
+        emitter.push_particle_animation(anim);
+    }
+}
+
+impl RegisterParticleAnimation for RegisterTurbulenceAnimation {
+    fn create_default(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+    ) -> Box<dyn ParticleAnimation> {
+        Box::new(TurbulenceAnimation::new(
+            TurbulenceUniform::default(),
+            emitter,
+            gfx_state,
+        ))
+    }
+
+    fn tag(&self) -> &'static str {
+        "turbulence"
+    }
+
+    fn import(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+        value: serde_json::Value,
+    ) -> Box<dyn ParticleAnimation> {
+        let uniform = serde_json::from_value(value).unwrap();
+        Box::new(TurbulenceAnimation::new(uniform, emitter, gfx_state))
+    }
+}
+
+pub struct TurbulenceAnimation {
+    pub pipeline: wgpu::ComputePipeline,
+    pub uniform: TurbulenceUniform,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub noise_bg: wgpu::BindGroup,
+    pub selected_action: ListAction,
+    pub update_uniform: bool,
+    pub enabled: bool,
+}
+
+impl HandleAction for TurbulenceAnimation {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let animation = serde_json::to_value(self.uniform).unwrap();
+
+        DynamicExport {
+            tag: RegisterTurbulenceAnimation.tag().to_owned(),
+            data: animation,
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl ParticleAnimation for TurbulenceAnimation {
+    fn update(&mut self, _: &Clock, gfx_state: &GfxState) {
+        let queue = &gfx_state.queue;
+
+        if self.update_uniform {
+            let buf_content = self.uniform.buffer_content();
+            queue.write_buffer(&self.buffer, 0, &buf_content);
+            self.update_uniform = false;
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn compute<'a>(
+        &'a self,
+        emitter: &'a EmitterState,
+        _clock: &Clock,
+        compute_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let nr = emitter.bindgroup_nr();
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
+        compute_pass.set_bind_group(1, &self.bind_group, &[]);
+        compute_pass.set_bind_group(2, &self.noise_bg, &[]);
+        emitter.dispatch(compute_pass);
+    }
+
+    /// Noise texture is seeded, so regenerating it gives the same field
+    fn recreate(&self, gfx_state: &GfxState, emitter: &EmitterState) -> Box<dyn ParticleAnimation> {
+        Box::new(Self::new(self.uniform, emitter, gfx_state))
+    }
+}
+
+impl TurbulenceAnimation {
+    fn new(uniform: TurbulenceUniform, emitter: &EmitterState, gfx_state: &GfxState) -> Self {
+        let device = &gfx_state.device;
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["turbulence.wgsl"],
+            label: "Turbulence animation",
+        });
+
+        let turbulence_ctx = UniformContext::from_uniform(&uniform, device, "Turbulence uniform");
+
+        let noise_view = gfx_state.create_curl_noise_view(NOISE_SIZE);
+
+        let noise_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let noise_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Turbulence noise layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let noise_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Turbulence noise bind group"),
+            layout: &noise_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&noise_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&noise_sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Turbulence layout"),
+            bind_group_layouts: &[&emitter.bg_layout, &turbulence_ctx.bg_layout, &noise_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Turbulence animation pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        Self {
+            pipeline,
+            bind_group: turbulence_ctx.bg,
+            noise_bg,
+            uniform,
+            buffer: turbulence_ctx.buf,
+            update_uniform: false,
+            selected_action: ListAction::None,
+            enabled: true,
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::any::Any;
+
+use crate::{
+    model::{Clock, EmitterState, GfxState, LifeCycle, LifeCycleMode},
+    shaders::ShaderOptions,
+    traits::{HandleAction, ParticleAnimation, RegisterParticleAnimation},
+    util::persistence::DynamicExport,
+    util::ListAction,
+};
+use egui_wgpu::wgpu::{self, util::DeviceExt};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindUniform {
+    pub life_cycle: LifeCycle,
+    /// Normalized when written to the buffer
+    pub direction: Vec3,
+    /// Acceleration along the direction in units per second
+    pub strength: f32,
+    /// Random acceleration on top of the wind, changes every frame
+    pub turbulence: f32,
+}
+
+impl Default for WindUniform {
+    fn default() -> Self {
+        Self {
+            life_cycle: LifeCycle {
+                from_sec: 0.,
+                until_sec: 10.,
+                lifetime_sec: 10.,
+                mode: LifeCycleMode::Loop,
+            },
+            direction: Vec3::X,
+            strength: 5.,
+            turbulence: 2.,
+        }
+    }
+}
+
+impl WindUniform {
+    fn create_buffer_content(&self) -> [f32; 8] {
+        let direction = self.direction.normalize_or_zero();
+
+        [
+            direction.x,
+            direction.y,
+            direction.z,
+            self.strength,
+            self.turbulence,
+            0., // padding
+            0., // padding
+            0., // padding
+        ]
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RegisterWindAnimation;
+
+impl RegisterWindAnimation {
+    /// Will append animation to emitter
+    pub fn append(uniform: WindUniform, emitter: &mut EmitterState, gfx_state: &GfxState) {
+        let anim = Box::new(WindAnimation::new(uniform, emitter, gfx_state));
+
+        emitter.push_particle_animation(anim);
+    }
+}
+
+impl RegisterParticleAnimation for RegisterWindAnimation {
+    fn tag(&self) -> &'static str {
+        "wind"
+    }
+
+    fn create_default(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+    ) -> Box<dyn ParticleAnimation> {
+        Box::new(WindAnimation::new(
+            WindUniform::default(),
+            emitter,
+            gfx_state,
+        ))
+    }
+
+    fn import(
+        &self,
+        gfx_state: &GfxState,
+        emitter: &EmitterState,
+        value: serde_json::Value,
+    ) -> Box<dyn ParticleAnimation> {
+        let uniform = serde_json::from_value(value).unwrap();
+        Box::new(WindAnimation::new(uniform, emitter, gfx_state))
+    }
+}
+
+pub struct WindAnimation {
+    pub pipeline: wgpu::ComputePipeline,
+    pub uniform: WindUniform,
+    pub bind_group: wgpu::BindGroup,
+    pub buffer: wgpu::Buffer,
+    pub update_uniform: bool,
+    pub should_animate: bool,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+}
+
+impl HandleAction for WindAnimation {
+    fn export(&self) -> DynamicExport {
+        let animation = serde_json::to_value(self.uniform).unwrap();
+
+        DynamicExport {
+            tag: RegisterWindAnimation.tag().to_owned(),
+            data: animation,
+        }
+    }
+
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl ParticleAnimation for WindAnimation {
+    fn update(&mut self, clock: &Clock, gfx_state: &GfxState) {
+        let queue = &gfx_state.queue;
+        let uniform = &self.uniform;
+        let current_sec = uniform.life_cycle.get_current_sec(clock);
+        self.should_animate = uniform.life_cycle.shoud_animate(current_sec);
+
+        if self.update_uniform {
+            let buf_content_raw = self.uniform.create_buffer_content();
+            let buf_content = bytemuck::cast_slice(&buf_content_raw);
+            queue.write_buffer(&self.buffer, 0, buf_content);
+            self.update_uniform = false;
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn compute<'a>(
+        &'a self,
+        emitter: &'a EmitterState,
+        _clock: &Clock,
+        compute_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        if !self.should_animate {
+            return;
+        }
+
+        let nr = emitter.bindgroup_nr();
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
+        compute_pass.set_bind_group(1, &self.bind_group, &[]);
+        emitter.dispatch(compute_pass);
+    }
+
+    fn recreate(&self, gfx_state: &GfxState, emitter: &EmitterState) -> Box<dyn ParticleAnimation> {
+        Box::new(Self::new(self.uniform, emitter, gfx_state))
+    }
+}
+
+impl WindAnimation {
+    fn new(uniform: WindUniform, emitter: &EmitterState, gfx_state: &GfxState) -> Self {
+        let device = &gfx_state.device;
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["wind_anim.wgsl"],
+            label: "Wind animation",
+        });
+
+        let buffer_content = uniform.create_buffer_content();
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wind buffer"),
+            contents: bytemuck::cast_slice(&buffer_content),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let animation_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                // Uniform data
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(buffer_content.len() as u64 * 4),
+                    },
+                    count: None,
+                },
+            ],
+            label: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &animation_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("Wind animation bind group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Wind animation layout"),
+            bind_group_layouts: &[&emitter.bg_layout, &animation_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Wind animation pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        Self {
+            pipeline,
+            uniform,
+            bind_group,
+            buffer,
+            update_uniform: false,
+            should_animate: false,
+            selected_action: ListAction::None,
+            enabled: true,
+        }
+    }
+}
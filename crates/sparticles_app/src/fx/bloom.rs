@@ -13,6 +13,7 @@ use crate::fx::ColorFxSettings;
 use crate::fx::ColorFxUniform;
 use crate::model::gfx_state::Profiler;
 use crate::model::Camera;
+use crate::model::Clock;
 use crate::model::GfxState;
 use crate::traits::*;
 use crate::util::DynamicExport;
@@ -42,6 +43,8 @@ pub struct BloomFx {
     pub blend: BlendPass,
 
     pub bloom_treshold: f32,
+    /// Downscale and upscale passes, the chain is rebuilt when it differs from the passes
+    pub pass_count: usize,
 }
 
 pub struct DownscalePass {
@@ -111,6 +114,32 @@ impl PostFx for BloomFx {
         self
     }
 
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn reads_split_target(&self) -> bool {
+        true
+    }
+
+    fn rebuild(&mut self, options: &FxOptions) {
+        if self.pass_count == self.upscale_passes.len() {
+            return;
+        }
+
+        // Keeps the mix of the remaining passes, old pipelines are dropped with their passes
+        let blends: Vec<BlendUniform> = (0..self.pass_count)
+            .map(|i| match self.upscale_passes.get(i) {
+                Some(up) => up.blend_uniform,
+                None => BlendUniform { io_mix: 0.5 },
+            })
+            .collect();
+
+        let (downscale_passes, upscale_passes) = Self::create_passes(options, &blends);
+        self.downscale_passes = downscale_passes;
+        self.upscale_passes = upscale_passes;
+    }
+
     fn compute<'a>(
         &'a self,
         fx_state: &'a FxState,
@@ -136,7 +165,7 @@ impl PostFx for BloomFx {
         task::block_on(Profiler::end_scope(gfx, c_pass));
     }
 
-    fn update(&mut self, gfx_state: &GfxState, camera: &mut Camera) {
+    fn update(&mut self, gfx_state: &GfxState, camera: &mut Camera, clock: &Clock) {
         camera.bloom_treshold = glam::Vec3::splat(self.bloom_treshold);
 
         if let Some(UIAction::UpdateBuffer(i)) = self.update_event.take() {
@@ -151,7 +180,7 @@ impl PostFx for BloomFx {
             }
         };
 
-        self.color.update(gfx_state, camera);
+        self.color.update(gfx_state, camera, clock);
     }
 }
 
@@ -184,7 +213,13 @@ impl HandleAction for BloomFx {
 }
 
 impl BloomFx {
-    pub fn new(options: &FxOptions, settings: BloomSettings) -> Self {
+    /// Texture 1 up to the pass count are used, so it stays clear of the fxaa output
+    pub const MAX_PASSES: usize = 8;
+
+    fn create_passes(
+        options: &FxOptions,
+        upscale_blends: &[BlendUniform],
+    ) -> (Vec<DownscalePass>, Vec<UpscalePass>) {
         let FxOptions {
             gfx: gfx_state,
             fx_state,
@@ -195,7 +230,7 @@ impl BloomFx {
         let mut downscale_passes = Vec::new();
         let mut upscale_passes = Vec::new();
 
-        let downscale_count = settings.upscale_blends.len() as i32;
+        let downscale_count = upscale_blends.len() as i32;
         let downscale_list = FxIOUniform::create_downscale_list(
             &mut Vec::new(),
             &fx_state.tex_size,
@@ -206,9 +241,6 @@ impl BloomFx {
 
         let upscale_list = FxIOUniform::reverse_list(&downscale_list);
 
-        let blend_uniform = settings.final_blend;
-        let blend_ctx = UniformContext::from_uniform(&blend_uniform, device, "blend");
-
         for io_uniform in downscale_list {
             downscale_passes.push(DownscalePass {
                 downscale: Downscale::new(options, io_uniform),
@@ -216,7 +248,7 @@ impl BloomFx {
         }
 
         for (i, io_uniform) in upscale_list.into_iter().enumerate() {
-            let blend_uniform = settings.upscale_blends[i];
+            let blend_uniform = upscale_blends[i];
             let blend_ctx = UniformContext::from_uniform(&blend_uniform, device, "blend");
 
             upscale_passes.push(UpscalePass {
@@ -233,6 +265,21 @@ impl BloomFx {
             });
         }
 
+        (downscale_passes, upscale_passes)
+    }
+
+    pub fn new(options: &FxOptions, mut settings: BloomSettings) -> Self {
+        let device = &options.gfx.device;
+
+        settings.upscale_blends.truncate(Self::MAX_PASSES);
+
+        let pass_count = settings.upscale_blends.len();
+        let (downscale_passes, upscale_passes) =
+            Self::create_passes(options, &settings.upscale_blends);
+
+        let blend_uniform = settings.final_blend;
+        let blend_ctx = UniformContext::from_uniform(&blend_uniform, device, "blend");
+
         let color = ColorFx::new(
             options,
             ColorFxSettings {
@@ -261,6 +308,7 @@ impl BloomFx {
             update_event: None,
             selected_action: ListAction::None,
             bloom_treshold: settings.bloom_treshold,
+            pass_count,
         }
     }
 }
@@ -5,6 +5,7 @@ use super::blur_pass::BlurPassSettings;
 use super::FxOptions;
 use super::FxState;
 use crate::model::Camera;
+use crate::model::Clock;
 use crate::model::GfxState;
 use crate::traits::*;
 use crate::util::DynamicExport;
@@ -95,7 +96,7 @@ impl PostFx for BlurFx {
         self.blur_pass.resize(options);
     }
 
-    fn update(&mut self, gfx_state: &GfxState, _camera: &mut Camera) {
+    fn update(&mut self, gfx_state: &GfxState, _camera: &mut Camera, _: &Clock) {
         if self.update_uniform.take().is_some() {
             let queue = &gfx_state.queue;
             let buffer_content = self.blur_uniform.buffer_content();
@@ -107,6 +108,10 @@ impl PostFx for BlurFx {
         self
     }
 
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
     fn compute<'a>(
         &'a self,
         fx_state: &'a FxState,
@@ -102,7 +102,7 @@ impl BlurPass {
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Split layout"),
-            bind_group_layouts: &[&fx_state.bg_layout, &io_ctx.bg_layout, &blur_layout],
+            bind_group_layouts: &[&fx_state.bg_layout, &io_ctx.bg_layout, blur_layout],
             push_constant_ranges: &[],
         });
 
@@ -2,7 +2,7 @@ use std::{any::Any, sync::Arc};
 
 use super::{FxIOUniform, FxOptions, FxState};
 use crate::{
-    model::{gfx_state::Profiler, Camera, GfxState},
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
     shaders::{ShaderOptions, SDR_TONEMAPPING},
     traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
     util::{DynamicExport, ListAction, UniformContext},
@@ -65,6 +65,20 @@ pub struct ColorFxUniform {
     pub contrast: f32,
     pub brightness: f32,
     pub tonemap: u32,
+    /// HDR input value that is mapped to display white
+    #[serde(default = "default_white_point")]
+    pub white_point: f32,
+    /// Steepness of the tonemapping curve around middle grey, 1 is neutral
+    #[serde(default = "default_tonemap_contrast")]
+    pub tonemap_contrast: f32,
+}
+
+fn default_white_point() -> f32 {
+    11.2
+}
+
+fn default_tonemap_contrast() -> f32 {
+    1.
 }
 
 impl ColorFxUniform {
@@ -74,6 +88,8 @@ impl ColorFxUniform {
             contrast: 2.5,
             brightness: 0.3,
             tonemap: 0,
+            white_point: default_white_point(),
+            tonemap_contrast: default_tonemap_contrast(),
         }
     }
 
@@ -83,6 +99,8 @@ impl ColorFxUniform {
             contrast: 2.5,
             brightness: 0.3,
             tonemap: 0,
+            white_point: default_white_point(),
+            tonemap_contrast: default_tonemap_contrast(),
         }
     }
 }
@@ -109,7 +127,7 @@ impl PostFx for ColorFx {
         task::block_on(Profiler::end_scope(gfx, c_pass));
     }
 
-    fn update(&mut self, gfx_state: &GfxState, _: &mut Camera) {
+    fn update(&mut self, gfx_state: &GfxState, _: &mut Camera, _: &Clock) {
         if let Some(UpdateAction::UpdateBuffer) = self.update_event.take() {
             let queue = &gfx_state.queue;
             let color_content = self.color_uniform.buffer_content();
@@ -120,6 +138,10 @@ impl PostFx for ColorFx {
     fn as_any(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
 }
 
 impl HandleAction for ColorFx {
@@ -0,0 +1,276 @@
+use std::{any::Any, sync::Arc};
+
+use super::{
+    blur::BlurUniform,
+    blur_pass::{BlurPass, BlurPassSettings},
+    FxIOUniform, FxOptions, FxState,
+};
+use crate::{
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+
+/// Scratch textures, the blurred copy ends up in the first one
+const BLUR_IDX: (u32, u32) = (2, 3);
+
+pub enum DofEvent {
+    UpdateUniform,
+}
+
+/// Blends the frame with a blurred copy by the distance of each pixel to the focus plane.
+/// The blend factor only uses the pixel's own depth, so blurred far away pixels (like the sky)
+/// can't smear onto particles that are in focus
+pub struct DepthOfFieldFx {
+    pub dof_uniform: DofUniform,
+    pub dof_ctx: UniformContext,
+    pub io_uniform: FxIOUniform,
+    pub io_ctx: UniformContext,
+    pub copy_io_uniform: FxIOUniform,
+    pub copy_io_ctx: UniformContext,
+    pub blur_uniform: BlurUniform,
+    pub blur_ctx: UniformContext,
+    pub blur_pass: BlurPass,
+    pub copy_pipeline: wgpu::ComputePipeline,
+    pub pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+    pub update_event: Option<DofEvent>,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct DofUniform {
+    /// Linear distance from the camera that is fully sharp
+    pub focus_distance: f32,
+    /// Distance from the focus plane until the blur is at its maximum
+    pub focus_range: f32,
+    /// Gaussian blur radius in pixels
+    pub max_blur: f32,
+    #[serde(skip)]
+    pub near: f32,
+    #[serde(skip)]
+    pub far: f32,
+    #[serde(skip)]
+    pub blur_idx: u32,
+}
+
+impl Default for DofUniform {
+    fn default() -> Self {
+        Self {
+            focus_distance: 10.,
+            focus_range: 8.,
+            max_blur: 8.,
+            near: 0.,
+            far: 0.,
+            blur_idx: BLUR_IDX.0,
+        }
+    }
+}
+
+impl DofUniform {
+    fn blur_uniform(&self) -> BlurUniform {
+        let radius = self.max_blur.max(1.);
+
+        BlurUniform {
+            brightness_threshold: 0.,
+            radius: radius as i32,
+            sigma: radius / 2.,
+            intensity: 1.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct DofSettings {
+    pub dof_uniform: DofUniform,
+    pub io_uniform: FxIOUniform,
+}
+
+pub struct RegisterDofFx;
+
+impl RegisterPostFx for RegisterDofFx {
+    fn tag(&self) -> &'static str {
+        "dof"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = DofSettings {
+            dof_uniform: DofUniform::default(),
+            io_uniform: FxIOUniform::zero(options.fx_state),
+        };
+
+        Box::new(DepthOfFieldFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse depth of field Fx");
+
+        Box::new(DepthOfFieldFx::new(options, settings))
+    }
+}
+
+impl PostFx for DepthOfFieldFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+        self.copy_io_uniform.resize(&self.copy_io_ctx.buf, options);
+        self.blur_pass.resize(options);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "Depth of field Fx", c_pass));
+
+        c_pass.set_pipeline(&self.copy_pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.copy_io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.dof_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        self.blur_pass
+            .compute_gaussian(fx_state, gfx, &self.blur_ctx.bg, c_pass);
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.dof_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    fn update(&mut self, gfx_state: &GfxState, camera: &mut Camera, _: &Clock) {
+        let uniform = &mut self.dof_uniform;
+
+        if uniform.near != camera.near() || uniform.far != camera.far() {
+            uniform.near = camera.near();
+            uniform.far = camera.far();
+            self.update_event = Some(DofEvent::UpdateUniform);
+        }
+
+        if let Some(DofEvent::UpdateUniform) = self.update_event.take() {
+            let queue = &gfx_state.queue;
+
+            let content = self.dof_uniform.buffer_content();
+            queue.write_buffer(&self.dof_ctx.buf, 0, &content);
+
+            self.blur_uniform = self.dof_uniform.blur_uniform();
+            let content = self.blur_uniform.buffer_content();
+            queue.write_buffer(&self.blur_ctx.buf, 0, &content);
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for DepthOfFieldFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = DofSettings {
+            dof_uniform: self.dof_uniform,
+            io_uniform: self.io_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterDofFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap depth of field"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl DepthOfFieldFx {
+    pub fn new(options: &FxOptions, settings: DofSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let mut dof_uniform = settings.dof_uniform;
+        dof_uniform.blur_idx = BLUR_IDX.0;
+
+        let io_uniform = settings.io_uniform;
+        let copy_io_uniform =
+            FxIOUniform::asymetric_unscaled(fx_state, io_uniform.in_idx, BLUR_IDX.0);
+        let blur_uniform = dof_uniform.blur_uniform();
+
+        let io_ctx = UniformContext::from_uniform(&io_uniform, device, "IO");
+        let copy_io_ctx = UniformContext::from_uniform(&copy_io_uniform, device, "IO DoF copy");
+        let dof_ctx = UniformContext::from_uniform(&dof_uniform, device, "Depth of field");
+        let blur_ctx = UniformContext::from_uniform(&blur_uniform, device, "DoF blur");
+
+        let blur_pass = BlurPass::new(
+            options,
+            BlurPassSettings {
+                blur_layout: &blur_ctx.bg_layout,
+                io_idx: BLUR_IDX,
+                downscale: 1.,
+            },
+        );
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/dof.wgsl"],
+            label: "Depth of field",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth of field pipeline layout"),
+            bind_group_layouts: &[&fx_state.bg_layout, &io_ctx.bg_layout, &dof_ctx.bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        let new_pipeline = |entry_point: &str| -> wgpu::ComputePipeline {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Depth of field pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        let copy_pipeline = new_pipeline("copy_frame");
+        let pipeline = new_pipeline("cs_main");
+
+        Self {
+            dof_uniform,
+            dof_ctx,
+            io_uniform,
+            io_ctx,
+            copy_io_uniform,
+            copy_io_ctx,
+            blur_uniform,
+            blur_ctx,
+            blur_pass,
+            copy_pipeline,
+            pipeline,
+            selected_action: ListAction::None,
+            enabled: true,
+            update_event: None,
+        }
+    }
+}
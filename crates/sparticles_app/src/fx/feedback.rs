@@ -0,0 +1,200 @@
+use std::{any::Any, sync::Arc};
+
+use super::{FxHistory, FxIOUniform, FxOptions, FxState};
+use crate::{
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+
+pub enum FeedbackEvent {
+    UpdateUniform,
+}
+
+/// Blends the previous output of this fx into the current frame, producing echo trails.
+/// Place it last in the chain to feed back the finalized frame
+pub struct FeedbackFx {
+    pub feedback_uniform: FeedbackUniform,
+    pub feedback_ctx: UniformContext,
+    pub io_uniform: FxIOUniform,
+    pub io_ctx: UniformContext,
+    pub pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+    pub update_event: Option<FeedbackEvent>,
+    history: FxHistory,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct FeedbackUniform {
+    /// Fraction of the previous frame that remains, 0 disables the echo
+    pub decay: f32,
+    #[serde(skip)]
+    pub reset: u32,
+}
+
+impl Default for FeedbackUniform {
+    fn default() -> Self {
+        Self {
+            decay: 0.9,
+            reset: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct FeedbackSettings {
+    pub feedback_uniform: FeedbackUniform,
+    pub io_uniform: FxIOUniform,
+}
+
+pub struct RegisterFeedbackFx;
+
+impl RegisterPostFx for RegisterFeedbackFx {
+    fn tag(&self) -> &'static str {
+        "feedback"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = FeedbackSettings {
+            feedback_uniform: FeedbackUniform::default(),
+            io_uniform: FxIOUniform::zero(options.fx_state),
+        };
+
+        Box::new(FeedbackFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse feedback Fx");
+
+        Box::new(FeedbackFx::new(options, settings))
+    }
+}
+
+impl PostFx for FeedbackFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+        self.history.resize(options.gfx);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "Feedback Fx", c_pass));
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.feedback_ctx.bg, &[]);
+        c_pass.set_bind_group(3, &self.history.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    fn update(&mut self, gfx_state: &GfxState, _: &mut Camera, _: &Clock) {
+        if self.history.update_reset(&mut self.feedback_uniform.reset) {
+            self.update_event = Some(FeedbackEvent::UpdateUniform);
+        }
+
+        if let Some(FeedbackEvent::UpdateUniform) = self.update_event.take() {
+            let content = self.feedback_uniform.buffer_content();
+            gfx_state
+                .queue
+                .write_buffer(&self.feedback_ctx.buf, 0, &content);
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for FeedbackFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = FeedbackSettings {
+            feedback_uniform: self.feedback_uniform,
+            io_uniform: self.io_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterFeedbackFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap feedback"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl FeedbackFx {
+    pub fn new(options: &FxOptions, settings: FeedbackSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let io_ctx = UniformContext::from_uniform(&settings.io_uniform, device, "IO");
+        let feedback_ctx =
+            UniformContext::from_uniform(&settings.feedback_uniform, device, "Feedback");
+
+        let history = FxHistory::new(gfx_state, "Feedback");
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/feedback.wgsl"],
+            label: "Feedback",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Feedback pipeline layout"),
+            bind_group_layouts: &[
+                &fx_state.bg_layout,
+                &io_ctx.bg_layout,
+                &feedback_ctx.bg_layout,
+                &history.bg_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Feedback pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            feedback_uniform: settings.feedback_uniform,
+            feedback_ctx,
+            io_uniform: settings.io_uniform,
+            io_ctx,
+            pipeline,
+            selected_action: ListAction::None,
+            enabled: true,
+            update_event: None,
+            history,
+        }
+    }
+}
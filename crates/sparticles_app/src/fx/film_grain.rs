@@ -0,0 +1,184 @@
+use std::{any::Any, sync::Arc};
+
+use super::{FxIOUniform, FxOptions, FxState};
+use crate::{
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+
+/// Animated noise over the frame for a cinematic look
+pub struct FilmGrainFx {
+    pub grain_uniform: GrainUniform,
+    pub grain_ctx: UniformContext,
+    pub io_uniform: FxIOUniform,
+    pub io_ctx: UniformContext,
+    pub pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GrainUniform {
+    /// Brightness added and removed by the grain
+    pub intensity: f32,
+    /// Grain size in pixels, independent of the frame size
+    pub size: f32,
+    /// 1 gives every color channel its own noise, 0 is monochrome
+    pub colored: u32,
+    #[serde(skip)]
+    pub elapsed_sec: f32,
+}
+
+impl Default for GrainUniform {
+    fn default() -> Self {
+        Self {
+            intensity: 0.05,
+            size: 1.5,
+            colored: 0,
+            elapsed_sec: 0.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct GrainSettings {
+    pub grain_uniform: GrainUniform,
+    pub io_uniform: FxIOUniform,
+}
+
+pub struct RegisterFilmGrainFx;
+
+impl RegisterPostFx for RegisterFilmGrainFx {
+    fn tag(&self) -> &'static str {
+        "film_grain"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = GrainSettings {
+            grain_uniform: GrainUniform::default(),
+            io_uniform: FxIOUniform::zero(options.fx_state),
+        };
+
+        Box::new(FilmGrainFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse film grain Fx");
+
+        Box::new(FilmGrainFx::new(options, settings))
+    }
+}
+
+impl PostFx for FilmGrainFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "Film grain Fx", c_pass));
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.grain_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    /// The grain moves every frame, so the uniform is always written
+    fn update(&mut self, gfx_state: &GfxState, _camera: &mut Camera, clock: &Clock) {
+        self.grain_uniform.elapsed_sec = clock.elapsed_sec();
+
+        let content = self.grain_uniform.buffer_content();
+        gfx_state
+            .queue
+            .write_buffer(&self.grain_ctx.buf, 0, &content);
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for FilmGrainFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = GrainSettings {
+            grain_uniform: self.grain_uniform,
+            io_uniform: self.io_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterFilmGrainFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap film grain"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl FilmGrainFx {
+    pub fn new(options: &FxOptions, settings: GrainSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let io_ctx = UniformContext::from_uniform(&settings.io_uniform, device, "IO");
+        let grain_ctx = UniformContext::from_uniform(&settings.grain_uniform, device, "Film grain");
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["noise.wgsl", "fx/film_grain.wgsl"],
+            label: "Film grain",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Film grain pipeline layout"),
+            bind_group_layouts: &[&fx_state.bg_layout, &io_ctx.bg_layout, &grain_ctx.bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Film grain pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            grain_uniform: settings.grain_uniform,
+            grain_ctx,
+            io_uniform: settings.io_uniform,
+            io_ctx,
+            pipeline,
+            selected_action: ListAction::None,
+            enabled: true,
+        }
+    }
+}
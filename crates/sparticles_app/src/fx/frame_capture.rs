@@ -0,0 +1,170 @@
+use super::FxState;
+use crate::model::GfxState;
+use egui_wgpu::wgpu;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Copy of a post processed frame, saved as png with its alpha channel once the GPU is done
+pub struct FrameCapture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_row: u32,
+    format: wgpu::TextureFormat,
+    path: PathBuf,
+}
+
+impl FrameCapture {
+    /// Copies the fx texture at `tex_idx`, usually the one that is finalized
+    pub fn new(
+        gfx: &GfxState,
+        fx_state: &FxState,
+        tex_idx: u32,
+        path: &Path,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> Self {
+        let texture = fx_state.texture(tex_idx);
+        let width = texture.width();
+        let height = texture.height();
+        let format = fx_state.format;
+
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = (width * Self::bytes_per_pixel(format)).div_ceil(align) * align;
+
+        let buffer = gfx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame capture buffer"),
+            size: (padded_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_row,
+            format,
+            path: path.to_path_buf(),
+        }
+    }
+
+    fn bytes_per_pixel(format: wgpu::TextureFormat) -> u32 {
+        format.block_size(None).expect("Fx format has a block size")
+    }
+
+    /// Linear rgba of a pixel in one of the fx formats
+    fn read_pixel(format: wgpu::TextureFormat, px: &[u8]) -> [f32; 4] {
+        match format {
+            wgpu::TextureFormat::Rgba8Unorm => [0, 1, 2, 3].map(|i| px[i] as f32 / 255.),
+            wgpu::TextureFormat::Rgba32Float => [0, 1, 2, 3].map(|i| {
+                f32::from_le_bytes([px[i * 4], px[i * 4 + 1], px[i * 4 + 2], px[i * 4 + 3]])
+            }),
+            _ => [0, 1, 2, 3].map(|i| f16_to_f32(u16::from_le_bytes([px[i * 2], px[i * 2 + 1]]))),
+        }
+    }
+
+    /// Call after the copy is submitted, blocks until the buffer is mapped
+    pub fn save(self, gfx: &GfxState) {
+        let slice = self.buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |res| {
+            if let Err(err) = res {
+                println!("Can't map frame capture: {:?}", err);
+            }
+        });
+        gfx.device.poll(wgpu::Maintain::Wait);
+
+        let data = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((self.width * self.height * 4) as usize);
+
+        let bytes_per_pixel = Self::bytes_per_pixel(self.format);
+
+        for row in data.chunks(self.padded_row as usize) {
+            let row = &row[..(self.width * bytes_per_pixel) as usize];
+
+            for px in row.chunks(bytes_per_pixel as usize) {
+                let [r, g, b, a] = Self::read_pixel(self.format, px);
+                let rgb = [r, g, b].map(|c| c.max(0.));
+
+                // Additive light beyond the coverage (bloom) becomes coverage,
+                // that way glow survives in a straight alpha png
+                let alpha = a.max(rgb[0]).max(rgb[1]).max(rgb[2]).min(1.);
+
+                for c in rgb {
+                    let straight = if 0. < alpha { c / alpha } else { 0. };
+                    pixels.push((srgb_oetf(straight.min(1.)) * 255.).round() as u8);
+                }
+
+                pixels.push((alpha * 255.).round() as u8);
+            }
+        }
+
+        drop(data);
+        self.buffer.unmap();
+
+        let Some(img) = image::RgbaImage::from_raw(self.width, self.height, pixels) else {
+            println!("Frame capture has the wrong size");
+            return;
+        };
+
+        if let Some(dir) = self.path.parent() {
+            if let Err(err) = fs::create_dir_all(dir) {
+                println!("Can't create capture dir: {}", err);
+            }
+        }
+
+        match img.save(&self.path) {
+            Ok(_) => println!("Captured frame: {:?}", self.path),
+            Err(err) => println!("Can't save frame capture: {}", err),
+        }
+    }
+
+    /// Time stamped png in export/frames
+    pub fn default_path() -> PathBuf {
+        let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        dir.push("export/frames");
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+
+        dir.join(format!("frame_{}.png", millis))
+    }
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 == 0 { 1. } else { -1. };
+    let exp = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+
+    match exp {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0. => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1. + mantissa / 1024.) * 2f32.powi(exp - 15),
+    }
+}
+
+fn srgb_oetf(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use super::{FxIOUniform, FxOptions, FxState};
+use crate::{
+    model::{gfx_state::Profiler, GfxState},
+    shaders::ShaderOptions,
+    traits::BufferContent,
+    util::UniformContext,
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+
+/// Anti-aliases the viewed output before it's finalized, the last fx texture holds the result
+pub struct FxaaPass {
+    pipeline: wgpu::ComputePipeline,
+    io_uniform: FxIOUniform,
+    /// Also bound by the finalize pass, which reads the out idx
+    pub io_ctx: UniformContext,
+}
+
+impl FxaaPass {
+    pub const OUT_IDX: u32 = 15;
+
+    pub fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "FXAA", c_pass));
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    /// Follows the texture output that is selected for viewing
+    pub fn set_input(&mut self, gfx_state: &GfxState, in_idx: u32) {
+        self.io_uniform.in_idx = in_idx;
+
+        let contents = self.io_uniform.buffer_content();
+        gfx_state.queue.write_buffer(&self.io_ctx.buf, 0, &contents);
+    }
+
+    pub fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+    }
+
+    pub fn new(options: &FxOptions, in_idx: u32) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/fxaa.wgsl"],
+            label: "FXAA",
+        });
+
+        let io_uniform = FxIOUniform::asymetric_unscaled(fx_state, in_idx, Self::OUT_IDX);
+        let io_ctx = UniformContext::from_uniform(&io_uniform, device, "FXAA");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FXAA layout"),
+            bind_group_layouts: &[&fx_state.bg_layout, &io_ctx.bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("FXAA pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            pipeline,
+            io_ctx,
+            io_uniform,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::model::GfxState;
+use egui_wgpu::wgpu;
+
+/// Output of the previous frame, for fx that blend over time. Every invocation reads and
+/// writes only its own pixel, so one read write texture holds both the previous and next frame
+pub struct FxHistory {
+    pub bg_layout: wgpu::BindGroupLayout,
+    pub bg: wgpu::BindGroup,
+    label: &'static str,
+    /// Set until the history holds a frame, blending with it would fade in from black
+    is_empty: bool,
+}
+
+impl FxHistory {
+    pub fn new(gfx_state: &GfxState, label: &'static str) -> Self {
+        let device = &gfx_state.device;
+
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{} history layout", label)),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    format: gfx_state.fx_format(),
+                    access: wgpu::StorageTextureAccess::ReadWrite,
+                },
+                count: None,
+            }],
+        });
+
+        let bg = Self::create_bg(gfx_state, &bg_layout, label);
+
+        Self {
+            bg_layout,
+            bg,
+            label,
+            is_empty: true,
+        }
+    }
+
+    /// A new texture per resize, the old resolution would otherwise ghost into the new frame
+    pub fn resize(&mut self, gfx_state: &GfxState) {
+        self.bg = Self::create_bg(gfx_state, &self.bg_layout, self.label);
+        self.is_empty = true;
+    }
+
+    /// Sets the reset flag of the fx uniform for the first frame on an empty history, the
+    /// shader copies the current frame then. Returns true when the uniform changed
+    pub fn update_reset(&mut self, reset: &mut u32) -> bool {
+        let next = self.is_empty as u32;
+        let changed = *reset != next;
+
+        *reset = next;
+        self.is_empty = false;
+
+        changed
+    }
+
+    fn create_bg(
+        gfx_state: &GfxState,
+        layout: &wgpu::BindGroupLayout,
+        label: &str,
+    ) -> wgpu::BindGroup {
+        let history_view = gfx_state.create_fx_view();
+
+        gfx_state
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("{} history", label)),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&history_view),
+                }],
+            })
+    }
+}
@@ -0,0 +1,192 @@
+use std::{any::Any, sync::Arc};
+
+use super::{FxIOSwapCtx, FxIOUniform, FxOptions, FxState};
+use crate::{
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+
+pub enum KuwaharaEvent {
+    UpdateUniform,
+}
+
+/// Painterly filter, flattens regions into strokes while keeping edges sharp
+pub struct KuwaharaFx {
+    pub kuwahara_uniform: KuwaharaUniform,
+    pub kuwahara_ctx: UniformContext,
+    pub io_ctx: FxIOSwapCtx,
+    pub filter_pipeline: wgpu::ComputePipeline,
+    pub copy_pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+    pub update_event: Option<KuwaharaEvent>,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct KuwaharaUniform {
+    /// Quadrant size in pixels, cost grows quadratically
+    pub radius: i32,
+}
+
+impl Default for KuwaharaUniform {
+    fn default() -> Self {
+        Self { radius: 4 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct KuwaharaSettings {
+    pub kuwahara_uniform: KuwaharaUniform,
+}
+
+pub struct RegisterKuwaharaFx;
+
+impl RegisterPostFx for RegisterKuwaharaFx {
+    fn tag(&self) -> &'static str {
+        "kuwahara"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = KuwaharaSettings {
+            kuwahara_uniform: KuwaharaUniform::default(),
+        };
+
+        Box::new(KuwaharaFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse kuwahara Fx");
+
+        Box::new(KuwaharaFx::new(options, settings))
+    }
+}
+
+impl PostFx for KuwaharaFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_ctx.resize(options);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_ctx.uniforms[0]);
+
+        task::block_on(Profiler::begin_scope(gfx, "Kuwahara Fx", c_pass));
+
+        c_pass.set_pipeline(&self.filter_pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bgs[0], &[]);
+        c_pass.set_bind_group(2, &self.kuwahara_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        c_pass.set_pipeline(&self.copy_pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bgs[1], &[]);
+        c_pass.set_bind_group(2, &self.kuwahara_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    fn update(&mut self, gfx_state: &GfxState, _: &mut Camera, _: &Clock) {
+        if let Some(KuwaharaEvent::UpdateUniform) = self.update_event.take() {
+            let content = self.kuwahara_uniform.buffer_content();
+            gfx_state
+                .queue
+                .write_buffer(&self.kuwahara_ctx.buf, 0, &content);
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for KuwaharaFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = KuwaharaSettings {
+            kuwahara_uniform: self.kuwahara_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterKuwaharaFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap kuwahara"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl KuwaharaFx {
+    pub fn new(options: &FxOptions, settings: KuwaharaSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        // Filters into a scratch texture and copies back, a neighbourhood filter can't work in place
+        let io_ping = FxIOUniform::asymetric_unscaled(fx_state, 0, 2);
+        let io_pong = FxIOUniform::asymetric_unscaled(fx_state, 2, 0);
+        let io_ctx = FxIOSwapCtx::new([io_ping, io_pong], device, "IO Swap kuwahara");
+
+        let kuwahara_ctx =
+            UniformContext::from_uniform(&settings.kuwahara_uniform, device, "Kuwahara");
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/kuwahara.wgsl"],
+            label: "Kuwahara",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Kuwahara pipeline layout"),
+            bind_group_layouts: &[
+                &fx_state.bg_layout,
+                &io_ctx.bg_layout,
+                &kuwahara_ctx.bg_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let new_pipeline = |entry_point: &str| -> wgpu::ComputePipeline {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Kuwahara pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        Self {
+            kuwahara_uniform: settings.kuwahara_uniform,
+            kuwahara_ctx,
+            io_ctx,
+            filter_pipeline: new_pipeline("cs_main"),
+            copy_pipeline: new_pipeline("cs_copy"),
+            selected_action: ListAction::None,
+            enabled: true,
+            update_event: None,
+        }
+    }
+}
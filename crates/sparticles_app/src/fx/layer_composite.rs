@@ -0,0 +1,131 @@
+use crate::{model::GfxState, shaders::ShaderOptions};
+use egui_wgpu::wgpu;
+
+/// Draws the frames of layer 1 and higher over layer 0, in layer order
+pub struct LayerCompositePass {
+    bg_layout: wgpu::BindGroupLayout,
+    frame_pipeline: wgpu::RenderPipeline,
+    /// Also composites the split textures, for when the split target is rendered
+    split_pipeline: wgpu::RenderPipeline,
+}
+
+impl LayerCompositePass {
+    pub fn new(gfx: &GfxState) -> Self {
+        let device = &gfx.device;
+
+        let texture_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Layer composite layout"),
+            entries: &[texture_entry(0), texture_entry(1)],
+        });
+
+        let shader = gfx.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/layer_composite.wgsl"],
+            label: "Layer composite",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Layer composite pipeline layout"),
+            bind_group_layouts: &[&bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The layers hold premultiplied colors on a transparent clear
+        let frame_target = Some(wgpu::ColorTargetState {
+            format: gfx.fx_format(),
+            blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        });
+
+        let split_target = Some(wgpu::ColorTargetState {
+            format: gfx.fx_format(),
+            blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::COLOR,
+        });
+
+        let create_pipeline = |entry_point: &str, targets: &[Option<wgpu::ColorTargetState>]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Layer composite pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets,
+                }),
+                multiview: None,
+            })
+        };
+
+        let frame_pipeline = create_pipeline("fs_frame", std::slice::from_ref(&frame_target));
+        let split_pipeline = create_pipeline("fs_split", &[frame_target, split_target]);
+
+        Self {
+            bg_layout,
+            frame_pipeline,
+            split_pipeline,
+        }
+    }
+
+    pub fn create_bg(
+        &self,
+        gfx: &GfxState,
+        frame_view: &wgpu::TextureView,
+        split_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Layer composite bind group"),
+            layout: &self.bg_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(frame_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(split_view),
+                },
+            ],
+        })
+    }
+
+    /// Bind groups in layer order, the render pass targets the layer 0 textures
+    pub fn render<'a>(
+        &'a self,
+        layer_bgs: impl Iterator<Item = &'a wgpu::BindGroup>,
+        split_target: bool,
+        r_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        if split_target {
+            r_pass.set_pipeline(&self.split_pipeline);
+        } else {
+            r_pass.set_pipeline(&self.frame_pipeline);
+        }
+
+        for bg in layer_bgs {
+            r_pass.set_bind_group(0, bg, &[]);
+            r_pass.draw(0..3, 0..1);
+        }
+    }
+}
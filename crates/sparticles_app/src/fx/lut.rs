@@ -0,0 +1,295 @@
+use std::{any::Any, sync::Arc};
+
+use super::{FxIOUniform, FxOptions, FxState};
+use crate::{
+    loader::CubeLut,
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+pub enum LutEvent {
+    UpdateUniform,
+    /// Loads the lut path again, for when the path or the file changed
+    Reload,
+}
+
+/// Color grades the frame with a .cube lookup table from `assets/luts`
+pub struct LutGradingFx {
+    pub lut_uniform: LutUniform,
+    pub lut_ctx: UniformContext,
+    /// File in `assets/luts`, an empty or missing file grades with an identity lut
+    pub lut_path: String,
+    pub io_uniform: FxIOUniform,
+    pub io_ctx: UniformContext,
+    pub pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+    pub update_event: Option<LutEvent>,
+
+    lut_layout: wgpu::BindGroupLayout,
+    lut_bg: wgpu::BindGroup,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LutUniform {
+    /// Blend between the original (0) and graded (1) colors
+    pub strength: f32,
+    #[serde(skip)]
+    pub domain_min: Vec3,
+    #[serde(skip)]
+    pub domain_max: Vec3,
+}
+
+impl Default for LutUniform {
+    fn default() -> Self {
+        Self {
+            strength: 1.,
+            domain_min: Vec3::ZERO,
+            domain_max: Vec3::ONE,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LutSettings {
+    pub lut_uniform: LutUniform,
+    pub lut_path: String,
+    pub io_uniform: FxIOUniform,
+}
+
+pub struct RegisterLutFx;
+
+impl RegisterPostFx for RegisterLutFx {
+    fn tag(&self) -> &'static str {
+        "lut"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = LutSettings {
+            lut_uniform: LutUniform::default(),
+            lut_path: String::new(),
+            io_uniform: FxIOUniform::zero(options.fx_state),
+        };
+
+        Box::new(LutGradingFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse lut Fx");
+
+        Box::new(LutGradingFx::new(options, settings))
+    }
+}
+
+impl PostFx for LutGradingFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "LUT Fx", c_pass));
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.lut_ctx.bg, &[]);
+        c_pass.set_bind_group(3, &self.lut_bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    fn update(&mut self, gfx_state: &GfxState, _camera: &mut Camera, _: &Clock) {
+        match self.update_event.take() {
+            Some(LutEvent::Reload) => {
+                let lut = Self::load_lut(&self.lut_path);
+                self.lut_uniform.domain_min = lut.domain_min;
+                self.lut_uniform.domain_max = lut.domain_max;
+                self.lut_bg = Self::create_lut_bg(gfx_state, &self.lut_layout, &lut);
+            }
+            Some(LutEvent::UpdateUniform) => {}
+            None => return,
+        }
+
+        let content = self.lut_uniform.buffer_content();
+        gfx_state.queue.write_buffer(&self.lut_ctx.buf, 0, &content);
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for LutGradingFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = LutSettings {
+            lut_uniform: self.lut_uniform,
+            lut_path: self.lut_path.clone(),
+            io_uniform: self.io_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterLutFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap lut"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl LutGradingFx {
+    fn load_lut(lut_path: &str) -> CubeLut {
+        if lut_path.is_empty() {
+            return CubeLut::identity(2);
+        }
+
+        match CubeLut::load(lut_path) {
+            Ok(lut) => lut,
+            Err(err) => {
+                println!("Can't load lut {}, using identity: {}", lut_path, err);
+                CubeLut::identity(2)
+            }
+        }
+    }
+
+    fn create_lut_bg(
+        gfx_state: &GfxState,
+        lut_layout: &wgpu::BindGroupLayout,
+        lut: &CubeLut,
+    ) -> wgpu::BindGroup {
+        let device = &gfx_state.device;
+        let lut_view = gfx_state.create_lut_view(lut);
+
+        let lut_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("LUT bind group"),
+            layout: lut_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&lut_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&lut_sampler),
+                },
+            ],
+        })
+    }
+
+    pub fn new(options: &FxOptions, settings: LutSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let LutSettings {
+            mut lut_uniform,
+            lut_path,
+            io_uniform,
+        } = settings;
+
+        let lut = Self::load_lut(&lut_path);
+        lut_uniform.domain_min = lut.domain_min;
+        lut_uniform.domain_max = lut.domain_max;
+
+        let io_ctx = UniformContext::from_uniform(&io_uniform, device, "IO");
+        let lut_ctx = UniformContext::from_uniform(&lut_uniform, device, "LUT");
+
+        let lut_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("LUT layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let lut_bg = Self::create_lut_bg(gfx_state, &lut_layout, &lut);
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/lut.wgsl"],
+            label: "LUT",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("LUT pipeline layout"),
+            bind_group_layouts: &[
+                &fx_state.bg_layout,
+                &io_ctx.bg_layout,
+                &lut_ctx.bg_layout,
+                &lut_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("LUT pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            lut_uniform,
+            lut_ctx,
+            lut_path,
+            io_uniform,
+            io_ctx,
+            pipeline,
+            selected_action: ListAction::None,
+            enabled: true,
+            update_event: None,
+            lut_layout,
+            lut_bg,
+        }
+    }
+}
@@ -3,13 +3,39 @@ pub mod bloom;
 pub mod blur;
 pub mod blur_pass;
 pub mod color;
+pub mod dof;
 pub mod downscale;
+pub mod feedback;
+pub mod film_grain;
+pub mod frame_capture;
 pub mod fx_io;
+pub mod fxaa;
+pub mod history;
+pub mod kuwahara;
+pub mod layer_composite;
+pub mod lut;
+pub mod motion_blur;
+pub mod outline;
 pub mod post_process;
+pub mod radial_blur;
+pub mod vignette;
 
 pub use blend::BlendPass;
 pub use bloom::BloomFx;
 pub use color::{ColorFx, ColorFxSettings, ColorFxUniform, RegisterColorFx};
+pub use dof::{DepthOfFieldFx, RegisterDofFx};
 pub use downscale::Downscale;
+pub use feedback::{FeedbackFx, RegisterFeedbackFx};
+pub use film_grain::{FilmGrainFx, RegisterFilmGrainFx};
+pub use frame_capture::FrameCapture;
 pub use fx_io::{FxIO, FxIOSwapCtx, FxIOUniform, FxIOUniformOptions, FxOptions};
-pub use post_process::{FxState, PostProcessState};
+pub use fxaa::FxaaPass;
+pub use history::FxHistory;
+pub use kuwahara::{KuwaharaFx, RegisterKuwaharaFx};
+pub use layer_composite::LayerCompositePass;
+pub use lut::{LutGradingFx, RegisterLutFx};
+pub use motion_blur::{MotionBlurFx, RegisterMotionBlurFx};
+pub use outline::{OutlineFx, RegisterOutlineFx};
+pub use post_process::{FxState, LayerTextures, PostProcessState};
+pub use radial_blur::{RadialBlurFx, RegisterRadialBlurFx};
+pub use vignette::{RegisterVignetteFx, VignetteFx};
@@ -0,0 +1,200 @@
+use std::{any::Any, sync::Arc};
+
+use super::{FxHistory, FxIOUniform, FxOptions, FxState};
+use crate::{
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+
+pub enum MotionBlurEvent {
+    UpdateUniform,
+}
+
+/// Accumulates the frames of this fx, smearing fast particles along their path.
+/// Like feedback, but the blend is capped below 1 so trails stay short
+pub struct MotionBlurFx {
+    pub motion_uniform: MotionBlurUniform,
+    pub motion_ctx: UniformContext,
+    pub io_uniform: FxIOUniform,
+    pub io_ctx: UniformContext,
+    pub pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+    pub update_event: Option<MotionBlurEvent>,
+    history: FxHistory,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MotionBlurUniform {
+    /// Weight of the history, 0 disables the blur
+    pub blend: f32,
+    #[serde(skip)]
+    pub reset: u32,
+}
+
+impl Default for MotionBlurUniform {
+    fn default() -> Self {
+        Self {
+            blend: 0.5,
+            reset: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct MotionBlurSettings {
+    pub motion_uniform: MotionBlurUniform,
+    pub io_uniform: FxIOUniform,
+}
+
+pub struct RegisterMotionBlurFx;
+
+impl RegisterPostFx for RegisterMotionBlurFx {
+    fn tag(&self) -> &'static str {
+        "motion_blur"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = MotionBlurSettings {
+            motion_uniform: MotionBlurUniform::default(),
+            io_uniform: FxIOUniform::zero(options.fx_state),
+        };
+
+        Box::new(MotionBlurFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse motion blur Fx");
+
+        Box::new(MotionBlurFx::new(options, settings))
+    }
+}
+
+impl PostFx for MotionBlurFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+        self.history.resize(options.gfx);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "Motion blur Fx", c_pass));
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.motion_ctx.bg, &[]);
+        c_pass.set_bind_group(3, &self.history.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    fn update(&mut self, gfx_state: &GfxState, _: &mut Camera, _: &Clock) {
+        if self.history.update_reset(&mut self.motion_uniform.reset) {
+            self.update_event = Some(MotionBlurEvent::UpdateUniform);
+        }
+
+        if let Some(MotionBlurEvent::UpdateUniform) = self.update_event.take() {
+            let content = self.motion_uniform.buffer_content();
+            gfx_state
+                .queue
+                .write_buffer(&self.motion_ctx.buf, 0, &content);
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for MotionBlurFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = MotionBlurSettings {
+            motion_uniform: self.motion_uniform,
+            io_uniform: self.io_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterMotionBlurFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap motion blur"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl MotionBlurFx {
+    pub fn new(options: &FxOptions, settings: MotionBlurSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let io_ctx = UniformContext::from_uniform(&settings.io_uniform, device, "IO");
+        let motion_ctx =
+            UniformContext::from_uniform(&settings.motion_uniform, device, "Motion blur");
+
+        let history = FxHistory::new(gfx_state, "Motion blur");
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/motion_blur.wgsl"],
+            label: "Motion blur",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Motion blur pipeline layout"),
+            bind_group_layouts: &[
+                &fx_state.bg_layout,
+                &io_ctx.bg_layout,
+                &motion_ctx.bg_layout,
+                &history.bg_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Motion blur pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            motion_uniform: settings.motion_uniform,
+            motion_ctx,
+            io_uniform: settings.io_uniform,
+            io_ctx,
+            pipeline,
+            selected_action: ListAction::None,
+            enabled: true,
+            update_event: None,
+            history,
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::{any::Any, sync::Arc};
+
+use super::{FxIOUniform, FxOptions, FxState};
+use crate::{
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use glam::Vec4;
+use serde::{Deserialize, Serialize};
+
+pub enum OutlineEvent {
+    UpdateUniform,
+}
+
+/// Draws outlines on depth and normal discontinuities for a toon or technical look.
+/// Pairs well with pixelation and the color grading of the color fx
+pub struct OutlineFx {
+    pub outline_uniform: OutlineUniform,
+    pub outline_ctx: UniformContext,
+    pub io_uniform: FxIOUniform,
+    pub io_ctx: UniformContext,
+    pub pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+    pub update_event: Option<OutlineEvent>,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct OutlineUniform {
+    /// Alpha is the outline opacity
+    pub color: Vec4,
+    /// Distance in pixels to the compared neighbours
+    pub thickness: f32,
+    /// Relative depth difference that counts as an edge
+    pub depth_threshold: f32,
+    /// Normals are reconstructed from depth, the second render target holds the bloom split
+    pub normal_threshold: f32,
+    #[serde(skip)]
+    pub near: f32,
+    #[serde(skip)]
+    pub far: f32,
+}
+
+impl Default for OutlineUniform {
+    fn default() -> Self {
+        Self {
+            color: Vec4::new(0., 0., 0., 1.),
+            thickness: 1.,
+            depth_threshold: 0.1,
+            normal_threshold: 0.05,
+            near: 0.,
+            far: 0.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct OutlineSettings {
+    pub outline_uniform: OutlineUniform,
+    pub io_uniform: FxIOUniform,
+}
+
+pub struct RegisterOutlineFx;
+
+impl RegisterPostFx for RegisterOutlineFx {
+    fn tag(&self) -> &'static str {
+        "outline"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = OutlineSettings {
+            outline_uniform: OutlineUniform::default(),
+            io_uniform: FxIOUniform::zero(options.fx_state),
+        };
+
+        Box::new(OutlineFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse outline Fx");
+
+        Box::new(OutlineFx::new(options, settings))
+    }
+}
+
+impl PostFx for OutlineFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "Outline Fx", c_pass));
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.outline_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    fn update(&mut self, gfx_state: &GfxState, camera: &mut Camera, _: &Clock) {
+        let uniform = &mut self.outline_uniform;
+
+        if uniform.near != camera.near() || uniform.far != camera.far() {
+            uniform.near = camera.near();
+            uniform.far = camera.far();
+            self.update_event = Some(OutlineEvent::UpdateUniform);
+        }
+
+        if let Some(OutlineEvent::UpdateUniform) = self.update_event.take() {
+            let content = self.outline_uniform.buffer_content();
+            gfx_state
+                .queue
+                .write_buffer(&self.outline_ctx.buf, 0, &content);
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for OutlineFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = OutlineSettings {
+            outline_uniform: self.outline_uniform,
+            io_uniform: self.io_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterOutlineFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap outline"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl OutlineFx {
+    pub fn new(options: &FxOptions, settings: OutlineSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let io_ctx = UniformContext::from_uniform(&settings.io_uniform, device, "IO");
+        let outline_ctx =
+            UniformContext::from_uniform(&settings.outline_uniform, device, "Outline");
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/outline.wgsl"],
+            label: "Outline",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Outline pipeline layout"),
+            bind_group_layouts: &[
+                &fx_state.bg_layout,
+                &io_ctx.bg_layout,
+                &outline_ctx.bg_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Outline pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            outline_uniform: settings.outline_uniform,
+            outline_ctx,
+            io_uniform: settings.io_uniform,
+            io_ctx,
+            pipeline,
+            selected_action: ListAction::None,
+            enabled: true,
+            update_event: None,
+        }
+    }
+}
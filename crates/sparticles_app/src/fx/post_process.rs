@@ -1,33 +1,79 @@
-use super::{FxIOUniform, FxOptions};
+use super::{FrameCapture, FxIOUniform, FxOptions, FxaaPass, LayerCompositePass};
 use crate::init::AppVisitor;
 use crate::model::events::ViewIOEvent;
 use crate::model::gfx_state::Profiler;
 use crate::model::{GfxState, SparEvents, SparState};
-use crate::shaders::{ShaderOptions, SDR_TONEMAPPING};
+use crate::shaders::{ShaderOptions, DIR_PREMULTIPLIED_ALPHA, DIR_SRGB_OETF, SDR_TONEMAPPING};
 use crate::traits::*;
-use crate::util::{DynamicExport, ExportType, ListAction, Persistence, UniformContext};
+use crate::util::{ExportPostFx, ExportType, ListAction, Persistence, UniformContext};
 use async_std::sync::RwLock;
 use egui_wgpu::wgpu;
 use egui_winit::egui::ClippedPrimitive;
 use glam::Vec2;
 use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// Render targets for emitters that are not on the default layer. They are cleared transparent
+/// and composited over layer 0 before the post fx
+pub struct LayerTextures {
+    pub frame_view: wgpu::TextureView,
+    pub split_view: wgpu::TextureView,
+    pub depth_view: wgpu::TextureView,
+    composite_bg: wgpu::BindGroup,
+}
+
+impl LayerTextures {
+    fn new(gfx_state: &GfxState, composite: &LayerCompositePass) -> Self {
+        let frame_view = gfx_state.create_fx_view();
+        let split_view = gfx_state.create_fx_view();
+
+        Self {
+            composite_bg: composite.create_bg(gfx_state, &frame_view, &split_view),
+            frame_view,
+            split_view,
+            depth_view: gfx_state.create_depth_view(),
+        }
+    }
+}
+
 pub struct PostProcessState {
     pub effects: Vec<Box<dyn PostFx>>,
     pub fx_state: FxState,
 
+    /// Layer 1 and higher, layer 0 renders into the fx state
+    layers: Vec<LayerTextures>,
+    layer_composite: LayerCompositePass,
+
     render_pipeline: wgpu::RenderPipeline,
 
     pub io_uniform: FxIOUniform,
     pub io_ctx: UniformContext,
+
+    fxaa: FxaaPass,
+    /// The finalize reads the FXAA output instead of the viewed output
+    fxaa_enabled: bool,
+
+    /// Png path of the capture that is made at the end of the next render
+    capture_path: Option<PathBuf>,
+    capture: Option<FrameCapture>,
 }
 
 impl PostProcessState {
-    pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    /// HDR, so bright particle cores survive until tonemapping
+    pub const DEFAULT_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    /// Used when the adapter can't read and write the chosen format as storage texture
+    pub const FALLBACK_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        self.fx_state.format
+    }
 
     pub fn resize(&mut self, gfx_state: &GfxState) {
         self.fx_state = FxState::new(gfx_state);
+        self.layers = (0..self.layers.len())
+            .map(|_| LayerTextures::new(gfx_state, &self.layer_composite))
+            .collect();
 
         let options = FxOptions {
             fx_state: &self.fx_state,
@@ -35,6 +81,7 @@ impl PostProcessState {
         };
 
         self.io_uniform.resize(&self.io_ctx.buf, &options);
+        self.fxaa.resize(&options);
 
         for fx in self.effects.iter_mut() {
             fx.resize(&options);
@@ -46,11 +93,27 @@ impl PostProcessState {
             post_process: pp,
             gfx,
             camera,
+            clock,
+            emitters,
             ..
         } = state;
 
         let gfx = &gfx.read().await;
 
+        let max_layer = emitters.iter().map(|em| em.uniform.layer).max();
+        let layer_count = max_layer.unwrap_or(0) as usize;
+
+        // Follows the highest used layer, unused layers don't cost a render and composite pass
+        pp.layers.truncate(layer_count);
+
+        while pp.layers.len() < layer_count {
+            pp.layers.push(LayerTextures::new(gfx, &pp.layer_composite));
+        }
+
+        if events.capture_frame {
+            pp.capture_frame(&FrameCapture::default_path());
+        }
+
         if let Some(event) = &events.io_view {
             let io_uniform = &mut pp.io_uniform;
 
@@ -72,15 +135,44 @@ impl PostProcessState {
 
             let contents = pp.io_uniform.buffer_content();
             gfx.queue.write_buffer(&pp.io_ctx.buf, 0, &contents);
+
+            pp.fxaa.set_input(gfx, pp.io_uniform.out_idx);
         }
 
-        let effects = &mut pp.effects;
+        let options = FxOptions {
+            fx_state: &pp.fx_state,
+            gfx,
+        };
 
-        for fx in effects.iter_mut() {
-            fx.update(gfx, camera);
+        for fx in pp.effects.iter_mut() {
+            fx.update(gfx, camera, clock);
+            fx.rebuild(&options);
         }
 
-        ListAction::update_list(effects);
+        pp.apply_list_actions();
+    }
+
+    /// Moves or removes the effects for the list actions selected in the gui
+    fn apply_list_actions(&mut self) {
+        let mut i = 0;
+
+        while i < self.effects.len() {
+            match std::mem::take(self.effects[i].selected_action()) {
+                ListAction::Delete => {
+                    self.remove_fx(i);
+                    continue;
+                }
+                ListAction::MoveUp => self.move_fx_up(i),
+                ListAction::MoveDown => {
+                    self.move_fx_down(i);
+                    // Skip the moved fx
+                    i += 1;
+                }
+                action => *self.effects[i].selected_action() = action,
+            }
+
+            i += 1;
+        }
     }
 
     pub fn frame_view(&self) -> &wgpu::TextureView {
@@ -95,7 +187,110 @@ impl PostProcessState {
         &self.fx_state.depth_view
     }
 
-    pub async fn compute(state: &mut SparState, encoder: &mut wgpu::CommandEncoder) {
+    /// Textures of layer 1 and higher, use frame_view for layer 0
+    pub fn layer(&self, layer: u8) -> Option<&LayerTextures> {
+        if layer == 0 {
+            return None;
+        }
+
+        self.layers.get(layer as usize - 1)
+    }
+
+    /// The split texture is also rendered while it's the viewed output
+    pub fn uses_split_target(&self) -> bool {
+        self.io_uniform.out_idx == 1
+            || self
+                .effects
+                .iter()
+                .any(|fx| fx.enabled() && fx.reads_split_target())
+    }
+
+    pub fn fxaa_enabled(&self) -> bool {
+        self.fxaa_enabled
+    }
+
+    /// Smooths the edges of the final output, alpha to coverage still shimmers without it
+    pub fn set_fxaa(&mut self, enabled: bool) {
+        self.fxaa_enabled = enabled;
+    }
+
+    /// Texture index that is finalized to the screen
+    fn finalized_idx(&self) -> u32 {
+        if self.fxaa_enabled {
+            FxaaPass::OUT_IDX
+        } else {
+            self.io_uniform.out_idx
+        }
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len() + 1
+    }
+
+    /// Draws layer 1 and higher over layer 0 in order, so the post fx and finalize see every layer.
+    /// Layers that are rendered after the post fx stage show up in the next frame, layers without
+    /// a render stage are skipped because they still hold an old frame
+    async fn composite_layers(
+        state: &SparState,
+        encoder: &mut wgpu::CommandEncoder,
+        rendered_layers: &[u8],
+    ) {
+        let gfx = &state.gfx;
+        let pp = &state.post_process;
+
+        let layer_bgs: Vec<&wgpu::BindGroup> = pp
+            .layers
+            .iter()
+            .zip(1u8..)
+            .filter(|(_, layer)| rendered_layers.contains(layer))
+            .map(|(tex, _)| &tex.composite_bg)
+            .collect();
+
+        if layer_bgs.is_empty() {
+            return;
+        }
+
+        let split_target = pp.uses_split_target();
+
+        let attachment = |view| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        };
+
+        let mut color_attachments = vec![attachment(pp.frame_view())];
+
+        if split_target {
+            color_attachments.push(attachment(pp.split_view()));
+        }
+
+        let mut r_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Layer composite"),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        Profiler::begin_scope(gfx, "Composite layers", &mut r_pass).await;
+        pp.layer_composite
+            .render(layer_bgs.into_iter(), split_target, &mut r_pass);
+        Profiler::end_scope(gfx, &mut r_pass).await;
+    }
+
+    /// Composites the `rendered_layers` and runs the post fx
+    pub async fn compute(
+        state: &mut SparState,
+        encoder: &mut wgpu::CommandEncoder,
+        rendered_layers: &[u8],
+    ) {
+        Self::composite_layers(state, encoder, rendered_layers).await;
+
         let gfx = &state.gfx;
         let pp = &mut state.post_process;
         let fx_state = &mut pp.fx_state;
@@ -111,6 +306,10 @@ impl PostProcessState {
             fx.compute(fx_state, gfx, &mut c_pass);
         }
 
+        if pp.fxaa_enabled {
+            pp.fxaa.compute(fx_state, gfx, &mut c_pass);
+        }
+
         Profiler::end_scope(gfx, &mut c_pass).await;
     }
 
@@ -121,6 +320,7 @@ impl PostProcessState {
         primitives: &[ClippedPrimitive],
     ) {
         let gfx = &state.gfx;
+        let clear_color = gfx.read().await.clear_color();
 
         let mut r_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Post process render"),
@@ -128,7 +328,7 @@ impl PostProcessState {
                 view: &output_view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    load: wgpu::LoadOp::Clear(clear_color),
                     store: wgpu::StoreOp::Store,
                 },
             })],
@@ -139,22 +339,73 @@ impl PostProcessState {
 
         let pp = &mut state.post_process;
 
+        let io_bg = if pp.fxaa_enabled {
+            &pp.fxaa.io_ctx.bg
+        } else {
+            &pp.io_ctx.bg
+        };
+
         Profiler::begin_scope(gfx, "Post fx render", &mut r_pass).await;
         r_pass.set_pipeline(&pp.render_pipeline);
         r_pass.set_bind_group(0, &pp.fx_state.r_bg, &[]);
-        r_pass.set_bind_group(1, &pp.io_ctx.bg, &[]);
+        r_pass.set_bind_group(1, io_bg, &[]);
         r_pass.draw(0..3, 0..1);
         Profiler::end_scope(gfx, &mut r_pass).await;
 
-        GfxState::render_frame(gfx, r_pass, primitives).await;
+        if !primitives.is_empty() {
+            GfxState::render_frame(gfx, r_pass, primitives).await;
+        } else {
+            drop(r_pass);
+        }
+
+        let pp = &mut state.post_process;
+
+        // After the finalize pass, so the capture matches what is presented
+        if let Some(path) = pp.capture_path.take() {
+            let gfx = &gfx.read().await;
+
+            pp.capture = Some(FrameCapture::new(
+                gfx,
+                &pp.fx_state,
+                pp.finalized_idx(),
+                &path,
+                encoder,
+            ));
+        }
+    }
+
+    /// Writes the next rendered frame as png to `path`
+    pub fn capture_frame(&mut self, path: &Path) {
+        self.capture_path = Some(path.to_path_buf());
+    }
+
+    /// Saves a requested frame capture, after the frame is submitted
+    pub async fn save_capture(state: &mut SparState) {
+        if let Some(capture) = state.post_process.capture.take() {
+            let gfx = &state.gfx.read().await;
+            capture.save(gfx);
+        }
     }
 
     pub fn new(gfx: &GfxState, app_settings: &impl AppVisitor) -> Self {
         let device = &gfx.device;
         let config = &gfx.surface_config;
 
+        let mut if_directives = vec![];
+
+        if gfx.apply_srgb_oetf() {
+            if_directives.push(DIR_SRGB_OETF);
+        }
+
+        let write_mask = if gfx.premultiplied_alpha() {
+            if_directives.push(DIR_PREMULTIPLIED_ALPHA);
+            wgpu::ColorWrites::ALL
+        } else {
+            wgpu::ColorWrites::COLOR
+        };
+
         let finalize_shader = gfx.create_shader_builtin(ShaderOptions {
-            if_directives: &[],
+            if_directives: &if_directives,
             files: &[SDR_TONEMAPPING, "fx/finalize.wgsl"],
             label: "Finalize Post FX",
         });
@@ -190,38 +441,87 @@ impl PostProcessState {
                 targets: &[Some(wgpu::ColorTargetState {
                     format: config.format,
                     blend: None,
-                    write_mask: wgpu::ColorWrites::COLOR,
+                    write_mask,
                 })],
             }),
             multiview: None,
         });
 
+        let options = FxOptions {
+            fx_state: &fx_state,
+            gfx,
+        };
+
+        let fxaa = FxaaPass::new(&options, io_uniform.out_idx);
+
         let mut effects = vec![];
 
-        app_settings.add_post_fx(
-            &FxOptions {
-                fx_state: &fx_state,
-                gfx,
-            },
-            &mut effects,
-        );
+        app_settings.add_post_fx(&options, &mut effects);
 
         Self {
             fx_state,
             effects,
+            layers: vec![],
+            layer_composite: LayerCompositePass::new(gfx),
 
             render_pipeline,
 
             io_uniform,
             io_ctx,
+
+            fxaa,
+            fxaa_enabled: false,
+
+            capture_path: None,
+            capture: None,
+        }
+    }
+
+    /// Every fx reads and writes texture 0 when done, so the order can change freely
+    pub fn move_fx_up(&mut self, idx: usize) {
+        if 0 < idx && idx < self.effects.len() {
+            self.effects.swap(idx, idx - 1);
         }
     }
 
-    pub async fn import_fx(
+    pub fn move_fx_down(&mut self, idx: usize) {
+        if idx + 1 < self.effects.len() {
+            self.effects.swap(idx, idx + 1);
+        }
+    }
+
+    pub fn remove_fx(&mut self, idx: usize) -> Option<Box<dyn PostFx>> {
+        (idx < self.effects.len()).then(|| self.effects.remove(idx))
+    }
+
+    /// Appends the default fx registered with the tag, returns false if the tag is unknown
+    pub fn insert_fx(
+        &mut self,
+        gfx: &GfxState,
+        registry_fx: &[Box<dyn RegisterPostFx>],
+        tag: &str,
+    ) -> bool {
+        let Some(reg) = registry_fx.iter().find(|reg| reg.tag() == tag) else {
+            println!("Post fx {} is not registered", tag);
+            return false;
+        };
+
+        let options = FxOptions {
+            gfx,
+            fx_state: &self.fx_state,
+        };
+
+        self.effects.push(reg.create_default(&options));
+
+        true
+    }
+
+    /// Replaces the effects with the chain, in the same order and with the same enabled state
+    pub async fn import_chain(
         &mut self,
         gfx: &Arc<RwLock<GfxState>>,
-        registry_fx: &Vec<Box<dyn RegisterPostFx>>,
-        to_export: Vec<DynamicExport>,
+        registry_fx: &[Box<dyn RegisterPostFx>],
+        chain: Vec<ExportPostFx>,
     ) {
         let gfx = &gfx.read().await;
 
@@ -230,24 +530,33 @@ impl PostProcessState {
             fx_state: &self.fx_state,
         };
 
-        for item in to_export {
-            for reg in registry_fx {
-                if item.tag == reg.tag() {
-                    self.effects.push(reg.import(&options, item.data));
-                    break;
-                }
-            }
-        }
-    }
+        self.effects.clear();
 
-    pub fn export(pp: &PostProcessState) {
-        let mut to_export = Vec::new();
+        for item in chain {
+            let Some(reg) = registry_fx.iter().find(|reg| reg.tag() == item.fx.tag) else {
+                println!("Post fx {} is not registered", item.fx.tag);
+                continue;
+            };
 
-        for fx in pp.effects.iter() {
-            to_export.push(fx.export());
+            let mut fx = reg.import(&options, item.fx.data);
+            fx.set_enabled(item.enabled);
+            self.effects.push(fx);
         }
+    }
+
+    /// Effects with their params, in render order
+    pub fn export_chain(&self) -> Vec<ExportPostFx> {
+        self.effects
+            .iter()
+            .map(|fx| ExportPostFx {
+                fx: fx.export(),
+                enabled: fx.enabled(),
+            })
+            .collect()
+    }
 
-        Persistence::write_to_file(to_export, ExportType::PostFx);
+    pub fn export(pp: &PostProcessState) {
+        Persistence::write_to_file(pp.export_chain(), ExportType::PostFx);
     }
 }
 
@@ -262,22 +571,29 @@ pub struct FxState {
     pub count_y: u32,
 
     pub tex_size: glam::Vec2,
+    /// Width and height of a workgroup, see `GfxState::fx_workgroup_size`
+    pub workgroup_size: f32,
     pub depth_view: wgpu::TextureView,
+    /// See `GfxState::fx_format`
+    pub format: wgpu::TextureFormat,
 
+    textures: Vec<wgpu::Texture>,
     tex_views: Vec<wgpu::TextureView>,
 }
 
-const WORK_GROUP_SIZE: f32 = 16.;
-
 impl FxState {
+    pub fn texture(&self, idx: u32) -> &wgpu::Texture {
+        &self.textures[idx as usize]
+    }
+
     pub fn count_in(&self, io_uniform: &FxIOUniform) -> (u32, u32) {
-        let res = (self.tex_size / io_uniform.in_downscale as f32 / WORK_GROUP_SIZE).ceil();
+        let res = (self.tex_size / io_uniform.in_downscale as f32 / self.workgroup_size).ceil();
 
         (res.x as u32, res.y as u32)
     }
 
     pub fn count_out(&self, io_uniform: &FxIOUniform) -> (u32, u32) {
-        let res = (self.tex_size / io_uniform.out_downscale as f32 / WORK_GROUP_SIZE).ceil();
+        let res = (self.tex_size / io_uniform.out_downscale as f32 / self.workgroup_size).ceil();
 
         (res.x as u32, res.y as u32)
     }
@@ -285,6 +601,7 @@ impl FxState {
     fn new(gfx_state: &GfxState) -> Self {
         let device = &gfx_state.device;
         let depth_view = gfx_state.create_depth_view();
+        let format = gfx_state.fx_format();
 
         let array_count = 16;
 
@@ -295,7 +612,7 @@ impl FxState {
                 visibility: wgpu::ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::StorageTexture {
                     view_dimension: wgpu::TextureViewDimension::D2,
-                    format: PostProcessState::TEXTURE_FORMAT,
+                    format,
                     access: wgpu::StorageTextureAccess::ReadWrite,
                 },
                 count: NonZeroU32::new(array_count),
@@ -351,11 +668,12 @@ impl FxState {
             entries: &r_layout_entries,
         });
 
-        let mut tex_views = Vec::new();
+        let textures: Vec<wgpu::Texture> = (0..array_count)
+            .map(|_| gfx_state.create_fx_texture())
+            .collect();
 
-        for _ in 0..array_count {
-            tex_views.push(gfx_state.create_fx_view());
-        }
+        let tex_views: Vec<wgpu::TextureView> =
+            textures.iter().map(|tex| tex.default_view()).collect();
 
         let tex_refs: Vec<&wgpu::TextureView> = tex_views.iter().collect();
 
@@ -404,9 +722,10 @@ impl FxState {
         });
 
         let (x, y) = gfx_state.dimensions();
+        let workgroup_size = gfx_state.fx_workgroup_size() as f32;
 
-        let count_x = (x / WORK_GROUP_SIZE).ceil() as u32;
-        let count_y = (y / WORK_GROUP_SIZE).ceil() as u32;
+        let count_x = (x / workgroup_size).ceil() as u32;
+        let count_y = (y / workgroup_size).ceil() as u32;
 
         Self {
             bg,
@@ -414,9 +733,12 @@ impl FxState {
             r_bg,
             r_bg_layout,
             tex_size: Vec2::new(x, y),
+            workgroup_size,
             count_x,
             count_y,
             depth_view,
+            format,
+            textures,
             tex_views,
         }
     }
@@ -0,0 +1,232 @@
+use std::{any::Any, sync::Arc};
+
+use super::{FxIOUniform, FxOptions, FxState};
+use crate::{
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Scratch texture holding a copy of the input, the blur can't sample the texture it writes to
+const COPY_IDX: u32 = 2;
+
+pub enum RadialBlurEvent {
+    UpdateUniform,
+}
+
+/// Blurs along lines pointing away from a screen space center, for speed and impact effects
+pub struct RadialBlurFx {
+    pub radial_uniform: RadialBlurUniform,
+    pub radial_ctx: UniformContext,
+    pub io_uniform: FxIOUniform,
+    pub io_ctx: UniformContext,
+    pub copy_io_uniform: FxIOUniform,
+    pub copy_io_ctx: UniformContext,
+    pub copy_pipeline: wgpu::ComputePipeline,
+    pub pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+    pub update_event: Option<RadialBlurEvent>,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RadialBlurUniform {
+    /// Center in uv coordinates, 0.5 is the middle of the screen
+    pub center: Vec2,
+    /// Fraction of the distance to the center that gets sampled
+    pub strength: f32,
+    pub samples: u32,
+    #[serde(skip)]
+    pub copy_idx: u32,
+}
+
+impl Default for RadialBlurUniform {
+    fn default() -> Self {
+        Self {
+            center: Vec2::splat(0.5),
+            strength: 0.1,
+            samples: 12,
+            copy_idx: COPY_IDX,
+        }
+    }
+}
+
+impl RadialBlurUniform {
+    pub const MAX_SAMPLES: u32 = 32;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct RadialBlurSettings {
+    pub radial_uniform: RadialBlurUniform,
+    pub io_uniform: FxIOUniform,
+}
+
+pub struct RegisterRadialBlurFx;
+
+impl RegisterPostFx for RegisterRadialBlurFx {
+    fn tag(&self) -> &'static str {
+        "radial_blur"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = RadialBlurSettings {
+            radial_uniform: RadialBlurUniform::default(),
+            io_uniform: FxIOUniform::zero(options.fx_state),
+        };
+
+        Box::new(RadialBlurFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse radial blur Fx");
+
+        Box::new(RadialBlurFx::new(options, settings))
+    }
+}
+
+impl PostFx for RadialBlurFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+        self.copy_io_uniform.resize(&self.copy_io_ctx.buf, options);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "Radial blur Fx", c_pass));
+
+        c_pass.set_pipeline(&self.copy_pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.copy_io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.radial_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.radial_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    fn update(&mut self, gfx_state: &GfxState, _camera: &mut Camera, _: &Clock) {
+        if let Some(RadialBlurEvent::UpdateUniform) = self.update_event.take() {
+            let uniform = &mut self.radial_uniform;
+            uniform.samples = uniform.samples.clamp(1, RadialBlurUniform::MAX_SAMPLES);
+
+            let content = uniform.buffer_content();
+            gfx_state
+                .queue
+                .write_buffer(&self.radial_ctx.buf, 0, &content);
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for RadialBlurFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = RadialBlurSettings {
+            radial_uniform: self.radial_uniform,
+            io_uniform: self.io_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterRadialBlurFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap radial blur"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl RadialBlurFx {
+    pub fn new(options: &FxOptions, settings: RadialBlurSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let mut radial_uniform = settings.radial_uniform;
+        radial_uniform.samples = radial_uniform
+            .samples
+            .clamp(1, RadialBlurUniform::MAX_SAMPLES);
+        radial_uniform.copy_idx = COPY_IDX;
+
+        let io_uniform = settings.io_uniform;
+        let copy_io_uniform =
+            FxIOUniform::asymetric_unscaled(fx_state, io_uniform.in_idx, COPY_IDX);
+
+        let io_ctx = UniformContext::from_uniform(&io_uniform, device, "IO");
+        let copy_io_ctx = UniformContext::from_uniform(&copy_io_uniform, device, "IO radial copy");
+        let radial_ctx = UniformContext::from_uniform(&radial_uniform, device, "Radial blur");
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/radial_blur.wgsl"],
+            label: "Radial blur",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Radial blur pipeline layout"),
+            bind_group_layouts: &[
+                &fx_state.bg_layout,
+                &io_ctx.bg_layout,
+                &radial_ctx.bg_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let new_pipeline = |entry_point: &str| -> wgpu::ComputePipeline {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Radial blur pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        let copy_pipeline = new_pipeline("copy_frame");
+        let pipeline = new_pipeline("cs_main");
+
+        Self {
+            radial_uniform,
+            radial_ctx,
+            io_uniform,
+            io_ctx,
+            copy_io_uniform,
+            copy_io_ctx,
+            copy_pipeline,
+            pipeline,
+            selected_action: ListAction::None,
+            enabled: true,
+            update_event: None,
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::{any::Any, sync::Arc};
+
+use super::{FxIOUniform, FxOptions, FxState};
+use crate::{
+    model::{gfx_state::Profiler, Camera, Clock, GfxState},
+    shaders::ShaderOptions,
+    traits::{BufferContent, HandleAction, PostFx, RegisterPostFx},
+    util::{DynamicExport, ListAction, UniformContext},
+};
+use async_std::{sync::RwLock, task};
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use serde::{Deserialize, Serialize};
+
+pub enum VignetteEvent {
+    UpdateUniform,
+}
+
+/// Darkens the frame towards the corners. Works on the final colors, so place it after bloom
+pub struct VignetteFx {
+    pub vignette_uniform: VignetteUniform,
+    pub vignette_ctx: UniformContext,
+    pub io_uniform: FxIOUniform,
+    pub io_ctx: UniformContext,
+    pub pipeline: wgpu::ComputePipeline,
+    pub selected_action: ListAction,
+    pub enabled: bool,
+    pub update_event: Option<VignetteEvent>,
+}
+
+#[derive(ShaderType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct VignetteUniform {
+    /// Distance from the center where darkening starts, 1 reaches the top and bottom edges
+    pub radius: f32,
+    /// Distance over which it fades to full darkening
+    pub softness: f32,
+    /// Darkening in the corners, 1 is black
+    pub intensity: f32,
+}
+
+impl Default for VignetteUniform {
+    fn default() -> Self {
+        Self {
+            radius: 0.75,
+            softness: 0.5,
+            intensity: 0.5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct VignetteSettings {
+    pub vignette_uniform: VignetteUniform,
+    pub io_uniform: FxIOUniform,
+}
+
+pub struct RegisterVignetteFx;
+
+impl RegisterPostFx for RegisterVignetteFx {
+    fn tag(&self) -> &'static str {
+        "vignette"
+    }
+
+    fn create_default(&self, options: &FxOptions) -> Box<dyn PostFx> {
+        let settings = VignetteSettings {
+            vignette_uniform: VignetteUniform::default(),
+            io_uniform: FxIOUniform::zero(options.fx_state),
+        };
+
+        Box::new(VignetteFx::new(options, settings))
+    }
+
+    fn import(&self, options: &FxOptions, value: serde_json::Value) -> Box<dyn PostFx> {
+        let settings = serde_json::from_value(value).expect("Can't parse vignette Fx");
+
+        Box::new(VignetteFx::new(options, settings))
+    }
+}
+
+impl PostFx for VignetteFx {
+    fn resize(&mut self, options: &FxOptions) {
+        self.io_uniform.resize(&self.io_ctx.buf, options);
+    }
+
+    fn compute<'a>(
+        &'a self,
+        fx_state: &'a FxState,
+        gfx: &Arc<RwLock<GfxState>>,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let (count_x, count_y) = fx_state.count_out(&self.io_uniform);
+
+        task::block_on(Profiler::begin_scope(gfx, "Vignette Fx", c_pass));
+
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &fx_state.bg, &[]);
+        c_pass.set_bind_group(1, &self.io_ctx.bg, &[]);
+        c_pass.set_bind_group(2, &self.vignette_ctx.bg, &[]);
+        c_pass.dispatch_workgroups(count_x, count_y, 1);
+
+        task::block_on(Profiler::end_scope(gfx, c_pass));
+    }
+
+    fn update(&mut self, gfx_state: &GfxState, _camera: &mut Camera, _: &Clock) {
+        if let Some(VignetteEvent::UpdateUniform) = self.update_event.take() {
+            let content = self.vignette_uniform.buffer_content();
+            gfx_state
+                .queue
+                .write_buffer(&self.vignette_ctx.buf, 0, &content);
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl HandleAction for VignetteFx {
+    fn selected_action(&mut self) -> &mut ListAction {
+        &mut self.selected_action
+    }
+
+    fn export(&self) -> DynamicExport {
+        let settings = VignetteSettings {
+            vignette_uniform: self.vignette_uniform,
+            io_uniform: self.io_uniform,
+        };
+
+        DynamicExport {
+            tag: RegisterVignetteFx.tag().to_string(),
+            data: serde_json::to_value(settings).expect("Can't unwrap vignette"),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl VignetteFx {
+    pub fn new(options: &FxOptions, settings: VignetteSettings) -> Self {
+        let FxOptions {
+            gfx: gfx_state,
+            fx_state,
+        } = options;
+
+        let device = &gfx_state.device;
+
+        let io_ctx = UniformContext::from_uniform(&settings.io_uniform, device, "IO");
+        let vignette_ctx =
+            UniformContext::from_uniform(&settings.vignette_uniform, device, "Vignette");
+
+        let shader = gfx_state.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["fx/vignette.wgsl"],
+            label: "Vignette",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Vignette pipeline layout"),
+            bind_group_layouts: &[
+                &fx_state.bg_layout,
+                &io_ctx.bg_layout,
+                &vignette_ctx.bg_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Vignette pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            vignette_uniform: settings.vignette_uniform,
+            vignette_ctx,
+            io_uniform: settings.io_uniform,
+            io_ctx,
+            pipeline,
+            selected_action: ListAction::None,
+            enabled: true,
+            update_event: None,
+        }
+    }
+}
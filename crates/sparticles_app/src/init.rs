@@ -8,16 +8,33 @@ use egui_winit::winit::event::KeyboardInput;
 
 use crate::animations::color_animation::RegisterColorAnimation;
 use crate::animations::diffusion_animation::RegisterDiffusionAnimation;
+use crate::animations::path_animation::RegisterPathAnimation;
+use crate::animations::pulse_spawn_animation::RegisterPulseSpawnAnimation;
 use crate::animations::sway_animation::RegisterSwayAnimation;
-use crate::animations::{RegisterForceAnimation, RegisterGravityAnimation, RegisterStrayAnimation};
+use crate::animations::{
+    RegisterCurlNoiseAnimation, RegisterFlipbookAnimation, RegisterFloorCollisionAnimation,
+    RegisterForceAnimation, RegisterGravityAnimation, RegisterSizeOverLifeAnimation,
+    RegisterSpringAnimation, RegisterStrayAnimation, RegisterTurbulenceAnimation,
+    RegisterVortexAnimation, RegisterWindAnimation,
+};
 use crate::fx::bloom::RegisterBloomFx;
 use crate::fx::blur::RegisterBlurFx;
 use crate::fx::FxOptions;
 use crate::fx::PostProcessState;
 use crate::fx::RegisterColorFx;
+use crate::fx::RegisterDofFx;
+use crate::fx::RegisterFeedbackFx;
+use crate::fx::RegisterFilmGrainFx;
+use crate::fx::RegisterKuwaharaFx;
+use crate::fx::RegisterLutFx;
+use crate::fx::RegisterMotionBlurFx;
+use crate::fx::RegisterOutlineFx;
+use crate::fx::RegisterRadialBlurFx;
+use crate::fx::RegisterVignetteFx;
 use crate::loader::Model;
 use crate::model::{
-    Camera, CreateEmitterOptions, EmitterState, EmitterType, EmitterUniform, GfxState,
+    Camera, CameraBuilder, ClockMode, CreateEmitterOptions, EmitterState, EmitterType,
+    EmitterUniform, GfxState, ParticleBudget, RenderStage,
 };
 pub use crate::model::{SparEvents, SparState};
 use crate::traits::*;
@@ -52,9 +69,65 @@ pub trait AppVisitor {
         Path::new(env!("CARGO_MANIFEST_DIR")).join("exports")
     }
 
-    fn add_widget_builders(&mut self, state: &mut SparState);
+    /// Otherwise a linear surface is used and the sRGB conversion is done in the finalize shader
+    fn prefer_srgb_surface(&self) -> bool {
+        true
+    }
+
+    /// Keeps the particle alpha and outputs premultiplied colors for compositing.
+    /// The window becomes transparent where it's supported, disable the sky for a clear background
+    fn premultiplied_alpha(&self) -> bool {
+        false
+    }
+
+    /// Tile size of the post fx compute passes, clamped to the device limits (16 by default).
+    /// The best size differs per GPU
+    fn fx_workgroup_size(&self) -> u32 {
+        16
+    }
+
+    /// Format of the post fx textures, falls back to Rgba8Unorm when the adapter can't store it
+    fn fx_texture_format(&self) -> wgpu::TextureFormat {
+        PostProcessState::DEFAULT_TEXTURE_FORMAT
+    }
+
+    /// Initial camera position, target and projection
+    fn camera(&self) -> CameraBuilder {
+        CameraBuilder::default()
+    }
 
-    fn draw_ui(&mut self, state: &mut SparState, encoder: &mut wgpu::CommandEncoder) -> SparEvents;
+    /// Frame rate cap at startup, can be changed at runtime through the clock
+    fn max_fps(&self) -> Option<u32> {
+        None
+    }
+
+    /// Fixed timesteps make the simulation reproducible, for video export and comparisons
+    fn clock_mode(&self) -> ClockMode {
+        ClockMode::Realtime
+    }
+
+    /// Particle limit for all emitters together, checked when emitters are added or grown
+    fn particle_budget(&self) -> ParticleBudget {
+        ParticleBudget::default()
+    }
+
+    /// Order of the compute, render and post fx passes in a frame
+    fn render_stages(&self) -> Vec<RenderStage> {
+        RenderStage::default_order()
+    }
+
+    /// Without the gui egui skips its input handling and draw pass, for embedding or playback
+    fn gui_enabled(&self) -> bool {
+        true
+    }
+
+    /// Only called when the gui is enabled
+    fn add_widget_builders(&mut self, state: &mut SparState) {}
+
+    /// Only called when the gui is enabled
+    fn draw_ui(&mut self, state: &mut SparState, encoder: &mut wgpu::CommandEncoder) -> SparEvents {
+        SparEvents::default()
+    }
 
     fn process_events(
         &mut self,
@@ -97,6 +170,7 @@ impl Init {
         gfx: &Arc<RwLock<GfxState>>,
         collection: &Arc<RwLock<HashMap<ID, Model>>>,
         camera: &Camera,
+        budget: &ParticleBudget,
     ) -> Vec<EmitterState> {
         let mut emitters: Vec<EmitterState> = Vec::new();
 
@@ -111,7 +185,7 @@ impl Init {
         })
         .await;
 
-        for emitter_uniform in emitter_uniforms {
+        for mut emitter_uniform in emitter_uniforms {
             let is_unique = emitters
                 .iter()
                 .all(|emitter| emitter.uniform.id != emitter_uniform.id);
@@ -119,6 +193,14 @@ impl Init {
             assert!(!emitter_uniform.id.is_empty(), "Id can not be empty");
             assert!(is_unique, "Emitters require an unique ID");
 
+            let particle_usage = lights.particle_count()
+                + emitters.iter().map(|em| em.particle_count()).sum::<u64>();
+
+            if let Err(err) = budget.fit(&mut emitter_uniform, particle_usage) {
+                println!("{}", err.msg);
+                continue;
+            }
+
             emitters.push(
                 EmitterState::new(CreateEmitterOptions {
                     uniform: emitter_uniform,
@@ -150,6 +232,14 @@ impl Init {
             Box::new(RegisterForceAnimation),
             Box::new(RegisterGravityAnimation),
             Box::new(RegisterStrayAnimation),
+            Box::new(RegisterTurbulenceAnimation),
+            Box::new(RegisterSpringAnimation),
+            Box::new(RegisterWindAnimation),
+            Box::new(RegisterVortexAnimation),
+            Box::new(RegisterSizeOverLifeAnimation),
+            Box::new(RegisterCurlNoiseAnimation),
+            Box::new(RegisterFlipbookAnimation),
+            Box::new(RegisterFloorCollisionAnimation),
         ];
 
         app_visitor.register_particle_animations(&mut registry_par_anims);
@@ -157,6 +247,8 @@ impl Init {
         let mut registry_em_anims: Vec<Box<dyn RegisterEmitterAnimation>> = vec![
             Box::new(RegisterSwayAnimation),
             Box::new(RegisterDiffusionAnimation),
+            Box::new(RegisterPulseSpawnAnimation),
+            Box::new(RegisterPathAnimation),
         ];
 
         app_visitor.register_emitter_animations(&mut registry_em_anims);
@@ -165,14 +257,25 @@ impl Init {
             Box::new(RegisterBloomFx),
             Box::new(RegisterColorFx),
             Box::new(RegisterBlurFx),
+            Box::new(RegisterFeedbackFx),
+            Box::new(RegisterKuwaharaFx),
+            Box::new(RegisterOutlineFx),
+            Box::new(RegisterVignetteFx),
+            Box::new(RegisterFilmGrainFx),
+            Box::new(RegisterDofFx),
+            Box::new(RegisterRadialBlurFx),
+            Box::new(RegisterLutFx),
+            Box::new(RegisterMotionBlurFx),
         ];
 
         app_visitor.register_post_fx(&mut registry_post_fx);
 
+        let budget = app_visitor.particle_budget();
+
         match app_visitor.data_source() {
             DataSource::Code { lights, emitters } => {
                 let mut emitters =
-                    Self::code_emitters(*lights, emitters, gfx, collection, camera).await;
+                    Self::code_emitters(*lights, emitters, gfx, collection, camera, &budget).await;
 
                 let gfx = &gfx.read().await;
 
@@ -190,7 +293,8 @@ impl Init {
             }
             DataSource::Demo => {
                 let lights = EmitterUniform::new("lights".to_string());
-                let emitters = Self::code_emitters(lights, vec![], gfx, collection, camera).await;
+                let emitters =
+                    Self::code_emitters(lights, vec![], gfx, collection, camera, &budget).await;
 
                 Self {
                     emitters,
@@ -202,20 +306,27 @@ impl Init {
             DataSource::Json { path } => match Persistence::import_emitter_states(path) {
                 Ok(exported_emitters) => {
                     match Persistence::import_post_fx() {
-                        Ok(val) => pp.import_fx(gfx, &registry_post_fx, val).await,
+                        Ok(val) => pp.import_chain(gfx, &registry_post_fx, val).await,
                         Err(err) => println!("{}", err.msg),
                     }
 
-                    Self::json_emitters(
+                    let emitters = Self::json_emitters(
                         exported_emitters,
                         gfx,
                         camera,
                         collection,
+                        &budget,
+                        &registry_par_anims,
+                        &registry_em_anims,
+                    )
+                    .await;
+
+                    Self {
+                        emitters,
                         registry_par_anims,
                         registry_em_anims,
                         registry_post_fx,
-                    )
-                    .await
+                    }
                 }
                 Err(err) => {
                     panic!("{}", err.msg);
@@ -229,11 +340,11 @@ impl Init {
         gfx: &Arc<RwLock<GfxState>>,
         camera: &Camera,
         collection: &Arc<RwLock<HashMap<ID, Model>>>,
-        registry_par_anims: Vec<Box<dyn RegisterParticleAnimation>>,
-        registry_em_anims: Vec<Box<dyn RegisterEmitterAnimation>>,
-        registry_post_fx: Vec<Box<dyn RegisterPostFx>>,
-    ) -> Self {
-        let mut emitters = Vec::new();
+        budget: &ParticleBudget,
+        registry_par_anims: &[Box<dyn RegisterParticleAnimation>],
+        registry_em_anims: &[Box<dyn RegisterEmitterAnimation>],
+    ) -> Vec<EmitterState> {
+        let mut emitters: Vec<EmitterState> = Vec::new();
 
         let lights_export = emitters_export.remove(0);
 
@@ -277,8 +388,17 @@ impl Init {
         }
 
         for emitter_export in emitters_export {
+            let mut uniform = emitter_export.emitter;
+            let particle_usage = lights.particle_count()
+                + emitters.iter().map(|em| em.particle_count()).sum::<u64>();
+
+            if let Err(err) = budget.fit(&mut uniform, particle_usage) {
+                println!("{}", err.msg);
+                continue;
+            }
+
             let mut emitter = EmitterState::new(CreateEmitterOptions {
-                uniform: emitter_export.emitter,
+                uniform,
                 camera,
                 collection,
                 gfx,
@@ -314,11 +434,6 @@ impl Init {
 
         emitters.insert(0, lights);
 
-        Init {
-            emitters,
-            registry_em_anims,
-            registry_par_anims,
-            registry_post_fx,
-        }
+        emitters
     }
 }
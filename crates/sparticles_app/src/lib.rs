@@ -1,8 +1,13 @@
 use async_std::task;
 use egui_winit::winit;
 use init::AppVisitor;
-use model::{GfxState, SparEvents, SparState};
+use model::{ClockMode, GfxState, SparEvents, SparState};
+use std::path::Path;
+use std::time::Instant;
+use util::{ExportType, Persistence, WindowConfig};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 use winit::event::Event::*;
+use winit::event::{ElementState, VirtualKeyCode};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::{self, WindowId};
 
@@ -30,54 +35,117 @@ pub fn start(mut app_visitor: impl AppVisitor + 'static) {
 
     let event_loop = EventLoop::new();
 
-    let window = window::WindowBuilder::new()
+    let mut window_builder = window::WindowBuilder::new()
         .with_decorations(true)
         .with_transparent(false)
         //.with_resizable(false)
         //.with_max_inner_size(PhysicalSize::new(1920., 1080.))
-        .with_title("Sparticles")
-        .build(&event_loop)
-        .unwrap();
+        .with_title("Sparticles");
 
-    let mut state = task::block_on(SparState::new(&mut app_visitor, window));
+    match Persistence::import_window_config() {
+        Ok(config) => {
+            window_builder = window_builder
+                .with_inner_size(PhysicalSize::new(config.width, config.height))
+                .with_position(PhysicalPosition::new(config.x, config.y));
+        }
+        Err(err) => println!("{}", err.msg),
+    }
+
+    let window = window_builder.build(&event_loop).unwrap();
+
+    let size = window.inner_size();
+    let position = window.outer_position().unwrap_or_default();
+
+    let mut window_config = WindowConfig {
+        width: size.width,
+        height: size.height,
+        x: position.x,
+        y: position.y,
+    };
+
+    let mut state = SparState::new_blocking(&mut app_visitor, window);
     let mut shift_pressed = false;
     let mut events = SparEvents::default();
 
     event_loop.run(move |event, _, control_flow| {
         let gfx_window_id = task::block_on(GfxState::window_id(&state.gfx));
-        let do_exec = |window_id: WindowId| window_id == gfx_window_id;
+        let do_exec = |window_id: WindowId| Some(window_id) == gfx_window_id;
 
         match event {
             RedrawRequested(window_id) if do_exec(window_id) => {
-                task::block_on(state.update(&events));
-                events = task::block_on(GfxState::render(&mut state, &mut app_visitor));
+                state.update_blocking(&events);
+                events = state.render_blocking(&mut app_visitor);
             }
-            MainEventsCleared => {
-                let gfx = task::block_on(state.gfx.read());
-                gfx.request_redraw();
+            MainEventsCleared => match state.clock.next_frame_deadline() {
+                // Sleeps until the frame cap allows the next frame
+                Some(deadline) if Instant::now() < deadline => {
+                    *control_flow = ControlFlow::WaitUntil(deadline);
+                }
+                _ => {
+                    *control_flow = ControlFlow::Poll;
+                    state.with_gfx(|gfx| gfx.request_redraw());
+                }
+            },
+            // Raw motion keeps working at the window edges, the camera ignores it unless looking
+            DeviceEvent {
+                event: winit::event::DeviceEvent::MouseMotion { delta },
+                ..
+            } => {
+                state.process_mouse_motion(delta.0 as f32, delta.1 as f32);
             }
             WindowEvent { event, window_id } if do_exec(window_id) => {
                 let response = GfxState::handle_event(&state.gfx, &event);
 
                 match event {
                     winit::event::WindowEvent::Resized(size) => {
-                        task::block_on(state.resize(size));
+                        // Minimizing resizes to zero, keep the last visible size
+                        if 0 < size.width && 0 < size.height {
+                            window_config.width = size.width;
+                            window_config.height = size.height;
+                        }
+
+                        state.resize_blocking(size);
+                    }
+                    winit::event::WindowEvent::Moved(position) => {
+                        window_config.x = position.x;
+                        window_config.y = position.y;
                     }
                     winit::event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        task::block_on(state.resize(*new_inner_size));
+                        state.resize_blocking(*new_inner_size);
                     }
                     winit::event::WindowEvent::CloseRequested => {
+                        Persistence::write_to_file(window_config, ExportType::Window);
                         *control_flow = ControlFlow::Exit;
                     }
-                    winit::event::WindowEvent::KeyboardInput { input, .. } => {
-                        if !response.consumed {
-                            state.process_events(&input);
-                            app_visitor.process_events(&mut events, &input, shift_pressed);
-                        }
+                    // Works without the editor as well, the png ends up in export/frames
+                    winit::event::WindowEvent::KeyboardInput { input, .. }
+                        if input.virtual_keycode == Some(VirtualKeyCode::F12) =>
+                    {
+                        events.capture_frame |= input.state == ElementState::Released;
+                    }
+                    winit::event::WindowEvent::KeyboardInput { input, .. }
+                        if !response.consumed =>
+                    {
+                        state.process_events(&input);
+                        app_visitor.process_events(&mut events, &input, shift_pressed);
                     }
                     winit::event::WindowEvent::ModifiersChanged(modifier) => {
                         shift_pressed = modifier.shift()
                     }
+                    // Releases and cursor moves are always tracked, so drags end and don't jump over the gui
+                    winit::event::WindowEvent::MouseInput {
+                        state: ElementState::Released,
+                        ..
+                    }
+                    | winit::event::WindowEvent::CursorMoved { .. } => {
+                        state.process_mouse_event(&event);
+                    }
+                    winit::event::WindowEvent::MouseInput { .. }
+                    | winit::event::WindowEvent::MouseWheel { .. }
+                        if !response.consumed =>
+                    {
+                        state.process_mouse_event(&event);
+                    }
                     _ => {}
                 }
             }
@@ -85,3 +153,33 @@ pub fn start(mut app_visitor: impl AppVisitor + 'static) {
         }
     });
 }
+
+/// Fixed frame rate of the headless mode, so every run produces the same frames
+pub const HEADLESS_FPS: f32 = 60.;
+
+/// Renders `frames` frames without a window and writes them as png to `out_dir`
+pub fn start_headless(
+    mut app_visitor: impl AppVisitor,
+    width: u32,
+    height: u32,
+    frames: u32,
+    out_dir: impl AsRef<Path>,
+) {
+    env_logger::init();
+
+    let out_dir = out_dir.as_ref();
+    let mut state = SparState::new_headless_blocking(&mut app_visitor, width, height);
+    let mut events = SparEvents::default();
+
+    state.clock.set_mode(ClockMode::Fixed {
+        dt: 1. / HEADLESS_FPS,
+    });
+
+    for frame in 0..frames {
+        let path = out_dir.join(format!("frame_{:05}.png", frame));
+        state.post_process.capture_frame(&path);
+
+        state.update_blocking(&events);
+        events = state.render_blocking(&mut app_visitor);
+    }
+}
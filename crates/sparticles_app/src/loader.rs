@@ -96,10 +96,9 @@ impl Model {
             }
         }
 
-        async fn fetch_sampler(
+        fn fetch_sampler(
             sampler_data: gltf::texture::Sampler<'_>,
-            gfx: &Arc<RwLock<GfxState>>,
-        ) -> wgpu::Sampler {
+        ) -> wgpu::SamplerDescriptor<'static> {
             let default_sampler = wgpu::SamplerDescriptor::default();
 
             let (min_filter, mipmap_filter) = match &sampler_data.min_filter() {
@@ -136,17 +135,16 @@ impl Model {
                 gltf::texture::WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
             };
 
-            let device = &gfx.write().await.device;
-
-            device.create_sampler(&wgpu::SamplerDescriptor {
-                label: sampler_data.name(),
+            // Kept as a descriptor so the material can rebuild it with other sampling settings
+            wgpu::SamplerDescriptor {
+                label: None,
                 min_filter,
                 mag_filter,
                 mipmap_filter,
                 address_mode_u: get_wrapping_mode(sampler_data.wrap_s()),
                 address_mode_v: get_wrapping_mode(sampler_data.wrap_t()),
                 ..Default::default()
-            })
+            }
         }
 
         async fn fetch_texture(
@@ -174,15 +172,15 @@ impl Model {
 
         for (i, material) in gltf.materials().enumerate() {
             let albedo_tex: wgpu::Texture;
-            let albedo_s: wgpu::Sampler;
+            let albedo_s: wgpu::SamplerDescriptor<'static>;
             let metallic_roughness_tex: wgpu::Texture;
-            let metallic_roughness_s: wgpu::Sampler;
+            let metallic_roughness_s: wgpu::SamplerDescriptor<'static>;
             let normal_tex: wgpu::Texture;
-            let normal_s: wgpu::Sampler;
+            let normal_s: wgpu::SamplerDescriptor<'static>;
             let emissive_tex: wgpu::Texture;
-            let emissive_s: wgpu::Sampler;
+            let emissive_s: wgpu::SamplerDescriptor<'static>;
             let ao_tex: wgpu::Texture;
-            let ao_s: wgpu::Sampler;
+            let ao_s: wgpu::SamplerDescriptor<'static>;
             let cull_mode = Some(wgpu::Face::Back);
 
             let pbr = material.pbr_metallic_roughness();
@@ -190,19 +188,19 @@ impl Model {
             if let Some(tex_data) = pbr.base_color_texture() {
                 let tex = tex_data.texture();
                 albedo_tex = fetch_texture(tex.source(), true, &mut buffer_data, gfx).await;
-                albedo_s = fetch_sampler(tex.sampler(), gfx).await;
+                albedo_s = fetch_sampler(tex.sampler());
                 println!("Contains albedo tex");
             } else {
                 let gfx = &gfx.read().await;
                 albedo_tex = gfx.create_builtin_tex(TexType::White);
-                albedo_s = gfx.create_sampler();
+                albedo_s = wgpu::SamplerDescriptor::default();
             }
 
             if let Some(tex_data) = pbr.metallic_roughness_texture() {
                 let tex = tex_data.texture();
                 metallic_roughness_tex =
                     fetch_texture(tex.source(), true, &mut buffer_data, gfx).await;
-                metallic_roughness_s = fetch_sampler(tex.sampler(), gfx).await;
+                metallic_roughness_s = fetch_sampler(tex.sampler());
                 println!("Contains metallic_roughness_tex");
             } else {
                 let metallic_factor = pbr.metallic_factor();
@@ -213,24 +211,24 @@ impl Model {
                     srgb: true,
                     value: glam::Vec4::new(metallic_factor, roughness_factor, 0., 0.),
                 });
-                metallic_roughness_s = gfx.create_sampler();
+                metallic_roughness_s = wgpu::SamplerDescriptor::default();
             }
 
             if let Some(tex_data) = material.normal_texture() {
                 let tex = tex_data.texture();
                 normal_tex = fetch_texture(tex.source(), false, &mut buffer_data, gfx).await;
-                normal_s = fetch_sampler(tex.sampler(), gfx).await;
+                normal_s = fetch_sampler(tex.sampler());
                 println!("Contains normal_tex");
             } else {
                 let gfx = &gfx.read().await;
                 normal_tex = gfx.create_builtin_tex(TexType::Normal);
-                normal_s = gfx.create_sampler();
+                normal_s = wgpu::SamplerDescriptor::default();
             }
 
             if let Some(tex_data) = material.emissive_texture() {
                 let tex = tex_data.texture();
                 emissive_tex = fetch_texture(tex.source(), true, &mut buffer_data, gfx).await;
-                emissive_s = fetch_sampler(tex.sampler(), gfx).await;
+                emissive_s = fetch_sampler(tex.sampler());
 
                 if let Some(strenght) = material.emissive_strength() {
                     println!("Strength: {}", strenght);
@@ -244,18 +242,18 @@ impl Model {
                     srgb: true,
                     value: vec3.extend(0.),
                 });
-                emissive_s = gfx.create_sampler();
+                emissive_s = wgpu::SamplerDescriptor::default();
             }
 
             if let Some(tex_data) = material.occlusion_texture() {
                 let tex = tex_data.texture();
                 ao_tex = fetch_texture(tex.source(), true, &mut buffer_data, gfx).await;
-                ao_s = fetch_sampler(tex.sampler(), gfx).await;
+                ao_s = fetch_sampler(tex.sampler());
                 println!("contains occlusion_texture");
             } else {
                 let gfx = &gfx.read().await;
                 ao_tex = gfx.create_builtin_tex(TexType::White);
-                ao_s = gfx.create_sampler();
+                ao_s = wgpu::SamplerDescriptor::default();
             }
 
             let id = material
@@ -399,6 +397,99 @@ impl Model {
     }
 }
 
+/// 3D color lookup table from an Adobe .cube file, red changes fastest in the data
+pub struct CubeLut {
+    pub size: u32,
+    pub data: Vec<[f32; 3]>,
+    pub domain_min: glam::Vec3,
+    pub domain_max: glam::Vec3,
+}
+
+impl CubeLut {
+    pub fn identity(size: u32) -> Self {
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity((size * size * size) as usize);
+
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([r as f32 / max, g as f32 / max, b as f32 / max]);
+                }
+            }
+        }
+
+        Self {
+            size,
+            data,
+            domain_min: glam::Vec3::ZERO,
+            domain_max: glam::Vec3::ONE,
+        }
+    }
+
+    pub fn load(filename: &str) -> anyhow::Result<Self> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/assets/luts")
+            .join(filename);
+
+        let text = std::fs::read_to_string(path)?;
+
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut size = None;
+        let mut data = Vec::new();
+        let mut domain_min = glam::Vec3::ZERO;
+        let mut domain_max = glam::Vec3::ONE;
+
+        let parse_vec3 = |values: &[&str]| -> anyhow::Result<glam::Vec3> {
+            anyhow::ensure!(values.len() == 3, "Expected 3 values, got {:?}", values);
+
+            Ok(glam::Vec3::new(
+                values[0].parse()?,
+                values[1].parse()?,
+                values[2].parse()?,
+            ))
+        };
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            match words[0] {
+                "TITLE" => {}
+                "LUT_1D_SIZE" => anyhow::bail!("1D LUTs are not supported"),
+                "LUT_3D_SIZE" => size = Some(words.get(1).unwrap_or(&"").parse::<u32>()?),
+                "DOMAIN_MIN" => domain_min = parse_vec3(&words[1..])?,
+                "DOMAIN_MAX" => domain_max = parse_vec3(&words[1..])?,
+                _ => data.push(parse_vec3(&words)?.to_array()),
+            }
+        }
+
+        let Some(size) = size else {
+            anyhow::bail!("LUT_3D_SIZE is missing");
+        };
+
+        anyhow::ensure!(1 < size, "LUT_3D_SIZE should be at least 2");
+        anyhow::ensure!(
+            data.len() == (size * size * size) as usize,
+            "Expected {} entries, got {}",
+            size * size * size,
+            data.len()
+        );
+
+        Ok(Self {
+            size,
+            data,
+            domain_min,
+            domain_max,
+        })
+    }
+}
+
 //let a = glam::Mat4::from_euler(glam::EulerRot::default(), 0., 0., 0.);
 //let b = glam::Mat4::from_translation(glam::Vec3::new(10., 20., 30.));
 //let c = glam::Mat4::from_scale(glam::Vec3::splat(3.0));
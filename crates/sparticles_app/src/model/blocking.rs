@@ -0,0 +1,53 @@
+//! Synchronous versions of the async state functions, for callers without an executor.
+//! Rendering runs on one thread, so blocking on the locks doesn't wait on other work.
+use super::{BudgetError, EmitterState, EmitterUniform, GfxState, SparEvents, SparState};
+use crate::init::AppVisitor;
+use async_std::task;
+use egui_winit::winit::{dpi::PhysicalSize, window::Window};
+
+impl SparState {
+    pub fn new_blocking(init: &mut impl AppVisitor, window: Window) -> Self {
+        task::block_on(Self::new(init, window))
+    }
+
+    pub fn new_headless_blocking(init: &mut impl AppVisitor, width: u32, height: u32) -> Self {
+        task::block_on(Self::new_headless(init, width, height))
+    }
+
+    pub fn update_blocking(&mut self, events: &SparEvents) {
+        task::block_on(self.update(events))
+    }
+
+    /// See `SparState::step`
+    pub fn step_blocking(&mut self, dt: f32) {
+        task::block_on(self.step(dt))
+    }
+
+    /// Renders and presents a frame, returns the events of the gui
+    pub fn render_blocking(&mut self, app_visitor: &mut impl AppVisitor) -> SparEvents {
+        task::block_on(GfxState::render(self, app_visitor))
+    }
+
+    pub fn resize_blocking(&mut self, size: PhysicalSize<u32>) {
+        task::block_on(self.resize(size))
+    }
+
+    /// See `EmitterState::seek`
+    pub fn seek_blocking(&mut self, target_sec: f32) {
+        task::block_on(EmitterState::seek(self, target_sec))
+    }
+
+    /// See `SparState::add_emitter`
+    pub fn add_emitter_blocking(&mut self, uniform: EmitterUniform) -> Result<(), BudgetError> {
+        task::block_on(self.add_emitter(uniform))
+    }
+
+    /// Read access to the device, queue and surface
+    pub fn with_gfx<R>(&self, f: impl FnOnce(&GfxState) -> R) -> R {
+        f(&task::block_on(self.gfx.read()))
+    }
+
+    pub fn with_gfx_mut<R>(&self, f: impl FnOnce(&mut GfxState) -> R) -> R {
+        f(&mut task::block_on(self.gfx.write()))
+    }
+}
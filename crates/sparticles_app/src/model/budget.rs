@@ -0,0 +1,79 @@
+use super::{EmitterUniform, PARTICLE_STRIDE};
+use std::fmt::Display;
+
+/// What happens when an emitter would exceed the particle budget
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetPolicy {
+    /// The change is refused
+    #[default]
+    Reject,
+    /// The spawn count is lowered until the emitter fits
+    ScaleDown,
+}
+
+#[derive(Debug)]
+pub struct BudgetError {
+    pub msg: String,
+}
+
+/// Limits the particles allocated by all emitters together
+#[derive(Debug, Clone, Copy)]
+pub struct ParticleBudget {
+    pub max_particles: u64,
+    pub policy: BudgetPolicy,
+}
+
+impl Default for ParticleBudget {
+    fn default() -> Self {
+        Self {
+            max_particles: 2_000_000,
+            policy: BudgetPolicy::default(),
+        }
+    }
+}
+
+impl Display for BudgetPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reject => write!(f, "Reject"),
+            Self::ScaleDown => write!(f, "Scale down"),
+        }
+    }
+}
+
+impl ParticleBudget {
+    /// GPU memory used by both ping-pong particle buffers
+    pub fn memory_bytes(particle_count: u64) -> u64 {
+        particle_count * PARTICLE_STRIDE * 2
+    }
+
+    pub fn max_memory_bytes(&self) -> u64 {
+        Self::memory_bytes(self.max_particles)
+    }
+
+    /// Checks if `uniform` fits next to the particles of the other emitters, applies the policy if not
+    pub fn fit(&self, uniform: &mut EmitterUniform, others_count: u64) -> Result<(), BudgetError> {
+        let available = self.max_particles.saturating_sub(others_count);
+        let requested = uniform.particle_count();
+
+        if requested <= available {
+            return Ok(());
+        }
+
+        if self.policy == BudgetPolicy::ScaleDown {
+            let spawn_count = available / uniform.spawn_batches_count() as u64;
+
+            if 0 < spawn_count {
+                uniform.spawn_count = spawn_count as u32;
+                return Ok(());
+            }
+        }
+
+        Err(BudgetError {
+            msg: format!(
+                "Particle budget exceeded for {}: {} requested, {} of {} available",
+                uniform.id, requested, available, self.max_particles
+            ),
+        })
+    }
+}
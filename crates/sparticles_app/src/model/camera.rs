@@ -1,8 +1,11 @@
 use super::{gfx_state::GfxState, SparEvents, SparState};
+use crate::util::{ExportCamera, ExportType, Persistence};
 use egui_wgpu::wgpu;
 use egui_winit::{
     egui::WidgetText,
-    winit::event::{ElementState, KeyboardInput, VirtualKeyCode},
+    winit::event::{
+        ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+    },
 };
 use encase::UniformBuffer;
 use glam::*;
@@ -21,6 +24,22 @@ pub enum TonemapType {
     AcesHill,
     Uchimura,
     Lottes,
+    Reinhard,
+    Uncharted2,
+    /// Clips at 1, shows how much of the frame is out of range
+    Linear,
+}
+
+impl TonemapType {
+    pub const ALL: [TonemapType; 7] = [
+        TonemapType::AcesNarkowicz,
+        TonemapType::AcesHill,
+        TonemapType::Uchimura,
+        TonemapType::Lottes,
+        TonemapType::Reinhard,
+        TonemapType::Uncharted2,
+        TonemapType::Linear,
+    ];
 }
 
 impl From<TonemapType> for WidgetText {
@@ -30,6 +49,9 @@ impl From<TonemapType> for WidgetText {
             TonemapType::AcesHill => "ACES Hill".into(),
             TonemapType::Uchimura => "Uchimura".into(),
             TonemapType::Lottes => "Lottes".into(),
+            TonemapType::Reinhard => "Reinhard".into(),
+            TonemapType::Uncharted2 => "Uncharted 2".into(),
+            TonemapType::Linear => "Linear".into(),
         }
     }
 }
@@ -40,6 +62,9 @@ impl From<u32> for TonemapType {
             1 => TonemapType::AcesHill,
             2 => TonemapType::Uchimura,
             3 => TonemapType::Lottes,
+            4 => TonemapType::Reinhard,
+            5 => TonemapType::Uncharted2,
+            6 => TonemapType::Linear,
             _ => TonemapType::AcesNarkowicz,
         }
     }
@@ -51,6 +76,136 @@ impl From<TonemapType> for u32 {
     }
 }
 
+/// Global debug view, overrides the particle color in the render shaders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugView {
+    #[default]
+    None,
+    /// Colors particles by their speed, from blue (still) to red (max speed)
+    Speed,
+}
+
+impl From<DebugView> for WidgetText {
+    fn from(value: DebugView) -> Self {
+        match value {
+            DebugView::None => "None".into(),
+            DebugView::Speed => "Speed".into(),
+        }
+    }
+}
+
+const DEFAULT_DISTANCE: f32 = 10.;
+/// Seconds to close most of the gap to the target speed at damping 1
+const MAX_DAMPING_SEC: f32 = 2.;
+/// Pixels per scroll line
+const SCROLL_LINE_PX: f32 = 20.;
+/// 89 degrees, keeps the view from flipping over the poles
+const MAX_PITCH: f32 = 89. * std::f32::consts::PI / 180.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraMode {
+    /// WASD and arrow keys move and rotate the camera
+    FreeFly,
+    /// Rotates around the target with mouse drag or arrow keys, scroll or W/S changes the distance
+    Orbit { target: Vec3, distance: f32 },
+}
+
+impl From<CameraMode> for WidgetText {
+    fn from(value: CameraMode) -> Self {
+        match value {
+            CameraMode::FreeFly => "Free fly".into(),
+            CameraMode::Orbit { .. } => "Orbit".into(),
+        }
+    }
+}
+
+/// Initial camera settings, return one from `AppVisitor::camera` to frame the scene on startup
+#[derive(Debug, Clone, Copy)]
+pub struct CameraBuilder {
+    position: Vec3,
+    /// Looks down the negative z axis when empty
+    target: Option<Vec3>,
+    /// Yaw and pitch of a restored camera, used instead of the target
+    orientation: Option<(f32, f32)>,
+    fov_deg: f32,
+    near: f32,
+    far: f32,
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0., 0., 10.),
+            target: None,
+            orientation: None,
+            fov_deg: 45.,
+            near: 0.1,
+            far: 100.,
+        }
+    }
+}
+
+impl CameraBuilder {
+    pub fn with_position(mut self, position: Vec3) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_target(mut self, target: Vec3) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Vertical field of view in degrees
+    pub fn with_fov(mut self, fov_deg: f32) -> Self {
+        self.fov_deg = fov_deg;
+        self
+    }
+
+    pub fn with_near(mut self, near: f32) -> Self {
+        self.near = near;
+        self
+    }
+
+    pub fn with_far(mut self, far: f32) -> Self {
+        self.far = far;
+        self
+    }
+
+    pub fn build(self, gfx_state: &GfxState) -> Camera {
+        Camera::from_builder(gfx_state, self)
+    }
+
+    /// Yaw and pitch that look from the position at the target
+    fn orientation(&self) -> (f32, f32) {
+        self.orientation
+            .or_else(|| {
+                self.target
+                    .and_then(|target| yaw_pitch_towards(target - self.position))
+            })
+            .unwrap_or((0., 0.))
+    }
+}
+
+/// 1, -1 or 0 when both or none are pressed
+fn axis(positive: bool, negative: bool) -> f32 {
+    positive as u8 as f32 - negative as u8 as f32
+}
+
+/// None when the direction has no length
+fn yaw_pitch_towards(dir: Vec3) -> Option<(f32, f32)> {
+    let dir = dir.normalize_or_zero();
+
+    if dir == Vec3::ZERO {
+        return None;
+    }
+
+    let pitch = dir.y.clamp(-1., 1.).asin().clamp(-MAX_PITCH, MAX_PITCH);
+    let yaw = (-dir.x).atan2(-dir.z);
+
+    Some((yaw, pitch))
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct Camera {
@@ -61,11 +216,39 @@ pub struct Camera {
     pub bg_layout: wgpu::BindGroupLayout,
     pub bloom_treshold: Vec3, // To prepare for post FX
     pub tonemap_type: TonemapType,
+    pub debug_view: DebugView,
+    /// Speed mapped to the end of the debug palette
+    pub debug_max_speed: f32,
+    /// Radians per pixel for mouse look and orbit dragging
+    pub mouse_sensitivity: f32,
+    /// Units per second
+    pub move_speed: f32,
+    /// Radians per second for the arrow keys
+    pub rotation_speed: f32,
+    /// Multiplies the move speed while shift is held
+    pub boost_multiplier: f32,
+    /// Easing of key movement and rotation, 0 is instant and 1 is very floaty
+    pub damping: f32,
     pub look_at: Vec3,
     fov: f32,  // Field of view (frustum vertical degrees)
     near: f32, // What is too close to show
     far: f32,  // What is too far to show
     buffer: wgpu::Buffer,
+    mode: CameraMode,
+    /// Startup settings, reset returns to them
+    home: CameraBuilder,
+
+    is_dragging: bool,
+    /// Right mouse button, mouse motion only rotates the camera while it's held
+    is_look_pressed: bool,
+    last_cursor: Option<Vec2>,
+    /// Mouse input since the last update, in pixels
+    pending_drag: Vec2,
+    pending_scroll: f32,
+    /// Units per second in world space, eased towards the pressed keys
+    velocity: Vec3,
+    /// Yaw and pitch radians per second, eased towards the pressed keys
+    angular_velocity: Vec2,
 
     is_forward_pressed: bool,
     is_backward_pressed: bool,
@@ -77,6 +260,7 @@ pub struct Camera {
     is_rotate_up_pressed: bool,
     is_down_pressed: bool,
     is_rotate_down_pressed: bool,
+    is_boost_pressed: bool,
 
     proj: Mat4,
     bg: wgpu::BindGroup,
@@ -89,6 +273,8 @@ struct CameraUniform {
     position: glam::Vec3,
     bloom_treshold: glam::Vec3,
     tonemap: u32,
+    debug_view: u32,
+    debug_max_speed: f32,
 }
 
 impl Camera {
@@ -96,17 +282,117 @@ impl Camera {
         &self.bg
     }
 
+    pub fn near(&self) -> f32 {
+        self.near
+    }
+
+    pub fn far(&self) -> f32 {
+        self.far
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    /// Normalized world space direction the camera looks at
+    pub fn view_dir(&self) -> Vec3 {
+        self.rotated_view_dir().normalize()
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Vertical field of view in radians
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switching to orbit keeps the current view direction, the position moves onto the orbit
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+
+        if let CameraMode::Orbit { .. } = self.mode {
+            self.pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+            self.apply_orbit();
+        }
+    }
+
+    /// Orbit mode rotates around the target, free fly turns the camera towards it
+    pub fn set_target(&mut self, new_target: Vec3) {
+        match &mut self.mode {
+            CameraMode::Orbit { target, .. } => {
+                *target = new_target;
+                self.apply_orbit();
+            }
+            CameraMode::FreeFly => {
+                if let Some((yaw, pitch)) = yaw_pitch_towards(new_target - self.position) {
+                    self.yaw = yaw;
+                    self.pitch = pitch;
+                }
+            }
+        }
+    }
+
+    /// Point in front of the camera at the orbit distance, or the default distance in free fly
+    pub fn view_target(&self) -> Vec3 {
+        let distance = match self.mode {
+            CameraMode::Orbit { distance, .. } => distance,
+            CameraMode::FreeFly => DEFAULT_DISTANCE,
+        };
+
+        self.position + self.rotated_view_dir().normalize() * distance
+    }
+
     pub fn new(gfx_state: &GfxState) -> Self {
+        CameraBuilder::default().build(gfx_state)
+    }
+
+    /// Restores a saved camera, reset returns to the saved placement
+    pub fn from_export(gfx_state: &GfxState, export: ExportCamera) -> Self {
+        let builder = CameraBuilder {
+            position: export.position,
+            target: None,
+            orientation: Some((export.yaw, export.pitch)),
+            fov_deg: export.fov.to_degrees(),
+            near: export.near,
+            far: export.far,
+        };
+
+        Self::from_builder(gfx_state, builder)
+    }
+
+    pub fn export(&self) {
+        let export = ExportCamera {
+            position: self.position,
+            yaw: self.yaw,
+            pitch: self.pitch,
+            fov: self.fov,
+            near: self.near,
+            far: self.far,
+        };
+
+        Persistence::write_to_file(export, ExportType::Camera);
+    }
+
+    fn from_builder(gfx_state: &GfxState, builder: CameraBuilder) -> Self {
         let device = &gfx_state.device;
 
-        let position = Vec3::new(0., 0., 10.);
+        let position = builder.position;
         let view_dir = Vec3::new(0., 0., -10.);
         let look_at = position + view_dir;
-        let pitch = 0.;
-        let yaw = 0.;
-        let near = 0.1;
-        let far = 100.0;
-        let fov = (45.0f32).to_radians();
+        let (yaw, pitch) = builder.orientation();
+        let near = builder.near;
+        let far = builder.far;
+        let fov = builder.fov_deg.to_radians();
         let aspect = gfx_state.aspect();
         let proj = Mat4::perspective_rh(fov, aspect, near, far);
 
@@ -120,7 +406,8 @@ impl Camera {
         let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                // Compute for the frustum culling
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT | wgpu::ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
@@ -152,8 +439,24 @@ impl Camera {
             buffer,
             bg_layout,
             bg,
+            mode: CameraMode::FreeFly,
+            home: builder,
+            is_dragging: false,
+            is_look_pressed: false,
+            last_cursor: None,
+            pending_drag: Vec2::ZERO,
+            pending_scroll: 0.,
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec2::ZERO,
             bloom_treshold: Vec3::MAX,
             tonemap_type: TonemapType::AcesNarkowicz,
+            debug_view: DebugView::None,
+            debug_max_speed: 20.,
+            mouse_sensitivity: 0.005,
+            move_speed: 3.,
+            rotation_speed: 1.,
+            boost_multiplier: 4.,
+            damping: 0.,
             proj,
             is_forward_pressed: false,
             is_backward_pressed: false,
@@ -165,6 +468,7 @@ impl Camera {
             is_rotate_up_pressed: false,
             is_down_pressed: false,
             is_rotate_down_pressed: false,
+            is_boost_pressed: false,
         }
     }
 
@@ -176,64 +480,144 @@ impl Camera {
         let gfx = &gfx.read().await;
 
         if events.reset_camera {
-            camera.pitch = 0.;
-            camera.yaw = 0.;
-            camera.position = glam::Vec3::new(0., 0., 10.);
+            (camera.yaw, camera.pitch) = camera.home.orientation();
+            camera.position = camera.home.position;
             camera.view_dir = glam::Vec3::new(0., 0., -10.);
+
+            camera.velocity = Vec3::ZERO;
+            camera.angular_velocity = Vec2::ZERO;
+
+            if let CameraMode::Orbit { distance, .. } = &mut camera.mode {
+                *distance = DEFAULT_DISTANCE;
+            }
         }
 
+        if let CameraMode::Orbit { .. } = camera.mode {
+            // Real delta, so the camera keeps moving while the simulation is paused
+            camera.update_orbit(clock.real_delta_sec());
+
+            let buf_content = camera.create_buffer_content();
+            gfx.queue.write_buffer(&camera.buffer, 0, &buf_content);
+            return;
+        }
+
+        camera.pending_drag = Vec2::ZERO;
+        camera.pending_scroll = 0.;
+
         let queue = &gfx.queue;
-        let speed = 3.0;
+        let delta_sec = clock.real_delta_sec();
 
-        let move_delta = speed * clock.delta_sec();
-        let rotation = move_delta / 3.0;
         let yaw_mat = Mat3::from_rotation_y(camera.yaw);
         let pitch_mat = Mat3::from_rotation_x(camera.pitch);
 
-        let rotate_vec = |unrotated_vec: Vec3| yaw_mat * pitch_mat * unrotated_vec;
+        let local_dir = Vec3::new(
+            axis(camera.is_right_pressed, camera.is_left_pressed),
+            0.,
+            axis(camera.is_backward_pressed, camera.is_forward_pressed),
+        );
 
-        if camera.is_forward_pressed {
-            camera.position += rotate_vec(Vec3::new(0., 0., -move_delta));
-        }
+        let mut target_velocity = yaw_mat * pitch_mat * local_dir;
+        target_velocity.y += axis(camera.is_up_pressed, camera.is_down_pressed);
+        target_velocity *= camera.boosted_move_speed();
 
-        if camera.is_backward_pressed {
-            camera.position += rotate_vec(Vec3::new(0., 0., move_delta));
-        }
+        let ease = camera.ease_factor(delta_sec);
+        camera.velocity = camera.velocity.lerp(target_velocity, ease);
+        camera.position += camera.velocity * delta_sec;
 
-        if camera.is_up_pressed {
-            camera.position.y += move_delta;
-        }
+        camera.update_angular_velocity(delta_sec);
+        camera.yaw += camera.angular_velocity.x * delta_sec;
+        camera.pitch += camera.angular_velocity.y * delta_sec;
 
-        if camera.is_down_pressed {
-            camera.position.y -= move_delta;
-        }
+        camera.pitch = camera.pitch.clamp(-MAX_PITCH, MAX_PITCH);
 
-        if camera.is_left_pressed {
-            camera.position += rotate_vec(Vec3::new(-move_delta, 0., 0.));
-        }
+        let buf_content = camera.create_buffer_content();
+        queue.write_buffer(&camera.buffer, 0, &buf_content);
+    }
 
-        if camera.is_right_pressed {
-            camera.position += rotate_vec(Vec3::new(move_delta, 0., 0.));
-        }
+    pub fn set_move_speed(&mut self, move_speed: f32) {
+        self.move_speed = move_speed.max(0.);
+    }
 
-        if camera.is_rotate_up_pressed {
-            camera.pitch += rotation;
-        }
+    pub fn set_rotation_speed(&mut self, rotation_speed: f32) {
+        self.rotation_speed = rotation_speed.max(0.);
+    }
 
-        if camera.is_rotate_down_pressed {
-            camera.pitch -= rotation;
+    pub fn set_boost_multiplier(&mut self, boost_multiplier: f32) {
+        self.boost_multiplier = boost_multiplier.max(0.);
+    }
+
+    /// Move speed including the boost while shift is held
+    fn boosted_move_speed(&self) -> f32 {
+        if self.is_boost_pressed {
+            self.move_speed * self.boost_multiplier
+        } else {
+            self.move_speed
         }
+    }
 
-        if camera.is_rotate_left_pressed {
-            camera.yaw += rotation;
+    /// Lerp factor towards the target speed, frame rate independent
+    fn ease_factor(&self, delta_sec: f32) -> f32 {
+        if self.damping <= 0. {
+            return 1.;
         }
 
-        if camera.is_rotate_right_pressed {
-            camera.yaw -= rotation;
+        1. - (-delta_sec / (self.damping * MAX_DAMPING_SEC)).exp()
+    }
+
+    fn update_angular_velocity(&mut self, delta_sec: f32) {
+        let target = Vec2::new(
+            axis(self.is_rotate_left_pressed, self.is_rotate_right_pressed),
+            axis(self.is_rotate_up_pressed, self.is_rotate_down_pressed),
+        ) * self.rotation_speed;
+
+        let ease = self.ease_factor(delta_sec);
+        self.angular_velocity = self.angular_velocity.lerp(target, ease);
+    }
+
+    fn update_orbit(&mut self, delta_sec: f32) {
+        let move_delta = self.boosted_move_speed() * delta_sec;
+
+        // Dragging right or down moves the camera right or up around the target
+        self.yaw -= self.pending_drag.x * self.mouse_sensitivity;
+        self.pitch -= self.pending_drag.y * self.mouse_sensitivity;
+
+        self.update_angular_velocity(delta_sec);
+        self.yaw += self.angular_velocity.x * delta_sec;
+        self.pitch += self.angular_velocity.y * delta_sec;
+
+        self.pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+
+        let near = self.near;
+
+        if let CameraMode::Orbit { distance, .. } = &mut self.mode {
+            // Zooms relative to the distance, so it feels the same close by and far away
+            *distance *= 1. - self.pending_scroll / SCROLL_LINE_PX * 0.1;
+
+            if self.is_forward_pressed {
+                *distance -= move_delta;
+            }
+
+            if self.is_backward_pressed {
+                *distance += move_delta;
+            }
+
+            // Stays beyond the near plane, otherwise the target gets clipped
+            *distance = distance.max(near * 2.);
         }
 
-        let buf_content = camera.create_buffer_content();
-        queue.write_buffer(&camera.buffer, 0, &buf_content);
+        self.pending_drag = Vec2::ZERO;
+        self.pending_scroll = 0.;
+        self.apply_orbit();
+    }
+
+    /// Places the camera on the orbit, looking at the target
+    fn apply_orbit(&mut self) {
+        let dir = self.rotated_view_dir().normalize();
+
+        if let CameraMode::Orbit { target, distance } = &mut self.mode {
+            *distance = distance.max(self.near * 2.);
+            self.position = *target - dir * *distance;
+        }
     }
 
     pub fn resize(&mut self, gfx_state: &GfxState) {
@@ -276,6 +660,57 @@ impl Camera {
             VirtualKeyCode::Space => {
                 self.is_up_pressed = is_pressed;
             }
+            VirtualKeyCode::LShift | VirtualKeyCode::RShift => {
+                self.is_boost_pressed = is_pressed;
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Mouse look with raw mouse motion, only while the right mouse button is held
+    pub fn process_mouse(&mut self, delta_x: f32, delta_y: f32) {
+        if !self.is_look_pressed {
+            return;
+        }
+
+        self.yaw -= delta_x * self.mouse_sensitivity;
+        self.pitch = (self.pitch - delta_y * self.mouse_sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Mouse drag rotates and scroll zooms in orbit mode, returns true if the event was used
+    pub fn process_mouse_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.is_dragging = *state == ElementState::Pressed;
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } => {
+                self.is_look_pressed = *state == ElementState::Pressed;
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = Vec2::new(position.x as f32, position.y as f32);
+
+                if let (true, Some(last)) = (self.is_dragging, self.last_cursor) {
+                    self.pending_drag += cursor - last;
+                }
+
+                self.last_cursor = Some(cursor);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.pending_scroll += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y * SCROLL_LINE_PX,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32,
+                };
+            }
             _ => return false,
         }
 
@@ -292,6 +727,8 @@ impl Camera {
             position: self.position,
             bloom_treshold: self.bloom_treshold,
             tonemap: self.tonemap_type as u32,
+            debug_view: self.debug_view as u32,
+            debug_max_speed: self.debug_max_speed,
         };
 
         let mut buffer = UniformBuffer::new(vec![]);
@@ -299,17 +736,33 @@ impl Camera {
         buffer.into_inner()
     }
 
-    pub fn view_mat(&self) -> Mat4 {
+    fn rotated_view_dir(&self) -> Vec3 {
         let yaw_mat = Mat3::from_rotation_y(self.yaw);
         let pitch_mat = Mat3::from_rotation_x(self.pitch);
 
-        let rotated_view_dir = yaw_mat * pitch_mat * self.view_dir;
+        yaw_mat * pitch_mat * self.view_dir
+    }
+
+    pub fn view_mat(&self) -> Mat4 {
+        let rotated_view_dir = self.rotated_view_dir();
         Mat4::look_at_rh(self.position, self.position + rotated_view_dir, Vec3::Y)
     }
 
     pub fn view_proj(&self, view_mat: &Mat4) -> Mat4 {
         OPENGL_TO_WGPU_MATRIX * self.proj * (*view_mat)
     }
+
+    /// Projects a world position to normalized device coordinates, None when behind the camera
+    pub fn project(&self, position: Vec3) -> Option<Vec3> {
+        let view_proj = self.view_proj(&self.view_mat());
+        let clip = view_proj * position.extend(1.);
+
+        if clip.w <= 0. {
+            return None;
+        }
+
+        Some(clip.truncate() / clip.w)
+    }
 }
 
 fn buffer_size() -> u64 {
@@ -317,6 +770,7 @@ fn buffer_size() -> u64 {
     let view_mat_size = 16;
     let position_size = 4;
     let bloom_treshold_size = 4;
+    // tonemap, debug view and debug max speed
     let tonemap_size = 4;
 
     // The most aligned member of that strut is aligned to 16. As such
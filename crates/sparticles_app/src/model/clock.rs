@@ -1,11 +1,33 @@
 use std::time::{Duration, Instant};
 
+/// Smallest time scale, pausing is done with `Clock::pause`
+pub const MIN_TIME_SCALE: f32 = 0.001;
+
 pub struct Clock {
     instant: Instant,
     last_update: Duration,
     current_delta: Duration,
+    /// Delta of the last update even while paused, for the camera and frame timings
+    real_delta: Duration,
+    elapsed: Duration,
     cpu_time: Duration,
     frame: usize,
+    max_fps: Option<u32>,
+    mode: ClockMode,
+    time_scale: f32,
+    paused: bool,
+    step_requested: bool,
+    advanced: bool,
+}
+
+/// How `Clock::update` advances the time
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClockMode {
+    /// Measures the wall clock time between updates
+    #[default]
+    Realtime,
+    /// Advances by `dt` seconds every update, so runs and recordings are reproducible
+    Fixed { dt: f32 },
 }
 
 impl Default for Clock {
@@ -14,21 +36,115 @@ impl Default for Clock {
             instant: Instant::now(),
             last_update: Duration::ZERO,
             current_delta: Duration::ZERO,
+            real_delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
             cpu_time: Duration::ZERO,
             frame: 0,
+            max_fps: None,
+            mode: ClockMode::Realtime,
+            time_scale: 1.,
+            paused: false,
+            step_requested: false,
+            advanced: false,
         }
     }
 }
 
 impl Clock {
-    pub fn update(&mut self, play: bool) {
+    /// While paused the delta stays zero and the frame doesn't change, unless a step was requested
+    pub fn update(&mut self) {
         let now = self.instant.elapsed();
-        self.current_delta = now - self.last_update;
+
+        self.real_delta = match self.mode {
+            ClockMode::Realtime => now - self.last_update,
+            ClockMode::Fixed { dt } => Duration::from_secs_f32(dt),
+        };
+
         self.last_update = now;
+        self.advanced = !self.paused || self.step_requested;
+        self.step_requested = false;
 
-        if play {
-            self.frame += 1;
+        if !self.advanced {
+            self.current_delta = Duration::ZERO;
+            return;
         }
+
+        self.current_delta = self.real_delta.mul_f32(self.time_scale);
+        self.elapsed += self.current_delta;
+        self.frame += 1;
+    }
+
+    /// Advances the clock by a fixed delta instead of the wall clock time, also while paused
+    pub fn advance(&mut self, delta_sec: f32) {
+        self.current_delta = Duration::from_secs_f32(delta_sec);
+        self.real_delta = self.current_delta;
+        self.last_update = self.instant.elapsed();
+        self.elapsed += self.current_delta;
+        self.frame += 1;
+        self.advanced = true;
+    }
+
+    /// Multiplies the simulation delta, below 1 is slow motion and above fast forward
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(MIN_TIME_SCALE);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Jumps the simulated time without advancing a frame, see `EmitterState::seek`
+    pub fn set_elapsed_sec(&mut self, elapsed_sec: f32) {
+        self.elapsed = Duration::from_secs_f32(elapsed_sec.max(0.));
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advances exactly one frame on the next update while paused
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    /// False when the last update was paused, the simulation should skip the frame
+    pub fn advanced(&self) -> bool {
+        self.advanced
+    }
+
+    /// A fixed mode without a positive `dt` falls back to realtime
+    pub fn set_mode(&mut self, mode: ClockMode) {
+        self.mode = match mode {
+            ClockMode::Fixed { dt } if 0. < dt => mode,
+            _ => ClockMode::Realtime,
+        };
+    }
+
+    pub fn mode(&self) -> ClockMode {
+        self.mode
+    }
+
+    /// Caps the frame rate, None renders as fast as the present mode allows
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.max_fps = max_fps.filter(|fps| 0 < *fps);
+    }
+
+    pub fn max_fps(&self) -> Option<u32> {
+        self.max_fps
+    }
+
+    /// Earliest moment the next frame may start, None when the frame rate isn't capped
+    pub fn next_frame_deadline(&self) -> Option<Instant> {
+        let frame_time = Duration::from_secs_f64(1. / self.max_fps? as f64);
+        Some(self.instant + self.last_update + frame_time)
     }
 
     pub fn measure_cpu_time(&mut self) {
@@ -44,12 +160,16 @@ impl Clock {
         self.current_delta.as_secs_f32()
     }
 
+    pub fn real_delta_sec(&self) -> f32 {
+        self.real_delta.as_secs_f32()
+    }
+
     pub fn elapsed_sec(&self) -> f32 {
-        self.instant.elapsed().as_secs_f32()
+        self.elapsed.as_secs_f32()
     }
 
     pub fn elapsed_sec_f64(&self) -> f64 {
-        self.instant.elapsed().as_secs_f64()
+        self.elapsed.as_secs_f64()
     }
 
     pub fn frame(&self) -> usize {
@@ -65,7 +185,7 @@ impl Clock {
     }
 
     pub fn fps_text(&self) -> String {
-        format!("FPS: {:.0}", 1. / self.delta_sec())
+        format!("FPS: {:.0}", 1. / self.real_delta_sec())
     }
 
     pub fn total_elapsed_text(&self) -> String {
@@ -80,7 +200,7 @@ impl Clock {
     }
 
     pub fn frame_time_text(&self) -> String {
-        let frame_time = self.delta_sec();
+        let frame_time = self.real_delta_sec();
         format!("Frame time ms: {:.0}", frame_time * 1000.)
     }
 }
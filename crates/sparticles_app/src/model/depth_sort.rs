@@ -0,0 +1,316 @@
+use super::{Camera, EmitterUniform, FrustumCull, GfxState};
+use crate::shaders::ShaderOptions;
+use egui_wgpu::wgpu;
+use egui_wgpu::wgpu::util::DeviceExt;
+
+/// Size of SortEntry in depth_sort.wgsl
+const ENTRY_SIZE: u64 = 2 * 4;
+/// Size of SortStep in depth_sort.wgsl
+const STEP_SIZE: u64 = 4 * 4;
+const WORKGROUP_SIZE: u32 = 128;
+
+/// Bitonic sort of the particles by view depth, far to near. The sorted indices replace the
+/// visible indices of the frustum cull, so the vertex shader draws them back to front
+pub struct DepthSort {
+    keys_pipeline: wgpu::ComputePipeline,
+    step_pipeline: wgpu::ComputePipeline,
+    write_pipeline: wgpu::ComputePipeline,
+    bg_layout: wgpu::BindGroupLayout,
+    step_layout: wgpu::BindGroupLayout,
+    /// One per particle buffer, indexed like the emitter bind groups
+    bgs: Vec<wgpu::BindGroup>,
+    step_bg: wgpu::BindGroup,
+    /// Power of two padded, holds a key and particle index per slot
+    entry_buffer: wgpu::Buffer,
+    /// Dynamic offset between the sort steps
+    step_stride: u32,
+    step_count: u32,
+    entry_count: u32,
+    particle_count: u32,
+}
+
+impl DepthSort {
+    pub fn new(
+        gfx: &GfxState,
+        camera: &Camera,
+        uniform: &EmitterUniform,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+        frustum_cull: &FrustumCull,
+    ) -> Self {
+        let device = &gfx.device;
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Depth sort layout"),
+            entries: &[
+                storage_entry(0, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(2, false),
+                storage_entry(3, true),
+                storage_entry(4, false),
+            ],
+        });
+
+        let step_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Depth sort step layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(STEP_SIZE),
+                },
+                count: None,
+            }],
+        });
+
+        let shader = gfx.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["depth_sort.wgsl"],
+            label: "Depth sort",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth sort pipeline layout"),
+            bind_group_layouts: &[&bg_layout, &camera.bg_layout, &step_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Depth sort pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        let keys_pipeline = create_pipeline("keys");
+        let step_pipeline = create_pipeline("sort_step");
+        let write_pipeline = create_pipeline("write_indices");
+
+        let step_stride = device
+            .limits()
+            .min_uniform_buffer_offset_alignment
+            .max(STEP_SIZE as u32);
+
+        let particle_count = Self::particle_count(uniform);
+        let entry_count = particle_count.next_power_of_two();
+        let entry_buffer = Self::create_entry_buffer(device, entry_count);
+
+        let bgs = Self::create_bind_groups(
+            device,
+            &bg_layout,
+            particle_buffers,
+            emitter_buffer,
+            frustum_cull,
+            &entry_buffer,
+        );
+
+        Self {
+            keys_pipeline,
+            step_pipeline,
+            write_pipeline,
+            step_bg: Self::create_step_bg(device, &step_layout, entry_count, step_stride),
+            bg_layout,
+            step_layout,
+            bgs,
+            entry_buffer,
+            step_stride,
+            step_count: Self::sort_steps(entry_count).len() as u32,
+            entry_count,
+            particle_count,
+        }
+    }
+
+    /// Sizes the sort for the new particle buffers, call after resizing the frustum cull
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        uniform: &EmitterUniform,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+        frustum_cull: &FrustumCull,
+    ) {
+        let particle_count = Self::particle_count(uniform);
+        let entry_count = particle_count.next_power_of_two();
+
+        if entry_count != self.entry_count {
+            self.entry_buffer = Self::create_entry_buffer(device, entry_count);
+            self.step_bg =
+                Self::create_step_bg(device, &self.step_layout, entry_count, self.step_stride);
+            self.step_count = Self::sort_steps(entry_count).len() as u32;
+            self.entry_count = entry_count;
+        }
+
+        self.particle_count = particle_count;
+        self.bgs = Self::create_bind_groups(
+            device,
+            &self.bg_layout,
+            particle_buffers,
+            emitter_buffer,
+            frustum_cull,
+            &self.entry_buffer,
+        );
+    }
+
+    /// Sorts every slot, or only the visible particles when the frustum cull ran first
+    pub fn compute<'a>(
+        &'a self,
+        camera: &'a Camera,
+        bg_nr: usize,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        let entry_workgroups = self.entry_count.div_ceil(WORKGROUP_SIZE);
+
+        c_pass.set_bind_group(0, &self.bgs[bg_nr], &[]);
+        c_pass.set_bind_group(1, camera.bg(), &[]);
+        c_pass.set_bind_group(2, &self.step_bg, &[0]);
+
+        c_pass.set_pipeline(&self.keys_pipeline);
+        c_pass.dispatch_workgroups(entry_workgroups, 1, 1);
+
+        // Every step reads the swaps of the previous one, so each is its own dispatch
+        c_pass.set_pipeline(&self.step_pipeline);
+
+        for step in 0..self.step_count {
+            c_pass.set_bind_group(2, &self.step_bg, &[step * self.step_stride]);
+            c_pass.dispatch_workgroups(entry_workgroups, 1, 1);
+        }
+
+        c_pass.set_pipeline(&self.write_pipeline);
+        c_pass.dispatch_workgroups(self.particle_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Same length as the visible indices, capped so the padded entry count fits in u32
+    fn particle_count(uniform: &EmitterUniform) -> u32 {
+        uniform.particle_count().clamp(1, 1 << 31) as u32
+    }
+
+    /// The (j, k) pairs of a bitonic sort over `entry_count` entries, in dispatch order
+    fn sort_steps(entry_count: u32) -> Vec<[u32; 2]> {
+        let mut steps = vec![];
+        let mut k = 2;
+
+        while k <= entry_count {
+            let mut j = k / 2;
+
+            while 0 < j {
+                steps.push([j, k]);
+                j /= 2;
+            }
+
+            k *= 2;
+        }
+
+        steps
+    }
+
+    fn create_entry_buffer(device: &wgpu::Device, entry_count: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Depth sort entries buffer"),
+            size: entry_count as u64 * ENTRY_SIZE,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_bind_groups(
+        device: &wgpu::Device,
+        bg_layout: &wgpu::BindGroupLayout,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+        frustum_cull: &FrustumCull,
+        entry_buffer: &wgpu::Buffer,
+    ) -> Vec<wgpu::BindGroup> {
+        particle_buffers
+            .iter()
+            .map(|particle_buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Depth sort bind group"),
+                    layout: bg_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: particle_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: emitter_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: frustum_cull.visible_buffer().as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: frustum_cull.indirect_buffer().as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: entry_buffer.as_entire_binding(),
+                        },
+                    ],
+                })
+            })
+            .collect()
+    }
+
+    fn create_step_bg(
+        device: &wgpu::Device,
+        step_layout: &wgpu::BindGroupLayout,
+        entry_count: u32,
+        step_stride: u32,
+    ) -> wgpu::BindGroup {
+        let steps = Self::sort_steps(entry_count);
+        let stride = step_stride as usize / 4;
+
+        // One step per stride, so a dynamic offset selects it. A single entry still needs a step
+        let mut contents = vec![0u32; steps.len().max(1) * stride];
+
+        for (i, [j, k]) in steps.into_iter().enumerate() {
+            contents[i * stride] = j;
+            contents[i * stride + 1] = k;
+        }
+
+        let step_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Depth sort steps buffer"),
+            contents: bytemuck::cast_slice(&contents),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Depth sort step bind group"),
+            layout: step_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &step_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(STEP_SIZE),
+                }),
+            }],
+        })
+    }
+}
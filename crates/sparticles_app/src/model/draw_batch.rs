@@ -0,0 +1,416 @@
+use super::emitter_state::{FsEntryPoint, RenderPipelineOptions};
+use super::{
+    BlendMode, Camera, EmitterState, GfxState, Material, MaterialRef, MeshRef, ShadingModel,
+    PARTICLE_STRIDE,
+};
+use crate::shaders::{ShaderOptions, DIR_BATCHED, DIR_UNLIT, SDR_PBR, SDR_TONEMAPPING};
+use egui_wgpu::wgpu;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// State consecutive emitters need in common to share a draw
+#[derive(Clone, PartialEq)]
+pub struct BatchKey {
+    pub mesh: MeshRef,
+    pub material: MaterialRef,
+    pub fs_entry_point: FsEntryPoint,
+    pub blend_mode: BlendMode,
+    pub shading_model: ShadingModel,
+    pub depth_bias: i32,
+}
+
+/// Material, fragment entry point, blend mode, shading model, depth bias and split target
+type PipelineKey = (
+    MaterialRef,
+    FsEntryPoint,
+    BlendMode,
+    ShadingModel,
+    i32,
+    bool,
+);
+
+/// One draw of a layer, in draw order
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawRun {
+    /// Index into the layer emitters, drawn with the pipeline of the emitter
+    Emitter(usize),
+    /// Consecutive layer emitters drawn as one instance range
+    Merged {
+        emitters: Range<usize>,
+        batch: usize,
+    },
+}
+
+/// Copy source of one emitter in a merged draw
+pub struct BatchSource<'a> {
+    pub particle_buffer: &'a wgpu::Buffer,
+    pub emitter_buffer: &'a wgpu::Buffer,
+    pub particle_count: u64,
+}
+
+pub struct BatchOptions<'a> {
+    pub gfx: &'a GfxState,
+    pub camera: &'a Camera,
+    pub lights_layout: &'a wgpu::BindGroupLayout,
+    pub material: &'a Material,
+    pub key: &'a BatchKey,
+    pub split_target: bool,
+    pub sources: &'a [BatchSource<'a>],
+}
+
+/// Buffers of one merged draw, reused every frame and grown when a run doesn't fit
+struct BatchBuffers {
+    particle_buffer: wgpu::Buffer,
+    emitter_buffer: wgpu::Buffer,
+    /// First instance of every emitter, unused slots hold u32::MAX
+    first_instance_buffer: wgpu::Buffer,
+    bg: wgpu::BindGroup,
+    pipeline_key: PipelineKey,
+}
+
+/// Draws runs of consecutive emitters that share mesh, material and pipeline state with one
+/// draw call. Their particles and emitter uniforms are copied next to each other before the
+/// render pass, the vertex shader looks up the emitter of an instance by its first instance.
+/// Merged emitters draw all active particle slots, the live counter draw isn't used
+pub struct DrawBatcher {
+    bg_layout: wgpu::BindGroupLayout,
+    shaders: HashMap<ShadingModel, wgpu::ShaderModule>,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    batches: Vec<BatchBuffers>,
+    /// Merged draws prepared this frame, every layer uses its own buffers
+    batch_count: usize,
+}
+
+impl DrawBatcher {
+    pub fn new(gfx: &GfxState) -> Self {
+        let device = &gfx.device;
+
+        let storage_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Draw batch layout"),
+            entries: &[storage_entry(0), storage_entry(1), storage_entry(2)],
+        });
+
+        Self {
+            bg_layout,
+            shaders: HashMap::new(),
+            pipelines: HashMap::new(),
+            batches: vec![],
+            batch_count: 0,
+        }
+    }
+
+    /// Call once per frame before rendering the layers
+    pub fn begin_frame(&mut self) {
+        self.batch_count = 0;
+    }
+
+    /// Splits the emitters of a layer into runs, in draw order. Emitters without a key are
+    /// drawn alone, runs end when the next key differs or the particles exceed `max_size`
+    pub fn plan<K: PartialEq>(
+        keys: &[Option<K>],
+        sizes: &[u64],
+        max_size: u64,
+    ) -> Vec<Range<usize>> {
+        let mut runs = vec![];
+        let mut start = 0;
+        let mut run_size = 0;
+
+        for (i, key) in keys.iter().enumerate() {
+            let joins = start < i
+                && key.is_some()
+                && *key == keys[start]
+                && run_size + sizes[i] <= max_size;
+
+            if !joins {
+                if start < i {
+                    runs.push(start..i);
+                }
+
+                start = i;
+                run_size = 0;
+            }
+
+            run_size += sizes[i];
+        }
+
+        if start < keys.len() {
+            runs.push(start..keys.len());
+        }
+
+        runs
+    }
+
+    /// Largest particle buffer a merged draw can bind
+    pub fn max_size(gfx: &GfxState) -> u64 {
+        let limits = gfx.device.limits();
+
+        (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size)
+    }
+
+    /// Copies the particles and emitter uniforms of a run into the next batch buffers and
+    /// builds its pipeline on first use, returns the batch
+    pub fn prepare(
+        &mut self,
+        options: BatchOptions<'_>,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> usize {
+        let BatchOptions {
+            gfx,
+            sources,
+            key,
+            split_target,
+            ..
+        } = options;
+
+        let pipeline_key = (
+            key.material.clone(),
+            key.fs_entry_point,
+            key.blend_mode,
+            key.shading_model,
+            key.depth_bias,
+            split_target,
+        );
+
+        if !self.pipelines.contains_key(&pipeline_key) {
+            let pipeline = self.create_pipeline(&options);
+            self.pipelines.insert(pipeline_key.clone(), pipeline);
+        }
+
+        let batch = self.batch_count;
+        self.batch_count += 1;
+
+        let particle_size: u64 = sources
+            .iter()
+            .map(|src| src.particle_count * PARTICLE_STRIDE)
+            .sum();
+        let emitter_stride = sources[0].emitter_buffer.size();
+        let emitter_count = sources.len() as u64;
+
+        let fits = self.batches.get(batch).is_some_and(|buffers| {
+            particle_size <= buffers.particle_buffer.size()
+                && emitter_count * emitter_stride <= buffers.emitter_buffer.size()
+        });
+
+        if !fits {
+            let buffers = self.create_buffers(
+                gfx,
+                particle_size,
+                emitter_count,
+                emitter_stride,
+                pipeline_key.clone(),
+            );
+
+            if batch < self.batches.len() {
+                self.batches[batch] = buffers;
+            } else {
+                self.batches.push(buffers);
+            }
+        }
+
+        let buffers = &mut self.batches[batch];
+        buffers.pipeline_key = pipeline_key;
+
+        let capacity = (buffers.first_instance_buffer.size() / 4) as usize;
+        let mut first_instances = Vec::with_capacity(capacity);
+        let mut instance = 0;
+
+        for (i, src) in sources.iter().enumerate() {
+            first_instances.push(instance as u32);
+
+            encoder.copy_buffer_to_buffer(
+                src.particle_buffer,
+                0,
+                &buffers.particle_buffer,
+                instance * PARTICLE_STRIDE,
+                src.particle_count * PARTICLE_STRIDE,
+            );
+
+            encoder.copy_buffer_to_buffer(
+                src.emitter_buffer,
+                0,
+                &buffers.emitter_buffer,
+                i as u64 * emitter_stride,
+                emitter_stride,
+            );
+
+            instance += src.particle_count;
+        }
+
+        // Unused slots stay above every instance, so the lookup in the shader skips them
+        first_instances.resize(capacity, u32::MAX);
+
+        gfx.queue.write_buffer(
+            &buffers.first_instance_buffer,
+            0,
+            bytemuck::cast_slice(&first_instances),
+        );
+
+        batch
+    }
+
+    pub fn pipeline(&self, batch: usize) -> &wgpu::RenderPipeline {
+        &self.pipelines[&self.batches[batch].pipeline_key]
+    }
+
+    pub fn bg(&self, batch: usize) -> &wgpu::BindGroup {
+        &self.batches[batch].bg
+    }
+
+    fn create_pipeline(&mut self, options: &BatchOptions<'_>) -> wgpu::RenderPipeline {
+        let gfx = options.gfx;
+        let material = options.material;
+        let key = options.key;
+
+        let shader = self.shaders.entry(key.shading_model).or_insert_with(|| {
+            gfx.create_shader_builtin(ShaderOptions {
+                files: &[SDR_TONEMAPPING, SDR_PBR, "particle.wgsl"],
+                if_directives: match key.shading_model {
+                    ShadingModel::Pbr => &[DIR_BATCHED],
+                    ShadingModel::Unlit => &[DIR_BATCHED, DIR_UNLIT],
+                },
+                label: "Merged particle render",
+            })
+        });
+
+        let pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Merged particle render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &options.camera.bg_layout,
+                    &material.bg_layout,
+                    &self.bg_layout,
+                    options.lights_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        EmitterState::create_pipeline(RenderPipelineOptions {
+            shader,
+            layout: &pipeline_layout,
+            material,
+            gfx,
+            fs_entry_point: key.fs_entry_point,
+            blend_mode: key.blend_mode,
+            depth_bias: key.depth_bias,
+            depth_prepass: false,
+            depth_sorted: false,
+            split_target: options.split_target,
+        })
+    }
+
+    fn create_buffers(
+        &self,
+        gfx: &GfxState,
+        particle_size: u64,
+        emitter_count: u64,
+        emitter_stride: u64,
+        pipeline_key: PipelineKey,
+    ) -> BatchBuffers {
+        let device = &gfx.device;
+        let emitter_capacity = emitter_count.next_power_of_two();
+
+        let create_buffer = |label: &str, size: u64| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                mapped_at_creation: false,
+                size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            })
+        };
+
+        let particle_buffer = create_buffer(
+            "Draw batch particles",
+            particle_size
+                .next_power_of_two()
+                .min(Self::max_size(gfx))
+                .max(PARTICLE_STRIDE),
+        );
+        let emitter_buffer =
+            create_buffer("Draw batch emitters", emitter_capacity * emitter_stride);
+        let first_instance_buffer =
+            create_buffer("Draw batch first instances", emitter_capacity * 4);
+
+        let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bg_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: particle_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: emitter_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: first_instance_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Draw batch bind group"),
+        });
+
+        BatchBuffers {
+            particle_buffer,
+            emitter_buffer,
+            first_instance_buffer,
+            bg,
+            pipeline_key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_emitters_merge_into_one_draw() {
+        let keys = vec![Some(1); 50];
+        let sizes = vec![1000; 50];
+
+        assert_eq!(DrawBatcher::plan(&keys, &sizes, u64::MAX), vec![0..50]);
+    }
+
+    #[test]
+    fn runs_keep_draw_order() {
+        let keys = [
+            Some(1),
+            Some(1),
+            Some(2),
+            Some(1),
+            None,
+            None,
+            Some(2),
+            Some(2),
+        ];
+        let sizes = [10; 8];
+
+        assert_eq!(
+            DrawBatcher::plan(&keys, &sizes, u64::MAX),
+            vec![0..2, 2..3, 3..4, 4..5, 5..6, 6..8]
+        );
+    }
+
+    #[test]
+    fn runs_split_at_max_size() {
+        let keys = [Some(1); 5];
+        let sizes = [40; 5];
+
+        assert_eq!(
+            DrawBatcher::plan(&keys, &sizes, 100),
+            vec![0..2, 2..4, 4..5]
+        );
+    }
+}
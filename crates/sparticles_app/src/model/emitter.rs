@@ -1,15 +1,19 @@
-use super::Clock;
+use super::{Clock, Mesh};
 use crate::loader::{Model, BUILTIN_ID, CIRCLE_MAT_ID, CIRCLE_MESH_ID};
 use crate::model::state::FastFetch;
 use crate::traits::{FromRGB, HandleAngles};
+use crate::util::hash;
+use crate::util::math::catmull_rom;
 use crate::util::ID;
 use async_std::sync::RwLock;
-use glam::{f32::Vec3, f32::Vec4};
+use egui_winit::egui::WidgetText;
+use glam::{f32::Mat4, f32::Quat, f32::Vec3, f32::Vec4};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
-const PARTICLE_BUFFER_SIZE: u64 = 26 * 4;
+/// Stride of one particle, matches the 16 byte aligned struct in particle_layout.wgsl
+pub const PARTICLE_STRIDE: u64 = 28 * 4;
 
 pub struct EmitSpawnOptions {
     pub spawn_count: u32,
@@ -27,13 +31,540 @@ impl Boundry {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Distribution used to sample the spawn position inside the emitter box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SpawnDistribution {
+    #[default]
+    Uniform,
+    Gaussian,
+    EdgeBiased,
+}
+
+impl From<SpawnDistribution> for WidgetText {
+    fn from(value: SpawnDistribution) -> Self {
+        match value {
+            SpawnDistribution::Uniform => "Uniform".into(),
+            SpawnDistribution::Gaussian => "Gaussian".into(),
+            SpawnDistribution::EdgeBiased => "Edge biased".into(),
+        }
+    }
+}
+
+impl From<SpawnDistribution> for f32 {
+    fn from(value: SpawnDistribution) -> Self {
+        value as u32 as f32
+    }
+}
+
+/// How an emitter schedules its spawning
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum SpawnMode {
+    /// A batch of the spawn count every spawn delay
+    #[default]
+    Continuous,
+    /// Spawns (seconds, count) once each and stops, for explosions and fireworks
+    Burst {
+        bursts: Vec<(f32, u32)>,
+        /// Bursts already due when the emitter starts or is reset fire at once instead of being skipped
+        catch_up: bool,
+    },
+}
+
+impl SpawnMode {
+    /// Default burst for switching modes in the editor
+    pub fn burst() -> Self {
+        SpawnMode::Burst {
+            bursts: vec![(0., 100)],
+            catch_up: false,
+        }
+    }
+}
+
+impl From<&SpawnMode> for WidgetText {
+    fn from(value: &SpawnMode) -> Self {
+        match value {
+            SpawnMode::Continuous => "Continuous".into(),
+            SpawnMode::Burst { .. } => "Burst".into(),
+        }
+    }
+}
+
+/// Local space moves already spawned particles along with the emitter box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SimulationSpace {
+    Local,
+    #[default]
+    World,
+}
+
+impl From<SimulationSpace> for WidgetText {
+    fn from(value: SimulationSpace) -> Self {
+        match value {
+            SimulationSpace::Local => "Local".into(),
+            SimulationSpace::World => "World".into(),
+        }
+    }
+}
+
+impl From<SimulationSpace> for f32 {
+    fn from(value: SimulationSpace) -> Self {
+        value as u32 as f32
+    }
+}
+
+/// Unlit skips the scene lights, cheaper and correct for glowing sprites
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ShadingModel {
+    #[default]
+    Pbr,
+    Unlit,
+}
+
+impl From<ShadingModel> for WidgetText {
+    fn from(value: ShadingModel) -> Self {
+        match value {
+            ShadingModel::Pbr => "PBR".into(),
+            ShadingModel::Unlit => "Unlit".into(),
+        }
+    }
+}
+
+/// How the particle color is combined with the frame, additive suits fire and magic effects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+    Replace,
+    /// Opaque meshes are drawn with replace, until the tint or color makes them translucent
+    #[default]
+    Alpha,
+    Additive,
+}
+
+impl From<BlendMode> for WidgetText {
+    fn from(value: BlendMode) -> Self {
+        match value {
+            BlendMode::Replace => "Replace".into(),
+            BlendMode::Alpha => "Alpha".into(),
+            BlendMode::Additive => "Additive".into(),
+        }
+    }
+}
+
+/// Stretched billboards are scaled along their velocity, for sparks and rain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BillboardMode {
+    #[default]
+    Round,
+    Stretched,
+}
+
+impl From<BillboardMode> for WidgetText {
+    fn from(value: BillboardMode) -> Self {
+        match value {
+            BillboardMode::Round => "Round".into(),
+            BillboardMode::Stretched => "Stretched".into(),
+        }
+    }
+}
+
+impl From<BillboardMode> for f32 {
+    fn from(value: BillboardMode) -> Self {
+        value as u32 as f32
+    }
+}
+
+/// Where particles spawn, curve points are relative to the box position and follow its rotation
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum SpawnShape {
+    #[default]
+    Box,
+    Curve {
+        control_points: Vec<Vec3>,
+    },
+    /// Circle around the axis through the box position, ignores the box rotation
+    Ring {
+        radius: f32,
+        /// Radial spread, the tube diameter for a torus
+        thickness: f32,
+        axis: Vec3,
+        /// Fills a round tube instead of a flat band
+        torus: bool,
+        /// Emits away from the ring center instead of along the box direction
+        outward: bool,
+    },
+    /// Every vertex of the mesh emits in turn, the box scales, rotates and moves the mesh
+    MeshVertices {
+        mesh: MeshRef,
+    },
+    /// Fills a ball around the box position, particles move away from its center
+    Sphere {
+        radius: f32,
+    },
+    /// Spawns on a disc and emits within `angle_deg` of the box direction, for flames and sparks
+    Cone {
+        angle_deg: f32,
+        radius: f32,
+    },
+    /// Flat round area facing the box direction
+    Disc {
+        radius: f32,
+    },
+    /// Random points on the triangles of the mesh weighted by area, the box transforms the mesh
+    MeshSurface {
+        mesh: MeshRef,
+    },
+}
+
+impl SpawnShape {
+    /// Points uploaded to the emitter shader, spaced evenly along the curve
+    pub const CURVE_SAMPLES: usize = 64;
+
+    /// Catmull-Rom curve through the control points, sampled at even arc length intervals
+    pub fn curve_samples(&self) -> Vec<Vec3> {
+        let points = match self {
+            SpawnShape::Curve { control_points } if 2 <= control_points.len() => control_points,
+            SpawnShape::Curve { control_points } => {
+                return vec![control_points.first().copied().unwrap_or(Vec3::ZERO)];
+            }
+            _ => return vec![Vec3::ZERO],
+        };
+
+        // Dense polyline first, the spline parameter doesn't map linearly to distance
+        let steps_per_segment = 32;
+        let segment_count = points.len() - 1;
+        let mut polyline = Vec::with_capacity(segment_count * steps_per_segment + 1);
+
+        for i in 0..segment_count {
+            let p0 = points[i.saturating_sub(1)];
+            let p1 = points[i];
+            let p2 = points[i + 1];
+            let p3 = points[(i + 2).min(points.len() - 1)];
+
+            for step in 0..steps_per_segment {
+                let t = step as f32 / steps_per_segment as f32;
+                polyline.push(catmull_rom(p0, p1, p2, p3, t));
+            }
+        }
+
+        polyline.push(points[points.len() - 1]);
+
+        let mut lengths = vec![0.];
+
+        for pair in polyline.windows(2) {
+            let last = lengths[lengths.len() - 1];
+            lengths.push(last + pair[0].distance(pair[1]));
+        }
+
+        let total_length = lengths[lengths.len() - 1];
+        let last_sample = (Self::CURVE_SAMPLES - 1) as f32;
+        let mut segment = 0;
+
+        (0..Self::CURVE_SAMPLES)
+            .map(|i| {
+                let target = total_length * i as f32 / last_sample;
+
+                while segment + 2 < lengths.len() && lengths[segment + 1] < target {
+                    segment += 1;
+                }
+
+                let range = lengths[segment + 1] - lengths[segment];
+                let local_t = if 0. < range {
+                    (target - lengths[segment]) / range
+                } else {
+                    0.
+                };
+
+                polyline[segment].lerp(polyline[segment + 1], local_t)
+            })
+            .collect()
+    }
+
+    /// Default ring for switching shapes in the editor
+    pub fn ring() -> Self {
+        SpawnShape::Ring {
+            radius: 2.,
+            thickness: 0.2,
+            axis: Vec3::Y,
+            torus: false,
+            outward: true,
+        }
+    }
+
+    /// Axis and params for the emitter uniform. Ring: radius, thickness, torus, outward.
+    /// Sphere and disc: radius. Cone: radius, angle in radians
+    pub fn shape_content(&self) -> [f32; 8] {
+        match self {
+            SpawnShape::Ring {
+                radius,
+                thickness,
+                axis,
+                torus,
+                outward,
+            } => [
+                axis.x,
+                axis.y,
+                axis.z,
+                0.,
+                *radius,
+                *thickness,
+                *torus as u32 as f32,
+                *outward as u32 as f32,
+            ],
+            SpawnShape::Sphere { radius } | SpawnShape::Disc { radius } => {
+                [0., 1., 0., 0., *radius, 0., 0., 0.]
+            }
+            SpawnShape::Cone { angle_deg, radius } => [
+                0.,
+                1.,
+                0.,
+                0.,
+                *radius,
+                angle_deg.clamp(0., 180.).to_radians(),
+                0.,
+                0.,
+            ],
+            _ => [0., 1., 0., 0., 0., 0., 0., 0.],
+        }
+    }
+
+    /// Curve samples as vec4<f32> floats for the spawn curve storage buffer
+    pub fn curve_content(&self) -> Vec<f32> {
+        self.curve_samples()
+            .iter()
+            .flat_map(|p| p.extend(0.).to_array())
+            .collect()
+    }
+
+    /// Mesh that has to be loaded for the spawn points
+    pub fn mesh(&self) -> Option<&MeshRef> {
+        match self {
+            SpawnShape::MeshVertices { mesh } | SpawnShape::MeshSurface { mesh } => Some(mesh),
+            _ => None,
+        }
+    }
+
+    /// Min and max of the mesh vertices, appended to the mesh spawn points. The shader maps
+    /// the spawn point into these bounds for the color mask, raw positions can be any size
+    fn bounds_content(mesh: &Mesh) -> [f32; 8] {
+        let mut min = Vec3::splat(f32::MAX);
+        let mut max = Vec3::splat(f32::MIN);
+
+        for vertex in mesh.vertices.iter() {
+            min = min.min(Vec3::from(vertex.position));
+            max = max.max(Vec3::from(vertex.position));
+        }
+
+        if mesh.vertices.is_empty() {
+            min = Vec3::ZERO;
+            max = Vec3::ZERO;
+        }
+
+        [min.x, min.y, min.z, 0., max.x, max.y, max.z, 0.]
+    }
+
+    /// Three points per triangle, the w of the first is the area of this and all previous
+    /// triangles as fraction of the total. The shader searches it so every area spawns evenly
+    fn triangles_content(mesh: &Mesh) -> Vec<f32> {
+        let position = |idx: u32| {
+            mesh.vertices
+                .get(idx as usize)
+                .map_or(Vec3::ZERO, |v| Vec3::from(v.position))
+        };
+
+        let triangles: Vec<[Vec3; 3]> = if mesh.indices.is_empty() {
+            (0..mesh.vertices.len() as u32 / 3)
+                .map(|i| [position(i * 3), position(i * 3 + 1), position(i * 3 + 2)])
+                .collect()
+        } else {
+            mesh.indices
+                .chunks_exact(3)
+                .map(|tri| [position(tri[0]), position(tri[1]), position(tri[2])])
+                .collect()
+        };
+
+        if triangles.is_empty() {
+            return vec![0., 0., 0., 1., 0., 0., 0., 0., 0., 0., 0., 0.];
+        }
+
+        let areas: Vec<f32> = triangles
+            .iter()
+            .map(|[a, b, c]| (*b - *a).cross(*c - *a).length() * 0.5)
+            .collect();
+        let total_area: f32 = areas.iter().sum();
+
+        let mut cumulative = 0.;
+        let mut content = Vec::with_capacity(triangles.len() * 12);
+
+        for (i, [a, b, c]) in triangles.iter().enumerate() {
+            // Degenerate meshes fall back to picking every triangle equally
+            cumulative += if 0. < total_area {
+                areas[i] / total_area
+            } else {
+                1. / triangles.len() as f32
+            };
+
+            // Rounding could leave the last fraction just below 1
+            let fraction = if i + 1 == triangles.len() {
+                1.
+            } else {
+                cumulative
+            };
+
+            content.extend_from_slice(&[a.x, a.y, a.z, fraction]);
+            content.extend_from_slice(&[b.x, b.y, b.z, 0.]);
+            content.extend_from_slice(&[c.x, c.y, c.z, 0.]);
+        }
+
+        content
+    }
+
+    /// Spawn curve buffer content, mesh vertices are stored in their original order. Mesh
+    /// shapes end with the bounds of the mesh
+    pub fn spawn_points_content(&self, collection: &HashMap<ID, Model>) -> Vec<f32> {
+        let mesh = match self {
+            SpawnShape::MeshVertices { mesh } | SpawnShape::MeshSurface { mesh } => {
+                collection.get_mesh(mesh)
+            }
+            _ => return self.curve_content(),
+        };
+
+        let mut content = match self {
+            SpawnShape::MeshSurface { .. } => Self::triangles_content(mesh),
+            _ if mesh.vertices.is_empty() => vec![0.; 4],
+            _ => mesh
+                .vertices
+                .iter()
+                .flat_map(|v| [v.position[0], v.position[1], v.position[2], 0.])
+                .collect(),
+        };
+
+        content.extend_from_slice(&Self::bounds_content(mesh));
+        content
+    }
+}
+
+impl From<&SpawnShape> for WidgetText {
+    fn from(value: &SpawnShape) -> Self {
+        match value {
+            SpawnShape::Box => "Box".into(),
+            SpawnShape::Curve { .. } => "Curve".into(),
+            SpawnShape::Ring { .. } => "Ring".into(),
+            SpawnShape::MeshVertices { .. } => "Mesh vertices".into(),
+            SpawnShape::Sphere { .. } => "Sphere".into(),
+            SpawnShape::Cone { .. } => "Cone".into(),
+            SpawnShape::Disc { .. } => "Disc".into(),
+            SpawnShape::MeshSurface { .. } => "Mesh surface".into(),
+        }
+    }
+}
+
+impl From<&SpawnShape> for f32 {
+    fn from(value: &SpawnShape) -> Self {
+        match value {
+            SpawnShape::Box => 0.,
+            SpawnShape::Curve { .. } => 1.,
+            SpawnShape::Ring { .. } => 2.,
+            SpawnShape::MeshVertices { .. } => 3.,
+            SpawnShape::Sphere { .. } => 4.,
+            SpawnShape::Cone { .. } => 5.,
+            SpawnShape::Disc { .. } => 6.,
+            SpawnShape::MeshSurface { .. } => 7.,
+        }
+    }
+}
+
+/// Halves the particles per batch for every LOD distance the emitter is past
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EmitterLod {
+    pub enabled: bool,
+    /// Camera distances at which the particle count halves
+    pub distances: [f32; 3],
+    /// Multiplies the camera distance, higher values reduce the particle count sooner
+    pub bias: f32,
+}
+
+impl Default for EmitterLod {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            distances: [25., 50., 100.],
+            bias: 1.,
+        }
+    }
+}
+
+/// Longest gap between batches when converting a spawn rate, slower rates spawn single particles
+const SPAWN_RATE_BATCH_SEC: f32 = 0.1;
+
+/// Spawn count and delay that emit `rate` particles per second, low rates get a long delay
+/// instead of a count that rounds to zero
+fn spawn_rate_batches(rate: f32) -> (u32, f32) {
+    let rate = rate.max(f32::EPSILON);
+    let count = (rate * SPAWN_RATE_BATCH_SEC).ceil().max(1.);
+
+    (count as u32, count / rate)
+}
+
+/// Particles alive at the same time for these spawn settings
+fn required_particles(
+    spawn_mode: &SpawnMode,
+    spawn_count: u32,
+    spawn_delay_sec: f32,
+    particle_lifetime_sec: f32,
+) -> u64 {
+    match spawn_mode {
+        SpawnMode::Continuous => {
+            let batches = (particle_lifetime_sec / spawn_delay_sec).ceil() as u64;
+            spawn_count as u64 * batches
+        }
+        SpawnMode::Burst { bursts, .. } => bursts
+            .iter()
+            .map(|(sec, _)| {
+                bursts
+                    .iter()
+                    .filter(|(other, _)| sec - particle_lifetime_sec < *other && other <= sec)
+                    .map(|(_, count)| *count as u64)
+                    .sum::<u64>()
+            })
+            .max()
+            .unwrap_or(0),
+    }
+}
+
+fn default_spawn_spread() -> f32 {
+    0.5
+}
+
+fn default_particle_mass() -> Boundry {
+    Boundry(1., 1.)
+}
+
+fn default_stretch_factor() -> f32 {
+    0.1
+}
+
+fn default_circle_aa() -> f32 {
+    1.
+}
+
+fn default_update_interval() -> u32 {
+    1
+}
+
+fn default_tint() -> Vec4 {
+    Vec4::ONE
+}
+
+fn default_light_intensity() -> Vec4 {
+    Vec4::ONE
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MeshRef {
     pub collection_id: ID,
     pub mesh_id: ID,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MaterialRef {
     pub collection_id: ID,
     pub material_id: ID,
@@ -51,12 +582,107 @@ pub struct EmitterUniform {
 
     pub spawn_count: u32,
     pub spawn_delay_sec: f32,
+    /// Particles spawned per batch, at most the spawn count. Reset to the spawn count
+    /// every frame so emitter animations can lower it
+    #[serde(skip)]
+    pub emit_count: u32,
 
     pub box_position: Vec3,
     /// width, height, depth
     pub box_dimensions: Vec3,
     /// yaw, pitch, roll
     pub box_rotation: Vec3,
+    #[serde(default)]
+    pub spawn_distribution: SpawnDistribution,
+    /// Gaussian: standard deviation relative to the box, edge biased: strength of the bias
+    #[serde(default = "default_spawn_spread")]
+    pub spawn_spread: f32,
+    #[serde(default)]
+    pub simulation_space: SimulationSpace,
+    #[serde(default)]
+    pub spawn_shape: SpawnShape,
+    #[serde(default)]
+    pub spawn_mode: SpawnMode,
+    #[serde(skip)]
+    prev_box_placement: Option<Mat4>,
+    /// Box movement of the frames since the last compute frame
+    #[serde(skip)]
+    pending_box_delta: Mat4,
+    /// Moves and turns the particles from the previous box placement to the current one
+    #[serde(skip)]
+    box_delta: Mat4,
+    /// Emitters are rendered per layer, each layer has its own frame texture. Higher layers are
+    /// drawn over the lower ones before the post fx
+    #[serde(default)]
+    pub layer: u8,
+    #[serde(default)]
+    pub lod: EmitterLod,
+    /// Emitters in the same layer render from low to high draw order, ties keep insertion order
+    #[serde(default)]
+    pub draw_order: i32,
+    /// Constant depth bias, lets coplanar emitters avoid z-fighting
+    #[serde(default)]
+    pub depth_bias: i32,
+    /// Writes depth in a separate pass first, so the color pass only shades visible fragments.
+    /// Only used for opaque mesh particles, circles blend their edges
+    #[serde(default)]
+    pub depth_prepass: bool,
+    /// Light emitters ignore it, they are always drawn without scene lighting
+    #[serde(default)]
+    pub shading_model: ShadingModel,
+    /// Circle edge anti-aliasing, 0 is coverage based (hard edge), 1 is analytic
+    #[serde(default = "default_circle_aa")]
+    pub circle_aa: f32,
+    #[serde(default)]
+    pub billboard_mode: BillboardMode,
+    /// Extra length per unit of speed for stretched billboards
+    #[serde(default = "default_stretch_factor")]
+    pub stretch_factor: f32,
+    /// Draws only the particles in the camera frustum, the compaction pass pays off for large
+    /// particle counts. Light emitters are never culled
+    #[serde(default)]
+    pub frustum_culling: bool,
+    /// Draws the particles back to front for correct alpha blending, sorted on the GPU every
+    /// frame. Sorted emitters don't write depth and skip the depth prepass
+    #[serde(default)]
+    pub depth_sorted: bool,
+    /// Every mode has its own pipeline, so switching doesn't recreate the emitter
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    /// Set every frame by the emitter state, false when the emitter can't cull or sort
+    #[serde(skip)]
+    pub culling_active: bool,
+    #[serde(skip)]
+    pub sorting_active: bool,
+    /// Simulate only every n frames, rendering still happens every frame
+    #[serde(default = "default_update_interval")]
+    pub update_interval: u32,
+    /// Seconds added to the elapsed time, phase shifts otherwise identical emitters
+    #[serde(default)]
+    pub time_offset: f32,
+    #[serde(skip)]
+    pending_delta_sec: f32,
+    #[serde(skip)]
+    last_frame: usize,
+    #[serde(skip)]
+    looped: bool,
+    #[serde(skip)]
+    fired_all_bursts: bool,
+    #[serde(skip)]
+    pending_burst: u32,
+    #[serde(skip)]
+    burst_from: u32,
+    #[serde(skip)]
+    burst_count: u32,
+    /// Elapsed time of the last compute frame in burst mode, None after a reset
+    #[serde(skip)]
+    burst_elapsed_sec: Option<f32>,
+    /// Next particle a burst mode burst overwrites
+    #[serde(skip)]
+    burst_cursor: u32,
+    /// Amount of LOD distances the emitter is past, see `lod_spawn_count`
+    #[serde(skip)]
+    lod_level: u32,
 
     /// Diffusion emission in radians
     pub diff_width: f32,
@@ -65,12 +691,29 @@ pub struct EmitterUniform {
 
     pub hdr_mul: f32,
     pub particle_color: Vec4,
+    /// Multiplied into the final color of every particle, e.g. to fade the whole emitter
+    #[serde(default = "default_tint")]
+    pub tint: Vec4,
+    /// Light brightness at the start, a third, two thirds and the end of the particle life.
+    /// Only used by light emitters, scales both their own color and the light they cast
+    #[serde(default = "default_light_intensity")]
+    pub light_intensity: Vec4,
+    /// Columns, rows and frames per second of a sprite sheet, zero when no flipbook plays.
+    /// Set every frame by the particle animations
+    #[serde(skip)]
+    pub flipbook: Vec4,
     pub particle_friction_coefficient: f32,
     pub particle_speed: Boundry,
     pub particle_size: Boundry,
     /// Mass per size 1
     pub particle_material_mass: f32,
+    /// Random mass multiplier per particle (min, max)
+    #[serde(default = "default_particle_mass")]
+    pub particle_mass: Boundry,
     pub particle_lifetime_sec: f32,
+    /// Texture in assets/textures sampled at the spawn position, multiplied with the particle color
+    #[serde(default)]
+    pub color_mask: Option<String>,
     pub mesh: MeshRef,
     pub material: MaterialRef,
 }
@@ -88,6 +731,25 @@ pub struct EmitterSettings {
     pub box_position: Vec3,
     pub box_dimensions: Vec3,
     pub box_rotation_deg: Vec3,
+    pub spawn_distribution: SpawnDistribution,
+    pub spawn_spread: f32,
+    pub simulation_space: SimulationSpace,
+    pub spawn_shape: SpawnShape,
+    pub spawn_mode: SpawnMode,
+    pub layer: u8,
+    pub lod: EmitterLod,
+    pub draw_order: i32,
+    pub depth_bias: i32,
+    pub depth_prepass: bool,
+    pub shading_model: ShadingModel,
+    pub circle_aa: f32,
+    pub billboard_mode: BillboardMode,
+    pub stretch_factor: f32,
+    pub frustum_culling: bool,
+    pub depth_sorted: bool,
+    pub blend_mode: BlendMode,
+    pub update_interval: u32,
+    pub time_offset: f32,
 
     pub diff_width_deg: f32,
     pub diff_depth_deg: f32,
@@ -96,9 +758,34 @@ pub struct EmitterSettings {
     pub particle_speed_max: f32,
     pub particle_size_min: f32,
     pub particle_size_max: f32,
+    pub particle_mass_min: f32,
+    pub particle_mass_max: f32,
 
     pub particle_color: Vec4,
     pub hdr_mul: f32,
+    pub tint: Vec4,
+    pub light_intensity: Vec4,
+    pub color_mask: Option<String>,
+}
+
+impl EmitterSettings {
+    pub fn spawn_rate_per_sec(&self) -> f32 {
+        self.spawn_count as f32 / self.spawn_delay_sec
+    }
+
+    /// Sets spawn count and delay, applied with the other spawn settings on recreate
+    pub fn set_spawn_rate_per_sec(&mut self, rate: f32) {
+        (self.spawn_count, self.spawn_delay_sec) = spawn_rate_batches(rate);
+    }
+
+    pub fn required_particles(&self) -> u64 {
+        required_particles(
+            &self.spawn_mode,
+            self.spawn_count,
+            self.spawn_delay_sec,
+            self.particle_lifetime_sec,
+        )
+    }
 }
 
 impl EmitterUniform {
@@ -123,24 +810,65 @@ impl EmitterUniform {
             spawn_until: 0,
             spawn_count,
             spawn_batches_count,
+            emit_count: spawn_count,
 
             box_position: box_pos,
             box_dimensions,
             box_rotation,
+            spawn_distribution: SpawnDistribution::Uniform,
+            spawn_spread: default_spawn_spread(),
+            simulation_space: SimulationSpace::World,
+            spawn_shape: SpawnShape::Box,
+            spawn_mode: SpawnMode::Continuous,
+            prev_box_placement: None,
+            pending_box_delta: Mat4::IDENTITY,
+            box_delta: Mat4::IDENTITY,
+            layer: 0,
+            lod: EmitterLod::default(),
+            draw_order: 0,
+            depth_bias: 0,
+            depth_prepass: false,
+            shading_model: ShadingModel::Pbr,
+            circle_aa: default_circle_aa(),
+            billboard_mode: BillboardMode::Round,
+            stretch_factor: default_stretch_factor(),
+            frustum_culling: false,
+            depth_sorted: false,
+            blend_mode: BlendMode::Alpha,
+            culling_active: false,
+            sorting_active: false,
+            update_interval: default_update_interval(),
+            time_offset: 0.,
+            pending_delta_sec: 0.,
+            last_frame: 0,
+            looped: false,
+            fired_all_bursts: false,
+            pending_burst: 0,
+            burst_from: 0,
+            burst_count: 0,
+            burst_elapsed_sec: None,
+            burst_cursor: 0,
+            lod_level: 0,
 
             hdr_mul: 1.0,
+            tint: default_tint(),
+            light_intensity: default_light_intensity(),
+            flipbook: Vec4::ZERO,
 
             diff_width: diffusion_width_rad,
             diff_depth: diffusion_depth_rad,
 
             particle_material_mass: 5.,
+            particle_mass: default_particle_mass(),
             particle_lifetime_sec,
             particle_speed: Boundry(10., 15.),
             particle_size: Boundry(0.1, 0.15),
             particle_friction_coefficient: 0.99,
             particle_color: Vec4::from_rgb(0, 255, 0),
 
-            iteration: 1000,
+            color_mask: None,
+
+            iteration: u32::MAX,
             elapsed_sec: 0.,
             delta_sec: 0.0,
 
@@ -159,6 +887,20 @@ impl EmitterUniform {
         self.box_rotation = settings.box_rotation_deg.to_radians();
         self.box_dimensions = settings.box_dimensions;
         self.box_position = settings.box_position;
+        self.spawn_distribution = settings.spawn_distribution;
+        self.spawn_spread = settings.spawn_spread;
+        self.simulation_space = settings.simulation_space;
+        self.layer = settings.layer;
+        self.lod = settings.lod;
+        self.draw_order = settings.draw_order;
+        self.circle_aa = settings.circle_aa;
+        self.billboard_mode = settings.billboard_mode;
+        self.stretch_factor = settings.stretch_factor;
+        self.frustum_culling = settings.frustum_culling;
+        self.blend_mode = settings.blend_mode;
+        self.update_interval = settings.update_interval;
+        self.time_offset = settings.time_offset;
+        self.spawn_mode = settings.spawn_mode.clone();
 
         self.diff_width = settings.diff_width_deg.to_radians();
         self.diff_depth = settings.diff_depth_deg.to_radians();
@@ -169,8 +911,13 @@ impl EmitterUniform {
         self.particle_size.0 = settings.particle_size_min;
         self.particle_size.1 = settings.particle_size_max;
 
+        self.particle_mass.0 = settings.particle_mass_min;
+        self.particle_mass.1 = settings.particle_mass_max;
+
         self.particle_color = settings.particle_color;
         self.hdr_mul = settings.hdr_mul;
+        self.tint = settings.tint;
+        self.light_intensity = settings.light_intensity;
 
         // TODO iets beter dan string kopieren
         self.mesh = settings.mesh.clone();
@@ -180,6 +927,15 @@ impl EmitterUniform {
             self.spawn_count = settings.spawn_count;
             self.spawn_delay_sec = settings.spawn_delay_sec;
             self.particle_lifetime_sec = settings.particle_lifetime_sec;
+            self.spawn_batches_count =
+                (self.particle_lifetime_sec / self.spawn_delay_sec).ceil() as u32;
+            self.fit_particle_capacity();
+            self.color_mask = settings.color_mask.clone();
+            self.spawn_shape = settings.spawn_shape.clone();
+            self.depth_bias = settings.depth_bias;
+            self.depth_prepass = settings.depth_prepass;
+            self.shading_model = settings.shading_model;
+            self.depth_sorted = settings.depth_sorted;
         }
     }
 
@@ -191,15 +947,39 @@ impl EmitterUniform {
             box_position: self.box_position,
             box_dimensions: self.box_dimensions,
             box_rotation_deg: self.box_rotation.to_degrees(),
+            spawn_distribution: self.spawn_distribution,
+            spawn_spread: self.spawn_spread,
+            simulation_space: self.simulation_space,
+            spawn_shape: self.spawn_shape.clone(),
+            spawn_mode: self.spawn_mode.clone(),
+            layer: self.layer,
+            lod: self.lod,
+            draw_order: self.draw_order,
+            depth_bias: self.depth_bias,
+            depth_prepass: self.depth_prepass,
+            shading_model: self.shading_model,
+            circle_aa: self.circle_aa,
+            billboard_mode: self.billboard_mode,
+            stretch_factor: self.stretch_factor,
+            frustum_culling: self.frustum_culling,
+            depth_sorted: self.depth_sorted,
+            blend_mode: self.blend_mode,
+            update_interval: self.update_interval,
+            time_offset: self.time_offset,
             diff_width_deg: self.diff_width.to_degrees(),
             diff_depth_deg: self.diff_depth.to_degrees(),
             particle_lifetime_sec: self.particle_lifetime_sec,
             particle_color: self.particle_color,
             hdr_mul: self.hdr_mul,
+            tint: self.tint,
+            light_intensity: self.light_intensity,
+            color_mask: self.color_mask.clone(),
             particle_speed_min: self.particle_speed.0,
             particle_speed_max: self.particle_speed.1,
             particle_size_min: self.particle_size.0,
             particle_size_max: self.particle_size.1,
+            particle_mass_min: self.particle_mass.0,
+            particle_mass_max: self.particle_mass.1,
             mesh: self.mesh.clone(),
             material: self.material.clone(),
 
@@ -208,29 +988,279 @@ impl EmitterUniform {
     }
 
     pub fn update(&mut self, clock: &Clock) {
-        self.delta_sec = clock.delta_sec();
-        self.elapsed_sec = clock.elapsed_sec();
+        self.looped = false;
+        self.emit_count = self.spawn_count;
+
+        // Paused frames don't add to the simulated time
+        if clock.frame() == self.last_frame {
+            return;
+        }
+
+        self.last_frame = clock.frame();
+        self.pending_delta_sec += clock.delta_sec();
+
+        // Skipped frames add up, so throttled emitters simulate the same amount of time
+        if !self.is_compute_frame(clock) {
+            return;
+        }
+
+        self.delta_sec = self.pending_delta_sec;
+        self.pending_delta_sec = 0.;
+        self.elapsed_sec = clock.elapsed_sec() + self.time_offset;
+
+        if let SpawnMode::Burst { .. } = self.spawn_mode {
+            self.update_bursts();
+            return;
+        }
+
+        self.fired_all_bursts = false;
 
         let new_iteration = (self.elapsed_sec / self.spawn_delay_sec) as u32;
-        let current_batch = new_iteration % self.spawn_batches_count;
+        let batches = self.spawn_batches_count;
 
         if new_iteration != self.iteration {
-            self.spawn_from = current_batch * self.spawn_count;
-            self.spawn_until = self.spawn_from + self.spawn_count;
+            // Every batch crossed since the last compute frame spawns, so emitters with a
+            // spawn delay shorter than the frame time still reach their spawn rate.
+            // A reset clock or the first frame only spawns the current batch
+            let crossed = match new_iteration.checked_sub(self.iteration) {
+                Some(crossed) => crossed.clamp(1, batches),
+                None => 1,
+            };
+            let first_iteration = new_iteration + 1 - crossed;
+
+            // LOD shrinks the batches, so the spawn ring stays inside the active particles
+            let spawn_count = self.lod_spawn_count();
+            self.spawn_from = (first_iteration % batches) * spawn_count;
+            self.spawn_until = self.spawn_from + crossed * spawn_count;
+            self.looped = (first_iteration..=new_iteration).any(|i| 0 < i && i % batches == 0);
             self.iteration = new_iteration;
         } else {
             // disables spawning in compute shader
             self.spawn_from = 0;
             self.spawn_until = 0;
         }
+
+        // Bursts overwrite the oldest particles, which are the batch after the current one
+        let next_batch = self.iteration.wrapping_add(1) % self.spawn_batches_count;
+        self.burst_from = next_batch * self.lod_spawn_count();
+        self.burst_count = self.pending_burst.min(self.active_particle_count() as u32);
+        self.pending_burst = 0;
+    }
+
+    /// Fires the bursts scheduled since the last compute frame, continuous spawning is off
+    fn update_bursts(&mut self) {
+        let SpawnMode::Burst { bursts, catch_up } = &self.spawn_mode else {
+            return;
+        };
+
+        let from_sec = match self.burst_elapsed_sec {
+            Some(last_sec) => last_sec,
+            None if *catch_up => f32::NEG_INFINITY,
+            None => self.elapsed_sec - self.delta_sec,
+        };
+
+        let in_frame = |sec: f32| from_sec < sec && sec <= self.elapsed_sec;
+
+        self.pending_burst += bursts
+            .iter()
+            .filter(|(sec, _)| in_frame(*sec))
+            .map(|(_, count)| count)
+            .sum::<u32>();
+
+        // The live counter tells when the particles of the last burst decayed
+        let last_sec = bursts.iter().map(|(sec, _)| *sec).reduce(f32::max);
+        self.fired_all_bursts = last_sec.is_some_and(|sec| sec <= self.elapsed_sec);
+
+        self.burst_elapsed_sec = Some(self.elapsed_sec);
+        self.spawn_from = 0;
+        self.spawn_until = 0;
+
+        // Consecutive bursts take the following particles, so they don't overwrite each other
+        let particle_count = self.active_particle_count().max(1) as u32;
+        self.burst_cursor %= particle_count;
+        self.burst_from = self.burst_cursor;
+        self.burst_count = self.pending_burst.min(particle_count);
+        self.burst_cursor = (self.burst_cursor + self.burst_count) % particle_count;
+        self.pending_burst = 0;
+    }
+
+    /// Forgets the spawn progress, for when the particles are cleared
+    pub fn reset_time(&mut self) {
+        self.iteration = u32::MAX;
+        self.burst_elapsed_sec = None;
+        self.pending_delta_sec = 0.;
+        self.pending_box_delta = Mat4::IDENTITY;
+        self.pending_burst = 0;
+        self.looped = false;
+        self.fired_all_bursts = false;
+    }
+
+    /// Copy under a new id with a random time offset within one spawn cycle
+    pub fn duplicate(&self, id: ID, seed: f32) -> Self {
+        let mut copy = self.clone();
+        copy.id = id;
+        copy.time_offset = hash::random(seed, self.elapsed_sec) * self.spawn_cycle_sec();
+        copy
+    }
+
+    /// Seconds until spawning wraps around to the first batch
+    pub fn spawn_cycle_sec(&self) -> f32 {
+        self.spawn_delay_sec * self.spawn_batches_count as f32
+    }
+
+    /// Spawns `count` extra particles on the next simulated frame
+    pub fn emit_burst(&mut self, count: u32) {
+        self.pending_burst += count;
+    }
+
+    /// True on the frame spawning wrapped around to the first batch
+    pub fn has_looped(&self) -> bool {
+        self.looped
+    }
+
+    /// True once the last burst of a burst mode emitter fired, until the time is reset
+    pub fn has_fired_all_bursts(&self) -> bool {
+        self.fired_all_bursts
+    }
+
+    pub fn is_compute_frame(&self, clock: &Clock) -> bool {
+        clock
+            .frame()
+            .is_multiple_of(self.update_interval.max(1) as usize)
+    }
+
+    /// Tracks how far the box moved and turned since the last compute frame, call before
+    /// creating the buffer content. Scaling the box doesn't move the particles
+    pub fn update_box_delta(&mut self, clock: &Clock) {
+        let placement =
+            Mat4::from_rotation_translation(self.box_rotation_quat(), self.box_position);
+        let prev = self.prev_box_placement.unwrap_or(placement);
+
+        // Skipped and paused frames add up, like the pending delta time
+        self.pending_box_delta = placement * prev.inverse() * self.pending_box_delta;
+        self.prev_box_placement = Some(placement);
+
+        if clock.advanced() && self.is_compute_frame(clock) {
+            self.box_delta = self.pending_box_delta;
+            self.pending_box_delta = Mat4::IDENTITY;
+        } else {
+            self.box_delta = Mat4::IDENTITY;
+        }
+    }
+
+    /// Scales the particles per batch by the distance between the box and the camera.
+    /// Call before `update`, which spawns into the batches of this level
+    pub fn update_lod(&mut self, camera_position: Vec3) {
+        if !self.lod.enabled {
+            self.lod_level = 0;
+            return;
+        }
+
+        let distance = self.box_position.distance(camera_position) * self.lod.bias;
+        let level = self
+            .lod
+            .distances
+            .iter()
+            .filter(|d| **d <= distance)
+            .count();
+
+        self.lod_level = level as u32;
+    }
+
+    /// Particles spawned per batch after LOD. Batches keep their delay, so the lifetime still
+    /// covers the ring of active particles and distant emitters thin out instead of pausing
+    pub fn lod_spawn_count(&self) -> u32 {
+        (self.spawn_count >> self.lod_level.min(31)).max(1)
+    }
+
+    /// Same rotation order the emitter shader applies to the emit velocity
+    pub fn box_rotation_quat(&self) -> Quat {
+        let [yaw, pitch, roll] = self.box_rotation.to_array();
+
+        Quat::from_rotation_x(-roll) * Quat::from_rotation_z(-pitch) * Quat::from_rotation_y(-yaw)
+    }
+
+    /// Maps the unit cube (-0.5..0.5) onto the rotated and scaled spawn box
+    pub fn box_transform(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            self.box_dimensions,
+            self.box_rotation_quat(),
+            self.box_position,
+        )
     }
 
     pub fn particle_count(&self) -> u64 {
         self.spawn_count as u64 * self.spawn_batches_count as u64
     }
 
+    pub fn spawn_rate_per_sec(&self) -> f32 {
+        self.spawn_count as f32 / self.spawn_delay_sec
+    }
+
+    /// Computes spawn count and delay, recreate the emitter as the particle count can change
+    pub fn set_spawn_rate_per_sec(&mut self, rate: f32) {
+        (self.spawn_count, self.spawn_delay_sec) = spawn_rate_batches(rate);
+        self.spawn_batches_count =
+            (self.particle_lifetime_sec / self.spawn_delay_sec).ceil() as u32;
+    }
+
+    /// Amount of batches alive at the same time, particle count is spawn count times this
+    pub fn spawn_batches_count(&self) -> u32 {
+        self.spawn_batches_count
+    }
+
+    /// Particle count after LOD, never exceeds the allocated particle count
+    pub fn active_particle_count(&self) -> u64 {
+        let active = self.lod_spawn_count() as u64 * self.spawn_batches_count as u64;
+        active.min(self.particle_count())
+    }
+
+    /// Live particle ceiling, the particle buffer holds no more than this
+    pub fn max_particles(&self) -> u64 {
+        self.particle_count()
+    }
+
+    /// Particles alive at the same time, bursts over the ceiling overwrite live particles
+    pub fn required_particles(&self) -> u64 {
+        required_particles(
+            &self.spawn_mode,
+            self.spawn_count,
+            self.spawn_delay_sec,
+            self.particle_lifetime_sec,
+        )
+    }
+
+    /// Grows the spawn count until the bursts fit, continuous spawning always fits
+    pub fn fit_particle_capacity(&mut self) {
+        let required = self.required_particles();
+        let batches = self.spawn_batches_count.max(1) as u64;
+
+        if self.max_particles() < required {
+            self.spawn_count = required.div_ceil(batches) as u32;
+        }
+    }
+
+    /// Logs a warning when the spawn settings would drop particles
+    pub fn validate_particle_capacity(&self) {
+        let required = self.required_particles();
+        let max = self.max_particles();
+
+        if max < required {
+            println!(
+                "Emitter {} spawns up to {} live particles but only {} fit, older particles get overwritten",
+                self.id, required, max
+            );
+        }
+    }
+
+    /// Neither the tint nor the particle color lowers the alpha
+    pub fn is_opaque(&self) -> bool {
+        1. <= self.tint.w && 1. <= self.particle_color.w
+    }
+
+    /// Holds one particle at least, an emitter without particles still needs a valid binding
     pub fn particle_buffer_size(&self) -> u64 {
-        self.particle_count() * PARTICLE_BUFFER_SIZE
+        self.particle_count().max(1) * PARTICLE_STRIDE
     }
 
     pub async fn create_buffer_content(
@@ -239,7 +1269,19 @@ impl EmitterUniform {
     ) -> Vec<f32> {
         let collection = &collection.read().await;
         let mesh = collection.get_mesh(&self.mesh);
-        let particle_model = mesh.model.to_cols_array();
+
+        self.buffer_content(mesh.model)
+    }
+
+    /// Laid out like the Emitter struct in declarations.wgsl
+    fn buffer_content(&self, mesh_model: Mat4) -> Vec<f32> {
+        let particle_model = mesh_model.to_cols_array();
+        let box_transform = self.box_transform().to_cols_array();
+
+        // Pulse spawning lowers the emit count, LOD scales it down with the batch
+        let spawn_count = self.lod_spawn_count();
+        let emit_count = (self.emit_count.min(self.spawn_count) as u64 * spawn_count as u64)
+            .div_ceil(self.spawn_count.max(1) as u64);
 
         [
             &[
@@ -258,7 +1300,7 @@ impl EmitterUniform {
                 self.box_rotation.z,
                 self.diff_width,
                 self.diff_depth,
-                0., // padding
+                self.spawn_distribution.into(),
             ],
             particle_model.as_slice(),
             &[
@@ -273,9 +1315,298 @@ impl EmitterUniform {
                 self.particle_friction_coefficient,
                 self.particle_material_mass,
                 self.particle_lifetime_sec,
-                0., // padding
+                self.spawn_spread,
+                self.simulation_space.into(),
+                self.particle_mass.0,
+                self.particle_mass.1,
+                self.active_particle_count() as f32,
+            ],
+            box_transform.as_slice(),
+            &self.tint.to_array(),
+            &[
+                self.burst_from as f32,
+                self.burst_count as f32,
+                (&self.spawn_shape).into(),
+                spawn_count as f32,
             ],
+            &self.spawn_shape.shape_content(),
+            &self.light_intensity.to_array(),
+            &self.flipbook.to_array(),
+            &[
+                emit_count as f32,
+                self.billboard_mode.into(),
+                self.stretch_factor,
+                self.culling_active as u32 as f32,
+            ],
+            &[self.sorting_active as u32 as f32, self.circle_aa, 0., 0.],
+            &self.box_delta.to_cols_array(),
         ]
         .concat()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    /// Corners, edge midpoints and the inside of the unit cube
+    fn unit_cube_samples() -> Vec<Vec3> {
+        let steps = [-0.5, -0.25, 0., 0.25, 0.5];
+        let mut samples = vec![];
+
+        for x in steps {
+            for y in steps {
+                for z in steps {
+                    samples.push(Vec3::new(x, y, z));
+                }
+            }
+        }
+
+        samples
+    }
+
+    fn uniform_with_box(rotation_deg: Vec3) -> EmitterUniform {
+        let mut uniform = EmitterUniform::new("Box".to_string());
+        uniform.box_position = Vec3::new(3., -2., 5.);
+        uniform.box_dimensions = Vec3::new(4., 1., 2.);
+        uniform.box_rotation = rotation_deg.to_radians();
+        uniform
+    }
+
+    /// Box axes after the yaw (y), pitch (z) and roll (x) rotation, written out with sines and
+    /// cosines so the tests don't reuse the quaternion of box_transform
+    fn box_axes_by_hand(rotation_deg: Vec3) -> [[f32; 3]; 3] {
+        let [yaw, pitch, roll] = rotation_deg.to_radians().to_array();
+        let (sy, cy) = (-yaw).sin_cos();
+        let (sp, cp) = (-pitch).sin_cos();
+        let (sr, cr) = (-roll).sin_cos();
+
+        // Rows of rot_x(-roll) * rot_z(-pitch) * rot_y(-yaw)
+        let rot_x = [[1., 0., 0.], [0., cr, -sr], [0., sr, cr]];
+        let rot_z = [[cp, -sp, 0.], [sp, cp, 0.], [0., 0., 1.]];
+        let rot_y = [[cy, 0., sy], [0., 1., 0.], [-sy, 0., cy]];
+
+        let mul = |a: [[f32; 3]; 3], b: [[f32; 3]; 3]| {
+            let mut out = [[0.; 3]; 3];
+
+            for (row, out_row) in out.iter_mut().enumerate() {
+                for (col, out_val) in out_row.iter_mut().enumerate() {
+                    *out_val = (0..3).map(|k| a[row][k] * b[k][col]).sum();
+                }
+            }
+
+            out
+        };
+
+        let rows = mul(mul(rot_x, rot_z), rot_y);
+
+        // Column j is where box axis j points
+        [0, 1, 2].map(|col| [rows[0][col], rows[1][col], rows[2][col]])
+    }
+
+    #[test]
+    fn box_samples_stay_in_rotated_box() {
+        let rotations = [
+            Vec3::ZERO,
+            Vec3::new(90., 0., 0.),
+            Vec3::new(0., 90., 0.),
+            Vec3::new(0., 0., 90.),
+            Vec3::new(45., 30., 0.),
+            Vec3::new(-120., 15., 200.),
+        ];
+
+        for rotation_deg in rotations {
+            let uniform = uniform_with_box(rotation_deg);
+            let transform = uniform.box_transform();
+            let axes = box_axes_by_hand(rotation_deg);
+            let half_dims = (uniform.box_dimensions / 2.).to_array();
+
+            // Each world axis spans the projections of the three rotated box edges
+            let extents = Vec3::from_array(
+                [0, 1, 2].map(|i| (0..3).map(|j| axes[j][i].abs() * half_dims[j]).sum()),
+            );
+
+            for sample in unit_cube_samples() {
+                let offset = transform.transform_point3(sample) - uniform.box_position;
+
+                assert!(
+                    offset.abs().cmple(extents + EPSILON).all(),
+                    "{:?} left the box rotated {:?}",
+                    sample,
+                    rotation_deg
+                );
+            }
+
+            for corner in unit_cube_samples()
+                .into_iter()
+                .filter(|s| s.abs() == Vec3::splat(0.5))
+            {
+                let signs = corner.signum().to_array();
+                let expected = (0..3).fold(uniform.box_position, |pos, j| {
+                    pos + Vec3::from_array(axes[j]) * signs[j] * half_dims[j]
+                });
+
+                assert!(
+                    transform
+                        .transform_point3(corner)
+                        .abs_diff_eq(expected, EPSILON),
+                    "corner {:?} of the box rotated {:?}",
+                    corner,
+                    rotation_deg
+                );
+            }
+        }
+    }
+
+    /// Floats of the Emitter struct in declarations.wgsl, vectors and matrices start 16 byte
+    /// aligned so there is no implicit padding
+    fn wgsl_emitter_floats() -> usize {
+        let declarations = include_str!("../shaders/declarations.wgsl");
+        let body = declarations
+            .split("struct Emitter {")
+            .nth(1)
+            .and_then(|rest| rest.split("};").next())
+            .expect("Emitter struct is declared");
+
+        let mut floats = 0;
+
+        for line in body.lines() {
+            let field = line.split("//").next().unwrap_or_default().trim();
+            let Some((name, ty)) = field.trim_end_matches(',').split_once(':') else {
+                continue;
+            };
+
+            let size = match ty.trim() {
+                "f32" | "u32" | "i32" => 1,
+                "vec4<f32>" => 4,
+                "mat4x4<f32>" => 16,
+                other => panic!("Unhandled type {} of {}", other, name),
+            };
+
+            assert!(
+                size == 1 || floats % 4 == 0,
+                "{} isn't 16 byte aligned",
+                name
+            );
+            floats += size;
+        }
+
+        assert_eq!(floats % 4, 0, "Emitter size isn't a multiple of 16 bytes");
+        floats
+    }
+
+    #[test]
+    fn buffer_content_matches_wgsl_emitter() {
+        let uniform = EmitterUniform::new("Layout".to_string());
+
+        assert_eq!(
+            uniform.buffer_content(Mat4::IDENTITY).len(),
+            wgsl_emitter_floats()
+        );
+    }
+
+    #[test]
+    fn box_delta_adds_up_skipped_frames() {
+        let mut uniform = uniform_with_box(Vec3::ZERO);
+        let mut clock = Clock::default();
+        uniform.update_interval = 4;
+
+        clock.advance(0.1);
+        uniform.update_box_delta(&clock);
+        let start =
+            Mat4::from_rotation_translation(uniform.box_rotation_quat(), uniform.box_position);
+
+        // Frames 2 and 3 only render, frame 4 simulates
+        for frame in 2..=4 {
+            clock.advance(0.1);
+            uniform.box_position += Vec3::new(1., 0., -0.5);
+            uniform.box_rotation.y += 30f32.to_radians();
+            uniform.update_box_delta(&clock);
+
+            if frame < 4 {
+                assert_eq!(
+                    uniform.box_delta,
+                    Mat4::IDENTITY,
+                    "Uploaded on frame {}",
+                    frame
+                );
+            }
+        }
+
+        let end =
+            Mat4::from_rotation_translation(uniform.box_rotation_quat(), uniform.box_position);
+        let moved = uniform.box_delta * start;
+
+        assert!(moved.abs_diff_eq(end, EPSILON), "{:?} != {:?}", moved, end);
+        assert!(uniform
+            .box_delta
+            .transform_point3(Vec3::new(3., -2., 5.))
+            .abs_diff_eq(Vec3::new(6., -2., 3.5), EPSILON));
+    }
+
+    #[test]
+    fn paused_box_movement_is_kept() {
+        let mut uniform = uniform_with_box(Vec3::ZERO);
+        let mut clock = Clock::default();
+
+        clock.advance(0.1);
+        uniform.update_box_delta(&clock);
+
+        clock.pause();
+        clock.update();
+        uniform.box_position.x += 2.;
+        uniform.update_box_delta(&clock);
+
+        assert_eq!(uniform.box_delta, Mat4::IDENTITY);
+
+        clock.resume();
+        clock.update();
+        uniform.update_box_delta(&clock);
+
+        let offset = uniform.box_delta.transform_point3(Vec3::ZERO);
+        assert!(
+            offset.abs_diff_eq(Vec3::new(2., 0., 0.), EPSILON),
+            "{:?}",
+            offset
+        );
+    }
+
+    #[test]
+    fn empty_emitter_keeps_particle_buffer() {
+        let mut uniform = EmitterUniform::new("Empty".to_string());
+        uniform.spawn_count = 0;
+
+        assert_eq!(uniform.particle_count(), 0);
+        assert_eq!(uniform.particle_buffer_size(), PARTICLE_STRIDE);
+    }
+
+    #[test]
+    fn quarter_turns_swap_box_axes() {
+        // Yaw turns around y, pitch around z and roll around x
+        let cases = [
+            (Vec3::new(90., 0., 0.), Vec3::new(1., 0.5, 2.)),
+            (Vec3::new(0., 90., 0.), Vec3::new(0.5, 2., 1.)),
+            (Vec3::new(0., 0., 90.), Vec3::new(2., 1., 0.5)),
+        ];
+
+        for (rotation_deg, half_extents) in cases {
+            let uniform = uniform_with_box(rotation_deg);
+            let transform = uniform.box_transform();
+            let mut max_offset = Vec3::ZERO;
+
+            for sample in unit_cube_samples() {
+                let offset = (transform.transform_point3(sample) - uniform.box_position).abs();
+                max_offset = max_offset.max(offset);
+            }
+
+            assert!(
+                max_offset.abs_diff_eq(half_extents, EPSILON),
+                "rotated {:?} spans {:?}",
+                rotation_deg,
+                max_offset
+            );
+        }
+    }
+}
@@ -1,15 +1,24 @@
+use super::draw_batch::{BatchKey, BatchOptions, BatchSource, DrawRun};
 use super::gfx_state::Profiler;
 use super::state::FastFetch;
-use super::{Camera, EmitterUniform, GfxState, Material, Mesh, ModelVertex, SparEvents, SparState};
-use crate::fx::PostProcessState;
+use super::{
+    BlendMode, Camera, Clock, ClockMode, DepthSort, DrawBatcher, EmitterEvent, EmitterUniform,
+    FrustumCull, GfxState, LiveCounter, Material, Mesh, ModelVertex, ShadingModel, SparEvents,
+    SparState, PARTICLE_STRIDE,
+};
 use crate::loader::{Model, BUILTIN_ID};
-use crate::shaders::{ShaderOptions, SDR_PBR, SDR_TONEMAPPING};
+use crate::shaders::{ShaderOptions, DIR_UNLIT, SDR_PBR, SDR_TONEMAPPING};
+use crate::texture::{TexType, TextureHandler};
+use crate::traits::CreateFxView;
 use crate::traits::{EmitterAnimation, ParticleAnimation};
 use crate::util::persistence::{ExportEmitter, ExportType};
-use crate::util::{ListAction, Persistence, ID};
+use crate::util::{DynamicExport, ListAction, Persistence, ID};
 use async_std::sync::RwLock;
 use egui_wgpu::wgpu::{self, ShaderModule};
+use glam::{Vec3, Vec4};
+use rayon::prelude::*;
 use std::fmt::Display;
+use std::ptr;
 use std::sync::Arc;
 use std::{
     collections::HashMap,
@@ -22,9 +31,23 @@ use wgpu::util::DeviceExt;
 pub struct EmitterState {
     pipeline: wgpu::ComputePipeline,
     pipeline_layout: wgpu::PipelineLayout,
-    render_pipelines: HashMap<FsEntryPoint, wgpu::RenderPipeline>,
+    /// One per blend mode, so the mode can change without recreating the emitter
+    render_pipelines: HashMap<(FsEntryPoint, BlendMode), wgpu::RenderPipeline>,
+    /// Without the bloom split target, used while no post fx reads it
+    single_target_pipelines: HashMap<(FsEntryPoint, BlendMode), wgpu::RenderPipeline>,
+    /// Depth only, set when the uniform asks for a depth prepass
+    depth_prepass_pipeline: Option<wgpu::RenderPipeline>,
     emitter_buffer: wgpu::Buffer,
     particle_buffers: Vec<wgpu::Buffer>,
+    spawn_resources: SpawnResources,
+    frustum_cull: FrustumCull,
+    /// Set when the uniform asks for depth sorting, lights are never sorted
+    depth_sort: Option<DepthSort>,
+    live_counter: LiveCounter,
+    /// Without indirect execution every particle slot is dispatched and drawn
+    indirect_execution: bool,
+    /// Finished event sent, until the bursts start over
+    finished: bool,
 
     pub particle_animations: Vec<Box<dyn ParticleAnimation>>,
     pub emitter_animations: Vec<Box<dyn EmitterAnimation>>,
@@ -34,6 +57,15 @@ pub struct EmitterState {
     pub bgs: Vec<wgpu::BindGroup>,
     pub bg_layout: wgpu::BindGroupLayout,
     pub is_light: bool,
+    /// Ping-pong index, only swaps on frames this emitter is computed
+    bg_nr: usize,
+}
+
+/// Bound next to the particle buffers, kept to rebuild the bind groups on a resize
+struct SpawnResources {
+    color_mask_view: wgpu::TextureView,
+    color_mask_sampler: wgpu::Sampler,
+    spawn_curve_buffer: wgpu::Buffer,
 }
 
 pub enum EmitterType<'a> {
@@ -43,7 +75,22 @@ pub enum EmitterType<'a> {
     },
 }
 
-#[derive(Hash, PartialEq, Eq)]
+/// Draw counters of the last frame. Consecutive emitters sharing mesh, material and pipeline
+/// state are merged into one draw, see DrawBatcher. A batch is a run of consecutive emitters
+/// sharing the mesh and material bindings
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DrawStats {
+    pub draw_calls: u32,
+    /// Draw calls without merging, one per emitter
+    pub emitter_draws: u32,
+    /// Draws that cover more than one emitter
+    pub merged_draws: u32,
+    pub batches: u32,
+    /// Depth only draws, their emitters shade each visible pixel once in the color pass
+    pub prepass_draw_calls: u32,
+}
+
+#[derive(Hash, PartialEq, Eq, Clone, Copy)]
 pub enum FsEntryPoint {
     Model,
     Circle,
@@ -74,6 +121,39 @@ pub struct RecreateEmitterOptions<'a> {
     pub emitter_type: EmitterType<'a>,
 }
 
+pub struct RenderPipelineOptions<'a> {
+    pub shader: &'a ShaderModule,
+    pub layout: &'a wgpu::PipelineLayout,
+    pub material: &'a Material,
+    pub gfx: &'a GfxState,
+    pub fs_entry_point: FsEntryPoint,
+    pub blend_mode: BlendMode,
+    pub depth_bias: i32,
+    /// Only draws the fragments the depth prepass kept
+    pub depth_prepass: bool,
+    pub depth_sorted: bool,
+    /// Also writes the bloom input
+    pub split_target: bool,
+}
+
+/// Catch up steps a seek may simulate, longer seeks only simulate the steps before the target
+pub const MAX_SEEK_STEPS: u32 = 600;
+
+/// Seek step size when the clock runs in realtime
+const REALTIME_SEEK_DT: f32 = 1. / 60.;
+
+/// Release build, a sway and a diffusion animation per emitter, per frame on one rayon thread:
+///
+///   emitters  sequential  rayon
+///   16        1.9 µs      8.2 µs
+///   50        5.6 µs      12.1 µs
+///   128       14.6 µs     20.7 µs
+///   512       52.5 µs     65.1 µs
+///
+/// An emitter takes about 110 ns and the fork-join about 6.5 µs, so on two threads the split
+/// only pays off above ~120 emitters. A 50 emitter scene is faster on the calling thread
+const PARALLEL_ANIMATE_MIN_EMITTERS: usize = 128;
+
 impl EmitterState {
     pub fn id(&self) -> &str {
         &self.uniform.id
@@ -86,77 +166,278 @@ impl EmitterState {
             gfx,
             camera,
             collection,
+            emitter_events,
+            budget,
+            registry_par_anims,
+            registry_em_anims,
             ..
         } = state;
 
         if let Some(tag) = &events.delete_emitter {
             emitters.retain(|em| em.id() != tag);
         } else if let Some(id) = &events.create_emitter {
-            let options = CreateEmitterOptions {
-                camera,
-                uniform: EmitterUniform::new(id.to_string()),
-                collection,
-                emitter_type: EmitterType::Normal {
-                    lights_layout: &emitters[0].bg_layout,
-                },
-                gfx,
-            };
+            let mut uniform = EmitterUniform::new(id.to_string());
+            let particle_usage = emitters.iter().map(|em| em.particle_count()).sum();
+
+            match budget.fit(&mut uniform, particle_usage) {
+                Ok(()) => {
+                    let options = CreateEmitterOptions {
+                        camera,
+                        uniform,
+                        collection,
+                        emitter_type: EmitterType::Normal {
+                            lights_layout: &emitters[0].bg_layout,
+                        },
+                        gfx,
+                    };
+
+                    emitters.push(Self::new(options).await);
+                }
+                Err(err) => println!("{}", err.msg),
+            }
+        } else if let Some(source_id) = &events.duplicate_emitter {
+            let source = emitters
+                .iter()
+                .find(|em| em.id() == source_id && !em.is_light);
+
+            if let Some(source) = source {
+                let id = (1..)
+                    .map(|n| format!("{}-{}", source_id, n))
+                    .find(|id| emitters.iter().all(|em| em.id() != id))
+                    .unwrap();
 
-            emitters.push(Self::new(options).await);
+                let mut uniform = source.uniform.duplicate(id, emitters.len() as f32);
+                let particle_exports: Vec<DynamicExport> = source
+                    .particle_animations
+                    .iter()
+                    .map(|anim| anim.export())
+                    .collect();
+                let emitter_exports: Vec<DynamicExport> = source
+                    .emitter_animations
+                    .iter()
+                    .map(|anim| anim.export())
+                    .collect();
+
+                let particle_usage = emitters.iter().map(|em| em.particle_count()).sum();
+
+                match budget.fit(&mut uniform, particle_usage) {
+                    Ok(()) => {
+                        let options = CreateEmitterOptions {
+                            camera,
+                            uniform,
+                            collection,
+                            emitter_type: EmitterType::Normal {
+                                lights_layout: &emitters[0].bg_layout,
+                            },
+                            gfx,
+                        };
+
+                        let mut emitter = Self::new(options).await;
+                        let gfx_lock = &gfx.read().await;
+
+                        for export in particle_exports {
+                            if let Some(reg) =
+                                registry_par_anims.iter().find(|r| r.tag() == export.tag)
+                            {
+                                let anim = reg.import(gfx_lock, &emitter, export.data);
+                                emitter.push_particle_animation(anim);
+                            }
+                        }
+
+                        for export in emitter_exports {
+                            if let Some(reg) =
+                                registry_em_anims.iter().find(|r| r.tag() == export.tag)
+                            {
+                                emitter.push_emitter_animation(reg.import(export.data));
+                            }
+                        }
+
+                        emitters.push(emitter);
+                    }
+                    Err(err) => println!("{}", err.msg),
+                }
+            }
         }
 
-        let mut update_mesh = false;
+        if let Some(burst) = &events.emit_burst {
+            if let Some(em) = emitters.iter_mut().find(|em| em.id() == burst.emitter_id) {
+                em.uniform.emit_burst(burst.count);
+            }
+        }
 
-        for emitter in emitters.iter_mut() {
-            emitter.uniform.update(clock);
+        let camera_position = camera.position;
 
-            ListAction::update_list(&mut emitter.emitter_animations);
+        // CPU side animations don't need the gpu, so large scenes run them in parallel. With a
+        // single rayon thread the fork-join is pure overhead
+        let parallel =
+            PARALLEL_ANIMATE_MIN_EMITTERS <= emitters.len() && 1 < rayon::current_num_threads();
 
+        if !parallel {
+            for em in emitters.iter_mut() {
+                Self::animate_uniform(
+                    &mut em.uniform,
+                    &mut em.emitter_animations,
+                    clock,
+                    camera_position,
+                );
+            }
+        } else {
+            emitters
+                .iter_mut()
+                .map(|em| (&mut em.uniform, &mut em.emitter_animations))
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .for_each(|(uniform, anims)| {
+                    Self::animate_uniform(uniform, anims, clock, camera_position)
+                });
+        }
+
+        let mut update_mesh = false;
+        let gfx_state = &gfx.read().await;
+
+        // Finishes the maps of the live count readbacks
+        gfx_state.device.poll(wgpu::Maintain::Poll);
+
+        for emitter in emitters.iter_mut() {
             if emitter.uniform.mesh.collection_id == BUILTIN_ID {
                 update_mesh = true;
             }
 
+            // Only the dispatched count changes, the particle buffers keep their size
+            emitter.dispatch_x_count =
+                Self::dispatch_count(emitter.uniform.active_particle_count());
+
+            ListAction::update_list(&mut emitter.particle_animations);
+
+            for anim in emitter.particle_animations.iter_mut() {
+                anim.update(clock, gfx_state);
+            }
+
+            emitter.uniform.flipbook = Vec4::ZERO;
+
             for anim in emitter
-                .emitter_animations
-                .iter_mut()
+                .particle_animations
+                .iter()
                 .filter(|item| item.enabled())
             {
-                anim.animate(&mut emitter.uniform, clock);
+                anim.animate_emitter(&mut emitter.uniform);
             }
 
+            emitter.uniform.culling_active = emitter.uses_frustum_cull();
+            emitter.uniform.sorting_active = emitter.uses_depth_sort();
+
             let buffer_content_raw = emitter.uniform.create_buffer_content(collection).await;
             let buffer_content = bytemuck::cast_slice(&buffer_content_raw);
 
-            let gfx = &gfx.read().await;
-            gfx.queue
+            gfx_state
+                .queue
                 .write_buffer(&emitter.emitter_buffer, 0, buffer_content);
 
-            ListAction::update_list(&mut emitter.particle_animations);
+            if emitter.uniform.has_looped() {
+                emitter_events.push(EmitterEvent::Looped(emitter.id().to_string()));
+            }
 
-            for anim in emitter.particle_animations.iter_mut() {
-                anim.update(clock, gfx);
+            if !emitter.uniform.has_fired_all_bursts() {
+                emitter.finished = false;
+            }
+
+            // Finished once a live count copied after the last burst reads zero
+            let alive = emitter.live_counter.read_alive();
+
+            if alive == Some(0) && emitter.uniform.has_fired_all_bursts() && !emitter.finished {
+                emitter.finished = true;
+                emitter_events.push(EmitterEvent::Finished(emitter.id().to_string()));
             }
         }
 
         if update_mesh {
             let mut collection = collection.write().await;
-            let gfx = gfx.read().await;
 
             if let Some(model) = collection.get_mut(BUILTIN_ID) {
-                Mesh::update_2d_meshes(&mut model.meshes, &gfx.queue, camera);
+                Mesh::update_2d_meshes(&mut model.meshes, &gfx_state.queue, camera);
             }
         }
     }
 
+    fn animate_uniform(
+        uniform: &mut EmitterUniform,
+        emitter_animations: &mut Vec<Box<dyn EmitterAnimation>>,
+        clock: &Clock,
+        camera_position: Vec3,
+    ) {
+        // Before the update, which spawns into batches of the LOD level
+        uniform.update_lod(camera_position);
+        uniform.update(clock);
+
+        ListAction::update_list(emitter_animations);
+
+        for anim in emitter_animations.iter_mut().filter(|item| item.enabled()) {
+            anim.animate(uniform, clock);
+        }
+
+        uniform.update_box_delta(clock);
+    }
+
+    /// Clears the particles of all emitters and simulates again in fixed steps up to
+    /// `target_sec`, so the same target always gives the same particles.
+    /// The cost is one compute submit per step, that is O(frames) and capped by `MAX_SEEK_STEPS`.
+    /// Particles older than the capped steps are missing, which is fine for lifetimes shorter
+    /// than the cap
+    pub async fn seek(state: &mut SparState, target_sec: f32) {
+        let dt = match state.clock.mode() {
+            ClockMode::Fixed { dt } => dt,
+            ClockMode::Realtime => REALTIME_SEEK_DT,
+        } * state.clock.time_scale();
+
+        let target_sec = target_sec.max(0.);
+        let steps = ((target_sec / dt).round() as u32).min(MAX_SEEK_STEPS);
+
+        {
+            let gfx = state.gfx.read().await;
+            let mut encoder = gfx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Seek encoder"),
+                });
+
+            for emitter in state.emitters.iter_mut() {
+                for buf in emitter.particle_buffers.iter() {
+                    encoder.clear_buffer(buf, 0, None);
+                }
+
+                emitter.uniform.reset_time();
+            }
+
+            gfx.queue.submit(Some(encoder.finish()));
+        }
+
+        state.clock.set_elapsed_sec(target_sec - steps as f32 * dt);
+
+        for _ in 0..steps {
+            state.step(dt).await;
+        }
+    }
+
     pub async fn compute_particles(state: &mut SparState, encoder: &mut wgpu::CommandEncoder) {
         let SparState {
             clock,
             emitters,
             gfx,
+            collection,
             ..
         } = state;
 
-        let nr = clock.get_bindgroup_nr();
+        {
+            let collection = collection.read().await;
+            let queue = &gfx.read().await.queue;
+
+            for emitter in emitters.iter().filter(|em| em.indirect_execution) {
+                let mesh = collection.get_mesh(&emitter.uniform.mesh);
+                emitter
+                    .live_counter
+                    .set_index_count(queue, mesh.indices.len() as u32);
+            }
+        }
 
         let mut c_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Compute pipeline"),
@@ -166,11 +447,20 @@ impl EmitterState {
         Profiler::begin_scope(gfx, "Compute", &mut c_pass).await;
 
         for emitter in emitters.iter() {
+            if !emitter.uniform.is_compute_frame(clock) {
+                continue;
+            }
+
             let scope_str = &format!("Compute emitter: {}", emitter.id());
             Profiler::begin_scope(gfx, scope_str, &mut c_pass).await;
+
+            // Also resets the alive count the finished event reads back
+            emitter.live_counter.prepare(&mut c_pass);
+
             c_pass.set_pipeline(&emitter.pipeline);
-            c_pass.set_bind_group(0, &emitter.bgs[nr], &[]);
-            c_pass.dispatch_workgroups(emitter.dispatch_x_count, 1, 1);
+            c_pass.set_bind_group(0, &emitter.bgs[emitter.bg_nr], &[]);
+            c_pass.set_bind_group(1, &emitter.live_counter.counter_bg, &[]);
+            emitter.dispatch(&mut c_pass);
             Profiler::end_scope(gfx, &mut c_pass).await;
 
             Profiler::begin_scope(gfx, "Compute particle animations", &mut c_pass).await;
@@ -184,37 +474,422 @@ impl EmitterState {
             Profiler::end_scope(gfx, &mut c_pass).await;
         }
 
+        Profiler::end_scope(gfx, &mut c_pass).await;
+        drop(c_pass);
+
+        for emitter in emitters.iter_mut() {
+            if !emitter.uniform.is_compute_frame(clock) {
+                continue;
+            }
+
+            emitter.bg_nr = (emitter.bg_nr + 1) % 2;
+
+            if emitter.uniform.has_fired_all_bursts() && !emitter.finished {
+                emitter.live_counter.copy_alive(encoder);
+            }
+        }
+
+        Self::cull_particles(state, encoder).await;
+        Self::sort_particles(state, encoder).await;
+    }
+
+    /// Runs every frame after the simulation, the camera can move while emitters are throttled
+    async fn cull_particles(state: &SparState, encoder: &mut wgpu::CommandEncoder) {
+        let SparState {
+            camera,
+            emitters,
+            gfx,
+            collection,
+            ..
+        } = state;
+
+        let culled: Vec<&EmitterState> = emitters
+            .iter()
+            .filter(|em| em.uses_frustum_cull())
+            .collect();
+
+        if culled.is_empty() {
+            return;
+        }
+
+        {
+            let collection = collection.read().await;
+            let queue = &gfx.read().await.queue;
+
+            for em in culled.iter() {
+                let mesh = collection.get_mesh(&em.uniform.mesh);
+                em.frustum_cull.reset(queue, mesh.indices.len() as u32);
+            }
+        }
+
+        let mut c_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Frustum cull pipeline"),
+            timestamp_writes: None,
+        });
+
+        Profiler::begin_scope(gfx, "Frustum cull", &mut c_pass).await;
+
+        for em in culled {
+            let dispatch_buffer = em.live_counter.dispatch_buffer();
+            em.frustum_cull
+                .compute(camera, em.bg_nr, dispatch_buffer, &mut c_pass);
+        }
+
+        Profiler::end_scope(gfx, &mut c_pass).await;
+    }
+
+    /// Runs after the frustum cull, so culled emitters only sort their visible particles
+    async fn sort_particles(state: &SparState, encoder: &mut wgpu::CommandEncoder) {
+        let SparState {
+            camera,
+            emitters,
+            gfx,
+            ..
+        } = state;
+
+        let sorted: Vec<(&EmitterState, &DepthSort)> = emitters
+            .iter()
+            .filter_map(|em| em.depth_sort.as_ref().map(|depth_sort| (em, depth_sort)))
+            .collect();
+
+        if sorted.is_empty() {
+            return;
+        }
+
+        let mut c_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Depth sort pipeline"),
+            timestamp_writes: None,
+        });
+
+        Profiler::begin_scope(gfx, "Depth sort", &mut c_pass).await;
+
+        for (em, depth_sort) in sorted {
+            depth_sort.compute(camera, em.bg_nr, &mut c_pass);
+        }
+
         Profiler::end_scope(gfx, &mut c_pass).await;
     }
 
     pub async fn render_particles(state: &mut SparState, encoder: &mut wgpu::CommandEncoder) {
+        let layers: Vec<u8> = (0..state.post_process.layer_count() as u8).collect();
+        state.draw_batcher.begin_frame();
+        Self::render_layers(state, encoder, &layers, &mut vec![]).await;
+    }
+
+    /// Layers in `rendered_layers` are drawn on top of, the others are cleared first
+    pub async fn render_layers(
+        state: &mut SparState,
+        encoder: &mut wgpu::CommandEncoder,
+        layers: &[u8],
+        rendered_layers: &mut Vec<u8>,
+    ) {
+        let collection = state.collection.clone();
+        let collection = &collection.read().await;
+        let split_target = state.post_process.uses_split_target();
+        let layers: Vec<u8> = layers
+            .iter()
+            .copied()
+            .filter(|l| (*l as usize) < state.post_process.layer_count())
+            .collect();
+
+        // The particle copies of merged draws can't be recorded inside a render pass
+        let mut layer_runs = vec![];
+
+        for &layer in layers.iter() {
+            let runs = Self::prepare_draws(state, collection, layer, split_target, encoder).await;
+            layer_runs.push(runs);
+        }
+
         let pp = &state.post_process;
-        let collection = &state.collection.read().await;
+        let mut draw_stats = state.draw_stats;
+        let clear_color = state.gfx.read().await.clear_color();
 
-        let mut r_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[
-                Some(wgpu::RenderPassColorAttachment {
-                    view: pp.frame_view(),
+        for (&layer, runs) in layers.iter().zip(layer_runs.iter()) {
+            let clear = !rendered_layers.contains(&layer);
+
+            // Higher layers are composited over layer 0, so only layer 0 gets the background
+            let layer_clear_color = if layer == 0 {
+                clear_color
+            } else {
+                wgpu::Color::TRANSPARENT
+            };
+
+            let (color_load, depth_load) = if clear {
+                rendered_layers.push(layer);
+                (
+                    wgpu::LoadOp::Clear(layer_clear_color),
+                    wgpu::LoadOp::Clear(1.0),
+                )
+            } else {
+                (wgpu::LoadOp::Load, wgpu::LoadOp::Load)
+            };
+
+            let (frame_view, split_view, depth_view) = match pp.layer(layer) {
+                Some(tex) => (&tex.frame_view, &tex.split_view, &tex.depth_view),
+                None => (pp.frame_view(), pp.split_view(), pp.depth_view()),
+            };
+
+            let prepass_draws = Self::render_depth_prepass(
+                state, collection, layer, depth_view, depth_load, encoder,
+            )
+            .await;
+
+            // The color pass keeps the depth of the prepass
+            let depth_load = if 0 < prepass_draws {
+                wgpu::LoadOp::Load
+            } else {
+                depth_load
+            };
+
+            let mut color_attachments = vec![Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: color_load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })];
+
+            // Skipping the split target saves its clear and writes when bloom is off
+            if split_target {
+                color_attachments.push(Some(wgpu::RenderPassColorAttachment {
+                    view: split_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        load: color_load,
                         store: wgpu::StoreOp::Store,
                     },
-                }),
-                Some(wgpu::RenderPassColorAttachment {
-                    view: pp.split_view(),
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                }));
+            }
+
+            let mut r_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: depth_load,
                         store: wgpu::StoreOp::Store,
-                    },
+                    }),
+                    stencil_ops: None,
                 }),
-            ],
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if clear && layer == 0 && state.sky.enabled {
+                state.sky.render(&mut r_pass, split_target);
+            }
+
+            let stats =
+                Self::render_layer(state, collection, layer, runs, split_target, &mut r_pass).await;
+            draw_stats.draw_calls += stats.draw_calls;
+            draw_stats.emitter_draws += stats.emitter_draws;
+            draw_stats.merged_draws += stats.merged_draws;
+            draw_stats.batches += stats.batches;
+            draw_stats.prepass_draw_calls += prepass_draws;
+        }
+
+        state.draw_stats = draw_stats;
+    }
+
+    /// Emitters of the layer in draw order
+    fn layer_emitters(emitters: &[EmitterState], layer: u8) -> Vec<&EmitterState> {
+        let mut layer_emitters: Vec<&EmitterState> = emitters
+            .iter()
+            .filter(|em| em.uniform.layer == layer)
+            .collect();
+
+        layer_emitters.sort_by_key(|em| em.uniform.draw_order);
+        layer_emitters
+    }
+
+    /// Splits the layer in draws and records the copies of the merged ones
+    async fn prepare_draws(
+        state: &mut SparState,
+        collection: &HashMap<ID, Model>,
+        layer: u8,
+        split_target: bool,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> Vec<DrawRun> {
+        let SparState {
+            emitters,
+            camera,
+            gfx,
+            draw_batcher,
+            ..
+        } = state;
+
+        let gfx = gfx.read().await;
+        let lights_layout = &emitters[0].bg_layout;
+        let layer_emitters = Self::layer_emitters(emitters, layer);
+
+        let keys: Vec<Option<BatchKey>> = layer_emitters
+            .iter()
+            .map(|em| em.batch_key(collection.get_mesh(&em.uniform.mesh)))
+            .collect();
+
+        let sizes: Vec<u64> = layer_emitters
+            .iter()
+            .map(|em| em.active_particle_count() * PARTICLE_STRIDE)
+            .collect();
+
+        let runs = DrawBatcher::plan(&keys, &sizes, DrawBatcher::max_size(&gfx));
+
+        runs.into_iter()
+            .map(|run| {
+                let Some(key) = keys[run.start].as_ref().filter(|_| 1 < run.len()) else {
+                    return DrawRun::Emitter(run.start);
+                };
+
+                let sources: Vec<BatchSource> = layer_emitters[run.clone()]
+                    .iter()
+                    .map(|em| BatchSource {
+                        particle_buffer: &em.particle_buffers[em.bg_nr],
+                        emitter_buffer: &em.emitter_buffer,
+                        particle_count: em.active_particle_count(),
+                    })
+                    .collect();
+
+                let batch = draw_batcher.prepare(
+                    BatchOptions {
+                        gfx: &gfx,
+                        camera,
+                        lights_layout,
+                        material: collection.get_mat(&key.material),
+                        key,
+                        split_target,
+                        sources: &sources,
+                    },
+                    encoder,
+                );
+
+                DrawRun::Merged {
+                    emitters: run,
+                    batch,
+                }
+            })
+            .collect()
+    }
+
+    /// Lights, culled, sorted and prepass emitters draw alone, see DrawBatcher
+    fn batch_key(&self, mesh: &Mesh) -> Option<BatchKey> {
+        if self.is_light
+            || self.uses_frustum_cull()
+            || self.uses_depth_sort()
+            || self.uses_depth_prepass(mesh)
+            || self.active_particle_count() == 0
+        {
+            return None;
+        }
+
+        Some(BatchKey {
+            mesh: self.uniform.mesh.clone(),
+            material: self.uniform.material.clone(),
+            fs_entry_point: mesh.fs_entry_point,
+            blend_mode: self.blend_mode(mesh.fs_entry_point),
+            shading_model: self.uniform.shading_model,
+            depth_bias: self.uniform.depth_bias,
+        })
+    }
+
+    fn render_pipeline(
+        &self,
+        fs_entry_point: FsEntryPoint,
+        split_target: bool,
+    ) -> &wgpu::RenderPipeline {
+        let key = (fs_entry_point, self.blend_mode(fs_entry_point));
+
+        if split_target {
+            &self.render_pipelines[&key]
+        } else {
+            &self.single_target_pipelines[&key]
+        }
+    }
+
+    /// Alpha blending is for circles and translucent meshes, opaque meshes replace
+    fn blend_mode(&self, fs_entry_point: FsEntryPoint) -> BlendMode {
+        match self.uniform.blend_mode {
+            BlendMode::Alpha
+                if fs_entry_point == FsEntryPoint::Model && self.uniform.is_opaque() =>
+            {
+                BlendMode::Replace
+            }
+            blend_mode => blend_mode,
+        }
+    }
+
+    /// Light emitters are never culled, every light shades the scene
+    fn uses_frustum_cull(&self) -> bool {
+        self.uniform.frustum_culling && !self.is_light && self.indirect_execution
+    }
+
+    fn uses_depth_sort(&self) -> bool {
+        self.depth_sort.is_some()
+    }
+
+    /// Dispatches the particle slots that can be alive, for the emitter and particle animations
+    pub fn dispatch<'a>(&'a self, c_pass: &mut wgpu::ComputePass<'a>) {
+        if self.indirect_execution {
+            c_pass.dispatch_workgroups_indirect(self.live_counter.dispatch_buffer(), 0);
+        } else {
+            c_pass.dispatch_workgroups(self.dispatch_x_count, 1, 1);
+        }
+    }
+
+    /// Culled emitters draw the visible particles, the others up to the highest live slot
+    fn draw<'a>(&'a self, mesh: &Mesh, r_pass: &mut wgpu::RenderPass<'a>) {
+        if self.uses_frustum_cull() {
+            r_pass.draw_indexed_indirect(self.frustum_cull.indirect_buffer(), 0);
+        } else if self.indirect_execution {
+            r_pass.draw_indexed_indirect(self.live_counter.draw_buffer(), 0);
+        } else {
+            r_pass.draw_indexed(
+                mesh.indices_range(),
+                0,
+                0..self.active_particle_count() as u32,
+            );
+        }
+    }
+
+    /// Only opaque meshes use the prepass, circles blend their edges
+    fn uses_depth_prepass(&self, mesh: &Mesh) -> bool {
+        self.depth_prepass_pipeline.is_some() && mesh.fs_entry_point == FsEntryPoint::Model
+    }
+
+    /// Writes depth for the emitters in the layer that use a prepass, returns the draw count
+    async fn render_depth_prepass(
+        state: &SparState,
+        collection: &HashMap<ID, Model>,
+        layer: u8,
+        depth_view: &wgpu::TextureView,
+        depth_load: wgpu::LoadOp<f32>,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> u32 {
+        let emitters = &state.emitters;
+        let camera = &state.camera;
+        let gfx = &state.gfx;
+        let lights = &emitters[0];
+
+        let prepass_emitters: Vec<&EmitterState> = emitters
+            .iter()
+            .filter(|em| {
+                em.uniform.layer == layer
+                    && em.uses_depth_prepass(collection.get_mesh(&em.uniform.mesh))
+            })
+            .collect();
+
+        if prepass_emitters.is_empty() {
+            return 0;
+        }
+
+        let mut r_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Depth prepass"),
+            color_attachments: &[],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: pp.depth_view(),
+                view: depth_view,
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
+                    load: depth_load,
                     store: wgpu::StoreOp::Store,
                 }),
                 stencil_ops: None,
@@ -223,40 +898,141 @@ impl EmitterState {
             occlusion_query_set: None,
         });
 
-        let clock = &state.clock;
+        Profiler::begin_scope(gfx, "Depth prepass", &mut r_pass).await;
+
+        for em in prepass_emitters.iter() {
+            let Some(pipeline) = &em.depth_prepass_pipeline else {
+                continue;
+            };
+
+            let mesh = collection.get_mesh(&em.uniform.mesh);
+            let mat = collection.get_mat(&em.uniform.material);
+
+            r_pass.set_pipeline(pipeline);
+            r_pass.set_bind_group(0, camera.bg(), &[]);
+            r_pass.set_bind_group(1, &mat.bg, &[]);
+            r_pass.set_bind_group(2, &em.bgs[em.bg_nr], &[]);
+
+            if !em.is_light {
+                r_pass.set_bind_group(3, &lights.bgs[lights.bg_nr], &[]);
+            }
+
+            r_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            r_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            em.draw(mesh, &mut r_pass);
+        }
+
+        Profiler::end_scope(gfx, &mut r_pass).await;
+
+        prepass_emitters.len() as u32
+    }
+
+    async fn render_layer<'a>(
+        state: &'a SparState,
+        collection: &'a HashMap<ID, Model>,
+        layer: u8,
+        runs: &[DrawRun],
+        split_target: bool,
+        r_pass: &mut wgpu::RenderPass<'a>,
+    ) -> DrawStats {
         let emitters = &state.emitters;
         let camera = &state.camera;
         let gfx = &state.gfx;
+        let batcher = &state.draw_batcher;
+        let lights = &emitters[0];
+
+        let scope_str = if layer == 0 {
+            "Render".to_string()
+        } else {
+            format!("Render layer: {}", layer)
+        };
+
+        Profiler::begin_scope(gfx, &scope_str, r_pass).await;
 
-        let nr = clock.get_alt_bindgroup_nr();
+        let layer_emitters = Self::layer_emitters(emitters, layer);
 
-        Profiler::begin_scope(gfx, "Render", &mut r_pass).await;
+        let mut stats = DrawStats::default();
+        let mut batch: Option<(&Mesh, &Material)> = None;
+        let mut lights_bound = false;
 
-        for em in emitters.iter() {
+        // A binding stays set across pipelines that share its group layout, so the camera and
+        // the lights are bound once. Groups after the emitter group are replayed when it changes
+        r_pass.set_bind_group(0, camera.bg(), &[]);
+
+        for run in runs {
+            let run_emitters = match run {
+                DrawRun::Emitter(idx) => &layer_emitters[*idx..*idx + 1],
+                DrawRun::Merged { emitters, .. } => &layer_emitters[emitters.clone()],
+            };
+
+            let em = run_emitters[0];
             let mesh = collection.get_mesh(&em.uniform.mesh);
             let mat = collection.get_mat(&em.uniform.material);
 
-            let scope_str = format!("Emitter: {}", em.id());
-            Profiler::begin_scope(gfx, &scope_str, &mut r_pass).await;
+            let scope_str = match run {
+                DrawRun::Emitter(_) => format!("Emitter: {}", em.id()),
+                DrawRun::Merged { emitters, .. } => {
+                    format!("Merged emitters: {} + {}", em.id(), emitters.len() - 1)
+                }
+            };
 
-            r_pass.set_pipeline(&em.render_pipelines[&mesh.fs_entry_point]);
-            r_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-            r_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            Profiler::begin_scope(gfx, &scope_str, r_pass).await;
 
-            r_pass.set_bind_group(0, camera.bg(), &[]);
-            r_pass.set_bind_group(1, &mat.bg, &[]);
-            r_pass.set_bind_group(2, &em.bgs[nr], &[]);
+            match run {
+                DrawRun::Emitter(_) => {
+                    r_pass.set_pipeline(em.render_pipeline(mesh.fs_entry_point, split_target))
+                }
+                DrawRun::Merged { batch, .. } => r_pass.set_pipeline(batcher.pipeline(*batch)),
+            }
 
-            if !em.is_light {
-                r_pass.set_bind_group(3, &emitters[0].bgs[nr], &[]);
+            // Reordering would break draw order, so only consecutive emitters share bindings
+            let same_batch =
+                batch.is_some_and(|(b_mesh, b_mat)| ptr::eq(b_mesh, mesh) && ptr::eq(b_mat, mat));
+
+            if !same_batch {
+                r_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                r_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                r_pass.set_bind_group(1, &mat.bg, &[]);
+
+                batch = Some((mesh, mat));
+                stats.batches += 1;
+            }
+
+            match run {
+                DrawRun::Emitter(_) => r_pass.set_bind_group(2, &em.bgs[em.bg_nr], &[]),
+                DrawRun::Merged { batch, .. } => r_pass.set_bind_group(2, batcher.bg(*batch), &[]),
+            }
+
+            // The light emitter pipeline has no lights group, bind them again after it
+            if em.is_light {
+                lights_bound = false;
+            } else if !lights_bound {
+                r_pass.set_bind_group(3, &lights.bgs[lights.bg_nr], &[]);
+                lights_bound = true;
+            }
+
+            match run {
+                DrawRun::Emitter(_) => em.draw(mesh, r_pass),
+                DrawRun::Merged { .. } => {
+                    let instance_count: u64 = run_emitters
+                        .iter()
+                        .map(|em| em.active_particle_count())
+                        .sum();
+
+                    r_pass.draw_indexed(mesh.indices_range(), 0, 0..instance_count as u32);
+                    stats.merged_draws += 1;
+                }
             }
 
-            r_pass.draw_indexed(mesh.indices_range(), 0, 0..em.particle_count() as u32);
+            stats.draw_calls += 1;
+            stats.emitter_draws += run_emitters.len() as u32;
 
-            Profiler::end_scope(gfx, &mut r_pass).await;
+            Profiler::end_scope(gfx, r_pass).await;
         }
 
-        Profiler::end_scope(gfx, &mut r_pass).await;
+        Profiler::end_scope(gfx, r_pass).await;
+
+        stats
     }
 
     pub async fn recreate_emitter(
@@ -281,6 +1057,8 @@ impl EmitterState {
             encoder.copy_buffer_to_buffer(old_buf, 0, new_buf, 0, buf_size);
         }
 
+        new_self.bg_nr = old_self.bg_nr;
+
         let gfx = &options.gfx.read().await;
         for i in 0..old_self.particle_animations.len() {
             let animation = old_self.particle_animations[i].recreate(gfx, &new_self);
@@ -295,6 +1073,146 @@ impl EmitterState {
         new_self
     }
 
+    /// Reallocates the particle buffers for about `particle_count` particles by changing the spawn
+    /// count, the first min(old, new) particles stay alive
+    pub fn resize_particle_count(
+        &mut self,
+        particle_count: u64,
+        gfx: &GfxState,
+        camera: &Camera,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let batches = self.uniform.spawn_batches_count().max(1) as u64;
+        let spawn_count = particle_count.div_ceil(batches).clamp(1, u32::MAX as u64) as u32;
+
+        if spawn_count == self.uniform.spawn_count {
+            return;
+        }
+
+        self.uniform.spawn_count = spawn_count;
+        self.uniform.validate_particle_capacity();
+
+        let device = &gfx.device;
+        let particle_buffers = Self::create_particle_buffers(device, &self.uniform);
+
+        for (old_buf, new_buf) in self.particle_buffers.iter().zip(particle_buffers.iter()) {
+            let buf_size = old_buf.size().min(new_buf.size());
+            encoder.copy_buffer_to_buffer(old_buf, 0, new_buf, 0, buf_size);
+        }
+
+        self.frustum_cull.resize(
+            device,
+            &self.uniform,
+            &particle_buffers,
+            &self.emitter_buffer,
+        );
+        self.live_counter
+            .resize(device, &particle_buffers, &self.emitter_buffer);
+
+        if let Some(depth_sort) = &mut self.depth_sort {
+            depth_sort.resize(
+                device,
+                &self.uniform,
+                &particle_buffers,
+                &self.emitter_buffer,
+                &self.frustum_cull,
+            );
+        }
+
+        // Same buffer order, so bg_nr still points at the latest particles
+        self.bgs = Self::create_bind_groups(
+            device,
+            &self.bg_layout,
+            &particle_buffers,
+            &self.emitter_buffer,
+            &self.spawn_resources,
+            &self.frustum_cull,
+        );
+        self.particle_buffers = particle_buffers;
+
+        self.uniform.update_lod(camera.position);
+        self.dispatch_x_count = Self::dispatch_count(self.uniform.active_particle_count());
+
+        let mut animations = std::mem::take(&mut self.particle_animations);
+
+        for anim in animations.iter_mut() {
+            anim.resize(gfx, self);
+        }
+
+        self.particle_animations = animations;
+    }
+
+    fn create_particle_buffers(
+        device: &wgpu::Device,
+        uniform: &EmitterUniform,
+    ) -> Vec<wgpu::Buffer> {
+        (0..2)
+            .map(|i| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&format!("Particle Buffer {}", i)),
+                    mapped_at_creation: false,
+                    size: uniform.particle_buffer_size(),
+                    usage: wgpu::BufferUsages::VERTEX
+                        | wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_SRC
+                        | wgpu::BufferUsages::COPY_DST,
+                })
+            })
+            .collect()
+    }
+
+    fn create_bind_groups(
+        device: &wgpu::Device,
+        bg_layout: &wgpu::BindGroupLayout,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+        spawn_resources: &SpawnResources,
+        frustum_cull: &FrustumCull,
+    ) -> Vec<wgpu::BindGroup> {
+        (0..2)
+            .map(|i| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: bg_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: particle_buffers[i].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: particle_buffers[(i + 1) % 2].as_entire_binding(), // bind to opposite buffer
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: emitter_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: wgpu::BindingResource::TextureView(
+                                &spawn_resources.color_mask_view,
+                            ),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: wgpu::BindingResource::Sampler(
+                                &spawn_resources.color_mask_sampler,
+                            ),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: spawn_resources.spawn_curve_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 6,
+                            resource: frustum_cull.visible_buffer().as_entire_binding(),
+                        },
+                    ],
+                    label: None,
+                })
+            })
+            .collect()
+    }
+
     pub fn push_particle_animation(&mut self, animation: Box<dyn ParticleAnimation>) {
         self.particle_animations.push(animation);
     }
@@ -307,6 +1225,26 @@ impl EmitterState {
         self.uniform.particle_count()
     }
 
+    /// Bind group with the latest particles in binding 0, compute writes into binding 1
+    pub fn bindgroup_nr(&self) -> usize {
+        self.bg_nr
+    }
+
+    pub fn active_particle_count(&self) -> u64 {
+        self.uniform.active_particle_count()
+    }
+
+    /// Ping-pong particle buffer, use bindgroup_nr() for the one holding the latest particles.
+    /// Layout is described by shaders/particle_layout.wgsl
+    pub fn particle_buffer(&self, nr: usize) -> &wgpu::Buffer {
+        &self.particle_buffers[nr]
+    }
+
+    fn dispatch_count(particle_count: u64) -> u32 {
+        let workgroup_size = 128f64;
+        (particle_count as f64 / workgroup_size).ceil() as u32
+    }
+
     pub fn export(emitters: &[EmitterState]) {
         let mut to_export = Vec::new();
 
@@ -332,7 +1270,7 @@ impl EmitterState {
 
     pub async fn new(options: CreateEmitterOptions<'_>) -> Self {
         let camera = options.camera;
-        let uniform = options.uniform;
+        let mut uniform = options.uniform;
         let gfx = options.gfx;
         let collection = options.collection;
 
@@ -359,27 +1297,40 @@ impl EmitterState {
                         .expect("Can't load model"),
                 );
             }
+
+            if let Some(mesh) = uniform.spawn_shape.mesh() {
+                if !collection.contains_key(&mesh.collection_id) {
+                    collection.insert(
+                        mesh.collection_id.to_string(),
+                        Model::load_gltf(gfx, &mesh.collection_id)
+                            .await
+                            .expect("Can't load model"),
+                    );
+                }
+            }
         }
 
-        let emitter_buf_content = uniform.create_buffer_content(collection).await;
+        uniform.validate_particle_capacity();
 
-        let mut particle_buffers = Vec::<wgpu::Buffer>::new();
-        let mut bind_groups = Vec::<wgpu::BindGroup>::new();
+        let emitter_buf_content = uniform.create_buffer_content(collection).await;
+        let spawn_points = uniform
+            .spawn_shape
+            .spawn_points_content(&*collection.read().await);
+
+        // White leaves the particle color untouched
+        let color_mask = match &uniform.color_mask {
+            Some(filename) => {
+                let path = Persistence::texture_path(filename);
+                TextureHandler::tex_from_string(gfx, &path.to_string_lossy(), true).await
+            }
+            None => gfx.read().await.create_builtin_tex(TexType::White),
+        };
+        let color_mask_view = color_mask.default_view();
 
-        for i in 0..2 {
-            let device = &gfx.read().await.device;
-            particle_buffers.push(device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some(&format!("Particle Buffer {}", i)),
-                mapped_at_creation: false,
-                size: uniform.particle_buffer_size(),
-                usage: wgpu::BufferUsages::VERTEX
-                    | wgpu::BufferUsages::STORAGE
-                    | wgpu::BufferUsages::COPY_SRC
-                    | wgpu::BufferUsages::COPY_DST,
-            }));
-        }
+        let particle_buffers = Self::create_particle_buffers(&gfx.read().await.device, &uniform);
 
-        let particle_buffer_size = NonZeroU64::new(particle_buffers[0].size());
+        // A single particle, so resizing the particle buffers keeps the layout and pipelines
+        let particle_buffer_size = NonZeroU64::new(PARTICLE_STRIDE);
         let emitter_buffer_size = NonZeroU64::new(emitter_buf_content.len() as u64 * 4);
 
         let visibility = match &options.emitter_type {
@@ -419,10 +1370,10 @@ impl EmitterState {
                     },
                     count: None,
                 },
-                // Emitter
+                // Emitter, fragments read the tint and the light intensity of the lights
                 wgpu::BindGroupLayoutEntry {
                     binding: 2,
-                    visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -430,40 +1381,91 @@ impl EmitterState {
                     },
                     count: None,
                 },
+                // Spawn color mask
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Spawn curve
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Visible particles of the frustum culling, or the depth sorted particles
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
             label: None,
         });
 
+        let color_mask_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
         let emitter_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Emitters buffer"),
             contents: bytemuck::cast_slice(&emitter_buf_content),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
         });
 
-        for i in 0..2 {
-            bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &bg_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: particle_buffers[i].as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: particle_buffers[(i + 1) % 2].as_entire_binding(), // bind to opposite buffer
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: emitter_buffer.as_entire_binding(),
-                    },
-                ],
-                label: None,
-            }));
-        }
+        // Box emitters get a single unused point
+        let spawn_curve_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Spawn curve buffer"),
+            contents: bytemuck::cast_slice(&spawn_points),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
 
-        let particle_count = uniform.particle_count() as f64;
-        let workgroup_size = 128f64;
-        let dispatch_x_count = (particle_count / workgroup_size).ceil() as u32;
+        let spawn_resources = SpawnResources {
+            color_mask_view,
+            color_mask_sampler,
+            spawn_curve_buffer,
+        };
+
+        let frustum_cull =
+            FrustumCull::new(&gfx, camera, &uniform, &particle_buffers, &emitter_buffer);
+        let live_counter = LiveCounter::new(&gfx, &particle_buffers, &emitter_buffer);
+
+        let bind_groups = Self::create_bind_groups(
+            device,
+            &bg_layout,
+            &particle_buffers,
+            &emitter_buffer,
+            &spawn_resources,
+            &frustum_cull,
+        );
+
+        uniform.update_lod(camera.position);
+        let dispatch_x_count = Self::dispatch_count(uniform.active_particle_count());
 
         let shader = gfx.create_shader_builtin(ShaderOptions {
             files: &["emitter.wgsl"],
@@ -473,7 +1475,7 @@ impl EmitterState {
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Compute layout"),
-            bind_group_layouts: &[&bg_layout],
+            bind_group_layouts: &[&bg_layout, &live_counter.counter_layout],
             push_constant_ranges: &[],
         });
 
@@ -510,7 +1512,10 @@ impl EmitterState {
             EmitterType::Normal { lights_layout } => {
                 shader = gfx.create_shader_builtin(ShaderOptions {
                     files: &[SDR_TONEMAPPING, SDR_PBR, "particle.wgsl"],
-                    if_directives: &[],
+                    if_directives: match uniform.shading_model {
+                        ShadingModel::Pbr => &[],
+                        ShadingModel::Unlit => &[DIR_UNLIT],
+                    },
                     label: "Particle render",
                 });
 
@@ -528,50 +1533,154 @@ impl EmitterState {
             }
         }
 
-        let model_pipeline = Self::create_pipeline(
-            &shader,
-            &pipeline_layout,
-            material,
-            device,
-            FsEntryPoint::Model.to_string(),
-        );
+        let depth_sort = (uniform.depth_sorted && !is_light).then(|| {
+            DepthSort::new(
+                &gfx,
+                camera,
+                &uniform,
+                &particle_buffers,
+                &emitter_buffer,
+                &frustum_cull,
+            )
+        });
 
-        let circle_pipeline = Self::create_pipeline(
-            &shader,
-            &pipeline_layout,
-            material,
-            device,
-            FsEntryPoint::Circle.to_string(),
-        );
+        // Sorted particles blend back to front, a prepass would hide the ones behind
+        let depth_sorted = depth_sort.is_some();
+        let depth_prepass = uniform.depth_prepass && !depth_sorted;
 
         let mut render_pipelines = HashMap::new();
-        render_pipelines.insert(FsEntryPoint::Model, model_pipeline);
-        render_pipelines.insert(FsEntryPoint::Circle, circle_pipeline);
+        let mut single_target_pipelines = HashMap::new();
+
+        for (fs_entry_point, depth_prepass) in [
+            (FsEntryPoint::Model, depth_prepass),
+            (FsEntryPoint::Circle, false),
+        ] {
+            for blend_mode in [BlendMode::Replace, BlendMode::Alpha, BlendMode::Additive] {
+                for split_target in [true, false] {
+                    let pipeline = Self::create_pipeline(RenderPipelineOptions {
+                        shader: &shader,
+                        layout: &pipeline_layout,
+                        material,
+                        gfx: &gfx,
+                        fs_entry_point,
+                        blend_mode,
+                        depth_bias: uniform.depth_bias,
+                        depth_prepass,
+                        depth_sorted,
+                        split_target,
+                    });
+
+                    let key = (fs_entry_point, blend_mode);
+
+                    if split_target {
+                        render_pipelines.insert(key, pipeline);
+                    } else {
+                        single_target_pipelines.insert(key, pipeline);
+                    }
+                }
+            }
+        }
+
+        let depth_prepass_pipeline = depth_prepass.then(|| {
+            Self::create_depth_prepass_pipeline(
+                &shader,
+                &pipeline_layout,
+                material,
+                device,
+                uniform.depth_bias,
+            )
+        });
 
         Self {
             uniform,
             pipeline,
             render_pipelines,
+            single_target_pipelines,
+            depth_prepass_pipeline,
             pipeline_layout,
             bg_layout,
             bgs: bind_groups,
             particle_buffers,
             emitter_buffer,
+            spawn_resources,
+            frustum_cull,
+            depth_sort,
+            live_counter,
+            indirect_execution: gfx.supports_indirect_execution(),
+            finished: false,
             dispatch_x_count,
             particle_animations: vec![],
             emitter_animations: vec![],
             shader,
             is_light,
+            bg_nr: 0,
         }
     }
 
-    fn create_pipeline(
-        shader: &ShaderModule,
-        layout: &wgpu::PipelineLayout,
-        material: &Material,
-        device: &wgpu::Device,
-        fs_entry_point: String,
-    ) -> wgpu::RenderPipeline {
+    pub fn create_pipeline(options: RenderPipelineOptions<'_>) -> wgpu::RenderPipeline {
+        let RenderPipelineOptions {
+            shader,
+            layout,
+            material,
+            gfx,
+            fs_entry_point,
+            blend_mode,
+            depth_bias,
+            depth_prepass,
+            depth_sorted,
+            split_target,
+        } = options;
+
+        let device = &gfx.device;
+        let additive = blend_mode == BlendMode::Additive;
+
+        // After a prepass only the nearest fragment passes and depth is already written.
+        // Sorted and additive particles blend over each other, so they don't write depth
+        let (depth_write_enabled, depth_compare) = if depth_prepass {
+            (false, wgpu::CompareFunction::Equal)
+        } else if depth_sorted || additive {
+            (false, wgpu::CompareFunction::Less)
+        } else {
+            (true, wgpu::CompareFunction::Less)
+        };
+
+        let additive_blend = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        };
+
+        let blend = match blend_mode {
+            BlendMode::Replace => wgpu::BlendState::REPLACE,
+            // Opaque output stays opaque, alpha comes from the circle edge and tint
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => additive_blend,
+        };
+
+        let mut targets = vec![Some(wgpu::ColorTargetState {
+            format: gfx.fx_format(),
+            blend: Some(blend),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        if split_target {
+            // The bloom input holds the bright part, additive glow adds up and the rest replaces
+            let split_blend = if additive {
+                additive_blend
+            } else {
+                wgpu::BlendState::REPLACE
+            };
+
+            targets.push(Some(wgpu::ColorTargetState {
+                format: gfx.fx_format(),
+                blend: Some(split_blend),
+                write_mask: wgpu::ColorWrites::COLOR,
+            }));
+        }
+
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
             layout: Some(layout),
@@ -582,19 +1691,8 @@ impl EmitterState {
             },
             fragment: Some(wgpu::FragmentState {
                 module: shader,
-                entry_point: &fs_entry_point,
-                targets: &[
-                    Some(wgpu::ColorTargetState {
-                        format: PostProcessState::TEXTURE_FORMAT,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                    Some(wgpu::ColorTargetState {
-                        format: PostProcessState::TEXTURE_FORMAT,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::COLOR,
-                    }),
-                ],
+                entry_point: &fs_entry_point.to_string(),
+                targets: &targets,
             }),
             primitive: wgpu::PrimitiveState {
                 cull_mode: material.ctx.cull_mode,
@@ -602,19 +1700,58 @@ impl EmitterState {
             },
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: GfxState::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
+                depth_write_enabled,
+                depth_compare,
                 stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: depth_bias,
+                    ..Default::default()
+                },
             }),
             multisample: wgpu::MultisampleState {
                 count: 1,
                 mask: !0,
-                alpha_to_coverage_enabled: true,
+                // Coverage would cut the soft edges that sorted and additive blending keep
+                alpha_to_coverage_enabled: !depth_sorted && !additive,
             },
             multiview: None,
         })
     }
+
+    fn create_depth_prepass_pipeline(
+        shader: &ShaderModule,
+        layout: &wgpu::PipelineLayout,
+        material: &Material,
+        device: &wgpu::Device,
+        depth_bias: i32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Depth prepass pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[ModelVertex::desc()],
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                cull_mode: material.ctx.cull_mode,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: GfxState::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: depth_bias,
+                    ..Default::default()
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
 }
 
 impl GfxState {}
@@ -11,12 +11,38 @@ pub enum EventAction {
     Update,
 }
 
+/// Spawns `count` particles on the next simulated frame, next to the regular spawning
+#[derive(Debug, Clone)]
+pub struct EmitBurst {
+    pub emitter_id: ID,
+    pub count: u32,
+}
+
 /// Every option event is consumed when fetched to prevent repeating behaviour
 #[derive(Default, Debug)]
 pub struct SparEvents {
     pub reset_camera: bool,
     pub create_emitter: Option<ID>,
     pub delete_emitter: Option<ID>,
+    /// Copies the emitter and its animations, the copy gets a random time offset
+    pub duplicate_emitter: Option<ID>,
     pub io_view: Option<ViewIOEvent>,
     pub toggle_play: bool,
+    /// Advances one frame while paused
+    pub step_frame: bool,
+    /// Simulates again up to this time in seconds, see `EmitterState::seek`
+    pub seek_sec: Option<f32>,
+    pub emit_burst: Option<EmitBurst>,
+    /// Saves the finalized fx texture as png with alpha in export/frames, also bound to F12
+    pub capture_frame: bool,
+}
+
+/// Emitted by the simulation, drain them with `SparState::drain_emitter_events`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmitterEvent {
+    /// Spawning wrapped around to the first batch of the particle buffer
+    Looped(ID),
+    /// The live particle count read back zero after the last burst, only burst mode emitters
+    /// finish. The readback arrives a few frames after the particles decayed
+    Finished(ID),
 }
@@ -0,0 +1,199 @@
+use super::{Camera, EmitterUniform, GfxState};
+use crate::shaders::ShaderOptions;
+use egui_wgpu::wgpu;
+
+/// Size of wgpu::util::DrawIndexedIndirectArgs
+const DRAW_ARGS_SIZE: u64 = 5 * 4;
+
+/// Compacts the particles inside the camera frustum, so the emitter can draw them indirectly
+pub struct FrustumCull {
+    pipeline: wgpu::ComputePipeline,
+    bg_layout: wgpu::BindGroupLayout,
+    /// One per particle buffer, indexed like the emitter bind groups
+    bgs: Vec<wgpu::BindGroup>,
+    visible_buffer: wgpu::Buffer,
+    indirect_buffer: wgpu::Buffer,
+}
+
+impl FrustumCull {
+    pub fn new(
+        gfx: &GfxState,
+        camera: &Camera,
+        uniform: &EmitterUniform,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+    ) -> Self {
+        let device = &gfx.device;
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Frustum cull layout"),
+            entries: &[
+                storage_entry(0, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(2, false),
+                storage_entry(3, false),
+            ],
+        });
+
+        let shader = gfx.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["frustum_cull.wgsl"],
+            label: "Frustum cull",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Frustum cull pipeline layout"),
+            bind_group_layouts: &[&bg_layout, &camera.bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Frustum cull pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let indirect_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frustum cull draw args"),
+            size: DRAW_ARGS_SIZE,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let visible_buffer = Self::create_visible_buffer(device, uniform);
+
+        let bgs = Self::create_bind_groups(
+            device,
+            &bg_layout,
+            particle_buffers,
+            emitter_buffer,
+            &visible_buffer,
+            &indirect_buffer,
+        );
+
+        Self {
+            pipeline,
+            bg_layout,
+            bgs,
+            visible_buffer,
+            indirect_buffer,
+        }
+    }
+
+    /// Reallocates the visible indices for the new particle buffers
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        uniform: &EmitterUniform,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+    ) {
+        self.visible_buffer = Self::create_visible_buffer(device, uniform);
+        self.bgs = Self::create_bind_groups(
+            device,
+            &self.bg_layout,
+            particle_buffers,
+            emitter_buffer,
+            &self.visible_buffer,
+            &self.indirect_buffer,
+        );
+    }
+
+    /// Visible particle indices, the vertex shader reads them when culling is on
+    pub fn visible_buffer(&self) -> &wgpu::Buffer {
+        &self.visible_buffer
+    }
+
+    pub fn indirect_buffer(&self) -> &wgpu::Buffer {
+        &self.indirect_buffer
+    }
+
+    /// Clears the visible count, written before the frame's command buffer runs
+    pub fn reset(&self, queue: &wgpu::Queue, index_count: u32) {
+        let args: [u32; 5] = [index_count, 0, 0, 0, 0];
+        queue.write_buffer(&self.indirect_buffer, 0, bytemuck::cast_slice(&args));
+    }
+
+    /// Dispatched with the live particle dispatch of the emitter
+    pub fn compute<'a>(
+        &'a self,
+        camera: &'a Camera,
+        bg_nr: usize,
+        dispatch_buffer: &'a wgpu::Buffer,
+        c_pass: &mut wgpu::ComputePass<'a>,
+    ) {
+        c_pass.set_pipeline(&self.pipeline);
+        c_pass.set_bind_group(0, &self.bgs[bg_nr], &[]);
+        c_pass.set_bind_group(1, camera.bg(), &[]);
+        c_pass.dispatch_workgroups_indirect(dispatch_buffer, 0);
+    }
+
+    fn create_visible_buffer(device: &wgpu::Device, uniform: &EmitterUniform) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Visible particles buffer"),
+            size: uniform.particle_count().max(1) * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_bind_groups(
+        device: &wgpu::Device,
+        bg_layout: &wgpu::BindGroupLayout,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+        visible_buffer: &wgpu::Buffer,
+        indirect_buffer: &wgpu::Buffer,
+    ) -> Vec<wgpu::BindGroup> {
+        particle_buffers
+            .iter()
+            .map(|particle_buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Frustum cull bind group"),
+                    layout: bg_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: particle_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: emitter_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: visible_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: indirect_buffer.as_entire_binding(),
+                        },
+                    ],
+                })
+            })
+            .collect()
+    }
+}
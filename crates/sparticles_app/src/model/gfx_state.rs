@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use super::state::SparState;
+use super::DrawStats;
 use super::EmitterState;
+use super::RenderStage;
 use super::SparEvents;
 use crate::fx::PostProcessState;
 use crate::init::AppVisitor;
@@ -35,12 +37,45 @@ pub struct GfxState {
     pub queue: wgpu::Queue,
     pub surface_config: wgpu::SurfaceConfiguration,
     pub ctx: Context,
-    pub window: window::Window,
+    pub target: RenderTarget,
     pub renderer: Renderer,
     pub screen_descriptor: ScreenDescriptor,
     pub profiler: GpuProfiler,
-    pub winit: egui_winit::State,
-    pub surface: wgpu::Surface,
+    /// Scope calls are no-ops while profiling is disabled
+    profiling: bool,
+    profiling_requested: bool,
+    /// Limits supported by the adapter, the device runs with `device.limits()`
+    adapter_limits: wgpu::Limits,
+    /// Indirect dispatches and draws, without them emitters process every particle slot
+    indirect_execution: bool,
+    premultiplied_alpha: bool,
+    /// Clears to transparent instead of black, so captured frames keep an alpha channel
+    pub transparent_background: bool,
+    fx_workgroup_size: u32,
+    fx_format: wgpu::TextureFormat,
+    gui_enabled: bool,
+}
+
+/// Where the frames end up
+pub enum RenderTarget {
+    Window {
+        window: window::Window,
+        surface: wgpu::Surface,
+        /// Boxed, it is most of the enum size and the offscreen target doesn't need it
+        winit: Box<egui_winit::State>,
+    },
+    /// Headless rendering, frames are read back with a frame capture
+    Offscreen { texture: wgpu::Texture },
+}
+
+/// The part of the setup that doesn't depend on the render target
+struct GpuDevice {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    adapter_limits: wgpu::Limits,
+    indirect_execution: bool,
+    fx_workgroup_size: u32,
+    fx_format: wgpu::TextureFormat,
 }
 
 unsafe impl Send for GfxState {}
@@ -71,11 +106,39 @@ impl Profiler {
 
 impl GfxState {
     fn begin_scope(&mut self, label: &str, pass: &mut impl ProfilerCommandRecorder) {
-        self.profiler.begin_scope(label, pass, &self.device);
+        if self.profiling {
+            self.profiler.begin_scope(label, pass, &self.device);
+        }
     }
 
     fn end_scope(&mut self, pass: &mut impl ProfilerCommandRecorder) {
-        self.profiler.end_scope(pass).unwrap();
+        if self.profiling {
+            self.profiler.end_scope(pass).unwrap();
+        }
+    }
+
+    /// Takes effect at the end of the frame, so no scope is left open
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiling_requested = enabled;
+    }
+
+    pub fn is_profiling(&self) -> bool {
+        self.profiling
+    }
+
+    fn apply_profiling(&mut self) {
+        if self.profiling == self.profiling_requested {
+            return;
+        }
+
+        self.profiling = self.profiling_requested;
+        self.profiler
+            .change_settings(GpuProfilerSettings {
+                enable_timer_queries: self.profiling,
+                enable_debug_groups: self.profiling,
+                ..Default::default()
+            })
+            .expect("Failed to change profiler settings");
     }
 
     pub fn gfx_render_frame<'a>(
@@ -83,16 +146,20 @@ impl GfxState {
         mut r_pass: wgpu::RenderPass<'a>,
         primitives: &'a [ClippedPrimitive],
     ) {
-        self.profiler
-            .begin_scope("Render GUI", &mut r_pass, &self.device);
+        if self.profiling {
+            self.profiler
+                .begin_scope("Render GUI", &mut r_pass, &self.device);
+        }
 
         self.renderer
             .render(&mut r_pass, primitives, &self.screen_descriptor);
 
-        self.profiler.end_scope(&mut r_pass).unwrap();
+        if self.profiling {
+            self.profiler.end_scope(&mut r_pass).unwrap();
+        }
     }
 
-    pub async fn render_frame<'a, 'b>(
+    pub async fn render_frame<'a>(
         gfx: &'a Arc<RwLock<GfxState>>,
         r_pass: wgpu::RenderPass<'a>,
         primitives: &'a [ClippedPrimitive],
@@ -101,10 +168,30 @@ impl GfxState {
         gfx.gfx_render_frame(r_pass, primitives);
     }
 
+    /// Surface texture to present, None when rendering offscreen
+    fn output_frame(
+        &self,
+    ) -> Result<(Option<wgpu::SurfaceTexture>, wgpu::TextureView), wgpu::SurfaceError> {
+        match &self.target {
+            RenderTarget::Window { surface, .. } => {
+                let frame = surface.get_current_texture()?;
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+
+                Ok((Some(frame), view))
+            }
+            RenderTarget::Offscreen { texture } => Ok((
+                None,
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            )),
+        }
+    }
+
     pub fn finish_frame(
         &mut self,
         mut encoder: CommandEncoder,
-        output_frame: wgpu::SurfaceTexture,
+        output_frame: Option<wgpu::SurfaceTexture>,
     ) {
         self.profiler.resolve_queries(&mut encoder);
 
@@ -112,13 +199,135 @@ impl GfxState {
         self.queue.submit(Some(encoder.finish()));
 
         // Redraw egui
-        output_frame.present();
+        if let Some(output_frame) = output_frame {
+            output_frame.present();
+        }
 
         // Signal to the profiler that the frame is finished.
         self.profiler.end_frame().unwrap();
+        self.apply_profiling();
+    }
+
+    /// Finalize shader applies the sRGB transfer function when the surface doesn't
+    pub fn apply_srgb_oetf(&self) -> bool {
+        !self.surface_config.format.is_srgb()
+    }
+
+    /// Alpha is kept through the post fx chain and the output colors are premultiplied
+    pub fn premultiplied_alpha(&self) -> bool {
+        self.premultiplied_alpha
     }
 
-    pub async fn new(window: window::Window) -> Self {
+    /// Transparent when compositing, so uncovered pixels end with zero alpha
+    pub fn clear_color(&self) -> wgpu::Color {
+        if self.transparent_background {
+            wgpu::Color::TRANSPARENT
+        } else {
+            wgpu::Color::BLACK
+        }
+    }
+
+    /// Width and height of the post fx workgroups, available as FX_WORKGROUP_SIZE in the shaders
+    pub fn fx_workgroup_size(&self) -> u32 {
+        self.fx_workgroup_size
+    }
+
+    /// Square workgroups of the post fx passes have to fit in the device limits
+    fn valid_fx_workgroup_size(size: u32, limits: &wgpu::Limits) -> u32 {
+        let max_invocations = (limits.max_compute_invocations_per_workgroup as f64).sqrt() as u32;
+        let max = limits
+            .max_compute_workgroup_size_x
+            .min(limits.max_compute_workgroup_size_y)
+            .min(max_invocations);
+
+        let valid = size.clamp(1, max);
+
+        if valid != size {
+            println!(
+                "Fx workgroup size {} doesn't fit the device limits, using {}",
+                size, valid
+            );
+        }
+
+        valid
+    }
+
+    /// Format of the post fx textures and particle render targets, available as FX_FORMAT in the shaders
+    pub fn fx_format(&self) -> wgpu::TextureFormat {
+        self.fx_format
+    }
+
+    /// Texel format name of `fx_format` for storage textures in WGSL
+    pub fn fx_format_wgsl(&self) -> &'static str {
+        Self::wgsl_format(self.fx_format).expect("Fx format is validated")
+    }
+
+    fn wgsl_format(format: wgpu::TextureFormat) -> Option<&'static str> {
+        match format {
+            wgpu::TextureFormat::Rgba16Float => Some("rgba16float"),
+            wgpu::TextureFormat::Rgba32Float => Some("rgba32float"),
+            wgpu::TextureFormat::Rgba8Unorm => Some("rgba8unorm"),
+            _ => None,
+        }
+    }
+
+    /// The fx textures are rendered to with blending, sampled and used as read write storage
+    fn valid_fx_format(
+        format: wgpu::TextureFormat,
+        adapter: &wgpu::Adapter,
+    ) -> wgpu::TextureFormat {
+        let features = adapter.get_texture_format_features(format);
+
+        let usages = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::STORAGE_BINDING;
+        let flags = wgpu::TextureFormatFeatureFlags::STORAGE_READ_WRITE
+            | wgpu::TextureFormatFeatureFlags::FILTERABLE
+            | wgpu::TextureFormatFeatureFlags::BLENDABLE;
+
+        let is_valid = Self::wgsl_format(format).is_some()
+            && features.allowed_usages.contains(usages)
+            && features.flags.contains(flags);
+
+        if is_valid {
+            return format;
+        }
+
+        let fallback = PostProcessState::FALLBACK_TEXTURE_FORMAT;
+
+        println!(
+            "Fx texture format {:?} is not supported by the adapter, using {:?}",
+            format, fallback
+        );
+
+        fallback
+    }
+
+    /// Egui input and drawing are skipped when disabled
+    pub fn gui_enabled(&self) -> bool {
+        self.gui_enabled
+    }
+
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.surface_config.format
+    }
+
+    pub fn adapter_limits(&self) -> &wgpu::Limits {
+        &self.adapter_limits
+    }
+
+    pub fn supports_indirect_execution(&self) -> bool {
+        self.indirect_execution
+    }
+
+    pub async fn new(
+        window: window::Window,
+        prefer_srgb: bool,
+        premultiplied_alpha: bool,
+        fx_workgroup_size: u32,
+        fx_format: wgpu::TextureFormat,
+        gui_enabled: bool,
+    ) -> Self {
         let instance = wgpu::Instance::default();
 
         let surface = unsafe {
@@ -127,36 +336,8 @@ impl GfxState {
                 .expect("Can't load surface")
         };
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-
-        // Higher limits for Post FX
-        let limits = wgpu::Limits {
-            max_sampled_textures_per_shader_stage: 32,
-            max_storage_textures_per_shader_stage: 32,
-            ..Default::default()
-        };
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::TEXTURE_BINDING_ARRAY
-                        | wgpu::Features::STORAGE_RESOURCE_BINDING_ARRAY
-                        | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
-                        | GpuProfiler::ALL_WGPU_TIMER_FEATURES,
-                    limits,
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        let (adapter, gpu) =
+            Self::request_device(&instance, Some(&surface), fx_workgroup_size, fx_format).await;
 
         let size = window.inner_size();
         let surface_caps = surface.get_capabilities(&adapter);
@@ -164,35 +345,200 @@ impl GfxState {
         let surface_format = surface_caps
             .formats
             .iter()
-            .find(|f| f.is_srgb())
+            .find(|f| f.is_srgb() == prefer_srgb)
             .copied()
             .unwrap_or(surface_caps.formats[0]);
 
+        let alpha_mode = if premultiplied_alpha
+            && surface_caps
+                .alpha_modes
+                .contains(&wgpu::CompositeAlphaMode::PreMultiplied)
+        {
+            wgpu::CompositeAlphaMode::PreMultiplied
+        } else {
+            surface_caps.alpha_modes[0]
+        };
+
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: size.width,
             height: size.height,
             present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
+            alpha_mode,
             view_formats: vec![],
         };
 
-        surface.configure(&device, &surface_config);
+        surface.configure(&gpu.device, &surface_config);
 
         let raw_input = RawInput::default();
         let vp = raw_input.viewport();
 
-        let ctx = Context::default();
-
-        let winit = egui_winit::State::new(
+        let winit = Box::new(egui_winit::State::new(
             raw_input.viewport_id,
             &window,
             vp.native_pixels_per_point,
             raw_input.max_texture_side,
-        );
+        ));
+
+        let pixels_per_point = window.scale_factor() as f32;
+
+        let target = RenderTarget::Window {
+            window,
+            surface,
+            winit,
+        };
+
+        Self::from_target(
+            gpu,
+            target,
+            surface_config,
+            pixels_per_point,
+            premultiplied_alpha,
+            gui_enabled,
+        )
+    }
+
+    /// Renders into an offscreen texture without a window, the gui is always disabled
+    pub async fn new_headless(
+        width: u32,
+        height: u32,
+        prefer_srgb: bool,
+        premultiplied_alpha: bool,
+        fx_workgroup_size: u32,
+        fx_format: wgpu::TextureFormat,
+    ) -> Self {
+        let instance = wgpu::Instance::default();
+        let (_, gpu) = Self::request_device(&instance, None, fx_workgroup_size, fx_format).await;
+
+        let format = if prefer_srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+
+        // Only the size and format are used, there is no surface to configure
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        let texture = Self::create_offscreen_texture(&gpu.device, &surface_config);
+
+        Self::from_target(
+            gpu,
+            RenderTarget::Offscreen { texture },
+            surface_config,
+            1.,
+            premultiplied_alpha,
+            false,
+        )
+    }
+
+    /// Features the device is requested with, adapters without them can't run sparticles
+    pub fn required_features() -> wgpu::Features {
+        wgpu::Features::TEXTURE_BINDING_ARRAY
+            | wgpu::Features::STORAGE_RESOURCE_BINDING_ARRAY
+            | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+            | GpuProfiler::ALL_WGPU_TIMER_FEATURES
+    }
+
+    pub fn required_limits() -> wgpu::Limits {
+        // Higher limits for Post FX
+        wgpu::Limits {
+            max_sampled_textures_per_shader_stage: 32,
+            max_storage_textures_per_shader_stage: 32,
+            ..Default::default()
+        }
+    }
 
-        let renderer = Renderer::new(&device, surface_format, None, 1);
+    async fn request_device(
+        instance: &wgpu::Instance,
+        compatible_surface: Option<&wgpu::Surface>,
+        fx_workgroup_size: u32,
+        fx_format: wgpu::TextureFormat,
+    ) -> (wgpu::Adapter, GpuDevice) {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface,
+                force_fallback_adapter: false,
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: Self::required_features(),
+                    limits: Self::required_limits(),
+                    label: None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let gpu = GpuDevice {
+            adapter_limits: adapter.limits(),
+            indirect_execution: adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::INDIRECT_EXECUTION),
+            fx_workgroup_size: Self::valid_fx_workgroup_size(fx_workgroup_size, &device.limits()),
+            fx_format: Self::valid_fx_format(fx_format, &adapter),
+            device,
+            queue,
+        };
+
+        (adapter, gpu)
+    }
+
+    fn create_offscreen_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &[],
+        })
+    }
+
+    fn from_target(
+        gpu: GpuDevice,
+        target: RenderTarget,
+        surface_config: wgpu::SurfaceConfiguration,
+        pixels_per_point: f32,
+        premultiplied_alpha: bool,
+        gui_enabled: bool,
+    ) -> Self {
+        let GpuDevice {
+            device,
+            queue,
+            adapter_limits,
+            indirect_execution,
+            fx_workgroup_size,
+            fx_format,
+        } = gpu;
+
+        let ctx = Context::default();
+
+        let renderer = Renderer::new(&device, surface_config.format, None, 1);
 
         let mut fonts = FontDefinitions::default();
 
@@ -206,42 +552,70 @@ impl GfxState {
             vec!["FiraMono-Medium".to_string()],
         );
 
-        ctx.set_fonts(fonts);
+        if gui_enabled {
+            ctx.set_fonts(fonts);
+        }
 
         let screen_descriptor = ScreenDescriptor {
             size_in_pixels: [surface_config.width, surface_config.height],
-            pixels_per_point: window.scale_factor() as f32,
+            pixels_per_point,
         };
 
-        let profiler =
-            GpuProfiler::new(GpuProfilerSettings::default()).expect("Failed to create profiler");
+        let profiler = GpuProfiler::new(GpuProfilerSettings {
+            enable_timer_queries: false,
+            enable_debug_groups: false,
+            ..Default::default()
+        })
+        .expect("Failed to create profiler");
 
         Self {
-            surface,
-            window,
+            target,
             device,
             surface_config,
             renderer,
             queue,
-            winit,
             ctx,
             screen_descriptor,
             profiler,
+            profiling: false,
+            profiling_requested: false,
+            adapter_limits,
+            indirect_execution,
+            premultiplied_alpha,
+            transparent_background: premultiplied_alpha,
+            fx_workgroup_size,
+            fx_format,
+            gui_enabled,
         }
     }
 
-    pub async fn window_id(gfx: &Arc<RwLock<GfxState>>) -> window::WindowId {
-        gfx.read().await.window.id()
+    /// None when rendering headless
+    pub async fn window_id(gfx: &Arc<RwLock<GfxState>>) -> Option<window::WindowId> {
+        match &gfx.read().await.target {
+            RenderTarget::Window { window, .. } => Some(window.id()),
+            RenderTarget::Offscreen { .. } => None,
+        }
     }
 
     pub fn handle_event(gfx: &Arc<RwLock<GfxState>>, event: &WindowEvent<'_>) -> EventResponse {
         let gfx = &mut task::block_on(gfx.write());
+
         let ctx = gfx.ctx.clone();
-        gfx.winit.on_window_event(&ctx, event)
+        let gui_enabled = gfx.gui_enabled;
+
+        match &mut gfx.target {
+            RenderTarget::Window { winit, .. } if gui_enabled => winit.on_window_event(&ctx, event),
+            _ => EventResponse {
+                consumed: false,
+                repaint: false,
+            },
+        }
     }
 
     pub fn request_redraw(&self) {
-        self.window.request_redraw();
+        if let RenderTarget::Window { window, .. } = &self.target {
+            window.request_redraw();
+        }
     }
 
     pub fn process_frame(&mut self) -> Option<Vec<GpuTimerScopeResult>> {
@@ -253,22 +627,41 @@ impl GfxState {
         if size.width > 0 && size.height > 0 {
             self.surface_config.width = size.width;
             self.surface_config.height = size.height;
-            self.surface.configure(&self.device, &self.surface_config);
 
-            self.screen_descriptor = ScreenDescriptor {
-                size_in_pixels: [size.width, size.height],
-                pixels_per_point: self.window.scale_factor() as f32,
-            };
+            match &mut self.target {
+                RenderTarget::Window {
+                    window, surface, ..
+                } => {
+                    surface.configure(&self.device, &self.surface_config);
+                    self.screen_descriptor.pixels_per_point = window.scale_factor() as f32;
+                }
+                RenderTarget::Offscreen { texture } => {
+                    *texture = Self::create_offscreen_texture(&self.device, &self.surface_config);
+                }
+            }
+
+            self.screen_descriptor.size_in_pixels = [size.width, size.height];
         }
     }
 
     fn egui_input(&mut self) -> RawInput {
-        self.winit.take_egui_input(&self.window)
+        match &mut self.target {
+            RenderTarget::Window { window, winit, .. } => winit.take_egui_input(window),
+            RenderTarget::Offscreen { .. } => RawInput::default(),
+        }
     }
 
     fn egui_handle_output(&mut self, platform_output: PlatformOutput) {
-        self.winit
-            .handle_platform_output(&self.window, &self.ctx, platform_output);
+        if let RenderTarget::Window { window, winit, .. } = &mut self.target {
+            winit.handle_platform_output(window, &self.ctx, platform_output);
+        }
+    }
+
+    fn pixels_per_point(&self) -> f32 {
+        match &self.target {
+            RenderTarget::Window { winit, .. } => winit.pixels_per_point(),
+            RenderTarget::Offscreen { .. } => self.screen_descriptor.pixels_per_point,
+        }
     }
 
     fn egui_update_texture(&mut self, tex_id: TextureId, img_delta: ImageDelta) {
@@ -308,9 +701,8 @@ impl GfxState {
             let gfx = &mut state.gfx.write().await;
             let full_output = gfx.ctx.end_frame();
 
-            let primitives = gfx
-                .ctx
-                .tessellate(full_output.shapes, gfx.winit.pixels_per_point());
+            let pixels_per_point = gfx.pixels_per_point();
+            let primitives = gfx.ctx.tessellate(full_output.shapes, pixels_per_point);
 
             gfx.egui_handle_output(full_output.platform_output);
 
@@ -331,11 +723,11 @@ impl GfxState {
     pub async fn render(state: &mut SparState, app_visitor: &mut impl AppVisitor) -> SparEvents {
         let mut encoder: CommandEncoder;
         let output_view: wgpu::TextureView;
-        let output_frame: wgpu::SurfaceTexture;
+        let output_frame: Option<wgpu::SurfaceTexture>;
 
         {
             let gfx = state.gfx.read().await;
-            output_frame = match gfx.surface.get_current_texture() {
+            (output_frame, output_view) = match gfx.output_frame() {
                 Ok(frame) => frame,
                 Err(wgpu::SurfaceError::Outdated) => {
                     return SparEvents::default();
@@ -351,25 +743,61 @@ impl GfxState {
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("encoder"),
                 });
-
-            output_view = output_frame
-                .texture
-                .create_view(&wgpu::TextureViewDescriptor::default());
         }
 
-        if state.play {
-            EmitterState::compute_particles(state, &mut encoder).await;
+        let mut rendered_layers = vec![];
+        let staged_layers =
+            RenderStage::rendered_layers(&state.render_stages, state.post_process.layer_count());
+        state.draw_stats = DrawStats::default();
+        state.draw_batcher.begin_frame();
+
+        for stage in state.render_stages.clone() {
+            match stage {
+                RenderStage::ComputeParticles => {
+                    if state.clock.advanced() {
+                        EmitterState::compute_particles(state, &mut encoder).await;
+                    }
+                }
+                RenderStage::RenderParticles => {
+                    let layers: Vec<u8> = (0..state.post_process.layer_count() as u8).collect();
+                    EmitterState::render_layers(state, &mut encoder, &layers, &mut rendered_layers)
+                        .await;
+                }
+                RenderStage::RenderLayer(layer) => {
+                    EmitterState::render_layers(
+                        state,
+                        &mut encoder,
+                        &[layer],
+                        &mut rendered_layers,
+                    )
+                    .await;
+                }
+                RenderStage::PostFx => {
+                    PostProcessState::compute(state, &mut encoder, &staged_layers).await;
+                }
+            }
         }
+        let gui_enabled = state.gfx.read().await.gui_enabled;
+
+        let res = if gui_enabled {
+            GfxState::draw_ui(state, &mut encoder, app_visitor).await
+        } else {
+            DrawGuiResult {
+                primitives: vec![],
+                events: SparEvents::default(),
+            }
+        };
 
-        EmitterState::render_particles(state, &mut encoder).await;
-        PostProcessState::compute(state, &mut encoder).await;
-        let res = GfxState::draw_ui(state, &mut encoder, app_visitor).await;
         PostProcessState::render(state, output_view, &mut encoder, &res.primitives).await;
 
         state.clock.measure_cpu_time();
 
-        let gfx = &mut state.gfx.write().await;
-        gfx.finish_frame(encoder, output_frame);
+        {
+            let gfx = &mut state.gfx.write().await;
+            gfx.finish_frame(encoder, output_frame);
+        }
+
+        PostProcessState::save_capture(state).await;
 
         res.events
     }
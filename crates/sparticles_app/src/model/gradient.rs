@@ -0,0 +1,144 @@
+use crate::util::math::catmull_rom;
+use egui_wgpu::wgpu::{self, util::DeviceExt};
+use egui_winit::egui::WidgetText;
+use glam::Vec4;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GradientInterpolation {
+    Step,
+    #[default]
+    Linear,
+    /// Smooth curve through all stops, can overshoot slightly between stops
+    CatmullRom,
+}
+
+impl From<GradientInterpolation> for WidgetText {
+    fn from(value: GradientInterpolation) -> Self {
+        match value {
+            GradientInterpolation::Step => "Step".into(),
+            GradientInterpolation::Linear => "Linear".into(),
+            GradientInterpolation::CatmullRom => "Catmull-Rom".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    /// 0..1
+    pub position: f32,
+    /// Linear RGBA, RGB can be HDR
+    pub color: Vec4,
+}
+
+/// Color stops shared by every effect that needs a gradient, sampled into a LUT for the GPU
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorGradient {
+    pub stops: Vec<GradientStop>,
+    pub interpolation: GradientInterpolation,
+}
+
+impl Default for ColorGradient {
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                GradientStop {
+                    position: 0.,
+                    color: Vec4::ONE,
+                },
+                GradientStop {
+                    position: 1.,
+                    color: Vec4::new(1., 1., 1., 0.),
+                },
+            ],
+            interpolation: GradientInterpolation::default(),
+        }
+    }
+}
+
+impl ColorGradient {
+    pub fn sort_stops(&mut self) {
+        self.stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+    }
+
+    fn sorted_stops(&self) -> Vec<GradientStop> {
+        let mut stops = self.stops.clone();
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        stops
+    }
+
+    /// Color at `t` (0..1), stops don't need to be sorted
+    pub fn sample(&self, t: f32) -> Vec4 {
+        Self::sample_sorted(&self.sorted_stops(), self.interpolation, t)
+    }
+
+    fn sample_sorted(stops: &[GradientStop], interpolation: GradientInterpolation, t: f32) -> Vec4 {
+        let (first, last) = match (stops.first(), stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Vec4::ONE,
+        };
+
+        if t <= first.position {
+            return first.color;
+        } else if last.position <= t {
+            return last.color;
+        }
+
+        let i = stops
+            .windows(2)
+            .position(|pair| t < pair[1].position)
+            .unwrap_or(stops.len() - 2);
+
+        let from = stops[i];
+        let to = stops[i + 1];
+        let range = to.position - from.position;
+        let local_t = if 0. < range {
+            (t - from.position) / range
+        } else {
+            1.
+        };
+
+        match interpolation {
+            GradientInterpolation::Step => from.color,
+            GradientInterpolation::Linear => from.color.lerp(to.color, local_t),
+            GradientInterpolation::CatmullRom => {
+                let before = stops[i.saturating_sub(1)].color;
+                let after = stops[(i + 2).min(stops.len() - 1)].color;
+
+                let color = catmull_rom(before, from.color, to.color, after, local_t);
+                let alpha = color.w.clamp(0., 1.);
+
+                color.max(Vec4::ZERO).truncate().extend(alpha)
+            }
+        }
+    }
+
+    /// Evenly spaced samples from 0 to 1
+    pub fn lut(&self, size: usize) -> Vec<Vec4> {
+        let stops = self.sorted_stops();
+        let last = size.saturating_sub(1).max(1) as f32;
+
+        (0..size)
+            .map(|i| Self::sample_sorted(&stops, self.interpolation, i as f32 / last))
+            .collect()
+    }
+
+    /// LUT as raw floats, for writing into an existing LUT buffer
+    pub fn lut_content(&self, size: usize) -> Vec<f32> {
+        self.lut(size).iter().flat_map(|c| c.to_array()).collect()
+    }
+
+    /// Storage buffer with `size` vec4<f32> colors
+    pub fn create_lut_buffer(
+        &self,
+        device: &wgpu::Device,
+        size: usize,
+        label: &str,
+    ) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&self.lut_content(size)),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+}
@@ -1,27 +1,166 @@
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 
 use super::Clock;
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ImportLifeCycle")]
 pub struct LifeCycle {
     pub from_sec: f32,
     pub until_sec: f32,
     /// Time until the animmation repeats
     pub lifetime_sec: f32,
+    pub mode: LifeCycleMode,
+}
+
+/// Scenes saved before the life cycle modes have a `looping` flag instead of a mode, and
+/// older scenes have neither and always loop
+#[derive(Deserialize)]
+struct ImportLifeCycle {
+    from_sec: f32,
+    until_sec: f32,
+    lifetime_sec: f32,
+    mode: Option<LifeCycleMode>,
+    looping: Option<bool>,
+}
+
+impl From<ImportLifeCycle> for LifeCycle {
+    fn from(import: ImportLifeCycle) -> Self {
+        let mode = match (import.mode, import.looping) {
+            (Some(mode), _) => mode,
+            (None, Some(false)) => LifeCycleMode::Once,
+            (None, _) => LifeCycleMode::Loop,
+        };
+
+        Self {
+            from_sec: import.from_sec,
+            until_sec: import.until_sec,
+            lifetime_sec: import.lifetime_sec,
+            mode,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LifeCycleMode {
+    /// Runs from `from_sec` until `until_sec` once
+    Once,
+    /// Restarts every `lifetime_sec`
+    #[default]
+    Loop,
+    /// Runs forward during `lifetime_sec` and backwards during the next, always animating
+    PingPong,
+}
+
+impl LifeCycleMode {
+    pub const ALL: [LifeCycleMode; 3] = [Self::Once, Self::Loop, Self::PingPong];
+}
+
+impl Display for LifeCycleMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Once => write!(f, "Once"),
+            Self::Loop => write!(f, "Loop"),
+            Self::PingPong => write!(f, "Ping pong"),
+        }
+    }
 }
 
 impl LifeCycle {
     pub fn get_current_sec(&self, clock: &Clock) -> f32 {
-        clock.elapsed_sec() % self.lifetime_sec
+        match self.mode {
+            LifeCycleMode::Once => clock.elapsed_sec(),
+            LifeCycleMode::Loop => clock.elapsed_sec() % self.lifetime_sec,
+            LifeCycleMode::PingPong => {
+                // Folds the time back at `lifetime_sec`, so it moves between 0 and the lifetime
+                let period_sec = 2. * self.lifetime_sec;
+                let wrapped_sec = clock.elapsed_sec() % period_sec;
+
+                if wrapped_sec <= self.lifetime_sec {
+                    wrapped_sec
+                } else {
+                    period_sec - wrapped_sec
+                }
+            }
+        }
     }
 
     pub fn shoud_animate(&self, current_sec: f32) -> bool {
-        self.from_sec <= current_sec && current_sec <= self.until_sec
+        self.mode == LifeCycleMode::PingPong
+            || self.from_sec <= current_sec && current_sec <= self.until_sec
     }
 
     pub fn get_fraction(&self, current_sec: f32) -> f32 {
         let delta_current = current_sec - self.from_sec;
         let delta_max = self.until_sec - self.from_sec;
-        delta_current / delta_max
+
+        match self.mode {
+            // Holds at the ends when the from until range is shorter than the lifetime
+            LifeCycleMode::PingPong => (delta_current / delta_max).clamp(0., 1.),
+            LifeCycleMode::Once | LifeCycleMode::Loop => delta_current / delta_max,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current_sec_at(life_cycle: &LifeCycle, elapsed_sec: f32) -> f32 {
+        let mut clock = Clock::default();
+        clock.set_elapsed_sec(elapsed_sec);
+        life_cycle.get_current_sec(&clock)
+    }
+
+    #[test]
+    fn ping_pong_folds_at_lifetime() {
+        let life_cycle = LifeCycle {
+            from_sec: 0.,
+            until_sec: 2.,
+            lifetime_sec: 2.,
+            mode: LifeCycleMode::PingPong,
+        };
+
+        // Forward before the fold, the turning point itself, then backwards after it
+        assert_eq!(current_sec_at(&life_cycle, 1.5), 1.5);
+        assert_eq!(current_sec_at(&life_cycle, 2.), 2.);
+        assert_eq!(current_sec_at(&life_cycle, 2.5), 1.5);
+        assert_eq!(current_sec_at(&life_cycle, 3.75), 0.25);
+
+        // The next period starts forward again
+        assert_eq!(current_sec_at(&life_cycle, 4.), 0.);
+        assert_eq!(current_sec_at(&life_cycle, 4.5), 0.5);
+
+        for elapsed_sec in [1.5, 2., 2.5] {
+            let current_sec = current_sec_at(&life_cycle, elapsed_sec);
+
+            assert!(life_cycle.shoud_animate(current_sec));
+            assert_eq!(life_cycle.get_fraction(current_sec), current_sec / 2.);
+        }
+    }
+
+    #[test]
+    fn imports_legacy_looping_flag() {
+        let import = |json: &str| -> LifeCycleMode {
+            serde_json::from_str::<LifeCycle>(json)
+                .expect("Parses life cycle")
+                .mode
+        };
+
+        let times = r#""from_sec": 0.0, "until_sec": 1.0, "lifetime_sec": 2.0"#;
+
+        assert_eq!(import(&format!("{{{}}}", times)), LifeCycleMode::Loop);
+        assert_eq!(
+            import(&format!(r#"{{{}, "looping": true}}"#, times)),
+            LifeCycleMode::Loop
+        );
+        assert_eq!(
+            import(&format!(r#"{{{}, "looping": false}}"#, times)),
+            LifeCycleMode::Once
+        );
+        assert_eq!(
+            import(&format!(r#"{{{}, "mode": "PingPong"}}"#, times)),
+            LifeCycleMode::PingPong
+        );
     }
 }
@@ -0,0 +1,283 @@
+use super::GfxState;
+use crate::shaders::ShaderOptions;
+use egui_wgpu::wgpu;
+use std::sync::{Arc, Mutex};
+
+/// Draw args of wgpu::util::DrawIndexedIndirectArgs followed by the alive count
+const COUNTER_SIZE: u64 = 6 * 4;
+/// Offset of the alive count in the counter buffer
+const ALIVE_OFFSET: u64 = 5 * 4;
+/// Size of wgpu::util::DispatchIndirectArgs
+const DISPATCH_ARGS_SIZE: u64 = 3 * 4;
+
+/// Counts the live particles on the GPU. Particles stay in their slot, so the dispatches and
+/// draws are sized from the highest live slot instead of the alive count
+pub struct LiveCounter {
+    prepare_pipeline: wgpu::ComputePipeline,
+    prepare_layout: wgpu::BindGroupLayout,
+    prepare_bg: wgpu::BindGroup,
+    /// Bound by emitter.wgsl, which counts the particles it keeps alive
+    pub counter_layout: wgpu::BindGroupLayout,
+    pub counter_bg: wgpu::BindGroup,
+    counter_buffer: wgpu::Buffer,
+    dispatch_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    readback: Readback,
+}
+
+/// Alive count on its way to the CPU. The buffer is mapped a frame after the copy, so reading
+/// it back never waits on the GPU
+enum Readback {
+    Idle,
+    Copied,
+    /// Holds the map result once the map finished
+    Mapping(Arc<Mutex<Option<bool>>>),
+}
+
+impl LiveCounter {
+    pub fn new(
+        gfx: &GfxState,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+    ) -> Self {
+        let device = &gfx.device;
+
+        let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let counter_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Live counter layout"),
+            entries: &[storage_entry(0, false)],
+        });
+
+        let prepare_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Live dispatch layout"),
+            entries: &[
+                storage_entry(0, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(2, false),
+                storage_entry(3, false),
+            ],
+        });
+
+        // Zeroed, so the first frame only dispatches the spawned particles
+        let counter_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Live counter buffer"),
+            size: COUNTER_SIZE,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Live readback buffer"),
+            size: 4,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let dispatch_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Live dispatch buffer"),
+            size: DISPATCH_ARGS_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+            mapped_at_creation: false,
+        });
+
+        let counter_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Live counter bind group"),
+            layout: &counter_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: counter_buffer.as_entire_binding(),
+            }],
+        });
+
+        let prepare_bg = Self::create_prepare_bg(
+            device,
+            &prepare_layout,
+            particle_buffers,
+            emitter_buffer,
+            &counter_buffer,
+            &dispatch_buffer,
+        );
+
+        let shader = gfx.create_shader_builtin(ShaderOptions {
+            if_directives: &[],
+            files: &["live_dispatch.wgsl"],
+            label: "Live dispatch",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Live dispatch pipeline layout"),
+            bind_group_layouts: &[&prepare_layout],
+            push_constant_ranges: &[],
+        });
+
+        let prepare_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Live dispatch pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        Self {
+            prepare_pipeline,
+            prepare_layout,
+            prepare_bg,
+            counter_layout,
+            counter_bg,
+            counter_buffer,
+            dispatch_buffer,
+            readback_buffer,
+            readback: Readback::Idle,
+        }
+    }
+
+    /// Binds the new particle buffers, the live slots survive the resize
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+    ) {
+        self.prepare_bg = Self::create_prepare_bg(
+            device,
+            &self.prepare_layout,
+            particle_buffers,
+            emitter_buffer,
+            &self.counter_buffer,
+            &self.dispatch_buffer,
+        );
+    }
+
+    /// Draw args with the highest live slot as instance count
+    pub fn draw_buffer(&self) -> &wgpu::Buffer {
+        &self.counter_buffer
+    }
+
+    /// Workgroups covering the live slots and the slots spawned this frame
+    pub fn dispatch_buffer(&self) -> &wgpu::Buffer {
+        &self.dispatch_buffer
+    }
+
+    /// The index count of the draw args, the mesh can change without recreating the emitter
+    pub fn set_index_count(&self, queue: &wgpu::Queue, index_count: u32) {
+        queue.write_buffer(
+            &self.counter_buffer,
+            0,
+            bytemuck::cast_slice(&[index_count]),
+        );
+    }
+
+    /// Sizes this frame's dispatch from the last frame's live slots and resets the counters.
+    /// Call before the emitter compute, after the emitter uniform is written
+    pub fn prepare<'a>(&'a self, c_pass: &mut wgpu::ComputePass<'a>) {
+        c_pass.set_pipeline(&self.prepare_pipeline);
+        c_pass.set_bind_group(0, &self.prepare_bg, &[]);
+        c_pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    /// Copies the alive count after the emitter compute, unless the last copy is still being read
+    pub fn copy_alive(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !matches!(self.readback, Readback::Idle) {
+            return;
+        }
+
+        encoder.copy_buffer_to_buffer(
+            &self.counter_buffer,
+            ALIVE_OFFSET,
+            &self.readback_buffer,
+            0,
+            4,
+        );
+        self.readback = Readback::Copied;
+    }
+
+    /// Alive count of the last copy once it reached the CPU. Call after the copy is submitted,
+    /// the device has to be polled for the map to finish
+    pub fn read_alive(&mut self) -> Option<u32> {
+        match &self.readback {
+            Readback::Idle => None,
+            Readback::Copied => {
+                let result = Arc::new(Mutex::new(None));
+                let map_result = result.clone();
+
+                self.readback_buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |res| {
+                        *map_result.lock().unwrap() = Some(res.is_ok());
+                    });
+                self.readback = Readback::Mapping(result);
+
+                None
+            }
+            Readback::Mapping(result) => {
+                let is_mapped = (*result.lock().unwrap())?;
+
+                let alive = is_mapped.then(|| {
+                    let data = self.readback_buffer.slice(..).get_mapped_range();
+                    bytemuck::cast_slice::<u8, u32>(&data)[0]
+                });
+
+                if is_mapped {
+                    self.readback_buffer.unmap();
+                }
+
+                self.readback = Readback::Idle;
+                alive
+            }
+        }
+    }
+
+    fn create_prepare_bg(
+        device: &wgpu::Device,
+        prepare_layout: &wgpu::BindGroupLayout,
+        particle_buffers: &[wgpu::Buffer],
+        emitter_buffer: &wgpu::Buffer,
+        counter_buffer: &wgpu::Buffer,
+        dispatch_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Live dispatch bind group"),
+            layout: prepare_layout,
+            entries: &[
+                // Only read for the length, both particle buffers have the same size
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: particle_buffers[0].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: emitter_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: counter_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: dispatch_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
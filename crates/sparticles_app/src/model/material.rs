@@ -1,44 +1,96 @@
 use super::GfxState;
 use crate::{loader::CIRCLE_MAT_ID, texture::TexType, traits::CreateFxView, util::ID};
-use egui_wgpu::wgpu;
+use egui_wgpu::wgpu::{self, util::DeviceExt};
 use std::collections::HashMap;
 
 pub struct Material {
     pub ctx: MaterialCtx,
     pub bg: wgpu::BindGroup,
     pub bg_layout: wgpu::BindGroupLayout,
+    sampler_settings: SamplerSettings,
+    sampling_buffer: wgpu::Buffer,
 }
 
 pub struct MaterialCtx {
     pub albedo_tex: wgpu::Texture,
-    pub albedo_s: wgpu::Sampler,
+    pub albedo_s: wgpu::SamplerDescriptor<'static>,
     pub metallic_roughness_tex: wgpu::Texture,
-    pub metallic_roughness_s: wgpu::Sampler,
+    pub metallic_roughness_s: wgpu::SamplerDescriptor<'static>,
     pub normal_tex: wgpu::Texture,
-    pub normal_s: wgpu::Sampler,
+    pub normal_s: wgpu::SamplerDescriptor<'static>,
     pub emissive_tex: wgpu::Texture,
-    pub emissive_s: wgpu::Sampler,
+    pub emissive_s: wgpu::SamplerDescriptor<'static>,
     pub ao_tex: wgpu::Texture,
-    pub ao_s: wgpu::Sampler,
+    pub ao_s: wgpu::SamplerDescriptor<'static>,
     pub cull_mode: Option<wgpu::Face>,
 }
 
+/// Sampling options shared by all textures of a material
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerSettings {
+    /// Added to the mip level the hardware picks, negative values sharpen distant textures.
+    /// Has no effect on textures without mip levels
+    pub mip_bias: f32,
+    /// 1 disables anisotropic filtering
+    pub max_anisotropy: u16,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            mip_bias: 0.,
+            max_anisotropy: 1,
+        }
+    }
+}
+
+impl SamplerSettings {
+    /// Highest anisotropy clamp WebGPU accepts
+    pub const MAX_ANISOTROPY: u16 = 16;
+    /// Range textureSampleBias accepts
+    pub const MIP_BIAS_MIN: f32 = -16.;
+    pub const MIP_BIAS_MAX: f32 = 15.99;
+
+    pub fn validated(&self) -> Self {
+        Self {
+            mip_bias: self.mip_bias.clamp(Self::MIP_BIAS_MIN, Self::MIP_BIAS_MAX),
+            max_anisotropy: self.max_anisotropy.clamp(1, Self::MAX_ANISOTROPY),
+        }
+    }
+
+    /// Anisotropic filtering is only valid when every filter is linear
+    fn apply(&self, desc: &wgpu::SamplerDescriptor<'static>) -> wgpu::SamplerDescriptor<'static> {
+        let is_linear = [desc.mag_filter, desc.min_filter, desc.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == wgpu::FilterMode::Linear);
+
+        wgpu::SamplerDescriptor {
+            anisotropy_clamp: if is_linear { self.max_anisotropy } else { 1 },
+            ..desc.clone()
+        }
+    }
+
+    fn buffer_content(&self) -> [f32; 4] {
+        [self.mip_bias, 0., 0., 0.]
+    }
+}
+
 impl Material {
     pub fn create_builtin(gfx: &GfxState) -> HashMap<ID, Material> {
         let mut materials = HashMap::new();
 
         // White
         let albedo_tex = gfx.create_builtin_tex(TexType::White);
-        let albedo_s = gfx.create_sampler();
+        let albedo_s = wgpu::SamplerDescriptor::default();
         let metallic_roughness_tex = gfx.create_builtin_tex(TexType::Black);
-        let metallic_roughness_s = gfx.create_sampler();
+        let metallic_roughness_s = wgpu::SamplerDescriptor::default();
 
         let normal_tex = gfx.create_builtin_tex(TexType::Normal);
-        let normal_s = gfx.create_sampler();
+        let normal_s = wgpu::SamplerDescriptor::default();
         let emissive_tex = gfx.create_builtin_tex(TexType::Black);
-        let emissive_s = gfx.create_sampler();
+        let emissive_s = wgpu::SamplerDescriptor::default();
         let ao_tex = gfx.create_builtin_tex(TexType::White);
-        let ao_s = gfx.create_sampler();
+        let ao_s = wgpu::SamplerDescriptor::default();
 
         materials.insert(
             CIRCLE_MAT_ID.to_string(),
@@ -63,35 +115,43 @@ impl Material {
         materials
     }
 
-    pub fn new(ctx: MaterialCtx, gfx: &GfxState) -> Self {
-        let device = &gfx.device;
-
-        let mut entries = vec![];
+    pub fn sampler_settings(&self) -> SamplerSettings {
+        self.sampler_settings
+    }
 
-        for i in 0..5 {
-            entries.push(wgpu::BindGroupLayoutEntry {
-                binding: i * 2,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    multisampled: false,
-                    view_dimension: wgpu::TextureViewDimension::D2,
-                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                },
-                count: None,
-            });
+    /// Rebuilds the samplers and bind group, the layout stays so pipelines remain valid
+    pub fn set_sampler_settings(&mut self, settings: SamplerSettings, gfx: &GfxState) {
+        let settings = settings.validated();
 
-            entries.push(wgpu::BindGroupLayoutEntry {
-                binding: i * 2 + 1,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                count: None,
-            });
+        if settings == self.sampler_settings {
+            return;
         }
 
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &entries,
-            label: Some("Material layout"),
-        });
+        gfx.queue.write_buffer(
+            &self.sampling_buffer,
+            0,
+            bytemuck::cast_slice(&settings.buffer_content()),
+        );
+
+        self.sampler_settings = settings;
+        self.bg = Self::create_bg(
+            &self.ctx,
+            &settings,
+            &self.bg_layout,
+            &self.sampling_buffer,
+            gfx,
+        );
+    }
+
+    fn create_bg(
+        ctx: &MaterialCtx,
+        settings: &SamplerSettings,
+        layout: &wgpu::BindGroupLayout,
+        sampling_buffer: &wgpu::Buffer,
+        gfx: &GfxState,
+    ) -> wgpu::BindGroup {
+        let device = &gfx.device;
+        let create_sampler = |desc| device.create_sampler(&settings.apply(desc));
 
         let albedo_view = ctx.albedo_tex.default_view();
         let normal_view = ctx.normal_tex.default_view();
@@ -99,8 +159,14 @@ impl Material {
         let emissive_view = ctx.emissive_tex.default_view();
         let ao_view = ctx.ao_tex.default_view();
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
+        let albedo_s = create_sampler(&ctx.albedo_s);
+        let normal_s = create_sampler(&ctx.normal_s);
+        let metallic_roughness_s = create_sampler(&ctx.metallic_roughness_s);
+        let emissive_s = create_sampler(&ctx.emissive_s);
+        let ao_s = create_sampler(&ctx.ao_s);
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -108,7 +174,7 @@ impl Material {
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&ctx.albedo_s),
+                    resource: wgpu::BindingResource::Sampler(&albedo_s),
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
@@ -116,7 +182,7 @@ impl Material {
                 },
                 wgpu::BindGroupEntry {
                     binding: 3,
-                    resource: wgpu::BindingResource::Sampler(&ctx.normal_s),
+                    resource: wgpu::BindingResource::Sampler(&normal_s),
                 },
                 wgpu::BindGroupEntry {
                     binding: 4,
@@ -124,7 +190,7 @@ impl Material {
                 },
                 wgpu::BindGroupEntry {
                     binding: 5,
-                    resource: wgpu::BindingResource::Sampler(&ctx.metallic_roughness_s),
+                    resource: wgpu::BindingResource::Sampler(&metallic_roughness_s),
                 },
                 wgpu::BindGroupEntry {
                     binding: 6,
@@ -132,7 +198,7 @@ impl Material {
                 },
                 wgpu::BindGroupEntry {
                     binding: 7,
-                    resource: wgpu::BindingResource::Sampler(&ctx.emissive_s),
+                    resource: wgpu::BindingResource::Sampler(&emissive_s),
                 },
                 wgpu::BindGroupEntry {
                     binding: 8,
@@ -140,16 +206,81 @@ impl Material {
                 },
                 wgpu::BindGroupEntry {
                     binding: 9,
-                    resource: wgpu::BindingResource::Sampler(&ctx.ao_s),
+                    resource: wgpu::BindingResource::Sampler(&ao_s),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 10,
+                    resource: sampling_buffer.as_entire_binding(),
                 },
             ],
             label: None,
+        })
+    }
+
+    pub fn new(ctx: MaterialCtx, gfx: &GfxState) -> Self {
+        let device = &gfx.device;
+
+        let mut entries = vec![];
+
+        for i in 0..5 {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: i * 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: i * 2 + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+        }
+
+        // Sampling settings
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 10,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("Material layout"),
         });
 
+        let sampler_settings = SamplerSettings::default();
+
+        let sampling_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material sampling buffer"),
+            contents: bytemuck::cast_slice(&sampler_settings.buffer_content()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = Self::create_bg(
+            &ctx,
+            &sampler_settings,
+            &bind_group_layout,
+            &sampling_buffer,
+            gfx,
+        );
+
         Self {
             ctx,
             bg: bind_group,
             bg_layout: bind_group_layout,
+            sampler_settings,
+            sampling_buffer,
         }
     }
 }
@@ -1,22 +1,43 @@
+pub mod blocking;
+pub mod budget;
 pub mod camera;
 pub mod clock;
 pub mod color;
+pub mod depth_sort;
+pub mod draw_batch;
 pub mod emitter;
 pub mod emitter_state;
 pub mod events;
+pub mod frustum_cull;
 pub mod gfx_state;
+pub mod gradient;
 pub mod life_cycle;
+pub mod live_counter;
 pub mod material;
 pub mod mesh;
+pub mod render_stage;
+pub mod sky;
 pub mod state;
 
-pub use camera::{Camera, TonemapType};
-pub use clock::Clock;
-pub use emitter::{Boundry, EmitterSettings, EmitterUniform, MaterialRef, MeshRef};
-pub use emitter_state::{CreateEmitterOptions, EmitterState, EmitterType};
-pub use events::SparEvents;
+pub use budget::{BudgetError, BudgetPolicy, ParticleBudget};
+pub use camera::{Camera, CameraBuilder, CameraMode, DebugView, TonemapType};
+pub use clock::{Clock, ClockMode};
+pub use depth_sort::DepthSort;
+pub use draw_batch::DrawBatcher;
+pub use emitter::{
+    BillboardMode, BlendMode, Boundry, EmitterLod, EmitterSettings, EmitterUniform, MaterialRef,
+    MeshRef, ShadingModel, SimulationSpace, SpawnDistribution, SpawnMode, SpawnShape,
+    PARTICLE_STRIDE,
+};
+pub use emitter_state::{CreateEmitterOptions, DrawStats, EmitterState, EmitterType};
+pub use events::{EmitBurst, EmitterEvent, SparEvents};
+pub use frustum_cull::FrustumCull;
 pub use gfx_state::GfxState;
-pub use life_cycle::LifeCycle;
-pub use material::Material;
+pub use gradient::{ColorGradient, GradientInterpolation, GradientStop};
+pub use life_cycle::{LifeCycle, LifeCycleMode};
+pub use live_counter::LiveCounter;
+pub use material::{Material, SamplerSettings};
 pub use mesh::{Mesh, ModelVertex};
+pub use render_stage::RenderStage;
+pub use sky::{Sky, SkyUniform};
 pub use state::SparState;
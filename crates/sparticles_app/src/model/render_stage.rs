@@ -0,0 +1,66 @@
+use std::fmt::Display;
+
+/// One step of a frame, `SparState::render_stages` runs them in order before the UI and the final composite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStage {
+    /// Simulates all emitters, skipped while paused
+    ComputeParticles,
+    /// Renders every layer
+    RenderParticles,
+    /// Renders a single layer, rendering a layer again in the same frame draws on top instead of clearing
+    RenderLayer(u8),
+    /// Runs the post fx chain
+    PostFx,
+}
+
+impl RenderStage {
+    pub fn default_order() -> Vec<Self> {
+        vec![Self::ComputeParticles, Self::RenderParticles, Self::PostFx]
+    }
+
+    /// Layers below `layer_count` that at least one of the stages renders
+    pub fn rendered_layers(stages: &[Self], layer_count: usize) -> Vec<u8> {
+        (0..layer_count as u8)
+            .filter(|layer| {
+                stages.iter().any(|stage| match stage {
+                    Self::RenderParticles => true,
+                    Self::RenderLayer(other) => other == layer,
+                    _ => false,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Display for RenderStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ComputeParticles => write!(f, "Compute particles"),
+            Self::RenderParticles => write!(f, "Render particles"),
+            Self::RenderLayer(layer) => write!(f, "Render layer {}", layer),
+            Self::PostFx => write!(f, "Post fx"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_without_stage_are_not_rendered() {
+        let stages = [
+            RenderStage::ComputeParticles,
+            RenderStage::RenderLayer(0),
+            RenderStage::PostFx,
+            RenderStage::RenderLayer(2),
+            RenderStage::RenderLayer(5),
+        ];
+
+        assert_eq!(RenderStage::rendered_layers(&stages, 3), vec![0, 2]);
+        assert_eq!(
+            RenderStage::rendered_layers(&RenderStage::default_order(), 3),
+            vec![0, 1, 2]
+        );
+    }
+}
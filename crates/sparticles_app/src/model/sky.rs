@@ -0,0 +1,159 @@
+use super::{GfxState, SparState};
+use crate::shaders::ShaderOptions;
+use crate::traits::BufferContent;
+use crate::util::UniformContext;
+use egui_wgpu::wgpu;
+use encase::ShaderType;
+use glam::{Mat4, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+/// Gradient from the horizon to the top of the sky with an optional sun disc
+#[derive(ShaderType, Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SkyUniform {
+    pub top_color: Vec4,
+    pub horizon_color: Vec4,
+    pub sun_color: Vec4,
+    /// Direction towards the sun
+    pub sun_direction: Vec3,
+    /// Angular radius of the sun disc in radians, 0 disables the sun
+    pub sun_size: f32,
+    #[serde(skip)]
+    inv_view_proj: Mat4,
+    #[serde(skip)]
+    camera_position: Vec3,
+}
+
+impl Default for SkyUniform {
+    fn default() -> Self {
+        Self {
+            top_color: Vec4::new(0.1, 0.25, 0.6, 1.),
+            horizon_color: Vec4::new(0.7, 0.8, 0.9, 1.),
+            sun_color: Vec4::new(1., 0.95, 0.8, 1.),
+            sun_direction: Vec3::new(0.3, 0.5, -1.),
+            sun_size: 2f32.to_radians(),
+            inv_view_proj: Mat4::IDENTITY,
+            camera_position: Vec3::ZERO,
+        }
+    }
+}
+
+/// Lightweight full screen backdrop, rendered before the particles of layer 0
+pub struct Sky {
+    pub uniform: SkyUniform,
+    pub enabled: bool,
+    ctx: UniformContext,
+    pipeline: wgpu::RenderPipeline,
+    /// Without the bloom split target
+    single_target_pipeline: wgpu::RenderPipeline,
+}
+
+impl Sky {
+    pub fn new(gfx: &GfxState) -> Self {
+        let device = &gfx.device;
+        let uniform = SkyUniform::default();
+        let ctx = UniformContext::from_uniform(&uniform, device, "Sky");
+
+        let shader = gfx.create_shader_builtin(ShaderOptions {
+            files: &["gradient_sky.wgsl"],
+            if_directives: &[],
+            label: "Gradient sky",
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sky layout"),
+            bind_group_layouts: &[&ctx.bg_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = Self::create_pipeline(gfx, &shader, &pipeline_layout, true);
+        let single_target_pipeline = Self::create_pipeline(gfx, &shader, &pipeline_layout, false);
+
+        Self {
+            uniform,
+            enabled: false,
+            ctx,
+            pipeline,
+            single_target_pipeline,
+        }
+    }
+
+    fn create_pipeline(
+        gfx: &GfxState,
+        shader: &wgpu::ShaderModule,
+        layout: &wgpu::PipelineLayout,
+        split_target: bool,
+    ) -> wgpu::RenderPipeline {
+        let device = &gfx.device;
+
+        let mut targets = vec![Some(wgpu::ColorTargetState {
+            format: gfx.fx_format(),
+            blend: Some(wgpu::BlendState::REPLACE),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        if split_target {
+            targets.push(Some(wgpu::ColorTargetState {
+                format: gfx.fx_format(),
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::COLOR,
+            }));
+        }
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sky pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &targets,
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            // Drawn first at the far plane, so it never occludes particles
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: GfxState::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    pub async fn update(state: &mut SparState) {
+        let SparState {
+            sky, camera, gfx, ..
+        } = state;
+
+        if !sky.enabled {
+            return;
+        }
+
+        sky.uniform.inv_view_proj = camera.view_proj(&camera.view_mat()).inverse();
+        sky.uniform.camera_position = camera.position;
+
+        let gfx = &gfx.read().await;
+        gfx.queue
+            .write_buffer(&sky.ctx.buf, 0, &sky.uniform.buffer_content());
+    }
+
+    pub fn render<'a>(&'a self, r_pass: &mut wgpu::RenderPass<'a>, split_target: bool) {
+        if split_target {
+            r_pass.set_pipeline(&self.pipeline);
+        } else {
+            r_pass.set_pipeline(&self.single_target_pipeline);
+        }
+
+        r_pass.set_bind_group(0, &self.ctx.bg, &[]);
+        r_pass.draw(0..3, 0..1);
+    }
+}
@@ -1,29 +1,42 @@
 use super::{
-    Camera, Clock, EmitterState, GfxState, Material, MaterialRef, Mesh, MeshRef, SparEvents,
+    BudgetError, Camera, Clock, CreateEmitterOptions, DrawBatcher, DrawStats, EmitterEvent,
+    EmitterState, EmitterType, EmitterUniform, GfxState, Material, MaterialRef, Mesh, MeshRef,
+    ParticleBudget, RenderStage, Sky, SparEvents,
 };
 use crate::fx::PostProcessState;
-use crate::init::{AppVisitor, Init};
+use crate::init::{AppVisitor, DataSource, Init};
 use crate::loader::Model;
 use crate::traits::*;
-use crate::util::ID;
+use crate::util::{Persistence, ID};
 use async_std::sync::RwLock;
 use async_std::task;
-use egui_winit::winit::{dpi::PhysicalSize, event::KeyboardInput, window::Window};
+use egui_wgpu::wgpu;
+use egui_winit::winit::{
+    dpi::PhysicalSize,
+    event::{KeyboardInput, WindowEvent},
+    window::Window,
+};
 use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Sparticles state
 pub struct SparState {
     pub camera: Camera,
+    pub sky: Sky,
     pub clock: Clock,
     pub emitters: Vec<EmitterState>,
     pub post_process: PostProcessState,
     pub gfx: Arc<RwLock<GfxState>>,
     pub collection: Arc<RwLock<HashMap<ID, Model>>>,
-    pub play: bool,
     pub registry_par_anims: Vec<Box<dyn RegisterParticleAnimation>>,
     pub registry_em_anims: Vec<Box<dyn RegisterEmitterAnimation>>,
     pub registered_post_fx: Vec<Box<dyn RegisterPostFx>>,
+    pub emitter_events: Vec<EmitterEvent>,
+    pub budget: ParticleBudget,
+    /// Pass sequence of a frame, see RenderStage
+    pub render_stages: Vec<RenderStage>,
+    pub draw_stats: DrawStats,
+    pub draw_batcher: DrawBatcher,
 }
 
 pub trait FastFetch {
@@ -51,17 +64,99 @@ impl FastFetch for HashMap<ID, Model> {
 
 impl SparState {
     pub async fn update(&mut self, events: &SparEvents) {
-        self.clock.update(self.play);
-
         if events.toggle_play {
-            self.play = !self.play;
+            if self.clock.is_paused() {
+                self.clock.resume();
+            } else {
+                self.clock.pause();
+            }
+        }
+
+        if events.step_frame {
+            self.clock.step();
+        }
+
+        if let Some(target_sec) = events.seek_sec {
+            EmitterState::seek(self, target_sec).await;
         }
 
+        self.clock.update();
+        self.emitter_events.clear();
+
         Camera::update(self, events).await;
+        Sky::update(self).await;
         PostProcessState::update(self, events).await;
         EmitterState::update(self, events).await;
     }
 
+    /// Advances the simulation by one frame of `dt` seconds, without rendering or presenting
+    pub async fn step(&mut self, dt: f32) {
+        let events = SparEvents::default();
+
+        self.clock.advance(dt);
+        self.emitter_events.clear();
+
+        Camera::update(self, &events).await;
+        EmitterState::update(self, &events).await;
+
+        let mut encoder = {
+            let gfx = self.gfx.read().await;
+
+            gfx.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Step encoder"),
+                })
+        };
+
+        EmitterState::compute_particles(self, &mut encoder).await;
+
+        let gfx = self.gfx.read().await;
+        gfx.queue.submit(Some(encoder.finish()));
+    }
+
+    pub fn emitter(&self, id: &str) -> Option<&EmitterState> {
+        self.emitters.iter().find(|em| em.id() == id)
+    }
+
+    pub fn emitter_mut(&mut self, id: &str) -> Option<&mut EmitterState> {
+        self.emitters.iter_mut().find(|em| em.id() == id)
+    }
+
+    /// Ids in render and update order, the lights emitter is always first
+    pub fn emitter_ids(&self) -> Vec<&str> {
+        self.emitters.iter().map(|em| em.id()).collect()
+    }
+
+    /// Creates an emitter from code, the particle budget policy applies like for the gui
+    pub async fn add_emitter(&mut self, mut uniform: EmitterUniform) -> Result<(), BudgetError> {
+        self.budget.fit(&mut uniform, self.particle_usage())?;
+
+        let emitter = EmitterState::new(CreateEmitterOptions {
+            uniform,
+            gfx: &self.gfx,
+            camera: &self.camera,
+            collection: &self.collection,
+            emitter_type: EmitterType::Normal {
+                lights_layout: &self.emitters[0].bg_layout,
+            },
+        })
+        .await;
+
+        self.emitters.push(emitter);
+
+        Ok(())
+    }
+
+    /// Particles allocated by all emitters
+    pub fn particle_usage(&self) -> u64 {
+        self.emitters.iter().map(|em| em.particle_count()).sum()
+    }
+
+    /// Takes the emitter events of the last update, undrained events are cleared on the next update
+    pub fn drain_emitter_events(&mut self) -> Vec<EmitterEvent> {
+        std::mem::take(&mut self.emitter_events)
+    }
+
     pub async fn resize(&mut self, size: PhysicalSize<u32>) {
         let mut gfx = self.gfx.write().await;
         gfx.resize(size);
@@ -73,23 +168,75 @@ impl SparState {
         self.camera.process_input(input);
     }
 
+    pub fn process_mouse_event(&mut self, event: &WindowEvent) {
+        self.camera.process_mouse_event(event);
+    }
+
+    /// Raw mouse motion, used for mouse look
+    pub fn process_mouse_motion(&mut self, delta_x: f32, delta_y: f32) {
+        self.camera.process_mouse(delta_x, delta_y);
+    }
+
     pub fn egui_ctx(&self) -> egui_winit::egui::Context {
         let gfx = task::block_on(self.gfx.read());
         gfx.ctx.clone()
     }
 
     pub async fn new(init: &mut impl AppVisitor, window: Window) -> Self {
-        let gfx = GfxState::new(window).await;
-        let clock = Clock::default();
+        let gfx = GfxState::new(
+            window,
+            init.prefer_srgb_surface(),
+            init.premultiplied_alpha(),
+            init.fx_workgroup_size(),
+            init.fx_texture_format(),
+            init.gui_enabled(),
+        )
+        .await;
+
+        Self::from_gfx(init, gfx).await
+    }
+
+    /// Renders offscreen without a window, the gui is disabled
+    pub async fn new_headless(init: &mut impl AppVisitor, width: u32, height: u32) -> Self {
+        let gfx = GfxState::new_headless(
+            width,
+            height,
+            init.prefer_srgb_surface(),
+            init.premultiplied_alpha(),
+            init.fx_workgroup_size(),
+            init.fx_texture_format(),
+        )
+        .await;
 
-        let camera = Camera::new(&gfx);
+        Self::from_gfx(init, gfx).await
+    }
+
+    async fn from_gfx(init: &mut impl AppVisitor, gfx: GfxState) -> Self {
+        let mut clock = Clock::default();
+        clock.set_max_fps(init.max_fps());
+        clock.set_mode(init.clock_mode());
+
+        // Saved scenes restore their camera, otherwise the app decides the framing
+        let camera = match init.data_source() {
+            DataSource::Json { .. } => match Persistence::import_camera() {
+                Ok(export) => Camera::from_export(&gfx, export),
+                Err(err) => {
+                    println!("{}", err.msg);
+                    init.camera().build(&gfx)
+                }
+            },
+            _ => init.camera().build(&gfx),
+        };
+        let sky = Sky::new(&gfx);
         let builtin = Model::load_builtin(&gfx);
 
         let mut collection = HashMap::new();
         let mut post_process = PostProcessState::new(&gfx, init);
+        let draw_batcher = DrawBatcher::new(&gfx);
 
         collection.insert(builtin.id.to_string(), builtin);
 
+        let gui_enabled = gfx.gui_enabled();
         let gfx = Arc::new(RwLock::new(gfx));
         let collection = Arc::new(RwLock::new(collection));
 
@@ -98,6 +245,7 @@ impl SparState {
         let mut state = Self {
             clock,
             camera,
+            sky,
             emitters: init_settings.emitters,
             post_process,
             gfx,
@@ -105,10 +253,16 @@ impl SparState {
             registry_em_anims: init_settings.registry_em_anims,
             registered_post_fx: init_settings.registry_post_fx,
             collection,
-            play: true,
+            emitter_events: Vec::new(),
+            budget: init.particle_budget(),
+            render_stages: init.render_stages(),
+            draw_stats: DrawStats::default(),
+            draw_batcher,
         };
 
-        init.add_widget_builders(&mut state);
+        if gui_enabled {
+            init.add_widget_builders(&mut state);
+        }
 
         state
     }
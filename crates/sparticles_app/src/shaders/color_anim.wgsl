@@ -23,11 +23,11 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
 
     var particle = particles[index];
 
-    if particle.lifetime < anim.from_sec || anim.until_sec <= particle.lifetime {
+    if is_decayed(emitter, particle) || particle.age < anim.from_sec || anim.until_sec <= particle.age {
         return;
     }
 
-    let delta_sec = particle.lifetime - anim.from_sec;
+    let delta_sec = particle.age - anim.from_sec;
     let delta_end = anim.until_sec - anim.from_sec;
     let fraction = delta_sec / delta_end;
 
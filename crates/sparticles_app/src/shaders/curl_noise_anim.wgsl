@@ -0,0 +1,66 @@
+// Includes declarations and noise
+struct CurlNoise {
+    scroll_x: f32,
+    scroll_y: f32,
+    scroll_z: f32,
+    frequency: f32,
+    amplitude: f32,
+    padding_1: f32,
+    padding_2: f32,
+    padding_3: f32,
+}
+
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> em: Emitter; 
+@group(1) @binding(0) var<uniform> curl_noise: CurlNoise; 
+
+// Vector potential made of three uncorrelated noise fields
+fn potential(p: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        perlin_noise(p),
+        perlin_noise(p + vec3<f32>(31.416, -47.853, 12.793)),
+        perlin_noise(p + vec3<f32>(-233.145, -113.408, -185.31)),
+    );
+}
+
+// Divergence free, so particles swirl without bunching up
+fn curl(p: vec3<f32>) -> vec3<f32> {
+    let e = 0.01;
+    let dx = vec3<f32>(e, 0., 0.);
+    let dy = vec3<f32>(0., e, 0.);
+    let dz = vec3<f32>(0., 0., e);
+
+    let d_dx = potential(p + dx) - potential(p - dx);
+    let d_dy = potential(p + dy) - potential(p - dy);
+    let d_dz = potential(p + dz) - potential(p - dz);
+
+    return vec3<f32>(
+        d_dy.z - d_dz.y,
+        d_dz.x - d_dx.z,
+        d_dx.y - d_dy.x,
+    ) / (2. * e);
+}
+
+@compute
+@workgroup_size(128)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let particle_len = arrayLength(&particles);
+    let index = global_invocation_id.x;
+
+    if particle_len <= index {
+        return;
+    }
+
+    var particle = particles[index];
+
+    if is_decayed(em, particle) {
+        return;
+    }
+
+    let scroll = vec3<f32>(curl_noise.scroll_x, curl_noise.scroll_y, curl_noise.scroll_z);
+    let p = particle.model.w.xyz * curl_noise.frequency + scroll * em.elapsed_sec;
+    let vel = curl(p) * curl_noise.amplitude;
+
+    particle.vel_mass += vec4<f32>(vel * em.delta_sec, 0.);
+    particles[index] = particle;
+}
@@ -1,11 +1,3 @@
-struct Particle {
-    model: mat4x4<f32>,
-    color: vec4<f32>,
-    vel_mass: vec4<f32>, 
-    scale: f32,
-    lifetime: f32, // lifetime == -1. is decayed
-};
-
 struct Emitter {
     delta_sec: f32,
     elapsed_sec: f32,
@@ -22,7 +14,7 @@ struct Emitter {
     box_roll: f32,
     diffusion_width: f32,
     diffusion_depth: f32,
-    padding: f32,
+    spawn_distribution: f32,
     particle_model: mat4x4<f32>,
     particle_color_r: f32,
     particle_color_g: f32,
@@ -35,15 +27,50 @@ struct Emitter {
     particle_friction_coefficient: f32,
     material_mass: f32,
     particle_lifetime: f32,
+    spawn_spread: f32,
+    simulation_space: f32,
+    particle_mass_min: f32,
+    particle_mass_max: f32,
+    active_particle_count: f32,
+    box_transform: mat4x4<f32>,
+    tint: vec4<f32>,
+    burst_from: f32,
+    burst_count: f32,
+    spawn_shape: f32,
+    spawn_count: f32, // particles per batch after LOD
+    shape_axis: vec4<f32>, // ring axis
+    shape_params: vec4<f32>, // see SpawnShape::shape_content
+    light_intensity: vec4<f32>, // samples at 0, 1/3, 2/3 and 1 of the particle life
+    flipbook: vec4<f32>, // columns, rows, frames per second (0 plays once per life), unused
+    emit_count: f32, // particles spawned at the start of every batch, at most the spawn count
+    billboard_mode: f32, // 0 round, 1 stretched along the velocity
+    stretch_factor: f32, // extra length per unit of speed
+    frustum_culling: f32, // 1 draws the visible_indices written by frustum_cull.wgsl
+    depth_sorted: f32, // 1 draws the visible_indices sorted by depth_sort.wgsl
+    circle_aa: f32,
+    padding_1: f32,
     padding_2: f32,
+    box_delta: mat4x4<f32>, // previous to current box rotation and position, for local space
 };
 
+// Draw args of an indexed indirect draw followed by the alive count, see live_counter.rs
+struct LiveCounter {
+    index_count: u32,
+    high_water: atomic<u32>, // highest live slot + 1, the instance count of the draw
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+    alive: atomic<u32>,
+}
+
 struct CameraUniform {
     view_proj: mat4x4<f32>,
     view: mat4x4<f32>,
     position: vec3<f32>,
     bloom_treshold: vec3<f32>,
     tonemap: u32,
+    debug_view: u32,
+    debug_max_speed: f32,
 };
 
 struct GaussianBlur {
@@ -66,13 +93,22 @@ struct FxIO {
 
 const PI: f32 = 3.141592653589;
 
+// Per particle, so changing the emitter lifetime doesn't decay the spawned particles early.
+// Zeroed particles were never spawned and have no lifetime yet
 fn is_decayed(em: Emitter, par: Particle) -> bool {
-    return em.particle_lifetime < par.lifetime;
+    return par.max_life == 0. || par.max_life < par.age;
+}
+
+// Linear through the evenly spaced intensity samples of a light emitter
+fn light_intensity(em: Emitter, par: Particle) -> f32 {
+    let scaled = normalized_life(par) * 3.;
+    let lower = min(u32(scaled), 2u);
+
+    return mix(em.light_intensity[lower], em.light_intensity[lower + 1u], scaled - f32(lower));
 }
 
 fn random(input: f32, elapsed_sec: f32) -> f32 {
-    let value = vec2<f32>(input, elapsed_sec);
-    return fract(sin(dot(value, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+    return hash_to_unit(hash_seeded(input, elapsed_sec));
 }
 
 fn gen_abs_range(unique: f32, value: f32, elapsed_sec: f32) -> f32 {
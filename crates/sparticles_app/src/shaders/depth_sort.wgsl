@@ -0,0 +1,101 @@
+// Includes declarations
+
+struct SortEntry {
+    key: f32,
+    index: u32,
+}
+
+struct SortStep {
+    j: u32, // distance of the compared entries
+    k: u32, // size of the bitonic sequences
+    padding_1: u32,
+    padding_2: u32,
+}
+
+@group(0) @binding(0) var<storage, read> particles: array<Particle>;
+@group(0) @binding(1) var<uniform> em: Emitter;
+@group(0) @binding(2) var<storage, read_write> visible_indices: array<u32>;
+@group(0) @binding(3) var<storage, read> cull_args: array<u32>; // DrawIndexedIndirect of the frustum cull
+@group(0) @binding(4) var<storage, read_write> entries: array<SortEntry>;
+@group(1) @binding(0) var<uniform> camera: CameraUniform;
+@group(2) @binding(0) var<uniform> step: SortStep;
+
+// Dead particles and the padding sort behind everything
+const FAR_KEY: f32 = 1e38;
+
+// View space z, the camera looks along -z so ascending keys go from far to near
+@compute
+@workgroup_size(128)
+fn keys(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let i = global_invocation_id.x;
+
+    if arrayLength(&entries) <= i {
+        return;
+    }
+
+    var entry = SortEntry(FAR_KEY, i);
+    var valid = false;
+
+    if em.frustum_culling == 1. {
+        valid = i < cull_args[1];
+
+        if valid {
+            entry.index = visible_indices[i];
+        }
+    } else {
+        valid = i < min(arrayLength(&particles), u32(em.active_particle_count));
+    }
+
+    if valid {
+        let p = particles[entry.index];
+
+        if !is_decayed(em, p) && 0. < p.scale {
+            // Same world position as vs_main in particle.wgsl
+            let center = (p.model * vec4(0., 0., 0., 1.)).xyz * p.scale;
+            entry.key = (camera.view * vec4(center, 1.)).z;
+        }
+    }
+
+    entries[i] = entry;
+}
+
+// Equal keys keep the slot order, so the padding stays behind the real slots
+fn is_before(a: SortEntry, b: SortEntry) -> bool {
+    if a.key == b.key {
+        return a.index < b.index;
+    }
+
+    return a.key < b.key;
+}
+
+@compute
+@workgroup_size(128)
+fn sort_step(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let i = global_invocation_id.x;
+    let l = i ^ step.j;
+
+    if l <= i || arrayLength(&entries) <= l {
+        return;
+    }
+
+    let a = entries[i];
+    let b = entries[l];
+    let ascending = (i & step.k) == 0u;
+
+    if is_before(b, a) == ascending {
+        entries[i] = b;
+        entries[l] = a;
+    }
+}
+
+@compute
+@workgroup_size(128)
+fn write_indices(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let i = global_invocation_id.x;
+
+    if arrayLength(&visible_indices) <= i {
+        return;
+    }
+
+    visible_indices[i] = entries[i].index;
+}
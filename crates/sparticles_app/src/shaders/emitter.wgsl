@@ -1,6 +1,21 @@
 @group(0) @binding(0) var<storage, read> particles_src : array<Particle>;
 @group(0) @binding(1) var<storage, read_write> particles_dst : array<Particle>;
 @group(0) @binding(2) var<uniform> em: Emitter; 
+@group(0) @binding(3) var color_mask: texture_2d<f32>;
+@group(0) @binding(4) var color_mask_sampler: sampler;
+// Curve samples, mesh vertices or mesh triangles. Mesh shapes end with the min and max of the mesh
+@group(0) @binding(5) var<storage, read> spawn_curve: array<vec4<f32>>;
+@group(1) @binding(0) var<storage, read_write> live: LiveCounter;
+
+// Particles alive in the source keep their slot in the dispatch one more frame, so their death
+// is written to both ping-pong buffers
+fn count_live(index: u32, alive: bool) {
+    atomicMax(&live.high_water, index + 1u);
+
+    if alive {
+        atomicAdd(&live.alive, 1u);
+    }
+}
 
 fn create_velocity(input_random: f32, speed_random: f32) -> vec3<f32> {
     let diff_width = gen_dyn_range(input_random * 0.12, em.diffusion_width, em.elapsed_sec) / 2.;
@@ -9,36 +24,287 @@ fn create_velocity(input_random: f32, speed_random: f32) -> vec3<f32> {
     return vec3<f32>(0., speed_random, 0.) * yaw_matrix(em.box_yaw) * pitch_matrix(em.box_pitch + diff_width) * roll_matrix(em.box_roll + diff_depth);
 }
 
-fn create_particle_position(input_random: f32) -> vec3<f32> {
-    let half_width = em.box_width / 2.0;
-    let half_height = em.box_height / 2.0;
-    let half_depth = em.box_depth / 2.0;
+// Attempts of the Gaussian distribution, every attempt draws two samples
+const GAUSS_ATTEMPTS: u32 = 8u;
+
+// Normal distribution cut off at the box (-1..1). Samples outside are drawn again instead of
+// clamped, so particles don't pile up on the faces of the box
+fn truncated_gauss(input_random: f32, seed: f32) -> f32 {
+    for (var i = 0u; i < GAUSS_ATTEMPTS; i++) {
+        let offset = seed + f32(i) * 1.37;
+
+        // Box-Muller, the sine and cosine give two independent samples
+        let r1 = random(input_random + offset, em.elapsed_sec);
+        let r2 = random(input_random - offset, em.elapsed_sec);
+        let radius = sqrt(-2. * log(max(r1, 0.0001))) * em.spawn_spread;
+        let angle = 2. * PI * r2;
+
+        let gauss_1 = radius * cos(angle);
+
+        if abs(gauss_1) <= 1. {
+            return gauss_1;
+        }
+
+        let gauss_2 = radius * sin(angle);
+
+        if abs(gauss_2) <= 1. {
+            return gauss_2;
+        }
+    }
+
+    // Only likely for wide spreads, which are close to uniform inside the box
+    return random(input_random + seed * 3., em.elapsed_sec) * 2. - 1.;
+}
+
+// Maps a random value (0..1) to an offset (-1..1) from the box center
+fn distribute(input_random: f32, seed: f32) -> f32 {
+    let r = random(input_random, em.elapsed_sec);
+    let distribution = u32(em.spawn_distribution);
 
-    let random_width = random(input_random * 1.6, em.elapsed_sec);
-    let random_height = random(input_random * 0.42, em.elapsed_sec);
-    let random_depth = random(input_random / 0.11, em.elapsed_sec);
+    if distribution == 1u {
+        return truncated_gauss(input_random, seed);
+    } else if distribution == 2u {
+        let centered = r * 2. - 1.;
+        return sign(centered) * pow(abs(centered), 1. / (1. + em.spawn_spread * 4.));
+    }
 
-    let unrotated_x = random_width * em.box_width - half_width;
-    let unrotated_y = random_height * em.box_height - half_height;
-    let unrotated_z = random_depth * em.box_depth - half_depth;
+    return r * 2. - 1.;
+}
 
-    let local_pos = vec3<f32>(unrotated_x, unrotated_y, unrotated_z);
+// Position inside the unit cube (-0.5..0.5)
+fn create_unit_position(input_random: f32) -> vec3<f32> {
+    let unit_x = distribute(input_random * 1.6, 0.31) * 0.5;
+    let unit_y = distribute(input_random * 0.42, 0.57) * 0.5;
+    let unit_z = distribute(input_random / 0.11, 0.89) * 0.5;
 
-    let local_rot = local_pos * yaw_matrix(em.box_yaw) * pitch_matrix(em.box_pitch) * roll_matrix(em.box_roll);
+    return vec3<f32>(unit_x, unit_y, unit_z);
+}
 
-    return vec3<f32>(em.box_x, em.box_y, em.box_z) + local_rot;
+fn create_particle_position(unit: vec3<f32>) -> vec3<f32> {
+    // Scales, rotates and translates the unit cube onto the spawn box
+    return (em.box_transform * vec4<f32>(unit, 1.0)).xyz;
+}
+
+// Samples are evenly spaced by arc length, so a uniform t spreads particles evenly along the curve
+fn create_curve_position(t: f32) -> vec3<f32> {
+    let last = arrayLength(&spawn_curve) - 1u;
+    let scaled = t * f32(last);
+    let lower = min(u32(scaled), max(last, 1u) - 1u);
+    let upper = min(lower + 1u, last);
+    let offset = mix(spawn_curve[lower].xyz, spawn_curve[upper].xyz, scaled - f32(lower));
+
+    // Same rotation as the box, without its scale
+    let rotated = offset * yaw_matrix(em.box_yaw) * pitch_matrix(em.box_pitch) * roll_matrix(em.box_roll);
+
+    return rotated + vec3<f32>(em.box_x, em.box_y, em.box_z);
+}
+
+struct ShapeSample {
+    // Offset from the box position
+    offset: vec3<f32>,
+    // Unit direction from the shape center to the sample, or the emit direction of a cone
+    direction: vec3<f32>,
+    // Position on the shape projected in the unit square, used for the color mask
+    unit: vec3<f32>,
+}
+
+// Box direction without the diffusion, the axis of the disc and cone
+fn create_emit_axis() -> vec3<f32> {
+    return vec3<f32>(0., 1., 0.) * yaw_matrix(em.box_yaw) * pitch_matrix(em.box_pitch) * roll_matrix(em.box_roll);
+}
+
+// Any unit vector perpendicular to the axis
+fn create_tangent(axis: vec3<f32>) -> vec3<f32> {
+    let up = select(vec3<f32>(0., 1., 0.), vec3<f32>(1., 0., 0.), 0.99 < abs(axis.y));
+    return normalize(cross(up, axis));
+}
+
+// Ring around the axis through the box position, the torus variant fills a round tube
+fn create_ring_sample(input_random: f32) -> ShapeSample {
+    let axis = normalize(em.shape_axis.xyz);
+    let radius = em.shape_params.x;
+    let thickness = em.shape_params.y;
+
+    let tangent = create_tangent(axis);
+    let bitangent = cross(axis, tangent);
+
+    let angle = random(input_random * 0.37, em.elapsed_sec) * 2. * PI;
+    let outward = cos(angle) * tangent + sin(angle) * bitangent;
+    let offset_random = random(input_random * 0.59, em.elapsed_sec);
+
+    var radial = radius + (offset_random - 0.5) * thickness;
+    var height = 0.;
+
+    if em.shape_params.z == 1. {
+        let tube_angle = random(input_random * 0.83, em.elapsed_sec) * 2. * PI;
+        let tube_radius = sqrt(offset_random) * thickness * 0.5;
+        radial = radius + cos(tube_angle) * tube_radius;
+        height = sin(tube_angle) * tube_radius;
+    }
+
+    var sample: ShapeSample;
+    sample.offset = outward * radial + axis * height;
+    sample.direction = outward;
+
+    let unit_radial = radial / max(radius + thickness * 0.5, 0.0001) * 0.5;
+    sample.unit = vec3<f32>(cos(angle) * unit_radial, sin(angle) * unit_radial, 0.);
+
+    return sample;
+}
+
+// Uniform over the volume, the cube root keeps the center from getting denser
+fn create_sphere_sample(input_random: f32) -> ShapeSample {
+    let radius = em.shape_params.x;
+
+    let z = 1. - 2. * random(input_random * 0.29, em.elapsed_sec);
+    let phi = random(input_random * 0.67, em.elapsed_sec) * 2. * PI;
+    let ring = sqrt(max(1. - z * z, 0.));
+    let direction = vec3<f32>(ring * cos(phi), ring * sin(phi), z);
+    let unit_radial = pow(random(input_random * 0.91, em.elapsed_sec), 1. / 3.);
+
+    var sample: ShapeSample;
+    sample.offset = direction * unit_radial * radius;
+    sample.direction = direction;
+    sample.unit = direction * unit_radial * 0.5;
+
+    return sample;
+}
+
+// Uniform over the area of the disc facing the axis, the direction is the axis
+fn create_disc_sample(input_random: f32, axis: vec3<f32>) -> ShapeSample {
+    let radius = em.shape_params.x;
+    let tangent = create_tangent(axis);
+    let bitangent = cross(axis, tangent);
+
+    let angle = random(input_random * 0.37, em.elapsed_sec) * 2. * PI;
+    let unit_radial = sqrt(random(input_random * 0.59, em.elapsed_sec));
+    let outward = cos(angle) * tangent + sin(angle) * bitangent;
+
+    var sample: ShapeSample;
+    sample.offset = outward * unit_radial * radius;
+    sample.direction = axis;
+    sample.unit = vec3<f32>(cos(angle), sin(angle), 0.) * unit_radial * 0.5;
+
+    return sample;
+}
+
+// Disc base, emitting uniformly over the solid angle within shape_params.y radians of the axis.
+// A uniform cos(theta) gives equal density per solid angle, a uniform angle would bunch up at the axis
+fn create_cone_sample(input_random: f32) -> ShapeSample {
+    let axis = create_emit_axis();
+    var sample = create_disc_sample(input_random, axis);
+
+    let cos_max = cos(em.shape_params.y);
+    let cos_theta = 1. - random(input_random * 0.43, em.elapsed_sec) * (1. - cos_max);
+    let sin_theta = sqrt(max(1. - cos_theta * cos_theta, 0.));
+    let phi = random(input_random * 0.77, em.elapsed_sec) * 2. * PI;
+
+    let tangent = create_tangent(axis);
+    let bitangent = cross(axis, tangent);
+    let side = cos(phi) * tangent + sin(phi) * bitangent;
+
+    sample.direction = axis * cos_theta + side * sin_theta;
+
+    return sample;
+}
+
+// Triangles are stored as three points, the first w is the cumulative area fraction.
+// Searching that picks triangles by area, so small triangles don't get as many particles as large ones
+fn create_mesh_surface_position(input_random: f32) -> vec3<f32> {
+    let triangle_count = (arrayLength(&spawn_curve) - 2u) / 3u;
+    let r = random(input_random * 0.53, em.elapsed_sec);
+
+    var low = 0u;
+    var high = max(triangle_count, 1u) - 1u;
+
+    while low < high {
+        let mid = (low + high) / 2u;
+
+        if spawn_curve[mid * 3u].w < r {
+            low = mid + 1u;
+        } else {
+            high = mid;
+        }
+    }
+
+    let a = spawn_curve[low * 3u].xyz;
+    let b = spawn_curve[low * 3u + 1u].xyz;
+    let c = spawn_curve[low * 3u + 2u].xyz;
+
+    // Square root keeps the barycentric point uniform over the triangle
+    let s = sqrt(random(input_random * 0.61, em.elapsed_sec));
+    let t = random(input_random * 0.97, em.elapsed_sec);
+
+    return a * (1. - s) + b * (s * (1. - t)) + c * (s * t);
+}
+
+// Mesh points are in model space, the color mask needs them in the unit cube like box samples
+fn mesh_unit_position(point: vec3<f32>) -> vec3<f32> {
+    let last = arrayLength(&spawn_curve) - 1u;
+    let bounds_min = spawn_curve[last - 1u].xyz;
+    let size = max(spawn_curve[last].xyz - bounds_min, vec3<f32>(0.0001));
+
+    return (point - bounds_min) / size - 0.5;
+}
+
+// Projects the spawn position on the front face of the box, image top is box top
+fn sample_color_mask(unit: vec3<f32>) -> vec4<f32> {
+    let uv = vec2<f32>(unit.x + 0.5, 0.5 - unit.y);
+    return textureSampleLevel(color_mask, color_mask_sampler, uv, 0.);
 }
 
 fn spawn_particle(index: u32) {
     var particle = particles_src[index];
     let input_random = f32(index);
 
+    let spawn_shape = u32(em.spawn_shape);
+    var unit_position = create_unit_position(input_random);
+    var position: vec3<f32>;
+    var shape_direction = vec3<f32>(0.);
+
+    if spawn_shape == 1u {
+        let t = random(input_random * 0.73, em.elapsed_sec);
+        // Color mask runs along the curve through the middle of the image
+        unit_position = vec3<f32>(t - 0.5, 0., 0.);
+        position = create_curve_position(t);
+    } else if spawn_shape == 2u {
+        let ring = create_ring_sample(input_random);
+        unit_position = ring.unit;
+        position = ring.offset + vec3<f32>(em.box_x, em.box_y, em.box_z);
+        shape_direction = ring.direction;
+    } else if spawn_shape == 3u {
+        // Particle index picks the vertex, so the vertices emit in a fixed order
+        let vertex = spawn_curve[index % (arrayLength(&spawn_curve) - 2u)].xyz;
+        unit_position = mesh_unit_position(vertex);
+        position = create_particle_position(vertex);
+    } else if spawn_shape == 7u {
+        let surface = create_mesh_surface_position(input_random);
+        unit_position = mesh_unit_position(surface);
+        position = create_particle_position(surface);
+    } else if spawn_shape >= 4u && spawn_shape <= 6u {
+        var sample: ShapeSample;
+
+        if spawn_shape == 4u {
+            sample = create_sphere_sample(input_random);
+        } else if spawn_shape == 5u {
+            sample = create_cone_sample(input_random);
+        } else {
+            sample = create_disc_sample(input_random, create_emit_axis());
+        }
+
+        unit_position = sample.unit;
+        position = sample.offset + vec3<f32>(em.box_x, em.box_y, em.box_z);
+        shape_direction = sample.direction;
+    } else {
+        position = create_particle_position(unit_position);
+    }
+
     let particle_color = vec4<f32>(
         em.particle_color_r,
         em.particle_color_g,
         em.particle_color_b,
         em.particle_color_a,
-    );
+    ) * sample_color_mask(unit_position);
 
     let size_delta = em.particle_size_max - em.particle_size_min;
     let size_random = gen_abs_range(input_random + 100., size_delta, em.elapsed_sec);
@@ -47,13 +313,22 @@ fn spawn_particle(index: u32) {
     let speed_delta = em.particle_speed_max - em.particle_speed_min;
     let speed_random = gen_abs_range(input_random + 40., speed_delta, em.elapsed_sec);
     let particle_speed = em.particle_speed_min + speed_random;
-    let position = create_particle_position(input_random);
-    let velocity = create_velocity(input_random, particle_speed);
+    let mass_delta = em.particle_mass_max - em.particle_mass_min;
+    let mass_random = gen_abs_range(input_random + 70., mass_delta, em.elapsed_sec);
+    let mass = em.material_mass * size * (em.particle_mass_min + mass_random);
+
+    var velocity = create_velocity(input_random, particle_speed);
+
+    // Shockwaves expand from the ring, spheres and cones move along their sampled direction
+    if (spawn_shape == 2u && em.shape_params.w == 1.) || spawn_shape == 4u || spawn_shape == 5u {
+        velocity = shape_direction * particle_speed;
+    }
 
     particle.scale = size;
     particle.color = particle_color;
-    particle.vel_mass = vec4<f32>(velocity, em.material_mass * size);
-    particle.lifetime = 0.;
+    particle.vel_mass = vec4<f32>(velocity, mass);
+    particle.age = 0.;
+    particle.max_life = em.particle_lifetime;
     particle.model = em.particle_model;
     particle.model.w = vec4(position, 1.0);
 
@@ -63,26 +338,41 @@ fn spawn_particle(index: u32) {
 @compute
 @workgroup_size(128)
 fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
-    let particle_len = arrayLength(&particles_src);
+    let particle_len = min(arrayLength(&particles_src), u32(em.active_particle_count));
     let index = global_invocation_id.x;
 
     if particle_len <= index {
         return;
     }
 
-    if u32(em.spawn_from) <= index && index < u32(em.spawn_until) {
+    // Spawn and burst ranges wrap around the active particles, LOD shrinks them with the batches
+    let spawn_offset = (index + particle_len - u32(em.spawn_from)) % particle_len;
+
+    // Batches spawn from their start, the rest keeps aging when the emit count is lowered
+    let batch_offset = spawn_offset % max(u32(em.spawn_count), 1u);
+
+    if spawn_offset < u32(em.spawn_until) - u32(em.spawn_from) && batch_offset < u32(em.emit_count) {
+        spawn_particle(index);
+        count_live(index, true);
+        return;
+    }
+
+    let burst_offset = (index + particle_len - u32(em.burst_from)) % particle_len;
+
+    if burst_offset < u32(em.burst_count) {
         spawn_particle(index);
+        count_live(index, true);
         return;
     }
 
     var particle = particles_src[index];
 
     if is_decayed(em, particle) {
-        particles_dst[index].lifetime = particle.lifetime;
+        particles_dst[index].age = particle.age;
         return;
     }
 
-    particle.lifetime += em.delta_sec;
+    particle.age += em.delta_sec;
 
     let new_vel = particle.vel_mass.xyz * em.particle_friction_coefficient;
     particle.vel_mass = vec4<f32>(new_vel, particle.vel_mass.w);
@@ -92,6 +382,15 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
 
     particle.model.w = vec4(new_pos, 1.);
 
+    // Local space, the particles move and turn along with the box
+    if u32(em.simulation_space) == 0u {
+        let box_rotation = mat3x3(em.box_delta[0].xyz, em.box_delta[1].xyz, em.box_delta[2].xyz);
+
+        particle.model = em.box_delta * particle.model;
+        particle.vel_mass = vec4<f32>(box_rotation * new_vel, particle.vel_mass.w);
+    }
+
     particles_dst[index] = particle;
+    count_live(index, !is_decayed(em, particle));
 }
 
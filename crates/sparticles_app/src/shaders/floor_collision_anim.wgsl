@@ -0,0 +1,48 @@
+// Includes declarations
+
+struct FloorCollision {
+    height: f32,
+    restitution: f32,
+    friction: f32,
+}
+
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> em: Emitter; 
+@group(1) @binding(0) var<uniform> anim: FloorCollision; 
+
+@compute
+@workgroup_size(128)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let particle_len = arrayLength(&particles);
+    let index = global_invocation_id.x;
+
+    if particle_len <= index {
+        return;
+    }
+
+    var particle = particles[index];
+
+    if is_decayed(em, particle) {
+        return;
+    }
+
+    let floor_y = anim.height + particle.scale / 2.;
+
+    if floor_y <= particle.model[3].y {
+        return;
+    }
+
+    // Pushed back on top, so a slow particle doesn't sink further each frame
+    particle.model[3].y = floor_y;
+
+    // Only moving down bounces, a particle that already bounced keeps rising
+    if particle.vel_mass.y < 0. {
+        let keep_horizontal = 1. - saturate(anim.friction);
+
+        particle.vel_mass.x *= keep_horizontal;
+        particle.vel_mass.y *= -anim.restitution;
+        particle.vel_mass.z *= keep_horizontal;
+    }
+
+    particles[index] = particle;
+}
@@ -64,7 +64,7 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
 
     var particle = particles[index];
 
-    if particle.lifetime == -1. {
+    if is_decayed(em, particle) {
         return;
     }
 
@@ -0,0 +1,75 @@
+// Includes declarations
+
+struct DrawIndexedIndirect {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+@group(0) @binding(0) var<storage, read> particles: array<Particle>;
+@group(0) @binding(1) var<uniform> em: Emitter;
+@group(0) @binding(2) var<storage, read_write> visible_indices: array<u32>;
+@group(0) @binding(3) var<storage, read_write> draw_args: DrawIndexedIndirect;
+@group(1) @binding(0) var<uniform> camera: CameraUniform;
+
+// Bounding sphere relative to the particle scale, covers the corners of the circle quad
+const BOUNDS_RADIUS: f32 = 1.5;
+
+fn is_in_frustum(center: vec3<f32>, radius: f32) -> bool {
+    let m = transpose(camera.view_proj);
+
+    // Left, right, bottom, top, near (depth 0..1) and far
+    var planes = array<vec4<f32>, 6>(
+        m[3] + m[0],
+        m[3] - m[0],
+        m[3] + m[1],
+        m[3] - m[1],
+        m[2],
+        m[3] - m[2],
+    );
+
+    for (var i = 0; i < 6; i++) {
+        let plane = planes[i] / length(planes[i].xyz);
+
+        if dot(plane.xyz, center) + plane.w < -radius {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+@compute
+@workgroup_size(128)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let particle_len = min(arrayLength(&particles), u32(em.active_particle_count));
+    let index = global_invocation_id.x;
+
+    if particle_len <= index {
+        return;
+    }
+
+    let p = particles[index];
+
+    if is_decayed(em, p) || p.scale <= 0. {
+        return;
+    }
+
+    // Same world position as vs_main in particle.wgsl
+    let center = (p.model * vec4(0., 0., 0., 1.)).xyz * p.scale;
+    let axis_scale = max(length(p.model[0].xyz), max(length(p.model[1].xyz), length(p.model[2].xyz)));
+    var radius = p.scale * axis_scale * BOUNDS_RADIUS;
+
+    if u32(em.billboard_mode) == 1u {
+        radius *= 1. + length(particle_velocity(p)) * max(em.stretch_factor, 0.);
+    }
+
+    if !is_in_frustum(center, radius) {
+        return;
+    }
+
+    let slot = atomicAdd(&draw_args.instance_count, 1u);
+    visible_indices[slot] = index;
+}
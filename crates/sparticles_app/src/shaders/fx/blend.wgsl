@@ -2,7 +2,7 @@ struct Blend {
     io_mix: f32,
 }
 
-@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<rgba16float, read_write>, 16>;
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
 @group(1) @binding(0) var<uniform> fx_io: FxIO; 
 @group(2) @binding(0) var<uniform> blend: Blend; 
 
@@ -62,7 +62,7 @@ fn in_color(in_pos: vec2<i32>) -> vec3<f32> {
 }
 
 @compute
-@workgroup_size(16, 16, 1)
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
 fn lerp_upscale_blend(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let pos = global_id.xy;
 
@@ -75,15 +75,15 @@ fn lerp_upscale_blend(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let out_pos = pos;
 
     let in_color = in_color(vec2<i32>(in_pos));
-    let out_color = textureLoad(fx_tex[fx_io.out_idx], out_pos).rgb;
+    let out_color = textureLoad(fx_tex[fx_io.out_idx], out_pos);
 
-    let result = mix(in_color, out_color, blend.io_mix);
+    let result = mix(in_color, out_color.rgb, blend.io_mix);
 
-    textureStore(fx_tex[fx_io.out_idx], out_pos, vec4<f32>(result, 1.0));
+    textureStore(fx_tex[fx_io.out_idx], out_pos, vec4<f32>(result, out_color.a));
 }
 
 @compute
-@workgroup_size(16, 16, 1)
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
 fn lerp_simple_blend(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let pos = global_id.xy;
 
@@ -91,16 +91,16 @@ fn lerp_simple_blend(@builtin(global_invocation_id) global_id: vec3<u32>) {
         return;
     }
 
-    let in_color = textureLoad(fx_tex[fx_io.in_idx], pos).rgb;
-    let out_color = textureLoad(fx_tex[fx_io.out_idx], pos).rgb;
+    let in_color = textureLoad(fx_tex[fx_io.in_idx], pos);
+    let out_color = textureLoad(fx_tex[fx_io.out_idx], pos);
 
     let result = mix(in_color, out_color, blend.io_mix);
 
-    textureStore(fx_tex[fx_io.out_idx], pos, vec4<f32>(result, 1.0));
+    textureStore(fx_tex[fx_io.out_idx], pos, result);
 }
 
 @compute
-@workgroup_size(16, 16, 1)
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
 fn add_blend(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let pos = global_id.xy;
 
@@ -113,7 +113,8 @@ fn add_blend(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let out_pos = pos;
 
     let in_color = textureLoad(fx_tex[fx_io.in_idx], in_pos).rgb;
-    let out_color = textureLoad(fx_tex[fx_io.out_idx], out_pos).rgb;
+    let out_color = textureLoad(fx_tex[fx_io.out_idx], out_pos);
 
-    textureStore(fx_tex[fx_io.out_idx], out_pos, vec4<f32>(in_color + out_color, 1.0));
+    // Added light keeps the alpha of the frame, with premultiplied output it glows over the background
+    textureStore(fx_tex[fx_io.out_idx], out_pos, vec4<f32>(in_color + out_color.rgb, out_color.a));
 }
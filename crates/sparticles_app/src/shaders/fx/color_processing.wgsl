@@ -3,15 +3,17 @@ struct ColorCorrection {
     contrast: f32,
     brightness: f32,
     tonemap: u32,
+    white_point: f32,
+    tonemap_contrast: f32,
 }
 
-@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<rgba16float, read_write>, 16>;
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
 
 @group(1) @binding(0) var<uniform> fx_io: FxIO; 
 @group(2) @binding(0) var<uniform> globals: ColorCorrection; 
 
 @compute
-@workgroup_size(16, 16, 1)
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
 fn cs_general(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let pos = global_id.xy;
 
@@ -19,16 +21,16 @@ fn cs_general(@builtin(global_invocation_id) global_id: vec3<u32>) {
         return;
     }
 
-    var out = textureLoad(fx_tex[fx_io.in_idx], pos).rgb;
+    let color = textureLoad(fx_tex[fx_io.in_idx], pos);
 
-    out = tonemap(out, globals.tonemap);
+    var out = tonemap_shaped(color.rgb, globals.tonemap, globals.white_point, globals.tonemap_contrast);
     out = (out - 0.5) * globals.contrast + 0.5 + globals.brightness;
 
-    textureStore(fx_tex[fx_io.out_idx], pos, vec4<f32>(out, 1.0));
+    textureStore(fx_tex[fx_io.out_idx], pos, vec4<f32>(out, color.a));
 }
 
 @compute
-@workgroup_size(16, 16, 1)
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
 fn cs_tonemap(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let pos = global_id.xy;
 
@@ -36,11 +38,11 @@ fn cs_tonemap(@builtin(global_invocation_id) global_id: vec3<u32>) {
         return;
     }
 
-    let hdr = textureLoad(fx_tex[fx_io.in_idx], pos).rgb;      
+    let hdr = textureLoad(fx_tex[fx_io.in_idx], pos);      
 
     // Tone mapping + Gamma correct
-    var sdr = tonemap(hdr, globals.tonemap);
+    var sdr = tonemap_shaped(hdr.rgb, globals.tonemap, globals.white_point, globals.tonemap_contrast);
     sdr = pow(sdr, vec3<f32>(1.0 / globals.gamma));
 
-    textureStore(fx_tex[fx_io.out_idx], pos, vec4<f32>(sdr, 1.0));
+    textureStore(fx_tex[fx_io.out_idx], pos, vec4<f32>(sdr, hdr.a));
 }
@@ -0,0 +1,55 @@
+struct DepthOfField {
+    focus_distance: f32,
+    focus_range: f32,
+    max_blur: f32,
+    near: f32,
+    far: f32,
+    blur_idx: u32,
+}
+
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+@group(0) @binding(1) var depth_tex: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO;
+@group(2) @binding(0) var<uniform> globals: DepthOfField;
+
+fn linear_depth(pos: vec2<i32>) -> f32 {
+    let max_pos = vec2<i32>(textureDimensions(depth_tex)) - 1;
+    let depth = textureLoad(depth_tex, clamp(pos, vec2<i32>(0), max_pos), 0).r;
+    let near = globals.near;
+    let far = globals.far;
+
+    return near * far / (far - depth * (far - near));
+}
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn copy_frame(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+
+    if any(vec2<i32>(i32(fx_io.out_size_x), i32(fx_io.out_size_y)) <= pos) {
+        return;
+    }
+
+    textureStore(fx_tex[fx_io.out_idx], pos, textureLoad(fx_tex[fx_io.in_idx], pos));
+}
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+
+    if any(vec2<i32>(i32(fx_io.out_size_x), i32(fx_io.out_size_y)) <= pos) {
+        return;
+    }
+
+    // Only the own depth decides the blur, so the blurred sky behind an in focus particle is never used
+    let distance = linear_depth(pos);
+    let range = max(globals.focus_range, 0.001);
+    let coc = saturate(abs(distance - globals.focus_distance) / range);
+
+    let sharp = textureLoad(fx_tex[fx_io.in_idx], pos);
+    let blurred = textureLoad(fx_tex[globals.blur_idx], pos);
+
+    textureStore(fx_tex[fx_io.out_idx], pos, mix(sharp, blurred, coc));
+}
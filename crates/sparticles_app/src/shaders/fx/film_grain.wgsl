@@ -0,0 +1,38 @@
+// Includes noise
+struct Grain {
+    intensity: f32,
+    size: f32,
+    colored: u32,
+    elapsed_sec: f32,
+}
+
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+@group(0) @binding(1) var depth_tex: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO;
+@group(2) @binding(0) var<uniform> globals: Grain;
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+
+    if any(vec2<i32>(i32(fx_io.out_size_x), i32(fx_io.out_size_y)) <= pos) {
+        return;
+    }
+
+    // Grain cells in pixels, the time axis gives every frame a new pattern
+    let cell = floor(vec2<f32>(pos) / max(globals.size, 1.));
+    let noise = hash33(vec3<f32>(cell, fract(globals.elapsed_sec) * 1000.));
+
+    var grain = vec3<f32>(noise.x);
+
+    if globals.colored == 1u {
+        grain = noise;
+    }
+
+    let color = textureLoad(fx_tex[fx_io.in_idx], pos);
+    let out = vec4<f32>(max(color.rgb + grain * globals.intensity, vec3<f32>(0.)), color.a);
+
+    textureStore(fx_tex[fx_io.out_idx], pos, out);
+}
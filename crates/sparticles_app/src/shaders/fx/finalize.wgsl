@@ -22,7 +22,26 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
 @group(0) @binding(1) var s: sampler;
 @group(1) @binding(0) var<uniform> fx_io: FxIO; 
 
+fn srgb_oetf(color: vec3<f32>) -> vec3<f32> {
+    let cutoff = color <= vec3<f32>(0.0031308);
+    let lower = color * 12.92;
+    let higher = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+
+    return select(higher, lower, cutoff);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(read_fx[fx_io.out_idx], s, in.uv);
+    var color = textureSample(read_fx[fx_io.out_idx], s, in.uv);
+
+#if PREMULTIPLIED_ALPHA
+    // Particles are alpha blended onto a transparent clear, so the colors are premultiplied already
+    color.a = clamp(color.a, 0., 1.);
+#endif
+
+#if SRGB_OETF
+    color = vec4<f32>(srgb_oetf(max(color.rgb, vec3<f32>(0.))), color.a);
+#endif
+
+    return color;
 }
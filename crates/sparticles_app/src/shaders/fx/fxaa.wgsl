@@ -0,0 +1,85 @@
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+@group(0) @binding(1) var depth_tex: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO;
+
+const EDGE_THRESHOLD: f32 = 0.125;
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const REDUCE_MUL: f32 = 0.125;
+const REDUCE_MIN: f32 = 0.0078125;
+const SPAN_MAX: f32 = 8.;
+
+// Storage textures can't be sampled, so filter by hand. Coords are in pixels
+fn sample_bilinear(coord: vec2<f32>) -> vec4<f32> {
+    let max_pos = vec2<i32>(i32(fx_io.in_size_x), i32(fx_io.in_size_y)) - 1;
+    let p = coord - 0.5;
+    let base = vec2<i32>(floor(p));
+    let f = fract(p);
+
+    let c00 = textureLoad(fx_tex[fx_io.in_idx], clamp(base, vec2<i32>(0), max_pos));
+    let c10 = textureLoad(fx_tex[fx_io.in_idx], clamp(base + vec2<i32>(1, 0), vec2<i32>(0), max_pos));
+    let c01 = textureLoad(fx_tex[fx_io.in_idx], clamp(base + vec2<i32>(0, 1), vec2<i32>(0), max_pos));
+    let c11 = textureLoad(fx_tex[fx_io.in_idx], clamp(base + vec2<i32>(1, 1), vec2<i32>(0), max_pos));
+
+    return mix(mix(c00, c10, f.x), mix(c01, c11, f.x), f.y);
+}
+
+// Clamped because HDR highlights would otherwise dominate the edge detection
+fn luma(color: vec4<f32>) -> f32 {
+    return dot(saturate(color.rgb), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+
+    if any(vec2<i32>(i32(fx_io.out_size_x), i32(fx_io.out_size_y)) <= pos) {
+        return;
+    }
+
+    let coord = vec2<f32>(pos) + 0.5;
+    let color_m = textureLoad(fx_tex[fx_io.in_idx], pos);
+
+    let luma_nw = luma(sample_bilinear(coord + vec2<f32>(-1., -1.)));
+    let luma_ne = luma(sample_bilinear(coord + vec2<f32>(1., -1.)));
+    let luma_sw = luma(sample_bilinear(coord + vec2<f32>(-1., 1.)));
+    let luma_se = luma(sample_bilinear(coord + vec2<f32>(1., 1.)));
+    let luma_m = luma(color_m);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // No visible edge, keep the pixel as is
+    if luma_max - luma_min < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD) {
+        textureStore(fx_tex[fx_io.out_idx], pos, color_m);
+        return;
+    }
+
+    var dir = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+
+    let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let rcp_dir_min = 1. / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX));
+
+    // Blend along the edge, the wider sample is dropped when it crosses into another edge
+    let color_a = 0.5 * (
+        sample_bilinear(coord + dir * (1. / 3. - 0.5)) +
+        sample_bilinear(coord + dir * (2. / 3. - 0.5))
+    );
+    let color_b = color_a * 0.5 + 0.25 * (
+        sample_bilinear(coord + dir * -0.5) +
+        sample_bilinear(coord + dir * 0.5)
+    );
+
+    let luma_b = luma(color_b);
+
+    if luma_b < luma_min || luma_max < luma_b {
+        textureStore(fx_tex[fx_io.out_idx], pos, color_a);
+    } else {
+        textureStore(fx_tex[fx_io.out_idx], pos, color_b);
+    }
+}
@@ -1,4 +1,4 @@
-@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<rgba16float, read_write>, 16>;
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
 @group(1) @binding(0) var<uniform> fx_io: FxIO; 
 @group(2) @binding(0) var<uniform> globals: GaussianBlur; 
 
@@ -13,6 +13,7 @@ fn apply_blur(pos: vec2<i32>, offset: vec2<i32>) {
     let lhs = 1. / sqrt(two_ss * PI);
 
     var result = vec3<f32>(0.);
+    var alpha = 0.;
 
     for (var i = -edge; i < edge; i++) {
         var tex_offset = offset * i;
@@ -23,29 +24,31 @@ fn apply_blur(pos: vec2<i32>, offset: vec2<i32>) {
             var rhs = exp(-(t_off.x + t_off.y) / two_ss);
 
             var coeff = lhs * rhs * globals.intensity;
-            var col = textureLoad(fx_tex[fx_io.in_idx], tex_pos).rgb;
+            var col = textureLoad(fx_tex[fx_io.in_idx], tex_pos);
 
-            result += col * coeff;
+            result += col.rgb * coeff;
+            // Coverage isn't brightened by the intensity
+            alpha += col.a * lhs * rhs;
         }
     }
 
-    textureStore(fx_tex[fx_io.out_idx], pos, vec4<f32>(result, 1.0));
+    textureStore(fx_tex[fx_io.out_idx], pos, vec4<f32>(result, min(alpha, 1.)));
 }
 
 @compute
-@workgroup_size(16, 16, 1)
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
 fn apply_blur_x(@builtin(global_invocation_id) pos: vec3<u32>) {
     apply_blur(vec2<i32>(pos.xy), vec2<i32>(1, 0));
 }
 
 @compute
-@workgroup_size(16, 16, 1)
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
 fn apply_blur_y(@builtin(global_invocation_id) pos: vec3<u32>) {
     apply_blur(vec2<i32>(pos.xy), vec2<i32>(0, 1));
 }
 
 @compute
-@workgroup_size(16, 16, 1)
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
 fn split_bloom(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     let pos = global_invocation_id.xy;
 
@@ -0,0 +1,78 @@
+struct Kuwahara {
+    radius: i32,
+}
+
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO; 
+@group(2) @binding(0) var<uniform> globals: Kuwahara; 
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+    let in_max = vec2<i32>(i32(fx_io.in_size_x), i32(fx_io.in_size_y)) - 1;
+
+    if any(vec2<i32>(i32(fx_io.out_size_x), i32(fx_io.out_size_y)) <= pos) {
+        return;
+    }
+
+    let radius = globals.radius;
+    let quadrants = array<vec2<i32>, 4>(
+        vec2<i32>(-1, -1),
+        vec2<i32>(1, -1),
+        vec2<i32>(-1, 1),
+        vec2<i32>(1, 1),
+    );
+
+    var best_mean = vec4<f32>(0.);
+    var best_variance = 1e20;
+
+    // Least variance quadrant wins, this keeps edges while flattening regions
+    for (var q = 0; q < 4; q++) {
+        let dir = quadrants[q];
+        var sum = vec4<f32>(0.);
+        var lum_sum = 0.;
+        var lum_sq_sum = 0.;
+
+        for (var y = 0; y <= radius; y++) {
+            for (var x = 0; x <= radius; x++) {
+                let sample_pos = clamp(pos + vec2<i32>(x, y) * dir, vec2<i32>(0), in_max);
+                let color = textureLoad(fx_tex[fx_io.in_idx], sample_pos);
+                let lum = luminance(color.rgb);
+
+                sum += color;
+                lum_sum += lum;
+                lum_sq_sum += lum * lum;
+            }
+        }
+
+        let count = f32((radius + 1) * (radius + 1));
+        let lum_mean = lum_sum / count;
+        let variance = lum_sq_sum / count - lum_mean * lum_mean;
+
+        if variance < best_variance {
+            best_variance = variance;
+            best_mean = sum / count;
+        }
+    }
+
+    // Neighbours are read, so the output must not be the input
+    textureStore(fx_tex[fx_io.out_idx], pos, best_mean);
+}
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_copy(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = global_id.xy;
+
+    if any(vec2<u32>(fx_io.out_size_x, fx_io.out_size_y) <= pos) {
+        return;
+    }
+
+    textureStore(fx_tex[fx_io.out_idx], pos, textureLoad(fx_tex[fx_io.in_idx], pos));
+}
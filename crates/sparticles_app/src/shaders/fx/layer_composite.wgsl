@@ -0,0 +1,38 @@
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+};
+
+struct SplitOutput {
+    @location(0) frame: vec4<f32>,
+    @location(1) split: vec4<f32>,
+};
+
+var<private> positions: array<vec2<f32>, 3> = array<vec2<f32>, 3>(
+    vec2<f32>(-1.0, -3.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(3.0, 1.0)
+);
+
+// The layers are cleared transparent and alpha blended, so their colors are premultiplied
+@group(0) @binding(0) var layer_frame: texture_2d<f32>;
+@group(0) @binding(1) var layer_split: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.pos = vec4<f32>(positions[vertex_index], 0., 1.);
+    return out;
+}
+
+// Same size as the frame, so every fragment reads its own texel
+@fragment
+fn fs_frame(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(layer_frame, vec2<i32>(in.pos.xy), 0);
+}
+
+@fragment
+fn fs_split(in: VertexOutput) -> SplitOutput {
+    let coord = vec2<i32>(in.pos.xy);
+
+    return SplitOutput(textureLoad(layer_frame, coord, 0), textureLoad(layer_split, coord, 0));
+}
@@ -0,0 +1,38 @@
+struct Lut {
+    strength: f32,
+    domain_min: vec3<f32>,
+    domain_max: vec3<f32>,
+}
+
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+@group(0) @binding(1) var depth_tex: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO;
+@group(2) @binding(0) var<uniform> globals: Lut;
+
+@group(3) @binding(0) var lut_tex: texture_3d<f32>;
+@group(3) @binding(1) var lut_sampler: sampler;
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+
+    if any(vec2<i32>(i32(fx_io.out_size_x), i32(fx_io.out_size_y)) <= pos) {
+        return;
+    }
+
+    let color = textureLoad(fx_tex[fx_io.in_idx], pos);
+
+    let domain = max(globals.domain_max - globals.domain_min, vec3<f32>(0.0001));
+    let coord = saturate((color.rgb - globals.domain_min) / domain);
+
+    // Texel centers, so 0 and 1 hit the first and last entry instead of blending with the border
+    let size = vec3<f32>(textureDimensions(lut_tex));
+    let uvw = coord * (size - 1.) / size + 0.5 / size;
+
+    let graded = textureSampleLevel(lut_tex, lut_sampler, uvw, 0.).rgb;
+    let out = vec4<f32>(mix(color.rgb, graded, globals.strength), color.a);
+
+    textureStore(fx_tex[fx_io.out_idx], pos, out);
+}
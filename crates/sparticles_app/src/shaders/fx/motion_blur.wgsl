@@ -0,0 +1,33 @@
+struct MotionBlur {
+    blend: f32,
+    reset: u32,
+}
+
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO; 
+@group(2) @binding(0) var<uniform> globals: MotionBlur; 
+@group(3) @binding(0) var history: texture_storage_2d<FX_FORMAT, read_write>;
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = global_id.xy;
+
+    if any(vec2<u32>(fx_io.out_size_x, fx_io.out_size_y) <= pos) {
+        return;
+    }
+
+    let current = textureLoad(fx_tex[fx_io.in_idx], pos);
+
+    // A fresh history is empty, blending with it would fade in from black
+    var out = current;
+
+    if globals.reset == 0u {
+        let previous = textureLoad(history, pos);
+        out = mix(current, previous, clamp(globals.blend, 0., 0.99));
+    }
+
+    textureStore(fx_tex[fx_io.out_idx], pos, out);
+    textureStore(history, pos, out);
+}
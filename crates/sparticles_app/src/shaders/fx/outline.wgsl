@@ -0,0 +1,63 @@
+struct Outline {
+    color: vec4<f32>,
+    thickness: f32,
+    depth_threshold: f32,
+    normal_threshold: f32,
+    near: f32,
+    far: f32,
+}
+
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+@group(0) @binding(1) var depth_tex: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO;
+@group(2) @binding(0) var<uniform> globals: Outline;
+
+fn linear_depth(pos: vec2<i32>) -> f32 {
+    let max_pos = vec2<i32>(textureDimensions(depth_tex)) - 1;
+    let depth = textureLoad(depth_tex, clamp(pos, vec2<i32>(0), max_pos), 0).r;
+    let near = globals.near;
+    let far = globals.far;
+
+    return near * far / (far - depth * (far - near));
+}
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+
+    if any(vec2<i32>(i32(fx_io.out_size_x), i32(fx_io.out_size_y)) <= pos) {
+        return;
+    }
+
+    let offset = max(i32(globals.thickness), 1);
+
+    let center = linear_depth(pos);
+    let left = linear_depth(pos - vec2<i32>(offset, 0));
+    let right = linear_depth(pos + vec2<i32>(offset, 0));
+    let up = linear_depth(pos - vec2<i32>(0, offset));
+    let down = linear_depth(pos + vec2<i32>(0, offset));
+
+    // Relative so distant geometry doesn't outline more than nearby geometry
+    let depth_diff = max(
+        max(abs(left - center), abs(right - center)),
+        max(abs(up - center), abs(down - center)),
+    ) / center;
+
+    // Inverse depth is linear across a plane, curvature in it marks a normal discontinuity
+    let inv_center = 1. / center;
+    let curve_x = abs(1. / left + 1. / right - 2. * inv_center);
+    let curve_y = abs(1. / up + 1. / down - 2. * inv_center);
+    let normal_diff = max(curve_x, curve_y) / inv_center;
+
+    let is_edge = globals.depth_threshold < depth_diff || globals.normal_threshold < normal_diff;
+
+    var out = textureLoad(fx_tex[fx_io.in_idx], pos);
+
+    if is_edge {
+        out = vec4<f32>(mix(out.rgb, globals.color.rgb, globals.color.a), out.a);
+    }
+
+    textureStore(fx_tex[fx_io.out_idx], pos, out);
+}
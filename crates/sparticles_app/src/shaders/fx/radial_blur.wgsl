@@ -0,0 +1,55 @@
+struct RadialBlur {
+    center: vec2<f32>,
+    strength: f32,
+    samples: u32,
+    copy_idx: u32,
+}
+
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+@group(0) @binding(1) var depth_tex: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO;
+@group(2) @binding(0) var<uniform> globals: RadialBlur;
+
+const MAX_SAMPLES: u32 = 32u;
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn copy_frame(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+
+    if any(vec2<i32>(i32(fx_io.out_size_x), i32(fx_io.out_size_y)) <= pos) {
+        return;
+    }
+
+    textureStore(fx_tex[fx_io.out_idx], pos, textureLoad(fx_tex[fx_io.in_idx], pos));
+}
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+    let size = vec2<f32>(f32(fx_io.out_size_x), f32(fx_io.out_size_y));
+
+    if any(size <= vec2<f32>(pos)) {
+        return;
+    }
+
+    let samples = clamp(globals.samples, 1u, MAX_SAMPLES);
+    let max_pos = vec2<i32>(size) - 1;
+
+    // Steps towards the center, so pixels further out get stretched more
+    let uv = (vec2<f32>(pos) + 0.5) / size;
+    let step = (globals.center - uv) * globals.strength / f32(samples);
+
+    var result = vec4<f32>(0.);
+
+    for (var i = 0u; i < samples; i++) {
+        let sample_uv = uv + step * f32(i);
+        let sample_pos = clamp(vec2<i32>(sample_uv * size), vec2<i32>(0), max_pos);
+
+        result += textureLoad(fx_tex[globals.copy_idx], sample_pos);
+    }
+
+    textureStore(fx_tex[fx_io.out_idx], pos, result / f32(samples));
+}
@@ -0,0 +1,36 @@
+struct Vignette {
+    radius: f32,
+    softness: f32,
+    intensity: f32,
+}
+
+@group(0) @binding(0) var fx_tex: binding_array<texture_storage_2d<FX_FORMAT, read_write>, 16>;
+@group(0) @binding(1) var depth_tex: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> fx_io: FxIO;
+@group(2) @binding(0) var<uniform> globals: Vignette;
+
+@compute
+@workgroup_size(FX_WORKGROUP_SIZE, FX_WORKGROUP_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pos = vec2<i32>(global_id.xy);
+    let size = vec2<f32>(f32(fx_io.out_size_x), f32(fx_io.out_size_y));
+
+    if any(size <= vec2<f32>(pos)) {
+        return;
+    }
+
+    // -1..1 from the center, corrected for the aspect ratio so the falloff stays round
+    let center = vec2<f32>(0.5);
+    let uv = (vec2<f32>(pos) + 0.5) / size;
+    var offset = (uv - center) * 2.;
+    offset.x *= size.x / size.y;
+
+    let dist = length(offset);
+    let falloff = smoothstep(globals.radius, globals.radius + max(globals.softness, 0.0001), dist);
+
+    let color = textureLoad(fx_tex[fx_io.in_idx], pos);
+    let out = vec4<f32>(color.rgb * (1. - falloff * globals.intensity), color.a);
+
+    textureStore(fx_tex[fx_io.out_idx], pos, out);
+}
@@ -0,0 +1,57 @@
+struct Sky {
+    top_color: vec4<f32>,
+    horizon_color: vec4<f32>,
+    sun_color: vec4<f32>,
+    sun_direction: vec3<f32>,
+    sun_size: f32,
+    inv_view_proj: mat4x4<f32>,
+    camera_position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) split: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> sky: Sky;
+
+var<private> positions: array<vec2<f32>, 3> = array<vec2<f32>, 3>(
+    vec2<f32>(-1.0, -3.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(3.0, 1.0)
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.pos = vec4<f32>(positions[vertex_index], 1., 1.);
+    out.ndc = positions[vertex_index];
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let world = sky.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let dir = normalize(world.xyz / world.w - sky.camera_position);
+
+    let height = clamp(dir.y, 0.0, 1.0);
+    var color = mix(sky.horizon_color.rgb, sky.top_color.rgb, sqrt(height));
+
+    let sun_angle = acos(clamp(dot(dir, normalize(sky.sun_direction)), -1.0, 1.0));
+    let edge = fwidth(sun_angle);
+
+    if 0.0 < sky.sun_size {
+        let sun = 1.0 - smoothstep(sky.sun_size - edge, sky.sun_size + edge, sun_angle);
+        color = mix(color, sky.sun_color.rgb, sun);
+    }
+
+    var out: FragmentOutput;
+    out.color = vec4<f32>(color, 1.0);
+    out.split = vec4<f32>(0.0);
+    return out;
+}
@@ -6,6 +6,12 @@ struct GravitationalForce {
     current_pos_x: f32,
     current_pos_y: f32,
     current_pos_z: f32,
+    mode: f32,
+    acceleration_x: f32,
+    acceleration_y: f32,
+    acceleration_z: f32,
+    padding_1: f32,
+    padding_2: f32,
 }
 
 @group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
@@ -24,7 +30,15 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
 
     var particle = particles[index];
 
-    if particle.lifetime == -1. {
+    if is_decayed(em, particle) {
+        return;
+    }
+
+    // Constant
+    if u32(force.mode) == 1u {
+        let acceleration = vec3<f32>(force.acceleration_x, force.acceleration_y, force.acceleration_z);
+        particle.vel_mass += vec4<f32>(acceleration * em.delta_sec, 0.);
+        particles[index] = particle;
         return;
     }
 
@@ -45,8 +59,10 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     let distance_pow_y = distance.y * distance.y;
     let distance_pow_z = distance.z * distance.z;
     let len_pow = distance_pow_x + distance_pow_y + distance_pow_z;
-    let top_formula = force.gravitational_force * force.mass * mass;
-    let force = top_formula / len_pow;
+    let base_mass = em.material_mass * size;
+    let top_formula = force.gravitational_force * force.mass * base_mass;
+    // Particles heavier than the base mass accelerate slower
+    let force = top_formula / len_pow * (base_mass / mass);
 
     let percentage_x = distance_pow_x / len_pow;
     let percentage_y = distance_pow_y / len_pow;
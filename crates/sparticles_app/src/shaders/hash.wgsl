@@ -0,0 +1,16 @@
+// PCG hash, shared by every shader so random streams stay uncorrelated and reproducible.
+// Mirrored on the CPU in util/hash.rs, keep both in sync
+fn pcg_hash(input: u32) -> u32 {
+    let state = input * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Maps a hash to 0..1 (exclusive) using the top 24 bits, exact in f32
+fn hash_to_unit(hash: u32) -> f32 {
+    return f32(hash >> 8u) / 16777216.;
+}
+
+fn hash_seeded(input: f32, seed: f32) -> u32 {
+    return pcg_hash(bitcast<u32>(input) ^ pcg_hash(bitcast<u32>(seed)));
+}
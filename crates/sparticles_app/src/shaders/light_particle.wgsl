@@ -23,7 +23,11 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     }
 
     var out: VertexOutput;
-    out.color = p.color;
+    out.color = particle_color(p);
+
+    if camera.debug_view != DEBUG_VIEW_SPEED {
+        out.color = vec4(out.color.rgb * light_intensity(em, p), out.color.a);
+    }
     out.world_pos = vec4<f32>(p.model.w.xyz + in.position * p.scale, 1.0);
     out.clip_position = camera.view_proj * out.world_pos;
     out.uv = in.uv;
@@ -35,17 +39,17 @@ fn vs_main(in: VertexInput) -> VertexOutput {
 fn fs_circle(in: VertexOutput) -> FragmentOutput {
     let v_pos = in.uv * 2. - 1.;
 
-    let len = length(v_pos);
-    let diff_color = textureSample(albedo_tex, albedo_s, in.uv).rgb;
+    let diff_color = textureSampleBias(albedo_tex, albedo_s, in.uv, material_sampling.mip_bias).rgb;
+    let coverage = circle_coverage(v_pos);
 
-    if 1.0 < len {
+    if coverage <= 0.0 {
         discard;
     }
 
-    let normal = sqrt(1. - v_pos.x * v_pos.x - v_pos.y * v_pos.y);
+    let normal = sqrt(max(1. - v_pos.x * v_pos.x - v_pos.y * v_pos.y, 0.));
 
     var out: FragmentOutput;
-    out.color = vec4<f32>(in.color.rgb * diff_color * normal, in.color.a);
+    out.color = vec4<f32>(in.color.rgb * diff_color * normal, in.color.a * coverage) * em.tint;
 
     if any(camera.bloom_treshold < out.color.rgb) {
         out.split = out.color;
@@ -60,7 +64,7 @@ fn fs_model(in: VertexOutput) -> FragmentOutput {
     let v_pos = in.uv * 2. - 1.;
 
     let len = length(v_pos);
-    let texture_color = textureSample(albedo_tex, albedo_s, in.uv).rgb;
+    let texture_color = textureSampleBias(albedo_tex, albedo_s, in.uv, material_sampling.mip_bias).rgb;
 
     if 1.0 < len {
         discard;
@@ -71,7 +75,7 @@ fn fs_model(in: VertexOutput) -> FragmentOutput {
     let normal = sqrt(1. - x * x - y * y);
 
     var out: FragmentOutput;
-    out.color = vec4<f32>(texture_color.rgb * in.color.rgb * normal, 1.0);
+    out.color = vec4<f32>(texture_color.rgb * in.color.rgb * normal, 1.0) * em.tint;
 
     if any(camera.bloom_treshold < out.color.rgb) {
         out.split = out.color;
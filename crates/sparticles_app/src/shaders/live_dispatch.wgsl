@@ -0,0 +1,39 @@
+// Includes declarations
+
+struct DispatchIndirect {
+    x: u32,
+    y: u32,
+    z: u32,
+}
+
+@group(0) @binding(0) var<storage, read> particles: array<Particle>;
+@group(0) @binding(1) var<uniform> em: Emitter;
+@group(0) @binding(2) var<storage, read_write> live: LiveCounter;
+@group(0) @binding(3) var<storage, read_write> dispatch: DispatchIndirect;
+
+// Spawn ranges that wrap around the end of the active particles reach the last active slot
+fn range_end(start: f32, count: f32, ring_len: u32) -> u32 {
+    if count <= 0. {
+        return 0u;
+    }
+
+    return min(u32(start + count), ring_len);
+}
+
+@compute
+@workgroup_size(1)
+fn main() {
+    let ring_len = min(arrayLength(&particles), u32(em.active_particle_count));
+
+    let spawn_end = range_end(em.spawn_from, em.spawn_until - em.spawn_from, ring_len);
+    let burst_end = range_end(em.burst_from, em.burst_count, ring_len);
+    let live_end = max(atomicLoad(&live.high_water), max(spawn_end, burst_end));
+    let slot_count = min(live_end, ring_len);
+
+    dispatch.x = (slot_count + 127u) / 128u;
+    dispatch.y = 1u;
+    dispatch.z = 1u;
+
+    atomicStore(&live.high_water, 0u);
+    atomicStore(&live.alive, 0u);
+}
@@ -16,13 +16,96 @@ struct VertexOutput {
     @location(3) normal: vec3<f32>,
     @location(4) tangent: vec3<f32>,
     @location(5) bitangent: vec3<f32>,
+    @location(6) quad_uv: vec2<f32>, // uv of the mesh, uv is the flipbook cell in the atlas
+    @location(7) @interpolate(flat) emitter_idx: u32, // only set in merged draws
 }
 
 @group(3) @binding(0) var<storage, read> light_particles: array<Particle>;
+@group(3) @binding(2) var<uniform> light_em: Emitter;
+
+// Maps the mesh uv to the current cell of the sprite sheet, left to right then top to bottom
+fn flipbook_uv(uv: vec2<f32>, p: Particle) -> vec2<f32> {
+    let columns = u32(em.flipbook.x);
+    let rows = u32(em.flipbook.y);
+    let fps = em.flipbook.z;
+
+    if columns == 0u || rows == 0u {
+        return uv;
+    }
+
+    let cell_count = columns * rows;
+    var cell: u32;
+
+    if 0. < fps {
+        cell = u32(p.age * fps) % cell_count;
+    } else {
+        cell = min(u32(normalized_life(p) * f32(cell_count)), cell_count - 1u);
+    }
+
+    let offset = vec2<f32>(f32(cell % columns), f32(cell / columns));
+
+    return (offset + uv) / vec2<f32>(f32(columns), f32(rows));
+}
+
+const BILLBOARD_STRETCHED: u32 = 1u;
+
+// Scales the quad along the velocity seen from the camera, slow particles stay round
+fn stretch_along_velocity(position: vec3<f32>, p: Particle) -> vec3<f32> {
+    let forward = normalize(vec3(camera.view[0].z, camera.view[1].z, camera.view[2].z));
+    let vel = particle_velocity(p);
+    let planar = vel - forward * dot(vel, forward);
+    let speed = length(planar);
+
+    if speed < 0.001 {
+        return position;
+    }
+
+    let dir = planar / speed;
+    let stretch = speed * max(em.stretch_factor, 0.);
+
+    return position + dir * dot(position, dir) * stretch;
+}
+
+// Culled emitters draw only the visible particles, sorted emitters draw back to front
+fn particle_index(instance_idx: u32) -> u32 {
+#if BATCHED
+    return instance_idx;
+#else
+    if em.frustum_culling == 1. || em.depth_sorted == 1. {
+        return visible_indices[instance_idx];
+    }
+
+    return instance_idx;
+#endif
+}
+
+#if BATCHED
+// Emitter of an instance in a merged draw, the first instances are ascending
+fn batch_emitter(instance_idx: u32) -> u32 {
+    var low = 0u;
+    var high = arrayLength(&first_instances);
+
+    while low + 1u < high {
+        let mid = (low + high) / 2u;
+
+        if first_instances[mid] <= instance_idx {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    return low;
+}
+#endif
 
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
-    let p = particles[in.instance_idx];
+#if BATCHED
+    let emitter_idx = batch_emitter(in.instance_idx);
+    em = emitters[emitter_idx];
+#endif
+    let p = particles[particle_index(in.instance_idx)];
 
     if is_decayed(em, p) {
         var out: VertexOutput;
@@ -31,9 +114,19 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     }
 
     var out: VertexOutput;
-    out.uv = in.uv;
-    out.color = p.color;
-    out.world_pos = (p.model * vec4(in.position, 1.0)).xyz * p.scale;
+    out.uv = flipbook_uv(in.uv, p);
+    out.quad_uv = in.uv;
+#if BATCHED
+    out.emitter_idx = emitter_idx;
+#endif
+    out.color = particle_color(p);
+    var position = in.position;
+
+    if u32(em.billboard_mode) == BILLBOARD_STRETCHED {
+        position = stretch_along_velocity(position, p);
+    }
+
+    out.world_pos = (p.model * vec4(position, 1.0)).xyz * p.scale;
     out.normal = in.normal;
     out.tangent = in.tangent.xyz;
     out.bitangent = in.bitangent;
@@ -44,11 +137,11 @@ fn vs_main(in: VertexInput) -> VertexOutput {
 
 fn apply_pbr(in: VertexOutput, N: vec3<f32>, WN: vec3<f32>, ALB: vec3<f32>) -> FragmentOutput {
     let albedo = pow(ALB, vec3(2.2));
-    let metallic_roughness = textureSample(metal_rough_tex, metal_rough_s, in.uv).rg;
+    let metallic_roughness = textureSampleBias(metal_rough_tex, metal_rough_s, in.uv, material_sampling.mip_bias).rg;
     let metallic = metallic_roughness.r;
     let roughness = metallic_roughness.g;
-    let ao = textureSample(ao_tex, ao_s, in.uv).r;
-    let emissive = pow(textureSample(emissive_tex, emissive_s, in.uv).rgb, vec3(2.2));
+    let ao = textureSampleBias(ao_tex, ao_s, in.uv, material_sampling.mip_bias).r;
+    let emissive = pow(textureSampleBias(emissive_tex, emissive_s, in.uv, material_sampling.mip_bias).rgb, vec3(2.2));
 
     let F0 = mix(vec3(0.04), albedo, metallic);
     let V = normalize(camera.position.xyz - in.world_pos);
@@ -58,7 +151,7 @@ fn apply_pbr(in: VertexOutput, N: vec3<f32>, WN: vec3<f32>, ALB: vec3<f32>) -> F
     for (var i = 0u; i < arrayLength(&light_particles); i++) {
         let light = light_particles[i];
         let light_pos = light.model.w.xyz;
-        let light_col = light.color.rgb;
+        let light_col = light.color.rgb * light_intensity(light_em, light);
 
         // calculate per-light radiance
         let L = normalize(light_pos - in.world_pos);
@@ -87,7 +180,25 @@ fn apply_pbr(in: VertexOutput, N: vec3<f32>, WN: vec3<f32>, ALB: vec3<f32>) -> F
 
     let color = tonemap(Diff * vec3(0.4) * albedo * ao + Lo + emissive, camera.tonemap);
 
-    out.color = vec4(linear_to_srgb(color), 1.0);
+    out.color = vec4(linear_to_srgb(color), 1.0) * em.tint;
+
+    if any(camera.bloom_treshold < out.color.rgb) {
+        out.split = out.color;
+    }
+
+    return out;
+}
+
+// Skips scene lighting, for glowing sprites that only emit light
+fn apply_unlit(in: VertexOutput, ALB: vec3<f32>) -> FragmentOutput {
+    let albedo = pow(ALB, vec3(2.2));
+    let emissive = pow(textureSampleBias(emissive_tex, emissive_s, in.uv, material_sampling.mip_bias).rgb, vec3(2.2));
+
+    var out: FragmentOutput;
+
+    let color = tonemap(albedo + emissive, camera.tonemap);
+
+    out.color = vec4(linear_to_srgb(color), 1.0) * em.tint;
 
     if any(camera.bloom_treshold < out.color.rgb) {
         out.split = out.color;
@@ -98,27 +209,54 @@ fn apply_pbr(in: VertexOutput, N: vec3<f32>, WN: vec3<f32>, ALB: vec3<f32>) -> F
 
 @fragment
 fn fs_model(in: VertexOutput) -> FragmentOutput {
-    let tangent_normal = textureSample(normal_tex, normal_s, in.uv).rgb * 2.0 - 1.0;
+#if BATCHED
+    em = emitters[in.emitter_idx];
+#endif
+    if camera.debug_view == DEBUG_VIEW_SPEED {
+        return debug_output(in.color.rgb);
+    }
+
+    let albedo = textureSampleBias(albedo_tex, albedo_s, in.uv, material_sampling.mip_bias).rgb;
+
+#if UNLIT
+    return apply_unlit(in, albedo);
+#else
+    let tangent_normal = textureSampleBias(normal_tex, normal_s, in.uv, material_sampling.mip_bias).rgb * 2.0 - 1.0;
     let TBN = mat3x3(in.normal, in.tangent, in.bitangent);
 
     let N = normalize(TBN * tangent_normal);
-    let albedo = textureSample(albedo_tex, albedo_s, in.uv).rgb;
 
     return apply_pbr(in, N, in.normal, albedo);
+#endif
 }
 
 @fragment
 fn fs_circle(in: VertexOutput) -> FragmentOutput {
-    let v_pos = in.uv * 2. - 1.;
-    let texture_color = textureSample(albedo_tex, albedo_s, in.uv);
+#if BATCHED
+    em = emitters[in.emitter_idx];
+#endif
+    let v_pos = in.quad_uv * 2. - 1.;
+    let texture_color = textureSampleBias(albedo_tex, albedo_s, in.uv, material_sampling.mip_bias);
+    let coverage = circle_coverage(v_pos);
 
-    if 1.0 < length(v_pos) {
+    if coverage <= 0.0 {
         discard;
     }
 
+    if camera.debug_view == DEBUG_VIEW_SPEED {
+        return debug_output(in.color.rgb);
+    }
+
+#if UNLIT
+    var out = apply_unlit(in, in.color.rgb);
+#else
     let x = v_pos.x;
     let y = v_pos.y * -1.;
-    let WN = (vec4(x, y, sqrt(1. - x * x - y * y), 0.) * camera.view).xyz;
+    let WN = (vec4(x, y, sqrt(max(1. - x * x - y * y, 0.)), 0.) * camera.view).xyz;
+
+    var out = apply_pbr(in, WN, WN, in.color.rgb);
+#endif
+    out.color.a *= coverage;
 
-    return apply_pbr(in, WN, WN, in.color.rgb);
+    return out;
 }
@@ -0,0 +1,44 @@
+// Stable particle layout, stride is 112 bytes (28 floats).
+// Custom render pipelines can vertex-pull from EmitterState::particle_buffer
+// using this struct and the accessors below.
+struct Particle {
+    model: mat4x4<f32>, // world position is in model[3].xyz
+    color: vec4<f32>,
+    vel_mass: vec4<f32>, // velocity in xyz, mass in w
+    scale: f32,
+    age: f32, // seconds since spawn, decayed once it passes max_life
+    max_life: f32, // seconds the particle lives, set on spawn and 0. before
+};
+
+fn particle_position(p: Particle) -> vec3<f32> {
+    return p.model[3].xyz;
+}
+
+fn particle_velocity(p: Particle) -> vec3<f32> {
+    return p.vel_mass.xyz;
+}
+
+fn particle_size(p: Particle) -> f32 {
+    return p.scale;
+}
+
+fn particle_age(p: Particle) -> f32 {
+    return p.age;
+}
+
+fn particle_max_life(p: Particle) -> f32 {
+    return p.max_life;
+}
+
+// Age as a fraction of the lifetime (0..1), use this for everything driven over the life of a particle
+fn normalized_life(p: Particle) -> f32 {
+    if p.max_life <= 0. {
+        return 1.;
+    }
+
+    return clamp(p.age / p.max_life, 0., 1.);
+}
+
+fn particle_is_alive(p: Particle) -> bool {
+    return 0. < p.max_life && p.age <= p.max_life;
+}
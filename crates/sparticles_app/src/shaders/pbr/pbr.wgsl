@@ -1,6 +1,15 @@
 @group(0) @binding(0) var<uniform> camera: CameraUniform;
+#if BATCHED
+// Merged draw, the particles of every emitter follow each other
+@group(2) @binding(0) var<storage, read> particles: array<Particle>;
+@group(2) @binding(1) var<storage, read> emitters: array<Emitter>;
+@group(2) @binding(2) var<storage, read> first_instances: array<u32>;
+var<private> em: Emitter;
+#else
 @group(2) @binding(0) var<storage, read> particles: array<Particle>;
 @group(2) @binding(2) var<uniform> em: Emitter; 
+@group(2) @binding(6) var<storage, read> visible_indices: array<u32>;
+#endif
 
 
 struct FragmentOutput {
@@ -8,6 +17,8 @@ struct FragmentOutput {
     @location(1) split: vec4<f32>,
 }
 
+const DEBUG_VIEW_SPEED: u32 = 1u;
+
 @group(1) @binding(0) var albedo_tex: texture_2d<f32>;
 @group(1) @binding(1) var albedo_s: sampler;
 @group(1) @binding(2) var normal_tex: texture_2d<f32>;
@@ -20,6 +31,12 @@ struct FragmentOutput {
 @group(1) @binding(8) var ao_tex: texture_2d<f32>;
 @group(1) @binding(9) var ao_s: sampler;
 
+struct MaterialSampling {
+    mip_bias: f32,
+}
+
+@group(1) @binding(10) var<uniform> material_sampling: MaterialSampling;
+
 fn fresnel_schlick(cos_theta: f32, F0: vec3<f32>) -> vec3<f32> {
     return F0 + (1.0 - F0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
 }
@@ -56,3 +73,38 @@ fn geometry_smith(N: vec3<f32>, V: vec3<f32>, L: vec3<f32>, roughness: f32) -> f
     return ggx1 * ggx2;
 }
 
+
+// Blue (still) to cyan, green, yellow and red (max speed)
+fn speed_palette(t: f32) -> vec3<f32> {
+    let x = clamp(t, 0.0, 1.0) * 4.0;
+
+    return vec3(
+        clamp(x - 2.0, 0.0, 1.0),
+        clamp(x, 0.0, 1.0) - clamp(x - 3.0, 0.0, 1.0),
+        1.0 - clamp(x - 1.0, 0.0, 1.0),
+    );
+}
+
+fn particle_color(p: Particle) -> vec4<f32> {
+    if camera.debug_view == DEBUG_VIEW_SPEED {
+        let speed = length(p.vel_mass.xyz);
+        return vec4(speed_palette(speed / max(camera.debug_max_speed, 0.0001)), 1.0);
+    }
+
+    return p.color;
+}
+
+fn debug_output(color: vec3<f32>) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.color = vec4(color, 1.0);
+    return out;
+}
+
+// Coverage of the unit circle, blends a hard edge with an analytic (fwidth) anti-aliased edge
+fn circle_coverage(v_pos: vec2<f32>) -> f32 {
+    let dist = length(v_pos);
+    let edge = max(fwidth(dist), 0.0001);
+    let analytic = clamp((1.0 - dist) / edge + 0.5, 0.0, 1.0);
+
+    return mix(step(dist, 1.0), analytic, em.circle_aa);
+}
@@ -6,6 +6,7 @@ struct Exposure {
 
 
 const GAMMA: f32 = 2.2;
+const MID_GREY: f32 = 0.18;
 const INV_GAMMA: f32 = 1.0 / GAMMA;
 
 
@@ -141,6 +142,29 @@ fn lottes(x: vec3<f32>) -> vec3<f32> {
     return pow(x, a) / (pow(x, a * d) * b + c);
 }
 
+fn reinhard(x: vec3<f32>) -> vec3<f32> {
+    return x / (1. + x);
+}
+
+// Hable 2010, "Filmic Tonemapping Operators"
+fn uncharted2_partial(x: vec3<f32>) -> vec3<f32> {
+    let a = 0.15; // shoulder strength
+    let b = 0.50; // linear strength
+    let c = 0.10; // linear angle
+    let d = 0.20; // toe strength
+    let e = 0.02; // toe numerator
+    let f = 0.30; // toe denominator
+
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+fn uncharted2(x: vec3<f32>) -> vec3<f32> {
+    let exposure_bias = 2.0;
+    let white = vec3(11.2);
+
+    return uncharted2_partial(x * exposure_bias) / uncharted2_partial(white);
+}
+
 fn tonemap(in: vec3<f32>, tonemap: u32) -> vec3<f32> {
     switch tonemap {
         case 0u {
@@ -155,6 +179,15 @@ fn tonemap(in: vec3<f32>, tonemap: u32) -> vec3<f32> {
         case 3u {
             return lottes(in);
         }
+        case 4u {
+            return reinhard(in);
+        }
+        case 5u {
+            return uncharted2(in);
+        }
+        case 6u {
+            return saturate(in);
+        }
         default {
             return vec3(1.0);
         }
@@ -168,3 +201,12 @@ fn tonemap(in: vec3<f32>, tonemap: u32) -> vec3<f32> {
     //color = aces_hill(color);
     //#endif
 }
+
+// Contrast steepens the curve around middle grey, the white point is the input that maps to 1.
+// Operators that already reach 1. before the white point are left as is
+fn tonemap_shaped(in: vec3<f32>, tonemap_type: u32, white_point: f32, contrast: f32) -> vec3<f32> {
+    let graded = MID_GREY * pow(max(in, vec3(0.)) / MID_GREY, vec3(contrast));
+    let white = tonemap(vec3(white_point), tonemap_type);
+
+    return tonemap(graded, tonemap_type) / clamp(white, vec3(1e-4), vec3(1.));
+}
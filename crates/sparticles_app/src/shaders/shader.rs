@@ -5,7 +5,15 @@ use std::{borrow::Cow, fs, path::PathBuf};
 pub const SDR_PBR: &str = "pbr/pbr.wgsl";
 pub const SDR_TONEMAPPING: &str = "pbr/tonemapping.wgsl";
 pub const DECLARATIONS: &str = "declarations.wgsl";
+pub const SDR_PARTICLE_LAYOUT: &str = "particle_layout.wgsl";
+pub const SDR_HASH: &str = "hash.wgsl";
+/// WGSL source of the particle struct and its accessors, for shaders built outside this crate
+pub const PARTICLE_LAYOUT: &str = include_str!("particle_layout.wgsl");
 pub const DIR_HAS_LIGHTS: &str = "HAS_LIGHTS";
+pub const DIR_SRGB_OETF: &str = "SRGB_OETF";
+pub const DIR_PREMULTIPLIED_ALPHA: &str = "PREMULTIPLIED_ALPHA";
+pub const DIR_UNLIT: &str = "UNLIT";
+pub const DIR_BATCHED: &str = "BATCHED";
 
 pub struct ShaderLocation<'a> {
     pub path: PathBuf,
@@ -45,8 +53,15 @@ impl GfxState {
     /// Uses builtin path /src/shaders/
     pub fn create_shader_builtin(&self, options: ShaderOptions) -> wgpu::ShaderModule {
         let device = &self.device;
-        let mut shader_str = String::new();
-        let all_files = [&["declarations.wgsl"], options.files].concat();
+        let mut shader_str = format!(
+            "const FX_WORKGROUP_SIZE: u32 = {}u;\n",
+            self.fx_workgroup_size()
+        );
+        let all_files = [
+            &[SDR_PARTICLE_LAYOUT, SDR_HASH, DECLARATIONS],
+            options.files,
+        ]
+        .concat();
 
         let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         path.push("src/shaders/dummy.txt");
@@ -61,20 +76,24 @@ impl GfxState {
             shader_str += res;
         }
 
+        let shader_str = shader_str.replace("FX_FORMAT", self.fx_format_wgsl());
+
         device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some(options.label),
             source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&shader_str)),
         })
     }
 
-    /// Automatically includes declarations.wgsl
+    /// Automatically includes particle_layout.wgsl, hash.wgsl and declarations.wgsl
     pub fn create_shader_custom(
         &self,
         sdr_locations: Vec<ShaderLocation>,
         label: &str,
     ) -> wgpu::ShaderModule {
         let device = &self.device;
-        let mut shader_str = include_str!("declarations.wgsl").to_string();
+        let mut shader_str = PARTICLE_LAYOUT.to_string();
+        shader_str += include_str!("hash.wgsl");
+        shader_str += include_str!("declarations.wgsl");
 
         for mut sdr_location in sdr_locations {
             let path = &mut sdr_location.path;
@@ -87,6 +106,8 @@ impl GfxState {
             }
         }
 
+        let shader_str = shader_str.replace("FX_FORMAT", self.fx_format_wgsl());
+
         device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some(label),
             source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&shader_str)),
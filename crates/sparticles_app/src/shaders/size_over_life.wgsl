@@ -0,0 +1,41 @@
+// Includes declarations 
+struct SizeOverLife {
+    start_size: f32,
+    peak_size: f32,
+    end_size: f32,
+    peak_at: f32,
+}
+
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> em: Emitter; 
+@group(1) @binding(0) var<uniform> size: SizeOverLife; 
+
+@compute
+@workgroup_size(128)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let particle_len = arrayLength(&particles);
+    let index = global_invocation_id.x;
+
+    if particle_len <= index {
+        return;
+    }
+
+    var particle = particles[index];
+
+    if is_decayed(em, particle) {
+        return;
+    }
+
+    let life = normalized_life(particle);
+
+    // A peak at 0 or 1 leaves a single segment, so never divide by a zero length
+    if life < size.peak_at {
+        particle.scale = mix(size.start_size, size.peak_size, life / size.peak_at);
+    } else {
+        let t = clamp((life - size.peak_at) / max(1. - size.peak_at, 0.0001), 0., 1.);
+        particle.scale = mix(size.peak_size, size.end_size, t);
+    }
+
+    particle.scale = max(particle.scale, 0.);
+    particles[index] = particle;
+}
@@ -0,0 +1,55 @@
+// Includes declarations
+
+struct Spring {
+    stiffness: f32,
+    damping: f32,
+    from_sec: f32,
+    target_mode: f32,
+    current_target_x: f32,
+    current_target_y: f32,
+    current_target_z: f32,
+    padding: f32,
+}
+
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> em: Emitter; 
+@group(1) @binding(0) var<uniform> spring: Spring; 
+@group(1) @binding(1) var<storage, read> targets: array<vec4<f32>>; 
+
+@compute
+@workgroup_size(128)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let particle_len = arrayLength(&particles);
+
+    let index = global_invocation_id.x;
+
+    if particle_len <= index || arrayLength(&targets) <= index {
+        return;
+    }
+
+    var particle = particles[index];
+
+    if is_decayed(em, particle) || particle.age < spring.from_sec {
+        return;
+    }
+
+    let position = particle.model.w.xyz;
+    let vel = particle.vel_mass.xyz;
+    let mass = particle.vel_mass.w;
+
+    var target_pos = targets[index].xyz;
+
+    // Moving target
+    if u32(spring.target_mode) == 1u {
+        target_pos = vec3<f32>(spring.current_target_x, spring.current_target_y, spring.current_target_z);
+    }
+
+    // F = -k * x - c * v, with x the offset from the target
+    let displacement = target_pos - position;
+    let spring_force = displacement * spring.stiffness - vel * spring.damping;
+    let new_vel = vel + spring_force / mass * em.delta_sec;
+
+    particle.vel_mass = vec4<f32>(new_vel, mass);
+
+    particles[index] = particle;
+}
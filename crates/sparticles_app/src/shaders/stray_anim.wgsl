@@ -36,7 +36,7 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
 
     var particle = particles[index];
 
-    if (particle.lifetime < anim.from_sec || anim.until_sec <= particle.lifetime) {
+    if (is_decayed(em, particle) || particle.age < anim.from_sec || anim.until_sec <= particle.age) {
         return;
     }
     
@@ -0,0 +1,40 @@
+// Includes declarations
+
+struct Turbulence {
+    scale: f32,
+    strength: f32,
+    scroll_speed: f32,
+}
+
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> em: Emitter; 
+@group(1) @binding(0) var<uniform> turbulence: Turbulence; 
+@group(2) @binding(0) var noise_tex: texture_3d<f32>;
+@group(2) @binding(1) var noise_sampler: sampler;
+
+@compute
+@workgroup_size(128)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let particle_len = arrayLength(&particles);
+
+    let index = global_invocation_id.x;
+
+    if particle_len <= index {
+        return;
+    }
+
+    var particle = particles[index];
+
+    if is_decayed(em, particle) {
+        return;
+    }
+
+    let position = particle.model.w.xyz;
+    let uvw = position * turbulence.scale + vec3<f32>(em.elapsed_sec * turbulence.scroll_speed);
+    let curl = textureSampleLevel(noise_tex, noise_sampler, uvw, 0.).xyz;
+
+    let vel = particle.vel_mass.xyz + curl * turbulence.strength * em.delta_sec;
+    particle.vel_mass = vec4<f32>(vel, particle.vel_mass.w);
+
+    particles[index] = particle;
+}
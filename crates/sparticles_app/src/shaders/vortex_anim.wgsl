@@ -0,0 +1,52 @@
+// Includes declarations 
+struct Vortex {
+    center_x: f32,
+    center_y: f32,
+    center_z: f32,
+    angular_velocity: f32,
+    axis_x: f32,
+    axis_y: f32,
+    axis_z: f32,
+    radial_pull: f32,
+}
+
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> em: Emitter; 
+@group(1) @binding(0) var<uniform> vortex: Vortex; 
+
+@compute
+@workgroup_size(128)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let particle_len = arrayLength(&particles);
+    let index = global_invocation_id.x;
+
+    if particle_len <= index {
+        return;
+    }
+
+    var particle = particles[index];
+
+    if is_decayed(em, particle) {
+        return;
+    }
+
+    let center = vec3<f32>(vortex.center_x, vortex.center_y, vortex.center_z);
+    let axis = vec3<f32>(vortex.axis_x, vortex.axis_y, vortex.axis_z);
+
+    // Only the part perpendicular to the axis, so particles spin in a plane
+    let to_center = center - particle.model.w.xyz;
+    let to_axis = to_center - axis * dot(to_center, axis);
+    let radius = length(to_axis);
+
+    if radius < 0.0001 {
+        return;
+    }
+
+    let inward = to_axis / radius;
+    let tangent = cross(axis, inward);
+
+    let acceleration = tangent * vortex.angular_velocity * radius + inward * vortex.radial_pull;
+
+    particle.vel_mass += vec4<f32>(acceleration * em.delta_sec, 0.);
+    particles[index] = particle;
+}
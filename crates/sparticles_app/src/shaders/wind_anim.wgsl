@@ -0,0 +1,47 @@
+// Includes declarations 
+struct Wind {
+    dir_x: f32,
+    dir_y: f32,
+    dir_z: f32,
+    strength: f32,
+    turbulence: f32,
+    padding_1: f32,
+    padding_2: f32,
+    padding_3: f32,
+}
+
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> em: Emitter; 
+@group(1) @binding(0) var<uniform> wind: Wind; 
+
+// Random direction per particle and frame, each axis in -1..1
+fn jitter(index: u32) -> vec3<f32> {
+    let h_x = pcg_hash(index ^ pcg_hash(bitcast<u32>(em.elapsed_sec)));
+    let h_y = pcg_hash(h_x);
+    let h_z = pcg_hash(h_y);
+
+    return vec3<f32>(hash_to_unit(h_x), hash_to_unit(h_y), hash_to_unit(h_z)) * 2. - 1.;
+}
+
+@compute
+@workgroup_size(128)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let particle_len = arrayLength(&particles);
+    let index = global_invocation_id.x;
+
+    if particle_len <= index {
+        return;
+    }
+
+    var particle = particles[index];
+
+    if is_decayed(em, particle) {
+        return;
+    }
+
+    let direction = vec3<f32>(wind.dir_x, wind.dir_y, wind.dir_z);
+    let acceleration = direction * wind.strength + jitter(index) * wind.turbulence;
+
+    particle.vel_mass += vec4<f32>(acceleration * em.delta_sec, 0.);
+    particles[index] = particle;
+}
@@ -1,7 +1,12 @@
-use crate::{fx::PostProcessState, model::gfx_state::GfxState, traits::CreateFxView};
+use crate::{
+    loader::CubeLut,
+    model::gfx_state::GfxState,
+    traits::CreateFxView,
+    util::hash::{hash_to_unit, pcg_hash},
+};
 use async_std::sync::RwLock;
 use egui_wgpu::wgpu::{self, util::align_to};
-use glam::Vec4;
+use glam::{Vec3, Vec4};
 use image::GenericImageView;
 use rand::{rngs::ThreadRng, Rng};
 use std::{fs, sync::Arc};
@@ -121,21 +126,25 @@ impl GfxState {
         device.create_texture(&desc).default_view()
     }
 
+    /// Copy source so frames can be captured
+    pub fn create_fx_texture(&self) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: self.tex_size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            view_formats: &[],
+            dimension: wgpu::TextureDimension::D2,
+            format: self.fx_format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        })
+    }
+
     pub fn create_fx_view(&self) -> wgpu::TextureView {
-        self.device
-            .create_texture(&wgpu::TextureDescriptor {
-                label: None,
-                size: self.tex_size(),
-                mip_level_count: 1,
-                sample_count: 1,
-                view_formats: &[],
-                dimension: wgpu::TextureDimension::D2,
-                format: PostProcessState::TEXTURE_FORMAT,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                    | wgpu::TextureUsages::TEXTURE_BINDING
-                    | wgpu::TextureUsages::STORAGE_BINDING,
-            })
-            .default_view()
+        self.create_fx_texture().default_view()
     }
 
     pub fn create_mip_fx_view(&self, mip_level: u32) -> wgpu::TextureView {
@@ -147,7 +156,7 @@ impl GfxState {
                 sample_count: 1,
                 view_formats: &[],
                 dimension: wgpu::TextureDimension::D2,
-                format: PostProcessState::TEXTURE_FORMAT,
+                format: self.fx_format(),
                 usage: wgpu::TextureUsages::RENDER_ATTACHMENT
                     | wgpu::TextureUsages::TEXTURE_BINDING
                     | wgpu::TextureUsages::STORAGE_BINDING,
@@ -262,6 +271,155 @@ impl GfxState {
 
         texture.default_view()
     }
+
+    /// Creates a tileable 3D texture containing a divergence free (curl) noise field
+    pub fn create_curl_noise_view(&self, size: u32) -> wgpu::TextureView {
+        let device = &self.device;
+        let queue = &self.queue;
+
+        let potential_at = |x: u32, y: u32, z: u32| -> Vec3 {
+            let p = Vec3::new(x as f32, y as f32, z as f32) / size as f32 * NOISE_PERIOD as f32;
+
+            Vec3::new(
+                periodic_value_noise(p, 0),
+                periodic_value_noise(p, 1),
+                periodic_value_noise(p, 2),
+            )
+        };
+
+        let wrap = |v: u32, offset: i32| (v as i32 + offset).rem_euclid(size as i32) as u32;
+        let mut curls = Vec::with_capacity((size * size * size) as usize);
+        let mut max_len: f32 = f32::EPSILON;
+
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let dx = potential_at(wrap(x, 1), y, z) - potential_at(wrap(x, -1), y, z);
+                    let dy = potential_at(x, wrap(y, 1), z) - potential_at(x, wrap(y, -1), z);
+                    let dz = potential_at(x, y, wrap(z, 1)) - potential_at(x, y, wrap(z, -1));
+
+                    let curl = Vec3::new(dy.z - dz.y, dz.x - dx.z, dx.y - dy.x);
+                    max_len = max_len.max(curl.length());
+                    curls.push(curl);
+                }
+            }
+        }
+
+        let noise_data: Vec<i8> = curls
+            .iter()
+            .flat_map(|curl| {
+                let norm = *curl / max_len * 127.;
+                [norm.x as i8, norm.y as i8, norm.z as i8, 0]
+            })
+            .collect();
+
+        let size = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Curl noise texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            view_formats: &[],
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Snorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&noise_data),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+
+        texture.default_view()
+    }
+
+    /// Uploads a color lookup table as 3D texture, sampled with the input color as coordinate
+    pub fn create_lut_view(&self, lut: &CubeLut) -> wgpu::TextureView {
+        let device = &self.device;
+        let queue = &self.queue;
+
+        let lut_data: Vec<u8> = lut
+            .data
+            .iter()
+            .flat_map(|rgb| {
+                let [r, g, b] = rgb.map(|v| (v.clamp(0., 1.) * 255.).round() as u8);
+                [r, g, b, 255]
+            })
+            .collect();
+
+        let size = wgpu::Extent3d {
+            width: lut.size,
+            height: lut.size,
+            depth_or_array_layers: lut.size,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("LUT texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            view_formats: &[],
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        queue.write_texture(
+            texture.as_image_copy(),
+            &lut_data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+
+        texture.default_view()
+    }
+}
+
+/// Lattice cells per texture axis, noise repeats after this
+const NOISE_PERIOD: u32 = 4;
+
+fn lattice_hash(x: u32, y: u32, z: u32, seed: u32) -> f32 {
+    let h = pcg_hash(x ^ pcg_hash(y ^ pcg_hash(z ^ pcg_hash(seed))));
+
+    hash_to_unit(h) * 2. - 1.
+}
+
+fn periodic_value_noise(p: Vec3, seed: u32) -> f32 {
+    let cell = p.floor();
+    let t = p - cell;
+    let t = t * t * (Vec3::splat(3.) - 2. * t);
+
+    let corner = |ox: u32, oy: u32, oz: u32| {
+        let x = (cell.x as u32 + ox) % NOISE_PERIOD;
+        let y = (cell.y as u32 + oy) % NOISE_PERIOD;
+        let z = (cell.z as u32 + oz) % NOISE_PERIOD;
+
+        lattice_hash(x, y, z, seed)
+    };
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), t.x);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), t.x);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), t.x);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), t.x);
+
+    lerp(lerp(x00, x10, t.y), lerp(x01, x11, t.y), t.z)
 }
 
 impl CreateFxView for wgpu::Texture {
@@ -66,6 +66,12 @@ pub trait ParticleAnimation: HandleAction {
 
     fn recreate(&self, gfx_state: &GfxState, emitter: &EmitterState) -> Box<dyn ParticleAnimation>;
     fn update(&mut self, clock: &Clock, gfx: &GfxState);
+
+    /// Called after the particle buffers of the emitter were resized, for per particle buffers
+    fn resize(&mut self, _gfx_state: &GfxState, _emitter: &EmitterState) {}
+
+    /// Called after update, before the emitter uniform is uploaded
+    fn animate_emitter(&self, _uniform: &mut EmitterUniform) {}
 }
 
 pub trait WidgetBuilder {
@@ -84,14 +90,16 @@ pub trait WidgetBuilder {
     );
 }
 
-pub trait EmitterAnimation: HandleAction {
+/// Scenes with many emitters update the animations on the rayon pool, so they need to be Send.
+/// This broke animations holding Rc or RefCell state, use Arc and Mutex instead
+pub trait EmitterAnimation: HandleAction + Send {
     fn animate(&mut self, emitter: &mut EmitterUniform, clock: &Clock);
     fn as_any(&mut self) -> &mut dyn Any;
 }
 
 // Post FX
 pub trait PostFx: HandleAction {
-    fn update(&mut self, gfx_state: &GfxState, camera: &mut Camera);
+    fn update(&mut self, gfx_state: &GfxState, camera: &mut Camera, clock: &Clock);
 
     fn compute<'a>(
         &'a self,
@@ -102,6 +110,16 @@ pub trait PostFx: HandleAction {
 
     fn resize(&mut self, options: &FxOptions);
     fn as_any(&mut self) -> &mut dyn Any;
+    /// Used to restore a saved post fx chain
+    fn set_enabled(&mut self, enabled: bool);
+
+    /// Particles only render the bloom split target while an enabled effect reads it
+    fn reads_split_target(&self) -> bool {
+        false
+    }
+
+    /// Called after update, for fx that rebuild their passes when a setting changed
+    fn rebuild(&mut self, _options: &FxOptions) {}
 }
 
 pub trait RegisterPostFx {
@@ -132,7 +150,7 @@ pub trait HandleAngles {
 pub type OtherIterMut<'a, T> = std::iter::Chain<IterMut<'a, T>, IterMut<'a, T>>;
 
 pub trait Splitting<T: std::fmt::Debug> {
-    fn split_item_mut(&mut self, idx: usize) -> (&mut T, OtherIterMut<T>);
+    fn split_item_mut(&mut self, idx: usize) -> (&mut T, OtherIterMut<'_, T>);
 }
 
 pub trait BufferContent {
@@ -122,7 +122,7 @@ where
 }
 
 impl<T: std::fmt::Debug> Splitting<T> for Vec<T> {
-    fn split_item_mut(&mut self, idx: usize) -> (&mut T, OtherIterMut<T>) {
+    fn split_item_mut(&mut self, idx: usize) -> (&mut T, OtherIterMut<'_, T>) {
         assert!(idx < self.len());
 
         let (head, rest) = self.split_at_mut(idx);
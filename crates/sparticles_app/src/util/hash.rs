@@ -0,0 +1,60 @@
+//! CPU mirror of shaders/hash.wgsl, keep both in sync
+
+/// PCG hash (Jarzynski & Olano, "Hash Functions for GPU Rendering")
+pub fn pcg_hash(input: u32) -> u32 {
+    let state = input.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// Maps a hash to 0..1 (exclusive) using the top 24 bits, exact in f32
+pub fn hash_to_unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 / 16777216.
+}
+
+pub fn hash_seeded(input: f32, seed: f32) -> u32 {
+    pcg_hash(input.to_bits() ^ pcg_hash(seed.to_bits()))
+}
+
+/// Same value the shaders get from `random(input, elapsed_sec)`
+pub fn random(input: f32, elapsed_sec: f32) -> f32 {
+    hash_to_unit(hash_seeded(input, elapsed_sec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Computed from the shaders/hash.wgsl algorithm in u32 arithmetic
+    const PCG_0_TO_7: [u32; 8] = [
+        129708002, 2831084092, 2055130248, 2131687100, 678955108, 2161170183, 4048597412,
+        2120684060,
+    ];
+
+    const SEEDED_1_5: [u32; 8] = [
+        3702202045, 3813077250, 1321297433, 1176474611, 2382322522, 1603783065, 661589293,
+        3437914243,
+    ];
+
+    #[test]
+    fn pcg_hash_matches_shader() {
+        for (input, expected) in PCG_0_TO_7.into_iter().enumerate() {
+            assert_eq!(pcg_hash(input as u32), expected, "pcg_hash({})", input);
+        }
+    }
+
+    #[test]
+    fn random_matches_shader() {
+        for (input, expected) in SEEDED_1_5.into_iter().enumerate() {
+            let input = input as f32;
+
+            assert_eq!(
+                hash_seeded(input, 1.5),
+                expected,
+                "hash_seeded({}, 1.5)",
+                input
+            );
+            assert_eq!(random(input, 1.5), (expected >> 8) as f32 / 16777216.);
+        }
+    }
+}
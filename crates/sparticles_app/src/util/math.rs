@@ -2,6 +2,7 @@ use crate::traits::{CalculateBufferSize, HandleAngles};
 use egui_wgpu::wgpu;
 use glam::{Vec2, Vec3};
 use std::num::NonZeroU64;
+use std::ops::{Add, Mul, Sub};
 
 impl HandleAngles for Vec3 {
     fn to_degrees(&self) -> Self {
@@ -48,3 +49,18 @@ impl CalculateBufferSize for [f32] {
         wgpu::BufferSize::new(self.len() as u64 * 4)
     }
 }
+
+/// Uniform Catmull-Rom spline between p1 and p2
+pub fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    (p1 * 2.
+        + (p2 - p0) * t
+        + (p0 * 2. - p1 * 5. + p2 * 4. - p3) * t2
+        + (p1 * 3. - p0 - p2 * 3. + p3) * t3)
+        * 0.5
+}
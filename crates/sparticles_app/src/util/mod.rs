@@ -1,6 +1,9 @@
 pub mod common;
+pub mod hash;
 pub mod math;
 pub mod persistence;
 
 pub use common::{ListAction, Tag, UniformContext, ID};
-pub use persistence::{DynamicExport, ExportEmitter, ExportType, Persistence};
+pub use persistence::{
+    DynamicExport, ExportCamera, ExportEmitter, ExportPostFx, ExportType, Persistence, WindowConfig,
+};
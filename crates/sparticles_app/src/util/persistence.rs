@@ -1,4 +1,5 @@
 use crate::model::EmitterUniform;
+use glam::Vec3;
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::Display,
@@ -28,9 +29,45 @@ pub struct DynamicExport {
     pub data: serde_json::Value,
 }
 
+/// One effect of the post fx chain, the chain is saved in render order
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportPostFx {
+    #[serde(flatten)]
+    pub fx: DynamicExport,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Camera placement and projection, saved with the scene
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ExportCamera {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Vertical field of view in radians
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+/// Window size and position of the last session
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
 pub enum ExportType {
     PostFx,
     EmitterStates,
+    Window,
+    Camera,
 }
 
 impl Display for ExportType {
@@ -38,6 +75,8 @@ impl Display for ExportType {
         match self {
             ExportType::PostFx => f.write_str("post_fx.json"),
             ExportType::EmitterStates => f.write_str("emitters.json"),
+            ExportType::Window => f.write_str("window.json"),
+            ExportType::Camera => f.write_str("camera.json"),
         }
     }
 }
@@ -54,7 +93,7 @@ impl Persistence {
         serde_json::to_writer(&mut writer, &to_export).expect("Can't write export");
     }
 
-    pub fn import_post_fx() -> Result<Vec<DynamicExport>, ImportError> {
+    pub fn import_post_fx() -> Result<Vec<ExportPostFx>, ImportError> {
         let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         dir.push(format!("export/{}", ExportType::PostFx));
 
@@ -64,7 +103,7 @@ impl Persistence {
         let error_msg;
 
         match file_str {
-            Ok(file_str) => match serde_json::from_str::<Vec<DynamicExport>>(&file_str) {
+            Ok(file_str) => match serde_json::from_str::<Vec<ExportPostFx>>(&file_str) {
                 Ok(val) => return Ok(val),
                 Err(err) => {
                     let filename = dir.file_name().unwrap().to_str().unwrap();
@@ -79,6 +118,40 @@ impl Persistence {
         Err(ImportError { msg: error_msg })
     }
 
+    pub fn import_window_config() -> Result<WindowConfig, ImportError> {
+        let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        dir.push(format!("export/{}", ExportType::Window));
+
+        let path = dir.to_str().expect("Path is not correct");
+
+        let error_msg = match fs::read_to_string(path) {
+            Ok(file_str) => match serde_json::from_str::<WindowConfig>(&file_str) {
+                Ok(val) => return Ok(val),
+                Err(err) => format!("Wrong syntaxed JSON for window config: {}", err),
+            },
+            Err(err) => format!("No window config: {}", err),
+        };
+
+        Err(ImportError { msg: error_msg })
+    }
+
+    pub fn import_camera() -> Result<ExportCamera, ImportError> {
+        let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        dir.push(format!("export/{}", ExportType::Camera));
+
+        let path = dir.to_str().expect("Path is not correct");
+
+        let error_msg = match fs::read_to_string(path) {
+            Ok(file_str) => match serde_json::from_str::<ExportCamera>(&file_str) {
+                Ok(val) => return Ok(val),
+                Err(err) => format!("Wrong syntaxed JSON for camera: {}", err),
+            },
+            Err(err) => format!("No camera export: {}", err),
+        };
+
+        Err(ImportError { msg: error_msg })
+    }
+
     pub fn import_emitter_states(path: PathBuf) -> Result<Vec<ExportEmitter>, ImportError> {
         let file_str = fs::read_to_string(path.to_str().expect("Export path is not correct"));
 
@@ -102,6 +175,13 @@ impl Persistence {
         })
     }
 
+    pub fn texture_path(filename: &str) -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("src/assets/textures");
+        path.push(filename);
+        path
+    }
+
     pub fn import_textures() -> Result<Vec<PathBuf>, io::Error> {
         let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         dir.push("src/assets/textures");
@@ -0,0 +1,602 @@
+//! Runs the simulation and post fx without a window. Skipped when there is no GPU adapter
+use async_std::task;
+use sparticles_app::animations::{RegisterSpringAnimation, SpringAnimation, SpringUniform};
+use sparticles_app::fx::{bloom::RegisterBloomFx, RegisterColorFx};
+use sparticles_app::glam::{Mat4, Vec4};
+use sparticles_app::gui::winit::event::KeyboardInput;
+use sparticles_app::init::{AppVisitor, DataSource};
+use sparticles_app::loader::BUILTIN_ID;
+use sparticles_app::model::emitter_state::FsEntryPoint;
+use sparticles_app::model::{
+    BudgetPolicy, ClockMode, EmitterEvent, EmitterUniform, GfxState, Mesh, MeshRef, ModelVertex,
+    ParticleBudget, SparEvents, SparState, SpawnMode, SpawnShape,
+};
+use sparticles_app::traits::{PostFx, RegisterPostFx};
+use sparticles_app::util::hash;
+use sparticles_app::{fx::FxOptions, wgpu};
+use wgpu::util::DeviceExt;
+
+/// Floats per particle, see shaders/particle_layout.wgsl
+const PARTICLE_FLOATS: usize = 28;
+const POSITION: usize = 12;
+const COLOR: usize = 16;
+const AGE: usize = 25;
+const MAX_LIFE: usize = 26;
+
+const EMITTER_ID: &str = "Test";
+const DT: f32 = 0.25;
+
+struct TestApp {
+    emitter: EmitterUniform,
+    post_fx: bool,
+    budget: ParticleBudget,
+}
+
+impl TestApp {
+    /// 6 particles every 0.5 seconds that live 6 seconds, so 12 batches and 72 particles
+    fn new(post_fx: bool) -> Self {
+        let mut emitter = EmitterUniform::new(EMITTER_ID.to_string());
+        emitter.spawn_count = 6;
+        emitter.spawn_delay_sec = 0.5;
+
+        Self {
+            emitter,
+            post_fx,
+            budget: ParticleBudget::default(),
+        }
+    }
+}
+
+impl AppVisitor for TestApp {
+    fn data_source(&self) -> DataSource {
+        let mut lights = EmitterUniform::new("Lights".to_string());
+        lights.spawn_count = 1;
+
+        DataSource::Code {
+            lights: Box::new(lights),
+            emitters: vec![self.emitter.clone()],
+        }
+    }
+
+    fn process_events(&mut self, _: &mut SparEvents, _: &KeyboardInput, _: bool) {}
+
+    fn particle_budget(&self) -> ParticleBudget {
+        self.budget
+    }
+
+    fn add_post_fx(&self, options: &FxOptions, effects: &mut Vec<Box<dyn PostFx>>) {
+        if self.post_fx {
+            effects.push(RegisterBloomFx.create_default(options));
+            effects.push(RegisterColorFx.create_default(options));
+        }
+    }
+}
+
+/// Same adapter as `GfxState::new_headless` requests, with the features and limits it needs
+fn has_adapter() -> bool {
+    let instance = wgpu::Instance::default();
+    let adapter = task::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        compatible_surface: None,
+        force_fallback_adapter: false,
+    }));
+
+    let supported = adapter.is_some_and(|adapter| {
+        adapter.features().contains(GfxState::required_features())
+            && GfxState::required_limits().check_limits(&adapter.limits())
+    });
+
+    if !supported {
+        println!("No GPU adapter that can run sparticles, skipping the headless test");
+    }
+
+    supported
+}
+
+/// Copies the latest particles of the emitter back to the CPU
+fn read_particles(state: &SparState, id: &str) -> Vec<Vec<f32>> {
+    let emitter = state.emitter(id).expect("Emitter exists");
+    let particle_buffer = emitter.particle_buffer(emitter.bindgroup_nr());
+
+    state.with_gfx(|gfx| {
+        let read_buffer = gfx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle read buffer"),
+            size: particle_buffer.size(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = gfx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Particle read encoder"),
+            });
+
+        encoder.copy_buffer_to_buffer(particle_buffer, 0, &read_buffer, 0, particle_buffer.size());
+        gfx.queue.submit(Some(encoder.finish()));
+
+        let slice = read_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |res| {
+            res.expect("Maps particle buffer")
+        });
+        gfx.device.poll(wgpu::Maintain::Wait);
+
+        let particles = bytemuck::cast_slice::<u8, f32>(&slice.get_mapped_range())
+            .chunks_exact(PARTICLE_FLOATS)
+            .map(|particle| particle.to_vec())
+            .collect();
+
+        read_buffer.unmap();
+        particles
+    })
+}
+
+/// Runs pcg_hash on `seeds` and hash_seeded on 0..seeds.len() with seed 1.5 in shaders/hash.wgsl
+fn gpu_hashes(state: &SparState, seeds: &[u32]) -> Vec<[u32; 2]> {
+    let shader_str = format!(
+        "{}{}",
+        include_str!("../src/shaders/hash.wgsl"),
+        r#"
+        @group(0) @binding(0) var<storage, read> seeds: array<u32>;
+        @group(0) @binding(1) var<storage, read_write> hashes: array<vec2<u32>>;
+
+        @compute
+        @workgroup_size(64)
+        fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+            let i = global_id.x;
+
+            if arrayLength(&seeds) <= i {
+                return;
+            }
+
+            hashes[i] = vec2(pcg_hash(seeds[i]), hash_seeded(f32(i), 1.5));
+        }
+        "#
+    );
+
+    state.with_gfx(|gfx| {
+        let device = &gfx.device;
+        let size = (seeds.len() * 8) as u64;
+
+        let seed_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Hash seeds"),
+            contents: bytemuck::cast_slice(seeds),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let hash_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Hashes"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let read_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Hash read buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Hash parity"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Hash parity pipeline"),
+            layout: None,
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Hash parity bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: seed_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: hash_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Hash parity encoder"),
+        });
+
+        {
+            let mut c_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Hash parity"),
+                timestamp_writes: None,
+            });
+
+            c_pass.set_pipeline(&pipeline);
+            c_pass.set_bind_group(0, &bg, &[]);
+            c_pass.dispatch_workgroups((seeds.len() as u32).div_ceil(64), 1, 1);
+        }
+
+        encoder.copy_buffer_to_buffer(&hash_buffer, 0, &read_buffer, 0, size);
+        gfx.queue.submit(Some(encoder.finish()));
+
+        let slice = read_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |res| res.expect("Maps hash buffer"));
+        device.poll(wgpu::Maintain::Wait);
+
+        let hashes = bytemuck::cast_slice::<u8, [u32; 2]>(&slice.get_mapped_range()).to_vec();
+
+        read_buffer.unmap();
+        hashes
+    })
+}
+
+/// Unspawned particles are zeroed, so they don't have a lifetime yet
+fn is_alive(particle: &[f32]) -> bool {
+    0. < particle[MAX_LIFE] && 0. <= particle[AGE] && particle[AGE] <= particle[MAX_LIFE]
+}
+
+#[test]
+fn simulates_particles() {
+    if !has_adapter() {
+        return;
+    }
+
+    let mut app = TestApp::new(false);
+    let mut state = SparState::new_headless_blocking(&mut app, 64, 64);
+
+    for _ in 0..8 {
+        state.step_blocking(DT);
+    }
+
+    let particles = read_particles(&state, EMITTER_ID);
+    let alive: Vec<_> = particles.iter().filter(|p| is_alive(p)).collect();
+
+    assert_eq!(particles.len(), 72);
+    assert!(!alive.is_empty(), "No particles spawned");
+
+    for particle in alive {
+        let position = &particle[POSITION..POSITION + 3];
+
+        assert!(particle.iter().all(|v| v.is_finite()), "{:?}", particle);
+        assert_eq!(particle[MAX_LIFE], 6.);
+        assert!(particle[AGE] <= 8. * DT, "Older than the simulated time");
+        assert!(position.iter().any(|v| *v != 0.), "Spawned at the origin");
+    }
+}
+
+#[test]
+fn emits_particles_over_time() {
+    if !has_adapter() {
+        return;
+    }
+
+    let mut app = TestApp::new(false);
+    let mut state = SparState::new_headless_blocking(&mut app, 64, 64);
+
+    // Past the lifetime, so the ring wraps and old batches get replaced
+    for step in 1..=40 {
+        state.step_blocking(DT);
+
+        let elapsed_sec = step as f32 * DT;
+        let batches = (elapsed_sec / 0.5) as usize + 1;
+        let expected = (batches * 6).min(72);
+
+        let particles = read_particles(&state, EMITTER_ID);
+        let alive = particles.iter().filter(|p| is_alive(p)).count();
+
+        assert_eq!(alive, expected, "Alive particles at {} sec", elapsed_sec);
+    }
+}
+
+#[test]
+fn spring_on_empty_emitter() {
+    if !has_adapter() {
+        return;
+    }
+
+    let mut app = TestApp::new(false);
+    app.emitter.spawn_count = 0;
+
+    let mut state = SparState::new_headless_blocking(&mut app, 64, 64);
+    let gfx = state.gfx.clone();
+    let emitter = state.emitter_mut(EMITTER_ID).expect("Emitter exists");
+
+    assert_eq!(emitter.particle_count(), 0);
+
+    // Zero sized storage bindings fail validation, which panics
+    task::block_on(async {
+        let gfx = gfx.read().await;
+        RegisterSpringAnimation::append(SpringUniform::default(), vec![], emitter, &gfx);
+    });
+
+    for _ in 0..4 {
+        state.step_blocking(DT);
+    }
+}
+
+#[test]
+fn spring_targets_follow_resize() {
+    if !has_adapter() {
+        return;
+    }
+
+    let mut app = TestApp::new(false);
+    let mut state = SparState::new_headless_blocking(&mut app, 64, 64);
+    let SparState {
+        emitters,
+        gfx,
+        camera,
+        ..
+    } = &mut state;
+
+    let gfx = task::block_on(gfx.read());
+    let emitter = emitters
+        .iter_mut()
+        .find(|em| em.id() == EMITTER_ID)
+        .expect("Emitter exists");
+
+    RegisterSpringAnimation::append(SpringUniform::default(), vec![], emitter, &gfx);
+
+    let mut encoder = gfx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Resize encoder"),
+        });
+
+    emitter.resize_particle_count(144, &gfx, camera, &mut encoder);
+    gfx.queue.submit(Some(encoder.finish()));
+
+    let particle_count = emitter.particle_count();
+    let spring = emitter.particle_animations[0]
+        .as_any()
+        .downcast_mut::<SpringAnimation>()
+        .expect("Spring animation");
+
+    assert_eq!(particle_count, 144);
+    assert_eq!(spring.particle_count, 144);
+    assert_eq!(spring.targets_buffer.size(), 144 * 16);
+}
+
+#[test]
+fn renders_post_fx() {
+    if !has_adapter() {
+        return;
+    }
+
+    let path = std::env::temp_dir().join("sparticles_headless_post_fx.png");
+    let _ = std::fs::remove_file(&path);
+
+    let mut app = TestApp::new(true);
+    let mut state = SparState::new_headless_blocking(&mut app, 64, 64);
+    let mut events = SparEvents::default();
+
+    state.clock.set_mode(ClockMode::Fixed { dt: DT });
+
+    for frame in 0..4 {
+        if frame == 3 {
+            state.post_process.capture_frame(&path);
+        }
+
+        state.update_blocking(&events);
+        events = state.render_blocking(&mut app);
+    }
+
+    let img = image::open(&path)
+        .expect("Post fx frame is captured")
+        .to_rgba8();
+
+    assert_eq!(img.dimensions(), (64, 64));
+}
+
+#[test]
+fn budget_applies_to_initial_emitters() {
+    if !has_adapter() {
+        return;
+    }
+
+    let mut app = TestApp::new(false);
+    app.budget = ParticleBudget {
+        max_particles: 48,
+        policy: BudgetPolicy::ScaleDown,
+    };
+
+    let state = SparState::new_headless_blocking(&mut app, 64, 64);
+    let emitter = state.emitter(EMITTER_ID).expect("Scaled down to fit");
+
+    assert!(emitter.particle_count() < 72);
+    assert!(state.particle_usage() <= 48);
+
+    app.budget.policy = BudgetPolicy::Reject;
+
+    let state = SparState::new_headless_blocking(&mut app, 64, 64);
+
+    assert!(state.emitter(EMITTER_ID).is_none(), "Exceeds the budget");
+}
+
+#[test]
+fn finishes_when_burst_particles_decayed() {
+    if !has_adapter() {
+        return;
+    }
+
+    let mut app = TestApp::new(false);
+    app.emitter.particle_lifetime_sec = 1.;
+    app.emitter.spawn_mode = SpawnMode::Burst {
+        bursts: vec![(0.5, 10)],
+        catch_up: false,
+    };
+
+    let mut state = SparState::new_headless_blocking(&mut app, 64, 64);
+    let mut finished_steps = vec![];
+
+    for step in 1..=24 {
+        state.step_blocking(DT);
+
+        let finished = EmitterEvent::Finished(EMITTER_ID.to_string());
+
+        if state.drain_emitter_events().contains(&finished) {
+            finished_steps.push(step);
+        }
+    }
+
+    // The burst decays at 1.5 seconds, the readback trails the simulation by a few steps
+    assert_eq!(finished_steps.len(), 1, "{:?}", finished_steps);
+    assert!(1.5 <= finished_steps[0] as f32 * DT, "{:?}", finished_steps);
+}
+
+#[test]
+fn color_mask_spans_mesh_bounds() {
+    if !has_adapter() {
+        return;
+    }
+
+    // Left texel white, right texel black
+    let mask_path = std::env::temp_dir().join("sparticles_mesh_mask.png");
+    image::RgbaImage::from_fn(2, 1, |x, _| match x {
+        0 => image::Rgba([255, 255, 255, 255]),
+        _ => image::Rgba([0, 0, 0, 255]),
+    })
+    .save(&mask_path)
+    .expect("Writes the mask");
+
+    let mut app = TestApp::new(false);
+    let mut state = SparState::new_headless_blocking(&mut app, 64, 64);
+
+    // Both vertices are right of the unit cube, raw positions would sample the black texel
+    let mesh = state.with_gfx(|gfx| {
+        let create_buffer = |usage| {
+            gfx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Mask mesh"),
+                size: 256,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+
+        Mesh {
+            vertices: [2., 4.]
+                .map(|x| ModelVertex {
+                    position: [x, 0., 0.],
+                    uv: [0.; 2],
+                    normal: [0., 0., 1.],
+                    tangent: [0.; 3],
+                    bitangent: [0.; 3],
+                })
+                .to_vec(),
+            indices: vec![],
+            vertex_buffer: create_buffer(wgpu::BufferUsages::VERTEX),
+            index_buffer: create_buffer(wgpu::BufferUsages::INDEX),
+            model: Mat4::IDENTITY,
+            fs_entry_point: FsEntryPoint::Model,
+        }
+    });
+
+    task::block_on(state.collection.write())
+        .get_mut(BUILTIN_ID)
+        .expect("Builtin collection")
+        .meshes
+        .insert("Mask mesh".to_string(), mesh);
+
+    let mut emitter = EmitterUniform::new("Mask".to_string());
+    emitter.spawn_shape = SpawnShape::MeshVertices {
+        mesh: MeshRef {
+            collection_id: BUILTIN_ID.to_string(),
+            mesh_id: "Mask mesh".to_string(),
+        },
+    };
+    emitter.color_mask = Some(mask_path.to_string_lossy().to_string());
+    emitter.particle_color = Vec4::ONE;
+    emitter.spawn_count = 2;
+
+    state
+        .add_emitter_blocking(emitter)
+        .expect("Fits in the particle budget");
+
+    for _ in 0..2 {
+        state.step_blocking(DT);
+    }
+
+    let particles = read_particles(&state, "Mask");
+    let alive: Vec<_> = particles
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| is_alive(p))
+        .collect();
+
+    assert!(!alive.is_empty(), "No particles spawned");
+
+    // Even particles spawn on the left vertex
+    for (i, particle) in alive {
+        let expected = if i % 2 == 0 { 1. } else { 0. };
+        let color = &particle[COLOR..COLOR + 3];
+
+        assert!(
+            color.iter().all(|c| (c - expected).abs() < 0.01),
+            "Particle {} has color {:?}",
+            i,
+            color
+        );
+    }
+}
+
+#[test]
+fn merges_draws_of_identical_emitters() {
+    if !has_adapter() {
+        return;
+    }
+
+    let mut app = TestApp::new(false);
+    let mut state = SparState::new_headless_blocking(&mut app, 64, 64);
+    let mut events = SparEvents::default();
+
+    for i in 1..50 {
+        let uniform = EmitterUniform::new(format!("{} {}", EMITTER_ID, i));
+        state
+            .add_emitter_blocking(uniform)
+            .expect("Fits in the particle budget");
+    }
+
+    state.clock.set_mode(ClockMode::Fixed { dt: DT });
+
+    for _ in 0..2 {
+        state.update_blocking(&events);
+        events = state.render_blocking(&mut app);
+    }
+
+    // The lights draw alone, the 50 emitters share one draw
+    let stats = state.draw_stats;
+    assert_eq!(stats.emitter_draws, 51);
+    assert_eq!(stats.draw_calls, 2);
+    assert_eq!(stats.merged_draws, 1);
+}
+
+#[test]
+fn hash_matches_cpu_mirror() {
+    if !has_adapter() {
+        return;
+    }
+
+    let mut app = TestApp::new(false);
+    let state = SparState::new_headless_blocking(&mut app, 8, 8);
+
+    // Small seeds, the high bit and spread out ones overflowing the multiplications
+    let mut seeds: Vec<u32> = (0..256).collect();
+    seeds.extend((0..256u32).map(|i| i.wrapping_mul(2654435761)));
+    seeds.extend([u32::MAX, u32::MAX - 1, 1 << 31, 0x7fff_ffff]);
+
+    let hashes = gpu_hashes(&state, &seeds);
+
+    for (i, (seed, [gpu_pcg, gpu_seeded])) in seeds.iter().zip(hashes).enumerate() {
+        assert_eq!(gpu_pcg, hash::pcg_hash(*seed), "pcg_hash({})", seed);
+        assert_eq!(
+            gpu_seeded,
+            hash::hash_seeded(i as f32, 1.5),
+            "hash_seeded({}, 1.5)",
+            i
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use sparticles_app::{
-    animations::{DiffusionAnimation, SwayAnimation},
-    gui::egui::{DragValue, Ui},
+    animations::{DiffusionAnimation, PathAnimation, PulseSpawnAnimation, SwayAnimation},
+    gui::egui::{DragValue, Slider, Ui},
+    model::LifeCycleMode,
     traits::{EmitterAnimation, HandleAngles},
 };
 
@@ -32,6 +33,9 @@ impl EditorWidgets {
             ui.horizontal(|ui| {
                 ui.label("Until restart animation");
                 ui.add(DragValue::new(&mut life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut life_cycle.mode, mode, mode.to_string());
+                }
             });
 
             ui.horizontal(|ui| {
@@ -90,6 +94,9 @@ impl EditorWidgets {
             ui.horizontal(|ui| {
                 ui.label("Until restart animation");
                 ui.add(DragValue::new(&mut life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut life_cycle.mode, mode, mode.to_string());
+                }
             });
 
             ui.horizontal(|ui| {
@@ -110,4 +117,97 @@ impl EditorWidgets {
             anim.diff_depth = gui.diff_depth.to_radians();
         }
     }
+
+    pub fn pulse_spawn_anim(
+        editor: &mut EditorData,
+        anim: &mut Box<dyn EmitterAnimation>,
+        ui: &mut Ui,
+    ) {
+        let downcast = anim.as_any().downcast_mut::<PulseSpawnAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Pulse spawn animation");
+
+            ui.horizontal(|ui| {
+                ui.label("Base rate / amplitude (particles per sec)");
+                ui.add(
+                    DragValue::new(&mut anim.base_rate)
+                        .speed(0.5)
+                        .clamp_range(0.0..=f32::MAX),
+                );
+                ui.add(DragValue::new(&mut anim.amplitude).speed(0.5));
+            });
+
+            ui.spacing_mut().slider_width = 200.0;
+
+            ui.add(Slider::new(&mut anim.frequency_hz, 0.0..=10.0).text("Frequency hz"));
+            ui.add(Slider::new(&mut anim.phase, 0.0..=std::f32::consts::TAU).text("Phase"));
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+        }
+    }
+
+    pub fn path_anim(editor: &mut EditorData, anim: &mut Box<dyn EmitterAnimation>, ui: &mut Ui) {
+        let downcast = anim.as_any().downcast_mut::<PathAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Path animation");
+            let life_cycle = &mut anim.life_cycle;
+
+            ui.horizontal(|ui| {
+                ui.label("Animate from sec");
+                ui.add(DragValue::new(&mut life_cycle.from_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Animate until sec");
+                ui.add(
+                    DragValue::new(&mut life_cycle.until_sec)
+                        .speed(0.1)
+                        .clamp_range(life_cycle.from_sec..=life_cycle.lifetime_sec),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Until restart animation");
+                ui.add(DragValue::new(&mut life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut life_cycle.mode, mode, mode.to_string());
+                }
+            });
+
+            let mut remove_idx = None;
+
+            for (i, point) in anim.control_points.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Point {} > ", i + 1));
+                    ui.label("x:");
+                    ui.add(DragValue::new(&mut point.x).speed(0.1));
+                    ui.label("y:");
+                    ui.add(DragValue::new(&mut point.y).speed(0.1));
+                    ui.label("z:");
+                    ui.add(DragValue::new(&mut point.z).speed(0.1));
+
+                    if ui.button("Remove").clicked() {
+                        remove_idx = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = remove_idx {
+                anim.control_points.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Add point").clicked() {
+                    let last = anim.control_points.last().copied().unwrap_or_default();
+                    anim.control_points.push(last);
+                }
+
+                ui.checkbox(&mut anim.looped, "Loop path");
+            });
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+        }
+    }
 }
@@ -3,9 +3,22 @@ use sparticles_app::{
         bloom::UIAction,
         blur::{BlurEvent, BlurFx, BlurType},
         color::UpdateAction,
-        BloomFx, ColorFx,
+        dof::DofEvent,
+        feedback::FeedbackEvent,
+        kuwahara::KuwaharaEvent,
+        lut::LutEvent,
+        motion_blur::MotionBlurEvent,
+        outline::OutlineEvent,
+        radial_blur::{RadialBlurEvent, RadialBlurUniform},
+        vignette::VignetteEvent,
+        BloomFx, ColorFx, DepthOfFieldFx, FeedbackFx, FilmGrainFx, KuwaharaFx, LutGradingFx,
+        MotionBlurFx, OutlineFx, RadialBlurFx, VignetteFx,
+    },
+    gui::egui::{
+        self,
+        color_picker::{color_edit_button_rgba, Alpha},
+        Rgba, Slider, Ui,
     },
-    gui::egui::{self, Slider, Ui},
     model::TonemapType,
     traits::PostFx,
 };
@@ -21,6 +34,7 @@ impl EditorWidgets {
             ui.add_space(5.0);
 
             ui.add(Slider::new(&mut bloom.bloom_treshold, 0.0..=10.0).text("Brightness treshold"));
+            ui.add(Slider::new(&mut bloom.pass_count, 1..=BloomFx::MAX_PASSES).text("Blur passes"));
 
             for (i, up) in bloom.upscale_passes.iter_mut().enumerate() {
                 let io_uniform = up.blend.io();
@@ -42,7 +56,7 @@ impl EditorWidgets {
             if ui
                 .add(
                     Slider::new(&mut bloom.blend_uniform.io_mix, 0.0..=1.0)
-                        .text("IO mix bloom to frame"),
+                        .text("Intensity (IO mix bloom to frame)"),
                 )
                 .changed()
             {
@@ -53,33 +67,10 @@ impl EditorWidgets {
 
             Self::gamma_widget(&mut bloom.color, ui);
 
-            let color_uniform = &mut bloom.color.color_uniform;
-
             ui.add_space(6.);
 
-            ui.horizontal_top(|ui| {
-                egui::ComboBox::from_label("tonemapping")
-                    .selected_text(TonemapType::from(color_uniform.tonemap))
-                    .show_ui(ui, |ui| {
-                        let mut tonemap_option = |tonemap_type: TonemapType| {
-                            if ui
-                                .selectable_value(
-                                    &mut color_uniform.tonemap,
-                                    tonemap_type.into(),
-                                    tonemap_type,
-                                )
-                                .changed()
-                            {
-                                bloom.color.update_event = Some(UpdateAction::UpdateBuffer);
-                            }
-                        };
-
-                        tonemap_option(TonemapType::AcesNarkowicz);
-                        tonemap_option(TonemapType::AcesHill);
-                        tonemap_option(TonemapType::Uchimura);
-                        tonemap_option(TonemapType::Lottes);
-                    });
-            });
+            Self::tonemap_widget(&mut bloom.color, ui);
+            Self::tonemap_shape_widget(&mut bloom.color, ui);
 
             ui.add_space(6.);
 
@@ -96,6 +87,44 @@ impl EditorWidgets {
         }
     }
 
+    pub fn tonemap_widget(color_fx: &mut ColorFx, ui: &mut Ui) {
+        let color_uniform = &mut color_fx.color_uniform;
+        let mut changed = false;
+
+        ui.horizontal_top(|ui| {
+            egui::ComboBox::from_label("tonemapping")
+                .selected_text(TonemapType::from(color_uniform.tonemap))
+                .show_ui(ui, |ui| {
+                    for tonemap_type in TonemapType::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut color_uniform.tonemap,
+                                tonemap_type.into(),
+                                tonemap_type,
+                            )
+                            .changed();
+                    }
+                });
+        });
+
+        if changed {
+            color_fx.update_event = Some(UpdateAction::UpdateBuffer)
+        }
+    }
+
+    pub fn tonemap_shape_widget(color_fx: &mut ColorFx, ui: &mut Ui) {
+        let color_uniform = &mut color_fx.color_uniform;
+
+        let a = ui.add(Slider::new(&mut color_uniform.white_point, 1.0..=20.0).text("White point"));
+        let b = ui.add(
+            Slider::new(&mut color_uniform.tonemap_contrast, 0.5..=2.0).text("Tonemap contrast"),
+        );
+
+        if a.changed() || b.changed() {
+            color_fx.update_event = Some(UpdateAction::UpdateBuffer)
+        }
+    }
+
     pub fn blur_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
         let downcast = post_fx.as_any().downcast_mut::<BlurFx>();
 
@@ -147,6 +176,8 @@ impl EditorWidgets {
             post_fx.selected_action = editor.create_li_header(ui, "Color correction");
 
             Self::gamma_widget(post_fx, ui);
+            Self::tonemap_widget(post_fx, ui);
+            Self::tonemap_shape_widget(post_fx, ui);
 
             ui.add(Slider::new(&mut post_fx.color_uniform.contrast, 0.1..=4.0).text("Contrast"))
                 .changed()
@@ -161,4 +192,210 @@ impl EditorWidgets {
             ui.checkbox(&mut post_fx.enabled, "Enabled");
         }
     }
+
+    pub fn feedback_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<FeedbackFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "Feedback");
+
+            ui.add(Slider::new(&mut post_fx.feedback_uniform.decay, 0.0..=0.99).text("Decay"))
+                .changed()
+                .then(|| post_fx.update_event = Some(FeedbackEvent::UpdateUniform));
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
+
+    pub fn motion_blur_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<MotionBlurFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "Motion blur");
+
+            ui.add(Slider::new(&mut post_fx.motion_uniform.blend, 0.0..=0.95).text("Blend"))
+                .changed()
+                .then(|| post_fx.update_event = Some(MotionBlurEvent::UpdateUniform));
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
+
+    pub fn kuwahara_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<KuwaharaFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "Kuwahara");
+
+            ui.add(Slider::new(&mut post_fx.kuwahara_uniform.radius, 1..=8).text("Radius"))
+                .changed()
+                .then(|| post_fx.update_event = Some(KuwaharaEvent::UpdateUniform));
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
+
+    pub fn outline_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<OutlineFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "Outline");
+
+            let uniform = &mut post_fx.outline_uniform;
+            let mut changed = false;
+
+            ui.horizontal(|ui| {
+                let col = uniform.color;
+                let mut outline_color = Rgba::from_rgba_unmultiplied(col.x, col.y, col.z, col.w);
+
+                if color_edit_button_rgba(ui, &mut outline_color, Alpha::OnlyBlend).changed() {
+                    uniform.color = outline_color.to_rgba_unmultiplied().into();
+                    changed = true;
+                }
+
+                ui.label("Outline color");
+            });
+
+            changed |= ui
+                .add(Slider::new(&mut uniform.thickness, 1.0..=8.0).text("Thickness"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut uniform.depth_threshold, 0.001..=1.0).text("Depth threshold"))
+                .changed();
+            changed |= ui
+                .add(
+                    Slider::new(&mut uniform.normal_threshold, 0.001..=1.0)
+                        .text("Normal threshold"),
+                )
+                .changed();
+
+            changed.then(|| post_fx.update_event = Some(OutlineEvent::UpdateUniform));
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
+
+    pub fn vignette_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<VignetteFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "Vignette");
+
+            let uniform = &mut post_fx.vignette_uniform;
+            let mut changed = false;
+
+            changed |= ui
+                .add(Slider::new(&mut uniform.radius, 0.0..=2.0).text("Radius"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut uniform.softness, 0.0..=2.0).text("Softness"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut uniform.intensity, 0.0..=1.0).text("Intensity"))
+                .changed();
+
+            changed.then(|| post_fx.update_event = Some(VignetteEvent::UpdateUniform));
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
+
+    pub fn dof_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<DepthOfFieldFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "Depth of field");
+
+            let uniform = &mut post_fx.dof_uniform;
+            let mut changed = false;
+
+            changed |= ui
+                .add(Slider::new(&mut uniform.focus_distance, 0.1..=200.0).text("Focus distance"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut uniform.focus_range, 0.1..=100.0).text("Focus range"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut uniform.max_blur, 1.0..=16.0).text("Max blur"))
+                .changed();
+
+            changed.then(|| post_fx.update_event = Some(DofEvent::UpdateUniform));
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
+
+    pub fn radial_blur_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<RadialBlurFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "Radial blur");
+
+            let uniform = &mut post_fx.radial_uniform;
+            let mut changed = false;
+
+            changed |= ui
+                .add(Slider::new(&mut uniform.center.x, 0.0..=1.0).text("Center x"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut uniform.center.y, 0.0..=1.0).text("Center y"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut uniform.strength, 0.0..=1.0).text("Strength"))
+                .changed();
+            changed |= ui
+                .add(
+                    Slider::new(&mut uniform.samples, 1..=RadialBlurUniform::MAX_SAMPLES)
+                        .text("Samples"),
+                )
+                .changed();
+
+            changed.then(|| post_fx.update_event = Some(RadialBlurEvent::UpdateUniform));
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
+
+    pub fn lut_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<LutGradingFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "LUT grading");
+
+            ui.add(Slider::new(&mut post_fx.lut_uniform.strength, 0.0..=1.0).text("Strength"))
+                .changed()
+                .then(|| post_fx.update_event = Some(LutEvent::UpdateUniform));
+
+            ui.horizontal(|ui| {
+                ui.label("LUT file");
+                ui.text_edit_singleline(&mut post_fx.lut_path);
+
+                if ui.button("Load").clicked() {
+                    post_fx.update_event = Some(LutEvent::Reload);
+                }
+            });
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
+
+    pub fn film_grain_fx(editor: &mut EditorData, post_fx: &mut Box<dyn PostFx>, ui: &mut Ui) {
+        let downcast = post_fx.as_any().downcast_mut::<FilmGrainFx>();
+
+        if let Some(post_fx) = downcast {
+            post_fx.selected_action = editor.create_li_header(ui, "Film grain");
+
+            let uniform = &mut post_fx.grain_uniform;
+            let mut colored = uniform.colored == 1;
+
+            ui.add(Slider::new(&mut uniform.intensity, 0.0..=0.5).text("Intensity"));
+            ui.add(Slider::new(&mut uniform.size, 1.0..=8.0).text("Grain size"));
+
+            if ui.checkbox(&mut colored, "Colored grain").changed() {
+                uniform.colored = colored as u32;
+            }
+
+            ui.checkbox(&mut post_fx.enabled, "Enabled");
+        }
+    }
 }
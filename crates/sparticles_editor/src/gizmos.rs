@@ -0,0 +1,57 @@
+use crate::EditorData;
+use sparticles_app::{
+    gui::egui::{self, Align2, Color32, FontId, Id, Order, Pos2, Sense, Stroke, Vec2},
+    model::SparState,
+};
+
+const GIZMO_RADIUS: f32 = 6.;
+const EMITTER_MENU_IDX: usize = 1;
+
+/// Draws the projected origin of every emitter, clicking a gizmo selects that emitter
+pub fn draw_emitter_gizmos(ctx: &egui::Context, data: &mut EditorData, state: &SparState) {
+    let screen = ctx.screen_rect();
+
+    for (idx, emitter) in state.emitters.iter().enumerate() {
+        let Some(ndc) = state.camera.project(emitter.uniform.box_position) else {
+            continue;
+        };
+
+        if 1. < ndc.x.abs() || 1. < ndc.y.abs() || !(0. ..=1.).contains(&ndc.z) {
+            continue;
+        }
+
+        let pos = Pos2::new(
+            screen.left() + (ndc.x + 1.) * 0.5 * screen.width(),
+            screen.top() + (1. - ndc.y) * 0.5 * screen.height(),
+        );
+
+        let color = if idx == data.selected_emitter_idx {
+            Color32::YELLOW
+        } else {
+            Color32::WHITE
+        };
+
+        egui::Area::new(Id::new("emitter-gizmo").with(idx))
+            .order(Order::Background)
+            .fixed_pos(pos - Vec2::splat(GIZMO_RADIUS))
+            .show(ctx, |ui| {
+                let size = Vec2::splat(GIZMO_RADIUS * 2.);
+                let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+
+                let painter = ui.painter();
+                painter.circle_stroke(rect.center(), GIZMO_RADIUS, Stroke::new(2., color));
+                painter.text(
+                    rect.right_center() + Vec2::new(4., 0.),
+                    Align2::LEFT_CENTER,
+                    emitter.id(),
+                    FontId::proportional(14.),
+                    color,
+                );
+
+                if response.clicked() {
+                    data.selected_emitter_idx = idx;
+                    data.selected_menu_idx = EMITTER_MENU_IDX;
+                }
+            });
+    }
+}
@@ -0,0 +1,177 @@
+use sparticles_app::{
+    glam::Vec4,
+    gui::egui::{
+        self,
+        color_picker::{color_edit_button_rgba, Alpha},
+        pos2, vec2, Color32, Id, Rect, Rgba, Sense, Shape, Stroke, Ui,
+    },
+    model::{ColorGradient, GradientInterpolation, GradientStop},
+};
+
+use crate::EditorWidgets;
+
+const BAR_HEIGHT: f32 = 20.;
+const MARKER_RADIUS: f32 = 5.;
+const PREVIEW_SAMPLES: usize = 64;
+
+fn to_color32(color: Vec4) -> Color32 {
+    let c = color.clamp(Vec4::ZERO, Vec4::ONE);
+    Rgba::from_rgba_unmultiplied(c.x, c.y, c.z, c.w).into()
+}
+
+impl EditorWidgets {
+    /// Edits the stops of a gradient, drag markers to move, double click the bar to add a stop.
+    /// Returns true if the gradient changed
+    pub fn gradient(ui: &mut Ui, id_source: &str, gradient: &mut ColorGradient) -> bool {
+        let id = Id::new(id_source);
+        let mut selected = ui
+            .data(|d| d.get_temp::<usize>(id))
+            .unwrap_or(0)
+            .min(gradient.stops.len().saturating_sub(1));
+        let mut changed = false;
+
+        let width = ui.available_width().min(300.);
+        let size = vec2(width, BAR_HEIGHT + MARKER_RADIUS * 3.);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        let bar = Rect::from_min_size(rect.min, vec2(width, BAR_HEIGHT));
+
+        let mut mesh = egui::Mesh::default();
+        let lut = gradient.lut(PREVIEW_SAMPLES);
+
+        for (i, color) in lut.iter().enumerate() {
+            let x = bar.left() + width * i as f32 / (PREVIEW_SAMPLES - 1) as f32;
+            mesh.colored_vertex(pos2(x, bar.top()), to_color32(*color));
+            mesh.colored_vertex(pos2(x, bar.bottom()), to_color32(*color));
+
+            if 0 < i {
+                let idx = i as u32 * 2;
+                mesh.add_triangle(idx - 2, idx - 1, idx);
+                mesh.add_triangle(idx - 1, idx, idx + 1);
+            }
+        }
+
+        ui.painter().add(Shape::mesh(mesh));
+        ui.painter()
+            .rect_stroke(bar, 0., Stroke::new(1., Color32::GRAY));
+
+        let to_position = |x: f32| ((x - bar.left()) / width).clamp(0., 1.);
+
+        if response.double_clicked() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let position = to_position(pointer.x);
+
+                gradient.stops.push(GradientStop {
+                    position,
+                    color: gradient.sample(position),
+                });
+                selected = gradient.stops.len() - 1;
+                changed = true;
+            }
+        }
+
+        let mut drag_released = false;
+
+        for (i, stop) in gradient.stops.iter_mut().enumerate() {
+            let center = pos2(
+                bar.left() + stop.position * width,
+                bar.bottom() + MARKER_RADIUS * 1.5,
+            );
+            let marker = Rect::from_center_size(center, vec2(MARKER_RADIUS, MARKER_RADIUS) * 2.);
+            let marker_response = ui.interact(marker, id.with(i), Sense::click_and_drag());
+
+            if marker_response.clicked() || marker_response.drag_started() {
+                selected = i;
+            }
+
+            if marker_response.dragged() {
+                stop.position = to_position(center.x + marker_response.drag_delta().x);
+                changed = true;
+            }
+
+            drag_released |= marker_response.drag_released();
+
+            let stroke_color = if i == selected {
+                Color32::WHITE
+            } else {
+                Color32::DARK_GRAY
+            };
+
+            ui.painter().circle(
+                center,
+                MARKER_RADIUS,
+                to_color32(stop.color.truncate().extend(1.)),
+                Stroke::new(1.5, stroke_color),
+            );
+        }
+
+        // Sorting while dragging would swap the marker under the pointer
+        if drag_released {
+            let selected_stop = gradient.stops.get(selected).copied();
+            gradient.sort_stops();
+
+            if let Some(stop) = selected_stop {
+                selected = gradient.stops.iter().position(|s| *s == stop).unwrap_or(0);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if let Some(stop) = gradient.stops.get_mut(selected) {
+                let c = stop.color;
+                let mut color = Rgba::from_rgba_unmultiplied(c.x, c.y, c.z, c.w);
+
+                if color_edit_button_rgba(ui, &mut color, Alpha::OnlyBlend).changed() {
+                    stop.color = color.to_rgba_unmultiplied().into();
+                    changed = true;
+                }
+            }
+
+            if ui.button("Add stop").clicked() {
+                let position = gradient
+                    .stops
+                    .get(selected)
+                    .map_or(0.5, |stop| (stop.position + 0.1).min(1.));
+
+                gradient.stops.push(GradientStop {
+                    position,
+                    color: gradient.sample(position),
+                });
+                gradient.sort_stops();
+                selected = gradient
+                    .stops
+                    .iter()
+                    .position(|s| s.position == position)
+                    .unwrap_or(0);
+                changed = true;
+            }
+
+            let can_delete = 2 < gradient.stops.len();
+
+            if ui
+                .add_enabled(can_delete, egui::Button::new("Delete stop"))
+                .clicked()
+            {
+                gradient.stops.remove(selected);
+                selected = selected.saturating_sub(1);
+                changed = true;
+            }
+        });
+
+        egui::ComboBox::from_id_source(id.with("interpolation"))
+            .selected_text(gradient.interpolation)
+            .show_ui(ui, |ui| {
+                for interpolation in [
+                    GradientInterpolation::Step,
+                    GradientInterpolation::Linear,
+                    GradientInterpolation::CatmullRom,
+                ] {
+                    changed |= ui
+                        .selectable_value(&mut gradient.interpolation, interpolation, interpolation)
+                        .changed();
+                }
+            });
+
+        ui.data_mut(|d| d.insert_temp(id, selected));
+
+        changed
+    }
+}
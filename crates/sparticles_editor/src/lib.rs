@@ -11,10 +11,15 @@ use menu::{
 };
 use sparticles_app::{
     animations::{
-        ColorAnimation, DiffusionAnimation, ForceAnimation, GravityAnimation, StrayAnimation,
-        SwayAnimation,
+        ColorAnimation, CurlNoiseAnimation, DiffusionAnimation, FlipbookAnimation,
+        FloorCollisionAnimation, ForceAnimation, GravityAnimation, PathAnimation,
+        PulseSpawnAnimation, SizeOverLifeAnimation, SpringAnimation, StrayAnimation, SwayAnimation,
+        TurbulenceAnimation, VortexAnimation, WindAnimation,
+    },
+    fx::{
+        blur::BlurFx, BloomFx, ColorFx, DepthOfFieldFx, FeedbackFx, FilmGrainFx, KuwaharaFx,
+        LutGradingFx, MotionBlurFx, OutlineFx, RadialBlurFx, VignetteFx,
     },
-    fx::{blur::BlurFx, BloomFx, ColorFx},
     gui::egui::{load::SizedTexture, *},
     gui::{
         egui::{self},
@@ -26,7 +31,7 @@ use sparticles_app::{
     profiler::GpuTimerScopeResult,
     texture::IconTexture,
     traits::{EmitterAnimation, ParticleAnimation, PostFx, WidgetBuilder},
-    util::ListAction,
+    util::{ListAction, Persistence},
     wgpu::{self, CommandEncoder},
 };
 use std::{
@@ -37,6 +42,8 @@ use std::{
 
 pub mod em_widgets;
 pub mod fx_widgets;
+pub mod gizmos;
+pub mod gradient_widget;
 pub mod menu;
 pub mod pa_widgets;
 
@@ -66,24 +73,35 @@ pub struct DynamicWidgets {
 pub struct EditorData {
     new_emitter_tag: String,
     profiling_results: Vec<GpuTimerScopeResult>,
+    /// GPU profiling only runs while the performance panel is open
+    performance_open: bool,
     selected_emitter_idx: usize,
     selected_menu_idx: usize,
+    show_gizmos: bool,
 
     fps_text: String,
     frame_time_text: String,
     cpu_time_text: String,
     total_elapsed_text: String,
     particle_count_text: String,
+    draw_stats_text: String,
+    surface_text: String,
     icon_textures: HashMap<String, TextureId>,
     selected_tab: Tab,
     selected_new_par_anim: usize,
     selected_new_em_anim: usize,
     selected_new_post_fx: usize,
+    spring_image_path: String,
+    burst_count: u32,
+    seek_sec: f32,
+    budget_error: Option<String>,
 
     //performance_event: Option<DisplayEvent>,
     //display_event: Option<DisplayEvent>,
     pub emitter_settings: Option<EmitterSettings>,
     pub model_files: Vec<PathBuf>,
+    /// File names in assets/textures, used for the spawn color mask
+    pub texture_files: Vec<String>,
 }
 
 const CHEVRON_UP_ID: &str = "chevron-up";
@@ -122,6 +140,8 @@ impl WidgetBuilder for Editor {
             //VirtualKeyCode::C => gui.display_event.set(DisplayEvent::ToggleCollapse),
             //VirtualKeyCode::P => gui.performance_event.set(DisplayEvent::ToggleCollapse),
             VirtualKeyCode::F => events.toggle_play = true,
+            VirtualKeyCode::N => events.step_frame = true,
+            VirtualKeyCode::G => data.show_gizmos = !data.show_gizmos,
             _ => return false,
         }
 
@@ -156,7 +176,12 @@ impl WidgetBuilder for Editor {
                     });
             });
 
+        if self.data.show_gizmos {
+            gizmos::draw_emitter_gizmos(ctx, &mut self.data, state);
+        }
+
         let idx = self.data.selected_menu_idx;
+        self.data.performance_open = false;
 
         let mut menu_ctx = MenuCtx {
             ctx,
@@ -168,6 +193,9 @@ impl WidgetBuilder for Editor {
         };
 
         self.menus[idx].draw_ui(&mut menu_ctx);
+
+        let gfx = &mut task::block_on(state.gfx.write());
+        gfx.set_profiling(self.data.performance_open);
     }
 }
 
@@ -211,7 +239,6 @@ impl Editor {
 
     pub fn new(state: &mut SparState, model_dir: PathBuf) -> Self {
         let gfx = &mut task::block_on(state.gfx.write());
-        //let texture_paths = Persistence::import_textures().unwrap();
         let icon_textures = Self::create_icons(gfx);
         let mut pa_widgets: HashMap<TypeId, PAWidgetPtr> = HashMap::new();
         let mut em_widgets: HashMap<TypeId, EMWidgetPtr> = HashMap::new();
@@ -237,6 +264,46 @@ impl Editor {
             Box::new(EditorWidgets::stray_anim),
         );
 
+        pa_widgets.insert(
+            TypeId::of::<TurbulenceAnimation>(),
+            Box::new(EditorWidgets::turbulence_anim),
+        );
+
+        pa_widgets.insert(
+            TypeId::of::<SpringAnimation>(),
+            Box::new(EditorWidgets::spring_anim),
+        );
+
+        pa_widgets.insert(
+            TypeId::of::<WindAnimation>(),
+            Box::new(EditorWidgets::wind_anim),
+        );
+
+        pa_widgets.insert(
+            TypeId::of::<VortexAnimation>(),
+            Box::new(EditorWidgets::vortex_anim),
+        );
+
+        pa_widgets.insert(
+            TypeId::of::<SizeOverLifeAnimation>(),
+            Box::new(EditorWidgets::size_over_life_anim),
+        );
+
+        pa_widgets.insert(
+            TypeId::of::<CurlNoiseAnimation>(),
+            Box::new(EditorWidgets::curl_noise_anim),
+        );
+
+        pa_widgets.insert(
+            TypeId::of::<FlipbookAnimation>(),
+            Box::new(EditorWidgets::flipbook_anim),
+        );
+
+        pa_widgets.insert(
+            TypeId::of::<FloorCollisionAnimation>(),
+            Box::new(EditorWidgets::floor_collision_anim),
+        );
+
         em_widgets.insert(
             TypeId::of::<SwayAnimation>(),
             Box::new(EditorWidgets::sway_anim),
@@ -247,8 +314,54 @@ impl Editor {
             Box::new(EditorWidgets::diffusion_anim),
         );
 
+        em_widgets.insert(
+            TypeId::of::<PulseSpawnAnimation>(),
+            Box::new(EditorWidgets::pulse_spawn_anim),
+        );
+
+        em_widgets.insert(
+            TypeId::of::<PathAnimation>(),
+            Box::new(EditorWidgets::path_anim),
+        );
+
         fx_widgets.insert(TypeId::of::<BloomFx>(), Box::new(EditorWidgets::bloom_fx));
         fx_widgets.insert(TypeId::of::<BlurFx>(), Box::new(EditorWidgets::blur_fx));
+        fx_widgets.insert(
+            TypeId::of::<FeedbackFx>(),
+            Box::new(EditorWidgets::feedback_fx),
+        );
+        fx_widgets.insert(
+            TypeId::of::<KuwaharaFx>(),
+            Box::new(EditorWidgets::kuwahara_fx),
+        );
+        fx_widgets.insert(
+            TypeId::of::<OutlineFx>(),
+            Box::new(EditorWidgets::outline_fx),
+        );
+        fx_widgets.insert(
+            TypeId::of::<VignetteFx>(),
+            Box::new(EditorWidgets::vignette_fx),
+        );
+        fx_widgets.insert(
+            TypeId::of::<FilmGrainFx>(),
+            Box::new(EditorWidgets::film_grain_fx),
+        );
+        fx_widgets.insert(
+            TypeId::of::<DepthOfFieldFx>(),
+            Box::new(EditorWidgets::dof_fx),
+        );
+        fx_widgets.insert(
+            TypeId::of::<RadialBlurFx>(),
+            Box::new(EditorWidgets::radial_blur_fx),
+        );
+        fx_widgets.insert(
+            TypeId::of::<LutGradingFx>(),
+            Box::new(EditorWidgets::lut_fx),
+        );
+        fx_widgets.insert(
+            TypeId::of::<MotionBlurFx>(),
+            Box::new(EditorWidgets::motion_blur_fx),
+        );
         fx_widgets.insert(TypeId::of::<ColorFx>(), Box::new(EditorWidgets::color_fx));
 
         let mut model_files = vec![];
@@ -277,25 +390,41 @@ impl Editor {
             }
         }
 
+        let texture_files = Persistence::import_textures()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .collect();
+
         let data = EditorData {
             frame_time_text: "".to_string(),
             cpu_time_text: "".to_string(),
             fps_text: "".to_string(),
             total_elapsed_text: "".to_string(),
             particle_count_text: "".to_string(),
+            draw_stats_text: "".to_string(),
+            surface_text: "".to_string(),
             selected_tab: Tab::EmitterSettings,
             selected_menu_idx: 0,
+            show_gizmos: true,
             selected_emitter_idx: 0,
             selected_new_par_anim: 0,
             selected_new_em_anim: 0,
             selected_new_post_fx: 0,
+            spring_image_path: "".to_string(),
+            burst_count: 50,
+            seek_sec: 0.,
+            budget_error: None,
             icon_textures,
             new_emitter_tag: "".to_string(),
             profiling_results: Vec::new(),
+            performance_open: false,
             //display_event: None,
             //performance_event: None,
             emitter_settings: None,
             model_files,
+            texture_files,
         };
 
         let menus: Vec<Box<dyn MenuWidget>> = vec![
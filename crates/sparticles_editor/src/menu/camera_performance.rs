@@ -1,7 +1,11 @@
 use async_std::task;
 use sparticles_app::{
-    gui::egui::{self, Ui},
-    model::{SparState, TonemapType},
+    glam::{Vec3, Vec4},
+    gui::egui::{self, color_picker::color_edit_button_rgb, Ui},
+    model::{
+        BudgetPolicy, CameraMode, ClockMode, DebugView, ParticleBudget, RenderStage, SparState,
+        TonemapType,
+    },
     profiler::GpuTimerScopeResult,
 };
 
@@ -27,8 +31,11 @@ impl MenuWidget for CameraPerformanceMenu {
                     clock,
                     emitters,
                     gfx,
-                    play,
                     camera,
+                    sky,
+                    budget,
+                    render_stages,
+                    draw_stats,
                     ..
                 } = menu_ctx.state;
 
@@ -38,15 +45,31 @@ impl MenuWidget for CameraPerformanceMenu {
                 data.create_title(ui, "Camera & Performance");
 
                 // Update gui info
-                if clock.frame() % 20 == 0 && *play {
+                if clock.frame() % 20 == 0 && !clock.is_paused() {
                     let gfx = &mut task::block_on(gfx.write());
                     let count: u64 = emitters.iter().map(|s| s.particle_count()).sum();
+                    let active_count: u64 =
+                        emitters.iter().map(|s| s.active_particle_count()).sum();
 
                     data.frame_time_text = clock.frame_time_text();
                     data.fps_text = clock.fps_text();
                     data.total_elapsed_text = clock.total_elapsed_text();
                     data.cpu_time_text = clock.cpu_time_text();
-                    data.particle_count_text = format!("Particle count: {}", count);
+                    data.particle_count_text =
+                        format!("Particle count: {} / {}", active_count, count);
+                    data.draw_stats_text = format!(
+                        "Draw calls: {} for {} emitters ({} merged, {} mesh/material batches, {} depth prepass)",
+                        draw_stats.draw_calls,
+                        draw_stats.emitter_draws,
+                        draw_stats.merged_draws,
+                        draw_stats.batches,
+                        draw_stats.prepass_draw_calls
+                    );
+                    data.surface_text = format!(
+                        "Surface: {:?} (sRGB in shader: {})",
+                        gfx.surface_format(),
+                        gfx.apply_srgb_oetf()
+                    );
 
                     if let Some(res) = gfx.process_frame() {
                         data.profiling_results = res;
@@ -58,10 +81,79 @@ impl MenuWidget for CameraPerformanceMenu {
                 Editor::create_label(ui, &data.cpu_time_text);
                 Editor::create_label(ui, &data.total_elapsed_text);
                 Editor::create_label(ui, &data.particle_count_text);
+                Editor::create_label(ui, &data.draw_stats_text);
+
+                let usage: u64 = emitters.iter().map(|em| em.particle_count()).sum();
+                let to_mb = |bytes: u64| bytes as f64 / (1024. * 1024.);
+
+                Editor::create_label(
+                    ui,
+                    format!(
+                        "Particle budget: {} / {} ({:.1} / {:.1} MB)",
+                        usage,
+                        budget.max_particles,
+                        to_mb(ParticleBudget::memory_bytes(usage)),
+                        to_mb(budget.max_memory_bytes()),
+                    ),
+                );
+
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut budget.max_particles)
+                            .speed(1000.)
+                            .clamp_range(0..=100_000_000),
+                    );
+
+                    egui::ComboBox::from_label("Max particles / policy")
+                        .selected_text(budget.policy.to_string())
+                        .show_ui(ui, |ui| {
+                            for policy in [BudgetPolicy::Reject, BudgetPolicy::ScaleDown] {
+                                ui.selectable_value(&mut budget.policy, policy, policy.to_string());
+                            }
+                        });
+                });
+                Editor::create_label(ui, &data.surface_text);
+
+                ui.horizontal(|ui| {
+                    let mut capped = clock.max_fps().is_some();
+                    let mut max_fps = clock.max_fps().unwrap_or(60);
+
+                    ui.checkbox(&mut capped, "Cap FPS");
+                    ui.add_enabled(capped, egui::Slider::new(&mut max_fps, 10..=240));
+
+                    clock.set_max_fps(capped.then_some(max_fps));
+                });
+
+                ui.horizontal(|ui| {
+                    let (mut fixed, mut dt) = match clock.mode() {
+                        ClockMode::Fixed { dt } => (true, dt),
+                        ClockMode::Realtime => (false, 1. / 60.),
+                    };
+
+                    ui.checkbox(&mut fixed, "Fixed timestep");
+                    ui.add_enabled(
+                        fixed,
+                        egui::Slider::new(&mut dt, 0.001..=0.1).text("Delta sec"),
+                    );
+
+                    clock.set_mode(if fixed {
+                        ClockMode::Fixed { dt }
+                    } else {
+                        ClockMode::Realtime
+                    });
+                });
+
+                let mut time_scale = clock.time_scale();
+                ui.add(
+                    egui::Slider::new(&mut time_scale, 0.05..=4.)
+                        .logarithmic(true)
+                        .text("Time scale"),
+                );
+                clock.set_time_scale(time_scale);
 
                 ui.separator();
 
-                egui::CollapsingHeader::new("Performance")
+                data.performance_open = egui::CollapsingHeader::new("Performance")
                     .id_source("total")
                     .show(ui, |ui| {
                         let total = display_performance(ui, &data.profiling_results);
@@ -69,6 +161,83 @@ impl MenuWidget for CameraPerformanceMenu {
                             ui,
                             format!("{} - {:.3}μs", "Total GPU time", total * 1_000_000.),
                         );
+                    })
+                    .body_returned
+                    .is_some();
+
+                egui::CollapsingHeader::new("Render stages")
+                    .id_source("render-stages")
+                    .show(ui, |ui| {
+                        let mut swap = None;
+                        let stage_count = render_stages.len();
+
+                        for (i, stage) in render_stages.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.add_enabled(0 < i, egui::Button::new("⬆")).clicked() {
+                                    swap = Some((i - 1, i));
+                                }
+
+                                if ui
+                                    .add_enabled(i + 1 < stage_count, egui::Button::new("⬇"))
+                                    .clicked()
+                                {
+                                    swap = Some((i, i + 1));
+                                }
+
+                                ui.label(stage.to_string());
+                            });
+                        }
+
+                        if let Some((a, b)) = swap {
+                            render_stages.swap(a, b);
+                        }
+
+                        if ui.button("Reset order").clicked() {
+                            *render_stages = RenderStage::default_order();
+                        }
+                    });
+
+                egui::CollapsingHeader::new("Limits (device / adapter)")
+                    .id_source("limits")
+                    .show(ui, |ui| {
+                        let gfx = &task::block_on(gfx.read());
+                        let device = gfx.device.limits();
+                        let adapter = gfx.adapter_limits();
+
+                        let mut limit_label = |name: &str, device: u32, adapter: u32| {
+                            Editor::create_label(ui, format!("{}: {} / {}", name, device, adapter));
+                        };
+
+                        limit_label(
+                            "Max compute workgroups per dimension",
+                            device.max_compute_workgroups_per_dimension,
+                            adapter.max_compute_workgroups_per_dimension,
+                        );
+                        limit_label(
+                            "Max compute workgroup size x",
+                            device.max_compute_workgroup_size_x,
+                            adapter.max_compute_workgroup_size_x,
+                        );
+                        limit_label(
+                            "Max compute invocations per workgroup",
+                            device.max_compute_invocations_per_workgroup,
+                            adapter.max_compute_invocations_per_workgroup,
+                        );
+                        limit_label(
+                            "Max storage buffer binding size",
+                            device.max_storage_buffer_binding_size,
+                            adapter.max_storage_buffer_binding_size,
+                        );
+                        limit_label(
+                            "Max bind groups",
+                            device.max_bind_groups,
+                            adapter.max_bind_groups,
+                        );
+
+                        Editor::create_label(
+                            ui,
+                            format!("Fx workgroup size: {0}x{0}", gfx.fx_workgroup_size()),
+                        );
                     });
 
                 ui.separator();
@@ -83,6 +252,78 @@ impl MenuWidget for CameraPerformanceMenu {
                     if ui.button("Toggle pause").clicked() {
                         events.toggle_play = true;
                     }
+
+                    if ui
+                        .add_enabled(clock.is_paused(), egui::Button::new("Step frame"))
+                        .clicked()
+                    {
+                        events.step_frame = true;
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut data.seek_sec)
+                            .speed(0.1)
+                            .clamp_range(0.0..=60.0)
+                            .suffix(" sec"),
+                    );
+
+                    if ui.button("Seek").clicked() {
+                        events.seek_sec = Some(data.seek_sec);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let mut orbit = matches!(camera.mode(), CameraMode::Orbit { .. });
+
+                    if ui
+                        .checkbox(&mut orbit, "Orbit camera (drag to rotate, scroll to zoom)")
+                        .changed()
+                    {
+                        if orbit {
+                            camera.set_mode(CameraMode::Orbit {
+                                target: camera.view_target(),
+                                distance: camera.position.distance(camera.view_target()),
+                            });
+                        } else {
+                            camera.set_mode(CameraMode::FreeFly);
+                        }
+                    }
+                });
+
+                ui.add(
+                    egui::Slider::new(&mut camera.mouse_sensitivity, 0.001..=0.02)
+                        .text("Mouse sensitivity (hold right button to look)"),
+                );
+
+                ui.horizontal(|ui| {
+                    let mut move_speed = camera.move_speed;
+                    let mut rotation_speed = camera.rotation_speed;
+                    let mut boost = camera.boost_multiplier;
+
+                    ui.add(egui::DragValue::new(&mut move_speed).speed(0.1));
+                    ui.add(egui::DragValue::new(&mut rotation_speed).speed(0.05));
+                    ui.add(egui::DragValue::new(&mut boost).speed(0.1));
+                    ui.label("Move speed / rotation speed / shift boost");
+
+                    camera.set_move_speed(move_speed);
+                    camera.set_rotation_speed(rotation_speed);
+                    camera.set_boost_multiplier(boost);
+                });
+
+                ui.add(
+                    egui::Slider::new(&mut camera.damping, 0.0..=1.0)
+                        .text("Camera damping (0 is instant)"),
+                );
+
+                ui.horizontal(|ui| {
+                    let gfx = &mut task::block_on(gfx.write());
+                    ui.checkbox(&mut gfx.transparent_background, "Transparent background");
+
+                    if ui.button("Capture frame").clicked() {
+                        events.capture_frame = true;
+                    }
                 });
 
                 ui.separator();
@@ -92,29 +333,75 @@ impl MenuWidget for CameraPerformanceMenu {
                 ui.horizontal_top(|ui| {
                     egui::ComboBox::from_label("tonemapping")
                         .selected_text(camera.tonemap_type)
+                        .show_ui(ui, |ui| {
+                            for tonemap_type in TonemapType::ALL {
+                                ui.selectable_value(
+                                    &mut camera.tonemap_type,
+                                    tonemap_type,
+                                    tonemap_type,
+                                );
+                            }
+                        });
+                });
+
+                ui.horizontal_top(|ui| {
+                    egui::ComboBox::from_label("debug view")
+                        .selected_text(camera.debug_view)
                         .show_ui(ui, |ui| {
                             ui.selectable_value(
-                                &mut camera.tonemap_type,
-                                TonemapType::AcesNarkowicz,
-                                TonemapType::AcesNarkowicz,
-                            );
-                            ui.selectable_value(
-                                &mut camera.tonemap_type,
-                                TonemapType::AcesHill,
-                                TonemapType::AcesHill,
-                            );
-                            ui.selectable_value(
-                                &mut camera.tonemap_type,
-                                TonemapType::Uchimura,
-                                TonemapType::Uchimura,
+                                &mut camera.debug_view,
+                                DebugView::None,
+                                DebugView::None,
                             );
                             ui.selectable_value(
-                                &mut camera.tonemap_type,
-                                TonemapType::Lottes,
-                                TonemapType::Lottes,
+                                &mut camera.debug_view,
+                                DebugView::Speed,
+                                DebugView::Speed,
                             );
                         });
                 });
+
+                if camera.debug_view == DebugView::Speed {
+                    ui.add(
+                        egui::Slider::new(&mut camera.debug_max_speed, 1.0..=100.0)
+                            .text("Debug max speed"),
+                    );
+                }
+
+                ui.separator();
+
+                ui.checkbox(&mut sky.enabled, "Gradient sky");
+
+                if sky.enabled {
+                    let uniform = &mut sky.uniform;
+
+                    let mut color_edit = |color: &mut Vec4, label: &str| {
+                        ui.horizontal(|ui| {
+                            let mut rgb = color.truncate().to_array();
+
+                            if color_edit_button_rgb(ui, &mut rgb).changed() {
+                                *color = Vec3::from(rgb).extend(1.);
+                            }
+
+                            ui.label(label);
+                        });
+                    };
+
+                    color_edit(&mut uniform.top_color, "Top color");
+                    color_edit(&mut uniform.horizon_color, "Horizon color");
+                    color_edit(&mut uniform.sun_color, "Sun color");
+
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut uniform.sun_direction.x).speed(0.05));
+                        ui.add(egui::DragValue::new(&mut uniform.sun_direction.y).speed(0.05));
+                        ui.add(egui::DragValue::new(&mut uniform.sun_direction.z).speed(0.05));
+                        ui.label("Sun direction");
+                    });
+
+                    ui.add(
+                        egui::Slider::new(&mut uniform.sun_size, 0.0..=0.2).text("Sun size (rad)"),
+                    );
+                }
             });
     }
 }
@@ -2,13 +2,18 @@ use crate::{DynamicWidgets, Editor, EditorData};
 use async_std::task;
 use sparticles_app::{
     fx::PostProcessState,
+    glam::Vec3,
     gui::egui::{
         self,
         color_picker::{color_edit_button_rgba, Alpha},
         scroll_area::ScrollBarVisibility,
         Color32, Rgba, RichText, Ui,
     },
-    model::{emitter_state::RecreateEmitterOptions, EmitterState, EmitterType, SparState},
+    model::{
+        emitter_state::RecreateEmitterOptions, BillboardMode, BlendMode, EmitBurst, EmitterState,
+        EmitterType, SamplerSettings, ShadingModel, SimulationSpace, SparState, SpawnDistribution,
+        SpawnMode, SpawnShape,
+    },
     traits::Splitting,
     wgpu,
 };
@@ -40,6 +45,7 @@ impl MenuWidget for EmitterMenu {
                 let SparState {
                     emitters,
                     post_process,
+                    camera,
                     ..
                 } = menu_ctx.state;
 
@@ -76,6 +82,7 @@ impl MenuWidget for EmitterMenu {
                     if ui.button("Export settings").clicked() {
                         EmitterState::export(emitters);
                         PostProcessState::export(post_process);
+                        camera.export();
                     }
 
                     ui.add_space(4.0);
@@ -90,6 +97,10 @@ impl MenuWidget for EmitterMenu {
                     ui.add_space(4.0);
 
                     let emitter = &emitters[data.selected_emitter_idx];
+                    if !emitter.is_light && ui.button("Duplicate emitter").clicked() {
+                        events.duplicate_emitter = Some(emitter.id().to_string());
+                    }
+
                     if !emitter.is_light && ui.button("Remove emitter").clicked() {
                         let id = emitter.id().to_string();
                         events.delete_emitter = Some(id);
@@ -210,11 +221,16 @@ impl EmitterMenu {
             emitter_data: data,
             state,
             encoder,
+            events,
             ..
         } = menu_ctx;
 
+        let is_light = state.emitters[data.selected_emitter_idx].is_light;
+        let budget = state.budget;
+        let others_usage =
+            state.particle_usage() - state.emitters[data.selected_emitter_idx].particle_count();
         let uniform = &mut state.emitters[data.selected_emitter_idx].uniform;
-        data.sync_emitter_settings(&uniform);
+        data.sync_emitter_settings(uniform);
         let emitter_settings = data.emitter_settings.as_mut().unwrap();
 
         ui.add_space(5.0);
@@ -246,6 +262,101 @@ impl EmitterMenu {
 
         ui.add_space(5.0);
 
+        ui.horizontal_top(|ui| {
+            egui::ComboBox::from_label("Spawn distribution")
+                .selected_text(emitter_settings.spawn_distribution)
+                .show_ui(ui, |ui| {
+                    let mut distribution_option = |distribution: SpawnDistribution| {
+                        ui.selectable_value(
+                            &mut emitter_settings.spawn_distribution,
+                            distribution,
+                            distribution,
+                        );
+                    };
+
+                    distribution_option(SpawnDistribution::Uniform);
+                    distribution_option(SpawnDistribution::Gaussian);
+                    distribution_option(SpawnDistribution::EdgeBiased);
+                });
+        });
+
+        ui.horizontal_top(|ui| {
+            egui::ComboBox::from_label("Simulation space")
+                .selected_text(emitter_settings.simulation_space)
+                .show_ui(ui, |ui| {
+                    let mut space_option = |space: SimulationSpace| {
+                        ui.selectable_value(&mut emitter_settings.simulation_space, space, space);
+                    };
+
+                    space_option(SimulationSpace::World);
+                    space_option(SimulationSpace::Local);
+                });
+        });
+
+        ui.horizontal_top(|ui| {
+            egui::ComboBox::from_label("Blend mode")
+                .selected_text(emitter_settings.blend_mode)
+                .show_ui(ui, |ui| {
+                    let mut blend_option = |mode: BlendMode| {
+                        ui.selectable_value(&mut emitter_settings.blend_mode, mode, mode);
+                    };
+
+                    blend_option(BlendMode::Replace);
+                    blend_option(BlendMode::Alpha);
+                    blend_option(BlendMode::Additive);
+                });
+        });
+
+        ui.horizontal_top(|ui| {
+            egui::ComboBox::from_label("Billboard")
+                .selected_text(emitter_settings.billboard_mode)
+                .show_ui(ui, |ui| {
+                    let mut billboard_option = |mode: BillboardMode| {
+                        ui.selectable_value(&mut emitter_settings.billboard_mode, mode, mode);
+                    };
+
+                    billboard_option(BillboardMode::Round);
+                    billboard_option(BillboardMode::Stretched);
+                });
+        });
+
+        if emitter_settings.billboard_mode == BillboardMode::Stretched {
+            ui.add(
+                egui::Slider::new(&mut emitter_settings.stretch_factor, 0.0..=1.0)
+                    .text("Stretch per unit of speed"),
+            );
+        }
+
+        if !is_light {
+            ui.checkbox(
+                &mut emitter_settings.frustum_culling,
+                "Frustum culling (for large particle counts)",
+            );
+        }
+
+        ui.add(egui::Slider::new(&mut emitter_settings.layer, 0..=7).text("Render layer"));
+        ui.add(
+            egui::Slider::new(&mut emitter_settings.draw_order, -10..=10)
+                .text("Draw order (within layer)"),
+        );
+        ui.add(
+            egui::Slider::new(&mut emitter_settings.update_interval, 1..=8)
+                .text("Update interval (frames)"),
+        );
+        ui.add(
+            egui::Slider::new(&mut emitter_settings.time_offset, 0.0..=20.0)
+                .text("Time offset (sec)"),
+        );
+
+        if emitter_settings.spawn_distribution != SpawnDistribution::Uniform {
+            ui.add(
+                egui::Slider::new(&mut emitter_settings.spawn_spread, 0.05..=1.0)
+                    .text("Spawn spread / bias"),
+            );
+        }
+
+        ui.add_space(5.0);
+
         ui.horizontal(|ui| {
             let col = &mut emitter_settings.particle_color;
             let mut particle_color = Rgba::from_rgba_unmultiplied(col.x, col.y, col.z, col.w);
@@ -264,6 +375,36 @@ impl EmitterMenu {
             );
         });
 
+        ui.horizontal(|ui| {
+            let tint = &mut emitter_settings.tint;
+            let mut tint_color = Rgba::from_rgba_unmultiplied(tint.x, tint.y, tint.z, tint.w);
+
+            if color_edit_button_rgba(ui, &mut tint_color, Alpha::OnlyBlend).changed() {
+                *tint = tint_color.to_rgba_unmultiplied().into();
+            };
+
+            ui.label("Emitter tint");
+        });
+
+        if is_light {
+            ui.horizontal(|ui| {
+                let intensity = &mut emitter_settings.light_intensity;
+                let mut samples = intensity.to_array();
+
+                for sample in samples.iter_mut() {
+                    ui.add(
+                        egui::DragValue::new(sample)
+                            .speed(0.01)
+                            .clamp_range(0.0..=10.0),
+                    );
+                }
+
+                *intensity = samples.into();
+
+                ui.label("Light intensity over life");
+            });
+        }
+
         ui.add_space(5.0);
         ui.add(
             egui::Slider::new(&mut emitter_settings.particle_speed_min, 0.0..=50.0)
@@ -297,10 +438,312 @@ impl EmitterMenu {
 
         ui.add(egui::Slider::new(&mut emitter_settings.spawn_count, 1..=100).text("Spawn count"));
 
+        ui.horizontal(|ui| {
+            let mut rate = emitter_settings.spawn_rate_per_sec();
+
+            let response = ui.add(
+                egui::DragValue::new(&mut rate)
+                    .speed(0.1)
+                    .clamp_range(0.05..=1000.)
+                    .max_decimals(2),
+            );
+
+            if response.changed() {
+                emitter_settings.set_spawn_rate_per_sec(rate);
+            }
+
+            ui.label("Spawn rate (particles/sec)");
+        });
+
+        ui.horizontal_top(|ui| {
+            let spawn_mode = &mut emitter_settings.spawn_mode;
+            let is_burst = matches!(spawn_mode, SpawnMode::Burst { .. });
+
+            egui::ComboBox::from_label("Spawn mode")
+                .selected_text(&*spawn_mode)
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(!is_burst, "Continuous").clicked() {
+                        *spawn_mode = SpawnMode::Continuous;
+                    }
+
+                    if ui.selectable_label(is_burst, "Burst").clicked() && !is_burst {
+                        *spawn_mode = SpawnMode::burst();
+                    }
+                });
+        });
+
+        if let SpawnMode::Burst { bursts, catch_up } = &mut emitter_settings.spawn_mode {
+            Editor::create_label(ui, "Bursts (sec, count)");
+
+            let mut remove_idx = None;
+
+            for (i, (sec, count)) in bursts.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(sec)
+                            .speed(0.05)
+                            .clamp_range(0.0..=f32::MAX),
+                    );
+                    ui.add(egui::DragValue::new(count).speed(1.));
+
+                    if ui.button("🗑").clicked() {
+                        remove_idx = Some(i);
+                    }
+                });
+            }
+
+            if let Some(idx) = remove_idx {
+                bursts.remove(idx);
+            }
+
+            if ui.button("Add burst").clicked() {
+                let (last_sec, last_count) = bursts.last().copied().unwrap_or((0., 100));
+                bursts.push((last_sec + 1., last_count));
+            }
+
+            ui.checkbox(catch_up, "Fire bursts already due on reset");
+        }
+
+        ui.add(egui::Slider::new(&mut emitter_settings.depth_bias, -100..=100).text("Depth bias"));
+        ui.checkbox(
+            &mut emitter_settings.depth_prepass,
+            "Depth prepass (opaque meshes)",
+        );
+
+        if !is_light {
+            ui.checkbox(
+                &mut emitter_settings.depth_sorted,
+                "Depth sort (back to front blending)",
+            );
+        }
+
+        ui.horizontal_top(|ui| {
+            egui::ComboBox::from_label("Shading model")
+                .selected_text(emitter_settings.shading_model)
+                .show_ui(ui, |ui| {
+                    let mut shading_option = |model: ShadingModel| {
+                        ui.selectable_value(&mut emitter_settings.shading_model, model, model);
+                    };
+
+                    shading_option(ShadingModel::Pbr);
+                    shading_option(ShadingModel::Unlit);
+                });
+        });
+
+        ui.horizontal_top(|ui| {
+            let color_mask = &mut emitter_settings.color_mask;
+
+            egui::ComboBox::from_label("Spawn color mask")
+                .selected_text(color_mask.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(color_mask, None, "None");
+
+                    for filename in data.texture_files.iter() {
+                        ui.selectable_value(color_mask, Some(filename.to_string()), filename);
+                    }
+                });
+        });
+
+        ui.horizontal_top(|ui| {
+            let spawn_shape = &mut emitter_settings.spawn_shape;
+            let is_box = *spawn_shape == SpawnShape::Box;
+            let is_curve = matches!(spawn_shape, SpawnShape::Curve { .. });
+            let is_ring = matches!(spawn_shape, SpawnShape::Ring { .. });
+            let is_mesh = matches!(spawn_shape, SpawnShape::MeshVertices { .. });
+            let is_sphere = matches!(spawn_shape, SpawnShape::Sphere { .. });
+            let is_cone = matches!(spawn_shape, SpawnShape::Cone { .. });
+            let is_disc = matches!(spawn_shape, SpawnShape::Disc { .. });
+            let is_surface = matches!(spawn_shape, SpawnShape::MeshSurface { .. });
+
+            egui::ComboBox::from_label("Spawn shape")
+                .selected_text(&*spawn_shape)
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(is_box, "Box").clicked() {
+                        *spawn_shape = SpawnShape::Box;
+                    }
+
+                    if ui.selectable_label(is_curve, "Curve").clicked() && !is_curve {
+                        *spawn_shape = SpawnShape::Curve {
+                            control_points: vec![[-1., 0., 0.].into(), [1., 0., 0.].into()],
+                        };
+                    }
+
+                    if ui.selectable_label(is_ring, "Ring").clicked() && !is_ring {
+                        *spawn_shape = SpawnShape::ring();
+                    }
+
+                    if ui.selectable_label(is_mesh, "Mesh vertices").clicked() && !is_mesh {
+                        *spawn_shape = SpawnShape::MeshVertices {
+                            mesh: emitter_settings.mesh.clone(),
+                        };
+                    }
+
+                    if ui.selectable_label(is_sphere, "Sphere").clicked() && !is_sphere {
+                        *spawn_shape = SpawnShape::Sphere { radius: 1. };
+                    }
+
+                    if ui.selectable_label(is_cone, "Cone").clicked() && !is_cone {
+                        *spawn_shape = SpawnShape::Cone {
+                            angle_deg: 20.,
+                            radius: 0.2,
+                        };
+                    }
+
+                    if ui.selectable_label(is_disc, "Disc").clicked() && !is_disc {
+                        *spawn_shape = SpawnShape::Disc { radius: 1. };
+                    }
+
+                    if ui.selectable_label(is_surface, "Mesh surface").clicked() && !is_surface {
+                        *spawn_shape = SpawnShape::MeshSurface {
+                            mesh: emitter_settings.mesh.clone(),
+                        };
+                    }
+                });
+        });
+
+        if let SpawnShape::MeshVertices { mesh } | SpawnShape::MeshSurface { mesh } =
+            &mut emitter_settings.spawn_shape
+        {
+            let collection = state.collection.read().await;
+
+            egui::ComboBox::from_label("Vertices of mesh")
+                .selected_text(format!("{} / {}", mesh.collection_id, mesh.mesh_id))
+                .show_ui(ui, |ui| {
+                    for (collection_id, model) in collection.iter() {
+                        for mesh_id in model.meshes.keys() {
+                            let selected =
+                                mesh.collection_id == *collection_id && mesh.mesh_id == *mesh_id;
+
+                            if ui
+                                .selectable_label(
+                                    selected,
+                                    format!("{} / {}", collection_id, mesh_id),
+                                )
+                                .clicked()
+                            {
+                                mesh.collection_id = collection_id.clone();
+                                mesh.mesh_id = mesh_id.clone();
+                            }
+                        }
+                    }
+                });
+        }
+
+        if let SpawnShape::Ring {
+            radius,
+            thickness,
+            axis,
+            torus,
+            outward,
+        } = &mut emitter_settings.spawn_shape
+        {
+            ui.add(egui::Slider::new(radius, 0.1..=50.0).text("Ring radius"));
+            ui.add(egui::Slider::new(thickness, 0.0..=10.0).text("Ring thickness"));
+
+            Editor::create_label(ui, "Ring axis");
+
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut axis.x).speed(0.05));
+                ui.add(egui::DragValue::new(&mut axis.y).speed(0.05));
+                ui.add(egui::DragValue::new(&mut axis.z).speed(0.05));
+            });
+
+            if axis.length_squared() == 0. {
+                *axis = Vec3::Y;
+            }
+
+            ui.checkbox(torus, "Torus");
+            ui.checkbox(outward, "Emit outward");
+        }
+
+        match &mut emitter_settings.spawn_shape {
+            SpawnShape::Sphere { radius } => {
+                ui.add(egui::Slider::new(radius, 0.01..=50.0).text("Sphere radius"));
+            }
+            SpawnShape::Cone { angle_deg, radius } => {
+                ui.add(egui::Slider::new(angle_deg, 0.0..=180.0).text("Cone angle (deg)"));
+                ui.add(egui::Slider::new(radius, 0.0..=50.0).text("Cone base radius"));
+            }
+            SpawnShape::Disc { radius } => {
+                ui.add(egui::Slider::new(radius, 0.01..=50.0).text("Disc radius"));
+            }
+            _ => {}
+        }
+
+        if let SpawnShape::Curve { control_points } = &mut emitter_settings.spawn_shape {
+            Editor::create_label(ui, "Curve points (relative to box)");
+
+            let mut remove_idx = None;
+
+            for (i, point) in control_points.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut point.x).speed(0.1));
+                    ui.add(egui::DragValue::new(&mut point.y).speed(0.1));
+                    ui.add(egui::DragValue::new(&mut point.z).speed(0.1));
+
+                    if ui.button("🗑").clicked() {
+                        remove_idx = Some(i);
+                    }
+                });
+            }
+
+            if let Some(idx) = remove_idx {
+                control_points.remove(idx);
+            }
+
+            if ui.button("Add curve point").clicked() {
+                let last = control_points.last().copied().unwrap_or_default();
+                control_points.push(last + Vec3::X);
+            }
+        }
+
         ui.add_space(5.0);
 
+        let max_particles = uniform.max_particles();
+        let available = budget.max_particles.saturating_sub(others_usage).max(1);
+        let mut particle_count = max_particles;
+
+        // Resizes in place, so scrubbing keeps the live particles
+        let resize_to = ui
+            .add(
+                egui::Slider::new(&mut particle_count, 1..=available)
+                    .logarithmic(true)
+                    .text("Particle count"),
+            )
+            .changed()
+            .then_some(particle_count);
+
+        ui.label(format!("Live particle ceiling: {}", max_particles));
+
+        if max_particles < emitter_settings.required_particles() {
+            ui.colored_label(
+                Color32::YELLOW,
+                format!(
+                    "Spawning needs {} particles, update the spawn settings to grow the buffer",
+                    emitter_settings.required_particles()
+                ),
+            );
+        }
+
         emitter_settings.recreate = ui.button("Update spawn settings").clicked();
 
+        if let Some(msg) = &data.budget_error {
+            ui.colored_label(Color32::RED, msg);
+        }
+
+        ui.add_space(5.0);
+
+        ui.horizontal(|ui| {
+            if ui.button("Emit now").clicked() {
+                events.emit_burst = Some(EmitBurst {
+                    emitter_id: emitter_settings.id.to_string(),
+                    count: data.burst_count,
+                });
+            }
+
+            ui.add(egui::Slider::new(&mut data.burst_count, 1..=1000).text("Burst count"));
+        });
+
         ui.add_space(5.0);
 
         Editor::create_label(ui, "Particle settings");
@@ -319,10 +762,77 @@ impl EmitterMenu {
             .text("Particle size max"),
         );
 
-        uniform.update_settings(&emitter_settings);
+        ui.add(
+            egui::Slider::new(&mut emitter_settings.particle_mass_min, 0.1..=10.0)
+                .text("Particle mass multiplier min"),
+        );
+        ui.add(
+            egui::Slider::new(
+                &mut emitter_settings.particle_mass_max,
+                emitter_settings.particle_mass_min..=10.0,
+            )
+            .text("Particle mass multiplier max"),
+        );
+
+        ui.add(
+            egui::Slider::new(&mut emitter_settings.circle_aa, 0.0..=1.0)
+                .text("Circle anti-aliasing (coverage - analytic)"),
+        );
+
+        if !is_light {
+            ui.add_space(5.0);
+            Editor::create_label(ui, "Level of detail");
+
+            let lod = &mut emitter_settings.lod;
+            ui.checkbox(&mut lod.enabled, "Reduce particle count by camera distance");
+
+            if lod.enabled {
+                ui.horizontal(|ui| {
+                    for distance in lod.distances.iter_mut() {
+                        ui.add(
+                            egui::DragValue::new(distance)
+                                .speed(0.5)
+                                .clamp_range(0.0..=1000.0),
+                        );
+                    }
+                    ui.label("LOD distances");
+                });
+
+                ui.add(egui::Slider::new(&mut lod.bias, 0.1..=4.0).text("LOD bias"));
+            }
+        }
 
         if emitter_settings.recreate {
-            recreate_emitter(data, state, encoder).await;
+            let mut resized = uniform.clone();
+            resized.update_settings(emitter_settings);
+
+            match budget.fit(&mut resized, others_usage) {
+                Ok(()) => {
+                    *uniform = resized;
+                    data.budget_error = None;
+                    recreate_emitter(data, state, encoder).await;
+                }
+                Err(err) => {
+                    emitter_settings.recreate = false;
+                    uniform.update_settings(emitter_settings);
+                    data.budget_error = Some(err.msg);
+                }
+            }
+        } else {
+            uniform.update_settings(emitter_settings);
+
+            if let Some(particle_count) = resize_to {
+                let SparState {
+                    emitters,
+                    gfx,
+                    camera,
+                    ..
+                } = state;
+
+                let em = &mut emitters[data.selected_emitter_idx];
+                em.resize_particle_count(particle_count, &*gfx.read().await, camera, encoder);
+                emitter_settings.spawn_count = em.uniform.spawn_count;
+            }
         }
     }
 }
@@ -335,7 +845,7 @@ async fn model_settings(menu_ctx: &mut MenuCtx<'_>, ui: &mut Ui) {
     } = menu_ctx;
 
     let uniform = &mut state.emitters[data.selected_emitter_idx].uniform;
-    data.sync_emitter_settings(&uniform);
+    data.sync_emitter_settings(uniform);
     let emitter_settings = data.emitter_settings.as_mut().unwrap();
 
     let mesh = &mut emitter_settings.mesh;
@@ -419,9 +929,34 @@ async fn model_settings(menu_ctx: &mut MenuCtx<'_>, ui: &mut Ui) {
         }
     });
 
+    drop(collection);
+
+    custom_header(ui, "Material sampling");
+
+    let mut collection = state.collection.write().await;
+    let material = collection
+        .get_mut(&mat.collection_id)
+        .and_then(|model| model.materials.get_mut(&mat.material_id));
+
+    if let Some(material) = material {
+        let mut settings = material.sampler_settings();
+
+        ui.add(egui::Slider::new(&mut settings.mip_bias, -4.0..=4.0).text("Mip bias"));
+        ui.add(
+            egui::Slider::new(
+                &mut settings.max_anisotropy,
+                1..=SamplerSettings::MAX_ANISOTROPY,
+            )
+            .text("Max anisotropy"),
+        );
+
+        let gfx = &state.gfx.read().await;
+        material.set_sampler_settings(settings, gfx);
+    }
+
     ui.add_space(10.);
 
-    uniform.update_settings(&emitter_settings);
+    uniform.update_settings(emitter_settings);
 }
 
 fn ui_emitter_animations(
@@ -1,6 +1,5 @@
 use async_std::task;
 use sparticles_app::{
-    fx::FxOptions,
     gui::egui,
     model::{events::ViewIOEvent, SparState},
 };
@@ -62,11 +61,9 @@ impl MenuWidget for PostFxMenu {
 
                     if ui.button("Add post fx").clicked() {
                         let gfx = &task::block_on(state.gfx.read());
+                        let tag = registered_post_fx[*sel_post_fx].tag();
 
-                        effects.push(registered_post_fx[*sel_post_fx].create_default(&FxOptions {
-                            fx_state: &post_process.fx_state,
-                            gfx,
-                        }));
+                        post_process.insert_fx(gfx, registered_post_fx, tag);
                     }
                 });
 
@@ -84,6 +81,12 @@ impl MenuWidget for PostFxMenu {
                         events.io_view = Some(ViewIOEvent::Idx(tex_output as u32))
                     }
                 });
+
+                let mut fxaa = post_process.fxaa_enabled();
+
+                if ui.checkbox(&mut fxaa, "FXAA").changed() {
+                    post_process.set_fxaa(fxaa);
+                }
             });
     }
 }
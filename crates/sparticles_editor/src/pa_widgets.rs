@@ -1,10 +1,16 @@
 use crate::EditorData;
 use sparticles_app::{
-    animations::{ColorAnimation, ForceAnimation, GravityAnimation, StrayAnimation},
+    animations::{
+        ColorAnimation, CurlNoiseAnimation, FlipbookAnimation, FloorCollisionAnimation,
+        ForceAnimation, GravityAnimation, GravityMode, SizeOverLifeAnimation, SpringAnimation,
+        SpringTarget, StrayAnimation, TurbulenceAnimation, VortexAnimation, WindAnimation,
+    },
     gui::egui::{
+        self,
         color_picker::{color_edit_button_rgba, Alpha},
         DragValue, Rgba, Slider, Ui,
     },
+    model::LifeCycleMode,
     traits::ParticleAnimation,
 };
 
@@ -81,41 +87,73 @@ impl EditorWidgets {
             ui.horizontal(|ui| {
                 ui.label("Lifetime sec");
                 ui.add(DragValue::new(&mut gui.life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut gui.life_cycle.mode, mode, mode.to_string());
+                }
             });
 
             ui.horizontal(|ui| {
-                ui.label("Start position > ");
-                ui.label("x:");
-                ui.add(DragValue::new(&mut gui.start_pos.x).speed(0.1));
-                ui.label("y:");
-                ui.add(DragValue::new(&mut gui.start_pos.y).speed(0.1));
-                ui.label("z:");
-                ui.add(DragValue::new(&mut gui.start_pos.z).speed(0.1));
-            });
+                let is_point_mass = gui.mode == GravityMode::PointMass;
 
-            ui.horizontal(|ui| {
-                ui.label("End position > ");
-                ui.label("x:");
-                ui.add(DragValue::new(&mut gui.end_pos.x).speed(0.1));
-                ui.label("y:");
-                ui.add(DragValue::new(&mut gui.end_pos.y).speed(0.1));
-                ui.label("z:");
-                ui.add(DragValue::new(&mut gui.end_pos.z).speed(0.1));
-            });
+                if ui.selectable_label(is_point_mass, "Point mass").clicked() {
+                    gui.mode = GravityMode::PointMass;
+                }
 
-            ui.horizontal(|ui| {
-                ui.label("Dead zone");
-                ui.add(DragValue::new(&mut gui.dead_zone).speed(0.1));
+                if ui.selectable_label(!is_point_mass, "Constant").clicked() && is_point_mass {
+                    gui.mode = GravityMode::Constant {
+                        acceleration: GravityMode::EARTH,
+                    };
+                }
             });
 
-            ui.horizontal(|ui| {
-                ui.label("Gravitational force");
-                ui.add(
-                    DragValue::new(&mut gui.gravitational_force)
-                        .speed(0.001)
-                        .clamp_range(-0.02..=0.02),
-                );
-            });
+            match &mut gui.mode {
+                GravityMode::Constant { acceleration } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Acceleration > ");
+                        ui.label("x:");
+                        ui.add(DragValue::new(&mut acceleration.x).speed(0.1));
+                        ui.label("y:");
+                        ui.add(DragValue::new(&mut acceleration.y).speed(0.1));
+                        ui.label("z:");
+                        ui.add(DragValue::new(&mut acceleration.z).speed(0.1));
+                    });
+                }
+                GravityMode::PointMass => {
+                    ui.horizontal(|ui| {
+                        ui.label("Start position > ");
+                        ui.label("x:");
+                        ui.add(DragValue::new(&mut gui.start_pos.x).speed(0.1));
+                        ui.label("y:");
+                        ui.add(DragValue::new(&mut gui.start_pos.y).speed(0.1));
+                        ui.label("z:");
+                        ui.add(DragValue::new(&mut gui.start_pos.z).speed(0.1));
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("End position > ");
+                        ui.label("x:");
+                        ui.add(DragValue::new(&mut gui.end_pos.x).speed(0.1));
+                        ui.label("y:");
+                        ui.add(DragValue::new(&mut gui.end_pos.y).speed(0.1));
+                        ui.label("z:");
+                        ui.add(DragValue::new(&mut gui.end_pos.z).speed(0.1));
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Dead zone");
+                        ui.add(DragValue::new(&mut gui.dead_zone).speed(0.1));
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Gravitational force");
+                        ui.add(
+                            DragValue::new(&mut gui.gravitational_force)
+                                .speed(0.001)
+                                .clamp_range(-0.02..=0.02),
+                        );
+                    });
+                }
+            }
 
             ui.checkbox(&mut anim.enabled, "Enabled");
 
@@ -184,6 +222,9 @@ impl EditorWidgets {
             ui.horizontal(|ui| {
                 ui.label("Lifetime sec");
                 ui.add(DragValue::new(&mut gui.life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut gui.life_cycle.mode, mode, mode.to_string());
+                }
             });
 
             ui.horizontal(|ui| {
@@ -209,4 +250,450 @@ impl EditorWidgets {
             }
         }
     }
+
+    pub fn wind_anim(editor: &mut EditorData, anim: &mut Box<dyn ParticleAnimation>, ui: &mut Ui) {
+        let downcast = anim.as_any().downcast_mut::<WindAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Wind animation");
+
+            let mut gui = anim.uniform;
+
+            ui.horizontal(|ui| {
+                ui.label("Animate from sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.from_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Animate until sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.until_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Lifetime sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut gui.life_cycle.mode, mode, mode.to_string());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Wind direction > ");
+                ui.label("x:");
+                ui.add(DragValue::new(&mut gui.direction.x).speed(0.01));
+                ui.label("y:");
+                ui.add(DragValue::new(&mut gui.direction.y).speed(0.01));
+                ui.label("z:");
+                ui.add(DragValue::new(&mut gui.direction.z).speed(0.01));
+            });
+
+            ui.spacing_mut().slider_width = 200.0;
+
+            ui.add(Slider::new(&mut gui.strength, 0.0..=50.0).text("Strength"));
+            ui.add(Slider::new(&mut gui.turbulence, 0.0..=20.0).text("Turbulence"));
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+
+            if anim.uniform != gui {
+                anim.update_uniform = true;
+                anim.uniform = gui;
+            }
+        }
+    }
+
+    pub fn vortex_anim(
+        editor: &mut EditorData,
+        anim: &mut Box<dyn ParticleAnimation>,
+        ui: &mut Ui,
+    ) {
+        let downcast = anim.as_any().downcast_mut::<VortexAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Vortex animation");
+
+            let mut gui = anim.uniform;
+
+            ui.horizontal(|ui| {
+                ui.label("Animate from sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.from_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Animate until sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.until_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Lifetime sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut gui.life_cycle.mode, mode, mode.to_string());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Center > ");
+                ui.label("x:");
+                ui.add(DragValue::new(&mut gui.center.x).speed(0.1));
+                ui.label("y:");
+                ui.add(DragValue::new(&mut gui.center.y).speed(0.1));
+                ui.label("z:");
+                ui.add(DragValue::new(&mut gui.center.z).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Axis > ");
+                ui.label("x:");
+                ui.add(DragValue::new(&mut gui.axis.x).speed(0.01));
+                ui.label("y:");
+                ui.add(DragValue::new(&mut gui.axis.y).speed(0.01));
+                ui.label("z:");
+                ui.add(DragValue::new(&mut gui.axis.z).speed(0.01));
+            });
+
+            ui.spacing_mut().slider_width = 200.0;
+
+            ui.add(Slider::new(&mut gui.angular_velocity, -10.0..=10.0).text("Angular velocity"));
+            ui.add(Slider::new(&mut gui.radial_pull, -20.0..=20.0).text("Radial pull"));
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+
+            if anim.uniform != gui {
+                anim.update_uniform = true;
+                anim.uniform = gui;
+            }
+        }
+    }
+
+    pub fn size_over_life_anim(
+        editor: &mut EditorData,
+        anim: &mut Box<dyn ParticleAnimation>,
+        ui: &mut Ui,
+    ) {
+        let downcast = anim.as_any().downcast_mut::<SizeOverLifeAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Size over life animation");
+
+            let mut gui = anim.uniform;
+
+            ui.horizontal(|ui| {
+                ui.label("Animate from sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.from_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Animate until sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.until_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Lifetime sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut gui.life_cycle.mode, mode, mode.to_string());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Size start / peak / end");
+                ui.add(
+                    DragValue::new(&mut gui.start_size)
+                        .speed(0.01)
+                        .clamp_range(0.0..=f32::MAX),
+                );
+                ui.add(
+                    DragValue::new(&mut gui.peak_size)
+                        .speed(0.01)
+                        .clamp_range(0.0..=f32::MAX),
+                );
+                ui.add(
+                    DragValue::new(&mut gui.end_size)
+                        .speed(0.01)
+                        .clamp_range(0.0..=f32::MAX),
+                );
+            });
+
+            ui.spacing_mut().slider_width = 200.0;
+
+            ui.add(Slider::new(&mut gui.peak_at, 0.0..=1.0).text("Peak at life fraction"));
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+
+            if anim.uniform != gui {
+                anim.update_uniform = true;
+                anim.uniform = gui;
+            }
+        }
+    }
+
+    pub fn curl_noise_anim(
+        editor: &mut EditorData,
+        anim: &mut Box<dyn ParticleAnimation>,
+        ui: &mut Ui,
+    ) {
+        let downcast = anim.as_any().downcast_mut::<CurlNoiseAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Curl noise animation");
+
+            let mut gui = anim.uniform;
+
+            ui.horizontal(|ui| {
+                ui.label("Animate from sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.from_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Animate until sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.until_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Lifetime sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut gui.life_cycle.mode, mode, mode.to_string());
+                }
+            });
+
+            ui.spacing_mut().slider_width = 200.0;
+
+            ui.add(Slider::new(&mut gui.frequency, 0.001..=2.0).text("Frequency"));
+            ui.add(Slider::new(&mut gui.amplitude, 0.0..=50.0).text("Amplitude"));
+
+            ui.horizontal(|ui| {
+                ui.label("Scroll speed > ");
+                ui.label("x:");
+                ui.add(DragValue::new(&mut gui.scroll_speed.x).speed(0.01));
+                ui.label("y:");
+                ui.add(DragValue::new(&mut gui.scroll_speed.y).speed(0.01));
+                ui.label("z:");
+                ui.add(DragValue::new(&mut gui.scroll_speed.z).speed(0.01));
+            });
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+
+            if anim.uniform != gui {
+                anim.update_uniform = true;
+                anim.uniform = gui;
+            }
+        }
+    }
+
+    pub fn flipbook_anim(
+        editor: &mut EditorData,
+        anim: &mut Box<dyn ParticleAnimation>,
+        ui: &mut Ui,
+    ) {
+        let downcast = anim.as_any().downcast_mut::<FlipbookAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Flipbook animation");
+
+            let mut gui = anim.uniform;
+
+            ui.horizontal(|ui| {
+                ui.label("Animate from sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.from_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Animate until sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.until_sec).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Lifetime sec");
+                ui.add(DragValue::new(&mut gui.life_cycle.lifetime_sec).speed(0.1));
+                for mode in LifeCycleMode::ALL {
+                    ui.selectable_value(&mut gui.life_cycle.mode, mode, mode.to_string());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Columns / rows");
+                ui.add(DragValue::new(&mut gui.columns).clamp_range(1..=64));
+                ui.add(DragValue::new(&mut gui.rows).clamp_range(1..=64));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Frames per second (0 plays once per life)");
+                ui.add(
+                    DragValue::new(&mut gui.fps)
+                        .speed(0.1)
+                        .clamp_range(0.0..=120.0),
+                );
+            });
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+
+            anim.uniform = gui;
+        }
+    }
+
+    pub fn floor_collision_anim(
+        editor: &mut EditorData,
+        anim: &mut Box<dyn ParticleAnimation>,
+        ui: &mut Ui,
+    ) {
+        let downcast = anim.as_any().downcast_mut::<FloorCollisionAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Floor collision animation");
+
+            let mut gui = anim.uniform;
+
+            ui.horizontal(|ui| {
+                ui.label("Floor height");
+                ui.add(DragValue::new(&mut gui.height).speed(0.1));
+            });
+
+            ui.spacing_mut().slider_width = 200.0;
+
+            ui.add(Slider::new(&mut gui.restitution, 0.0..=1.0).text("Restitution"));
+            ui.add(Slider::new(&mut gui.friction, 0.0..=1.0).text("Friction"));
+
+            ui.label("Keep this as the last animation, so it bounces the velocity of the forces");
+            ui.checkbox(&mut anim.enabled, "Enabled");
+
+            if anim.uniform != gui {
+                anim.update_uniform = true;
+                anim.uniform = gui;
+            }
+        }
+    }
+
+    pub fn turbulence_anim(
+        editor: &mut EditorData,
+        anim: &mut Box<dyn ParticleAnimation>,
+        ui: &mut Ui,
+    ) {
+        let downcast = anim.as_any().downcast_mut::<TurbulenceAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Turbulence animation");
+
+            let mut gui = anim.uniform;
+
+            ui.spacing_mut().slider_width = 200.0;
+
+            ui.add(Slider::new(&mut gui.scale, 0.001..=1.0).text("Noise scale"));
+            ui.add(Slider::new(&mut gui.strength, 0.0..=100.0).text("Strength"));
+            ui.add(Slider::new(&mut gui.scroll_speed, 0.0..=1.0).text("Scroll speed"));
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+
+            if anim.uniform != gui {
+                anim.update_uniform = true;
+                anim.uniform = gui;
+            }
+        }
+    }
+
+    pub fn spring_anim(
+        editor: &mut EditorData,
+        anim: &mut Box<dyn ParticleAnimation>,
+        ui: &mut Ui,
+    ) {
+        let downcast = anim.as_any().downcast_mut::<SpringAnimation>();
+
+        if let Some(anim) = downcast {
+            anim.selected_action = editor.create_li_header(ui, "Spring animation");
+
+            let mut gui = anim.uniform;
+
+            ui.horizontal(|ui| {
+                ui.label("Animate from sec");
+                ui.add(DragValue::new(&mut gui.from_sec).speed(0.1));
+            });
+
+            ui.spacing_mut().slider_width = 200.0;
+
+            ui.add(Slider::new(&mut gui.stiffness, 0.0..=50.0).text("Stiffness"));
+            ui.add(Slider::new(&mut gui.damping, 0.0..=20.0).text("Damping"));
+
+            ui.horizontal(|ui| {
+                let is_shape = gui.target == SpringTarget::Shape;
+
+                if ui.selectable_label(is_shape, "Shape targets").clicked() {
+                    gui.target = SpringTarget::Shape;
+                }
+
+                if ui.selectable_label(!is_shape, "Moving target").clicked() {
+                    gui.target = SpringTarget::Moving;
+                }
+            });
+
+            match gui.target {
+                SpringTarget::Shape => {
+                    ui.horizontal(|ui| {
+                        ui.label("Target image");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut editor.spring_image_path)
+                                .desired_width(150.),
+                        );
+
+                        if ui.button("Load targets").clicked() {
+                            let targets = SpringAnimation::targets_from_image(
+                                &editor.spring_image_path,
+                                anim.particle_count,
+                                10.,
+                            );
+
+                            if !targets.is_empty() {
+                                anim.set_targets(targets);
+                            }
+                        }
+                    });
+                }
+                SpringTarget::Moving => {
+                    ui.horizontal(|ui| {
+                        ui.label("Move target from sec");
+                        ui.add(DragValue::new(&mut gui.life_cycle.from_sec).speed(0.1));
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Move target until sec");
+                        ui.add(DragValue::new(&mut gui.life_cycle.until_sec).speed(0.1));
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Lifetime sec");
+                        ui.add(DragValue::new(&mut gui.life_cycle.lifetime_sec).speed(0.1));
+                        for mode in LifeCycleMode::ALL {
+                            ui.selectable_value(&mut gui.life_cycle.mode, mode, mode.to_string());
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Target start > ");
+                        ui.label("x:");
+                        ui.add(DragValue::new(&mut gui.target_start.x).speed(0.1));
+                        ui.label("y:");
+                        ui.add(DragValue::new(&mut gui.target_start.y).speed(0.1));
+                        ui.label("z:");
+                        ui.add(DragValue::new(&mut gui.target_start.z).speed(0.1));
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Target end > ");
+                        ui.label("x:");
+                        ui.add(DragValue::new(&mut gui.target_end.x).speed(0.1));
+                        ui.label("y:");
+                        ui.add(DragValue::new(&mut gui.target_end.y).speed(0.1));
+                        ui.label("z:");
+                        ui.add(DragValue::new(&mut gui.target_end.z).speed(0.1));
+                    });
+                }
+            }
+
+            ui.checkbox(&mut anim.enabled, "Enabled");
+
+            if anim.uniform != gui {
+                anim.update_uniform = true;
+                anim.uniform = gui;
+            }
+        }
+    }
 }
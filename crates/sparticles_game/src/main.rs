@@ -11,7 +11,8 @@ use sparticles_app::{
     loader::{BUILTIN_ID, CIRCLE_MAT_ID, CIRCLE_MESH_ID},
     model::{
         emitter::{MaterialRef, MeshRef},
-        Boundry, EmitterState, EmitterUniform, GfxState, LifeCycle, SparEvents, SparState,
+        Boundry, EmitterState, EmitterUniform, GfxState, LifeCycle, LifeCycleMode, SparEvents,
+        SparState,
     },
     traits::*,
     wgpu::CommandEncoder,
@@ -112,6 +113,7 @@ impl AppVisitor for GameState {
                     from_sec: 0.,
                     until_sec: 4.,
                     lifetime_sec: 4.,
+                    mode: LifeCycleMode::Loop,
                 },
                 glam::Vec2::ZERO,
                 Vec2::new(30., 120.),
@@ -125,6 +127,7 @@ impl AppVisitor for GameState {
                     from_sec: 0.,
                     until_sec: 5.,
                     lifetime_sec: 5.,
+                    mode: LifeCycleMode::Loop,
                 },
                 [0., 45.].into(),
                 [0., 15.].into(),
@@ -165,6 +168,7 @@ impl AppVisitor for GameState {
                         from_sec: 0.,
                         until_sec: 5.,
                         lifetime_sec: 10.,
+                        mode: LifeCycleMode::Loop,
                     },
                     velocity: [-15., -15., 0.].into(),
                     mass_per_unit: 8.5,
@@ -179,6 +183,7 @@ impl AppVisitor for GameState {
                         from_sec: 5.,
                         until_sec: 10.,
                         lifetime_sec: 10.,
+                        mode: LifeCycleMode::Loop,
                     },
                     velocity: [15., 0., 0.].into(),
                     mass_per_unit: 3.5,
@@ -193,6 +198,7 @@ impl AppVisitor for GameState {
                         from_sec: 1.,
                         until_sec: 6.,
                         lifetime_sec: 12.,
+                        mode: LifeCycleMode::Loop,
                     },
                     gravitational_force: 0.0015,
                     dead_zone: 4.,